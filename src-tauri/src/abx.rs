@@ -0,0 +1,138 @@
+use crate::audio::generator::Xorshift32;
+use crate::audio::profile::FrequencyBand;
+use serde::{Deserialize, Serialize};
+
+// One-sided p-value below which the listener is considered to hear a difference
+const SIGNIFICANCE: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbxSide {
+    A,
+    B,
+    X,
+}
+
+/// One of the two things being compared: an EQ to preview with, and the
+/// level change it brings, which is compensated so loudness gives nothing
+/// away. No bands means the original.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AbxCandidate {
+    pub bands: Vec<FrequencyBand>,
+    pub loudness_delta_db: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbxTrial {
+    pub x_was: AbxSide,
+    pub guess: AbxSide,
+    pub correct: bool,
+}
+
+/// Progress of a running test. Per-trial answers stay hidden until the
+/// report, so they can't influence later guesses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbxStatus {
+    pub trials: usize,
+    pub correct: usize,
+    pub p_value: f64, // Chance of scoring at least this well by guessing
+    pub significant: bool,
+    pub selected: Option<AbxSide>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AbxReport {
+    pub status: AbxStatus,
+    pub trials: Vec<AbxTrial>,
+}
+
+pub struct AbxSession {
+    a: AbxCandidate,
+    b: AbxCandidate,
+    x: AbxSide, // A or B for the current trial
+    selected: Option<AbxSide>,
+    trials: Vec<AbxTrial>,
+    rng: Xorshift32,
+}
+
+impl AbxSession {
+    pub fn new(a: AbxCandidate, b: AbxCandidate, seed: u32) -> Self {
+        let mut session = Self {
+            a,
+            b,
+            x: AbxSide::A,
+            selected: None,
+            trials: Vec::new(),
+            rng: Xorshift32::new(seed),
+        };
+        session.draw();
+        session
+    }
+
+    // What the preview should play for `side`; X resolves to the hidden pick
+    pub fn select(&mut self, side: AbxSide) -> &AbxCandidate {
+        self.selected = Some(side);
+        match side {
+            AbxSide::A => &self.a,
+            AbxSide::B => &self.b,
+            AbxSide::X if self.x == AbxSide::A => &self.a,
+            AbxSide::X => &self.b,
+        }
+    }
+
+    // Records the guess for X and starts the next trial
+    pub fn guess(&mut self, guess: AbxSide) -> Result<AbxStatus, String> {
+        if guess == AbxSide::X {
+            return Err("Guess A or B".to_string());
+        }
+        self.trials.push(AbxTrial {
+            x_was: self.x,
+            guess,
+            correct: guess == self.x,
+        });
+        self.selected = None;
+        self.draw();
+        Ok(self.status())
+    }
+
+    pub fn status(&self) -> AbxStatus {
+        let correct = self.trials.iter().filter(|t| t.correct).count();
+        let p_value = binomial_tail(self.trials.len(), correct);
+        AbxStatus {
+            trials: self.trials.len(),
+            correct,
+            p_value,
+            significant: !self.trials.is_empty() && p_value < SIGNIFICANCE,
+            selected: self.selected,
+        }
+    }
+
+    pub fn report(&self) -> AbxReport {
+        AbxReport {
+            status: self.status(),
+            trials: self.trials.clone(),
+        }
+    }
+
+    fn draw(&mut self) {
+        self.x = if self.rng.uniform() < 0.5 {
+            AbxSide::A
+        } else {
+            AbxSide::B
+        };
+    }
+}
+
+// P(at least `correct` of `trials` right) for a listener guessing at random
+fn binomial_tail(trials: usize, correct: usize) -> f64 {
+    let mut term = 0.5f64.powi(trials as i32); // C(n, 0) / 2^n
+    let mut tail = 0.0;
+    for k in 0..=trials {
+        if k >= correct {
+            tail += term;
+        }
+        term *= (trials - k) as f64 / (k + 1) as f64;
+    }
+    tail.min(1.0)
+}
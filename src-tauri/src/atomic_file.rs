@@ -0,0 +1,126 @@
+use crate::persist::{read_json, write_json};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const JOURNAL_FILE: &str = "pending_writes.json";
+const TEMP_SUFFIX: &str = ".part";
+
+// Temp files being written right now, mirrored to the journal file so the
+// next launch can remove what a crash left behind
+struct Journal {
+    path: Option<PathBuf>,
+    pending: Vec<PathBuf>,
+}
+
+static JOURNAL: Mutex<Journal> = Mutex::new(Journal {
+    path: None,
+    pending: Vec::new(),
+});
+
+/// Starts journaling to `path` and deletes the temp files an earlier run
+/// left unfinished. Returns the files removed.
+pub fn init_journal(path: PathBuf) -> Vec<String> {
+    let orphans: Vec<PathBuf> = read_json(&path).unwrap_or_default();
+    let removed = orphans
+        .iter()
+        .filter(|temp| fs::remove_file(temp).is_ok())
+        .map(|temp| temp.to_string_lossy().into_owned())
+        .collect();
+
+    let mut journal = JOURNAL.lock().unwrap();
+    journal.path = Some(path);
+    save(&journal);
+    removed
+}
+
+fn track(temp: &Path, pending: bool) {
+    let mut journal = JOURNAL.lock().unwrap();
+    journal.pending.retain(|p| p != temp);
+    if pending {
+        journal.pending.push(temp.to_path_buf());
+    }
+    save(&journal);
+}
+
+// Best effort: without a journal, temps are still cleaned up on errors
+fn save(journal: &Journal) {
+    if let Some(path) = &journal.path {
+        let _ = write_json(path, &journal.pending);
+    }
+}
+
+/// A file written under a temporary name next to its target and renamed
+/// over it by `commit`, so readers never see a half-written result. Dropped
+/// without a commit (error, cancel), the temp file is deleted.
+pub struct AtomicFile {
+    target: PathBuf,
+    temp: PathBuf,
+    out: Option<BufWriter<File>>,
+}
+
+impl AtomicFile {
+    pub fn create(target: &Path) -> Result<Self, String> {
+        let mut temp = target.as_os_str().to_owned();
+        temp.push(TEMP_SUFFIX);
+        let temp = PathBuf::from(temp);
+
+        track(&temp, true);
+        let file = File::create(&temp).map_err(|e| {
+            track(&temp, false);
+            format!("{}: {}", target.display(), e)
+        })?;
+
+        Ok(Self {
+            target: target.to_path_buf(),
+            temp,
+            out: Some(BufWriter::new(file)),
+        })
+    }
+
+    pub fn commit(mut self) -> Result<(), String> {
+        let out = self.out.take().ok_or("File already committed")?;
+        let file = out.into_inner().map_err(|e| e.error().to_string())?;
+        file.sync_all().map_err(|e| e.to_string())?;
+        drop(file);
+
+        fs::rename(&self.temp, &self.target)
+            .map_err(|e| format!("{}: {}", self.target.display(), e))?;
+        track(&self.temp, false);
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.out.as_mut() {
+            Some(out) => out.write(buf),
+            None => Err(io::Error::other("file already committed")),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.out.as_mut() {
+            Some(out) => out.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if self.out.take().is_some() {
+            let _ = fs::remove_file(&self.temp);
+            track(&self.temp, false);
+        }
+    }
+}
+
+// Whole-buffer convenience for exports
+pub fn write_atomic(target: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+    let mut file = AtomicFile::create(target)?;
+    file.write_all(contents.as_ref())
+        .map_err(|e| format!("{}: {}", target.display(), e))?;
+    file.commit()
+}
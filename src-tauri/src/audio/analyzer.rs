@@ -1,10 +1,23 @@
-use rustfft::{num_complex::Complex, FftPlanner};
+use super::finite::{amplitude_db, flag_non_finite, non_finite_in_spectrum};
+use super::profile::{extract_band_info, nearest_bin, third_octave_edges, FrequencyBand};
+use super::validation::validate_analysis_config;
+use crate::dsp::{fft, simd};
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::f32::consts::PI;
+use std::sync::{Arc, Mutex, OnceLock};
 
+pub const MIN_FFT_SIZE: usize = 256;
+pub const MAX_FFT_SIZE: usize = 65536;
+pub const MAX_OVERLAP: f32 = 0.95;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AnalysisConfig {
-    pub fft_size: usize,
+    pub fft_size: usize, // Power of two, MIN_FFT_SIZE - MAX_FFT_SIZE
     pub window_type: WindowType,
-    pub overlap: f32, // 0.0 - 0.9
+    pub overlap: f32, // 0.0 - MAX_OVERLAP
     pub frequency_bands: Vec<f32>,
 }
 
@@ -14,14 +27,30 @@ impl Default for AnalysisConfig {
             fft_size: 8192, // High resolution
             window_type: WindowType::BlackmanHarris,
             overlap: 0.75, // 75% overlap for smooth analysis
+            // Octave centers plus sub-bass and air detail at both ends
             frequency_bands: vec![
-                31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+                20.0, 25.0, 31.5, 40.0, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0,
+                10000.0, 12500.0, 16000.0, 20000.0,
             ],
         }
     }
 }
 
-#[derive(Clone, Copy)]
+impl AnalysisConfig {
+    /// Rejects settings the analysis can't run with, before any audio is
+    /// touched; see `validate_analysis_config` for the per-field errors.
+    pub fn validate(&self) -> Result<(), String> {
+        validate_analysis_config(self).map_err(|e| e.to_string())
+    }
+
+    // Samples between window starts; never zero, even for unvalidated configs
+    pub fn hop_size(&self) -> usize {
+        ((self.fft_size as f32 * (1.0 - self.overlap)) as usize).max(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WindowType {
     Hann,
     Hamming,
@@ -29,7 +58,21 @@ pub enum WindowType {
     FlatTop,        // Best for amplitude accuracy
 }
 
+// Generated windows by (type, size), shared by every analysis
+type WindowCache = HashMap<(WindowType, usize), Arc<[f32]>>;
+static WINDOWS: OnceLock<Mutex<WindowCache>> = OnceLock::new();
+
 impl WindowType {
+    pub fn window(&self, size: usize) -> Arc<[f32]> {
+        WINDOWS
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap()
+            .entry((*self, size))
+            .or_insert_with(|| self.generate(size).into())
+            .clone()
+    }
+
     fn generate(&self, size: usize) -> Vec<f32> {
         (0..size)
             .map(|i| {
@@ -48,7 +91,7 @@ impl WindowType {
                     WindowType::FlatTop => {
                         let a0 = 0.21557895;
                         let a1 = 0.41663158;
-                        let a2 = 0.277263158;
+                        let a2 = 0.277_263_16;
                         let a3 = 0.083578947;
                         let a4 = 0.006947368;
                         a0 - a1 * (2.0 * PI * x).cos() + a2 * (4.0 * PI * x).cos()
@@ -61,76 +104,296 @@ impl WindowType {
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FrequencySpectrum {
     pub frequencies: Vec<f32>,
     pub magnitudes: Vec<f32>, // dB
+    pub spread_db: Vec<f32>,  // Window-to-window variation per bin
     pub sample_rate: u32,
+    #[serde(default)]
+    pub duration_secs: f32, // Audio analyzed; 0 for running averages
 }
 
+/// Averaged spectrum of `samples`. Clips shorter than one FFT frame
+/// (including empty ones) are zero-padded to a single frame.
 pub fn analyze_spectrum(
     samples: &[f32],
     sample_rate: u32,
     config: &AnalysisConfig,
-) -> FrequencySpectrum {
-    let window = config.window_type.generate(config.fft_size);
-    let mut hop_size = (config.fft_size as f32 * (1.0 - config.overlap)) as usize;
-    if hop_size == 0 {
-        hop_size = 1;
+) -> Result<FrequencySpectrum, String> {
+    analyze_spectrum_streaming(samples, sample_rate, config, 0, &mut |_, _| {})
+}
+
+/// Like `analyze_spectrum`, but hands the running average to `on_update`
+/// about `updates` times along the way, with the fraction of windows done.
+pub fn analyze_spectrum_streaming(
+    samples: &[f32],
+    sample_rate: u32,
+    config: &AnalysisConfig,
+    updates: usize,
+    on_update: &mut dyn FnMut(FrequencySpectrum, f32),
+) -> Result<FrequencySpectrum, String> {
+    config.validate()?;
+    if sample_rate == 0 {
+        return Err("Sample rate must be positive".to_string());
     }
+    let window = config.window_type.window(config.fft_size);
+    let hop_size = config.hop_size();
 
     let mut accumulated_spectrum = vec![0.0f32; config.fft_size / 2 + 1];
-    let mut planner = FftPlanner::new();
-    let fft = planner.plan_fft_forward(config.fft_size);
+    let mut accumulated_squares = vec![0.0f32; config.fft_size / 2 + 1];
+    let fft = fft::forward(config.fft_size);
     let mut buffer = vec![Complex::new(0.0f32, 0.0f32); config.fft_size];
     let mut scratch = vec![Complex::new(0.0f32, 0.0f32); fft.get_inplace_scratch_len()];
 
     let mut processed_windows = 0usize;
     if samples.len() < config.fft_size {
-        for i in 0..config.fft_size {
-            let sample = samples.get(i).copied().unwrap_or(0.0);
-            buffer[i].re = sample * window[i];
-            buffer[i].im = 0.0;
-        }
+        let mut frame = samples.to_vec();
+        frame.resize(config.fft_size, 0.0);
+        simd::apply_window(&frame, &window, &mut buffer);
         fft.process_with_scratch(&mut buffer, &mut scratch);
         processed_windows = 1;
 
-        for (i, c) in buffer.iter().take(config.fft_size / 2 + 1).enumerate() {
-            accumulated_spectrum[i] += c.norm();
-        }
+        simd::accumulate_magnitudes(&buffer, &mut accumulated_spectrum, &mut accumulated_squares);
     } else {
+        let total_windows = (samples.len() - config.fft_size) / hop_size + 1;
+        let update_every = match updates {
+            0 => usize::MAX,
+            n => (total_windows / n).max(1),
+        };
+
         for start in (0..=samples.len() - config.fft_size).step_by(hop_size) {
             let frame = &samples[start..start + config.fft_size];
-
-            for i in 0..config.fft_size {
-                buffer[i].re = frame[i] * window[i];
-                buffer[i].im = 0.0;
-            }
+            simd::apply_window(frame, &window, &mut buffer);
 
             fft.process_with_scratch(&mut buffer, &mut scratch);
             processed_windows += 1;
 
-            for (i, c) in buffer.iter().take(config.fft_size / 2 + 1).enumerate() {
-                accumulated_spectrum[i] += c.norm();
+            // Only the first fft_size / 2 + 1 bins fit the accumulators
+            simd::accumulate_magnitudes(
+                &buffer,
+                &mut accumulated_spectrum,
+                &mut accumulated_squares,
+            );
+
+            if processed_windows.is_multiple_of(update_every) && processed_windows < total_windows {
+                on_update(
+                    average_spectrum(
+                        &accumulated_spectrum,
+                        &accumulated_squares,
+                        processed_windows,
+                        sample_rate,
+                        config.fft_size,
+                    ),
+                    processed_windows as f32 / total_windows as f32,
+                );
             }
         }
     }
 
-    // Average and convert to dB
-    let frequencies: Vec<f32> = (0..=config.fft_size / 2)
-        .map(|i| i as f32 * sample_rate as f32 / config.fft_size as f32)
+    Ok(FrequencySpectrum {
+        duration_secs: samples.len() as f32 / sample_rate as f32,
+        ..average_spectrum(
+            &accumulated_spectrum,
+            &accumulated_squares,
+            processed_windows,
+            sample_rate,
+            config.fft_size,
+        )
+    })
+}
+
+fn bin_frequency(bin: usize, sample_rate: u32, fft_size: usize) -> f32 {
+    bin as f32 * sample_rate as f32 / fft_size as f32
+}
+
+fn average_spectrum(
+    accumulated_spectrum: &[f32],
+    accumulated_squares: &[f32],
+    processed_windows: usize,
+    sample_rate: u32,
+    fft_size: usize,
+) -> FrequencySpectrum {
+    let frequencies = (0..=fft_size / 2)
+        .map(|i| bin_frequency(i, sample_rate, fft_size))
         .collect();
+    average_bins(
+        frequencies,
+        accumulated_spectrum,
+        accumulated_squares,
+        processed_windows,
+        sample_rate,
+    )
+}
 
+// `frequencies[i]` is the bin whose sums are at index i
+fn average_bins(
+    frequencies: Vec<f32>,
+    accumulated_spectrum: &[f32],
+    accumulated_squares: &[f32],
+    processed_windows: usize,
+    sample_rate: u32,
+) -> FrequencySpectrum {
+    // Average and convert to dB
     let magnitudes: Vec<f32> = accumulated_spectrum
         .iter()
         .map(|&mag| {
             let avg_mag = mag / processed_windows.max(1) as f32;
-            20.0 * (avg_mag + 1e-10).log10() // Convert to dB
+            amplitude_db(avg_mag)
+        })
+        .collect();
+
+    // Standard deviation relative to the mean, expressed in dB
+    let windows = processed_windows.max(1) as f32;
+    let spread_db: Vec<f32> = accumulated_spectrum
+        .iter()
+        .zip(accumulated_squares)
+        .map(|(&sum, &sum_sq)| {
+            let mean = sum / windows;
+            let variance = (sum_sq / windows - mean * mean).max(0.0);
+            20.0 * (1.0 + variance.sqrt() / (mean + 1e-10)).log10()
         })
         .collect();
 
-    FrequencySpectrum {
+    let spectrum = FrequencySpectrum {
         frequencies,
         magnitudes,
+        spread_db,
         sample_rate,
+        duration_secs: 0.0,
+    };
+    flag_non_finite("spectrum", &non_finite_in_spectrum(&spectrum));
+    spectrum
+}
+
+/// Streaming, band-only counterpart of `analyze_spectrum`: audio goes in
+/// chunk by chunk and only the FFT bins inside `config.frequency_bands` are
+/// accumulated, so neither the whole file nor a full-resolution spectrum is
+/// held. The bands come out identical to `extract_eq_profile`'s; loudness,
+/// centroid and resonances need the full spectrum and are not available.
+pub struct BandAccumulator {
+    config: AnalysisConfig,
+    sample_rate: u32,
+    window: Arc<[f32]>,
+    fft: Arc<dyn rustfft::Fft<f32>>,
+    hop_size: usize,
+    pending: Vec<f32>, // Samples from the next window start on
+    bins: Vec<usize>,  // Band bins, ascending
+    sums: Vec<f32>,
+    squares: Vec<f32>,
+    windows: usize,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl BandAccumulator {
+    pub fn new(sample_rate: u32, config: &AnalysisConfig) -> Result<Self, String> {
+        config.validate()?;
+        if sample_rate == 0 {
+            return Err("Sample rate must be positive".to_string());
+        }
+        let fft_size = config.fft_size;
+        let frequencies: Vec<f32> = (0..=fft_size / 2)
+            .map(|i| bin_frequency(i, sample_rate, fft_size))
+            .collect();
+        // Bins inside a band, plus each center's nearest bin for bands
+        // narrower than the bin spacing
+        let mut bins: Vec<usize> = (0..frequencies.len())
+            .filter(|&i| {
+                config.frequency_bands.iter().any(|&center| {
+                    let (lower, upper) = third_octave_edges(center);
+                    frequencies[i] >= lower && frequencies[i] <= upper
+                })
+            })
+            .chain(
+                config
+                    .frequency_bands
+                    .iter()
+                    .filter_map(|&center| nearest_bin(&frequencies, center)),
+            )
+            .collect();
+        bins.sort_unstable();
+        bins.dedup();
+
+        let fft = fft::forward(fft_size);
+        let scratch = vec![Complex::new(0.0, 0.0); fft.get_inplace_scratch_len()];
+        Ok(Self {
+            window: config.window_type.window(fft_size),
+            hop_size: config.hop_size(),
+            pending: Vec::with_capacity(2 * fft_size),
+            sums: vec![0.0; bins.len()],
+            squares: vec![0.0; bins.len()],
+            bins,
+            windows: 0,
+            buffer: vec![Complex::new(0.0, 0.0); fft_size],
+            scratch,
+            fft,
+            config: config.clone(),
+            sample_rate,
+        })
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        let fft_size = self.config.fft_size;
+        self.pending.extend_from_slice(samples);
+
+        let mut start = 0;
+        while start + fft_size <= self.pending.len() {
+            self.process_window(start);
+            start += self.hop_size;
+        }
+        // Keep what the next window needs; a hop past the end skips samples
+        self.pending.drain(..start.min(self.pending.len()));
+    }
+
+    pub fn finish(mut self) -> Vec<FrequencyBand> {
+        // Shorter than one window: a single zero-padded frame
+        if self.windows == 0 {
+            self.pending.resize(self.config.fft_size, 0.0);
+            self.process_window(0);
+        }
+
+        let frequencies = self
+            .bins
+            .iter()
+            .map(|&i| bin_frequency(i, self.sample_rate, self.config.fft_size))
+            .collect();
+        let spectrum = average_bins(
+            frequencies,
+            &self.sums,
+            &self.squares,
+            self.windows,
+            self.sample_rate,
+        );
+        self.config
+            .frequency_bands
+            .iter()
+            .map(|&center| extract_band_info(&spectrum, center))
+            .collect()
+    }
+
+    fn process_window(&mut self, start: usize) {
+        let frame = &self.pending[start..start + self.config.fft_size];
+        simd::apply_window(frame, &self.window, &mut self.buffer);
+        self.fft
+            .process_with_scratch(&mut self.buffer, &mut self.scratch);
+        self.windows += 1;
+
+        for (j, &bin) in self.bins.iter().enumerate() {
+            let power = self.buffer[bin].norm_sqr();
+            self.sums[j] += power.sqrt();
+            self.squares[j] += power;
+        }
     }
 }
+
+// Whole-buffer convenience for callers that only need band levels
+pub fn analyze_bands(
+    samples: &[f32],
+    sample_rate: u32,
+    config: &AnalysisConfig,
+) -> Result<Vec<FrequencyBand>, String> {
+    let mut accumulator = BandAccumulator::new(sample_rate, config)?;
+    accumulator.push(samples);
+    Ok(accumulator.finish())
+}
@@ -0,0 +1,76 @@
+use super::profile::{ChannelProfiles, EQProfile};
+use serde::{Deserialize, Serialize};
+
+// Bands are paired by frequency; this covers float round-trips
+const FREQUENCY_TOLERANCE: f32 = 0.01;
+
+/// Band-wise operations on profiles, mostly corrections: subtract a room
+/// correction from a match, or invert a match to hear the reference moved
+/// toward the mix.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ProfileOp {
+    Invert,
+    Add { other: EQProfile },
+    Subtract { other: EQProfile },
+    Scale { factor: f32 },
+}
+
+/// Applies `op` to `profile`. Gains and overall loudness change; the
+/// remaining statistics are kept from `profile`. Per-channel profiles
+/// follow when both sides have them in the same mode and are dropped
+/// otherwise.
+pub fn apply_profile_op(profile: &EQProfile, op: &ProfileOp) -> Result<EQProfile, String> {
+    match op {
+        ProfileOp::Invert => Ok(scale(profile, -1.0)),
+        ProfileOp::Scale { factor } if factor.is_finite() => Ok(scale(profile, *factor)),
+        ProfileOp::Scale { factor } => Err(format!("Scale factor must be finite, got {}", factor)),
+        ProfileOp::Add { other } => combine(profile, other, 1.0),
+        ProfileOp::Subtract { other } => combine(profile, other, -1.0),
+    }
+}
+
+fn scale(profile: &EQProfile, factor: f32) -> EQProfile {
+    let mut result = profile.clone();
+    for band in &mut result.bands {
+        band.gain_db *= factor;
+    }
+    result.overall_loudness *= factor;
+    result.channels = profile.channels.as_ref().map(|c| {
+        Box::new(ChannelProfiles {
+            mode: c.mode,
+            first: scale(&c.first, factor),
+            second: scale(&c.second, factor),
+        })
+    });
+    result
+}
+
+// a + sign * b, band by band
+fn combine(a: &EQProfile, b: &EQProfile, sign: f32) -> Result<EQProfile, String> {
+    if a.bands.len() != b.bands.len()
+        || a.bands
+            .iter()
+            .zip(&b.bands)
+            .any(|(x, y)| (x.frequency - y.frequency).abs() > FREQUENCY_TOLERANCE)
+    {
+        return Err("Profiles were analyzed with different band grids".to_string());
+    }
+
+    let mut result = a.clone();
+    for (band, other) in result.bands.iter_mut().zip(&b.bands) {
+        band.gain_db += sign * other.gain_db;
+        band.confidence = band.confidence.min(other.confidence);
+        band.out_of_range |= other.out_of_range;
+    }
+    result.overall_loudness += sign * b.overall_loudness;
+    result.channels = match (&a.channels, &b.channels) {
+        (Some(x), Some(y)) if x.mode == y.mode => Some(Box::new(ChannelProfiles {
+            mode: x.mode,
+            first: combine(&x.first, &y.first, sign)?,
+            second: combine(&x.second, &y.second, sign)?,
+        })),
+        _ => None,
+    };
+    Ok(result)
+}
@@ -0,0 +1,134 @@
+use super::profile::EQProfile;
+use super::provenance::settings_mismatch;
+use serde::{Deserialize, Serialize};
+
+// RMS shape difference at which similarity falls to 1/e
+const SIMILARITY_SCALE_DB: f32 = 6.0;
+
+/// Pairwise comparison of a set of profiles. Matrices are indexed
+/// `[row][column]` in `ids` order; differences read "row minus column".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProfileComparison {
+    pub ids: Vec<String>,
+    pub difference_rms_db: Vec<Vec<f32>>, // Level-normalized tonal difference
+    pub similarity: Vec<Vec<f32>>,        // 0.0 - 1.0, 1.0 = identical tonal shape
+    pub tilt_db_per_octave: Vec<f32>,     // Per profile, least-squares slope
+    pub tilt_difference_db_per_octave: Vec<Vec<f32>>,
+    pub loudness_difference_db: Vec<Vec<f32>>,
+    #[serde(default)]
+    pub settings_mismatches: Vec<String>, // Pairs analyzed with different settings
+}
+
+pub fn compare_profiles(
+    ids: Vec<String>,
+    profiles: &[&EQProfile],
+) -> Result<ProfileComparison, String> {
+    if let Some(first) = profiles.first() {
+        if !profiles.iter().all(|p| same_grid(p, first)) {
+            return Err("Profiles were analyzed with different band grids".to_string());
+        }
+    }
+
+    let shapes: Vec<Vec<f32>> = profiles.iter().map(|p| tonal_shape(p)).collect();
+    let tilts: Vec<f32> = profiles.iter().map(|p| spectral_tilt(p)).collect();
+
+    let matrix = |f: &dyn Fn(usize, usize) -> f32| -> Vec<Vec<f32>> {
+        (0..profiles.len())
+            .map(|i| (0..profiles.len()).map(|j| f(i, j)).collect())
+            .collect()
+    };
+    let difference_rms_db = matrix(&|i, j| rms_difference(&shapes[i], &shapes[j]));
+    let mut settings_mismatches = Vec::new();
+    for i in 0..profiles.len() {
+        for j in i + 1..profiles.len() {
+            let differences = settings_mismatch(profiles[i], profiles[j]);
+            if !differences.is_empty() {
+                settings_mismatches.push(format!(
+                    "{} / {}: {}",
+                    ids[i],
+                    ids[j],
+                    differences.join(", ")
+                ));
+            }
+        }
+    }
+
+    Ok(ProfileComparison {
+        ids,
+        similarity: difference_rms_db
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|d| (-d / SIMILARITY_SCALE_DB).exp())
+                    .collect()
+            })
+            .collect(),
+        difference_rms_db,
+        tilt_difference_db_per_octave: matrix(&|i, j| tilts[i] - tilts[j]),
+        tilt_db_per_octave: tilts,
+        loudness_difference_db: matrix(&|i, j| {
+            profiles[i].overall_loudness - profiles[j].overall_loudness
+        }),
+        settings_mismatches,
+    })
+}
+
+/// The `similarity` entry `compare_profiles` would report for the pair, or
+/// None when they are on different band grids.
+pub fn tonal_similarity(a: &EQProfile, b: &EQProfile) -> Option<f32> {
+    same_grid(a, b).then(|| {
+        let difference = rms_difference(&tonal_shape(a), &tonal_shape(b));
+        (-difference / SIMILARITY_SCALE_DB).exp()
+    })
+}
+
+fn same_grid(a: &EQProfile, b: &EQProfile) -> bool {
+    a.bands.len() == b.bands.len()
+        && a.bands
+            .iter()
+            .zip(&b.bands)
+            .all(|(x, y)| (x.frequency - y.frequency).abs() <= 0.01)
+}
+
+// Band levels with the mean removed, so loudness doesn't count as tone
+fn tonal_shape(profile: &EQProfile) -> Vec<f32> {
+    let mean =
+        profile.bands.iter().map(|b| b.gain_db).sum::<f32>() / profile.bands.len().max(1) as f32;
+    profile.bands.iter().map(|b| b.gain_db - mean).collect()
+}
+
+fn rms_difference(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum();
+    (sum / a.len() as f32).sqrt()
+}
+
+// Slope of band level against log2(frequency)
+pub fn spectral_tilt(profile: &EQProfile) -> f32 {
+    let points: Vec<(f32, f32)> = profile
+        .bands
+        .iter()
+        .filter(|b| b.frequency > 0.0)
+        .map(|b| (b.frequency.log2(), b.gain_db))
+        .collect();
+    if points.len() < 2 {
+        return 0.0;
+    }
+
+    let n = points.len() as f32;
+    let mean_x = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let covariance: f32 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f32 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+    if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    }
+}
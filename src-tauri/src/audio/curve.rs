@@ -0,0 +1,70 @@
+use super::profile::{EQProfile, FrequencyBand};
+use crate::dsp::filters::FilterType;
+
+/// Anchor points (Hz, dB) interpolated linearly on a log-frequency axis.
+#[derive(Debug, Clone)]
+pub struct ResponseCurve {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl ResponseCurve {
+    pub fn new(mut points: Vec<(f32, f32)>) -> Self {
+        points.retain(|(f, g)| *f > 0.0 && f.is_finite() && g.is_finite());
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { points }
+    }
+
+    pub fn gain_at(&self, freq: f32) -> f32 {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+
+        if freq <= first.0 {
+            return first.1;
+        }
+        if freq >= last.0 {
+            return last.1;
+        }
+
+        let upper = self.points.partition_point(|p| p.0 < freq);
+        let (f0, g0) = self.points[upper - 1];
+        let (f1, g1) = self.points[upper];
+        let t = (freq.ln() - f0.ln()) / (f1.ln() - f0.ln());
+
+        g0 + (g1 - g0) * t
+    }
+
+    // Samples the curve at the given band centers (1/3 octave bandwidth)
+    pub fn to_profile(&self, band_centers: &[f32]) -> EQProfile {
+        let bands: Vec<FrequencyBand> = band_centers
+            .iter()
+            .map(|&freq| FrequencyBand {
+                frequency: freq,
+                gain_db: self.gain_at(freq),
+                bandwidth: freq * 0.23,
+                confidence: 1.0,
+                temporal_spread_db: 0.0,
+                filter_type: FilterType::Peaking,
+                out_of_range: false,
+            })
+            .collect();
+
+        let overall_loudness = if bands.is_empty() {
+            0.0
+        } else {
+            bands.iter().map(|b| b.gain_db).sum::<f32>() / bands.len() as f32
+        };
+
+        EQProfile {
+            bands,
+            overall_loudness,
+            dynamic_range: 0.0,
+            spectral_centroid: 0.0,
+            spectral_rolloff: 0.0,
+            channels: None,
+            resonance: Default::default(),
+            source: None,
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use super::analyzer::{analyze_bands, AnalysisConfig};
+use super::profile::{EQProfile, FrequencyBand};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_SEGMENT_SECS: f32 = 2.0;
+const SILENCE_DB: f32 = -60.0; // Segment RMS, dBFS
+
+/// Tonal deviation of the input from the reference along the input's
+/// timeline. Rows are segments, columns the reference's bands; values read
+/// "input minus reference" after both are normalized to their mean level,
+/// so a muddy bridge shows as a positive low-mid patch in its rows only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviationMap {
+    pub frequencies: Vec<f32>,
+    pub segment_secs: f32,
+    pub start_secs: Vec<f32>,        // Per segment
+    pub deviation_db: Vec<Vec<f32>>, // [segment][band]
+    pub rms_deviation_db: Vec<f32>,  // Per segment, over in-range bands
+    pub silent: Vec<bool>,           // Too quiet to compare; rows are all zero
+}
+
+/// Splits `samples` into `segment_secs` slices and compares each slice's
+/// band levels with `reference` on the reference's band grid. A trailing
+/// slice shorter than one FFT window is dropped unless it is all there is.
+pub fn deviation_over_time(
+    samples: &[f32],
+    sample_rate: u32,
+    reference: &EQProfile,
+    config: &AnalysisConfig,
+    segment_secs: f32,
+) -> Result<DeviationMap, String> {
+    if !(segment_secs.is_finite() && segment_secs > 0.0) {
+        return Err(format!(
+            "Segment length must be positive, got {}",
+            segment_secs
+        ));
+    }
+    if reference.bands.is_empty() {
+        return Err("Reference has no bands".to_string());
+    }
+    let config = AnalysisConfig {
+        frequency_bands: reference.bands.iter().map(|b| b.frequency).collect(),
+        ..config.clone()
+    };
+    config.validate()?;
+
+    // At least one full window per segment
+    let segment_len = ((segment_secs * sample_rate as f32) as usize).max(config.fft_size);
+    let segments: Vec<&[f32]> = samples
+        .chunks(segment_len)
+        .enumerate()
+        .filter(|(i, chunk)| *i == 0 || chunk.len() >= config.fft_size)
+        .map(|(_, chunk)| chunk)
+        .collect();
+
+    let rows = segments
+        .par_iter()
+        .map(|segment| {
+            if rms_db(segment) < SILENCE_DB {
+                return Ok((vec![0.0; reference.bands.len()], 0.0, true));
+            }
+            let bands = analyze_bands(segment, sample_rate, &config)?;
+            let deviation: Vec<f32> = normalize(&bands, &reference.bands)
+                .iter()
+                .zip(normalize(&reference.bands, &bands))
+                .zip(bands.iter().zip(&reference.bands))
+                .map(|((&input, target), (band, ref_band))| {
+                    if band.out_of_range || ref_band.out_of_range {
+                        0.0
+                    } else {
+                        input - target
+                    }
+                })
+                .collect();
+            let compared = bands
+                .iter()
+                .zip(&reference.bands)
+                .filter(|(a, b)| !a.out_of_range && !b.out_of_range)
+                .count();
+            let rms =
+                (deviation.iter().map(|v| v * v).sum::<f32>() / compared.max(1) as f32).sqrt();
+            Ok((deviation, rms, false))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let actual_secs = segment_len as f32 / sample_rate as f32;
+
+    Ok(DeviationMap {
+        frequencies: config.frequency_bands,
+        segment_secs: actual_secs,
+        start_secs: (0..rows.len()).map(|i| i as f32 * actual_secs).collect(),
+        rms_deviation_db: rows.iter().map(|(_, rms, _)| *rms).collect(),
+        silent: rows.iter().map(|(_, _, silent)| *silent).collect(),
+        deviation_db: rows.into_iter().map(|(row, _, _)| row).collect(),
+    })
+}
+
+/// The `length_secs` stretch (whole segments, at least one) deviating most
+/// from the reference on average, as (start, end) seconds. Silent segments
+/// count as no deviation; None when every segment is silent.
+pub fn worst_region(map: &DeviationMap, length_secs: f32) -> Option<(f32, f32)> {
+    if map.silent.iter().all(|&s| s) {
+        return None;
+    }
+    let span = ((length_secs / map.segment_secs).round() as usize).clamp(1, map.silent.len());
+    let start = map
+        .rms_deviation_db
+        .windows(span)
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.iter().sum::<f32>().total_cmp(&b.iter().sum::<f32>()))
+        .map(|(i, _)| i)?;
+
+    Some((
+        map.start_secs[start],
+        map.start_secs[start] + span as f32 * map.segment_secs,
+    ))
+}
+
+// Band levels minus their mean over the bands in range on both sides
+fn normalize(bands: &[FrequencyBand], reference: &[FrequencyBand]) -> Vec<f32> {
+    let in_range: Vec<f32> = bands
+        .iter()
+        .zip(reference)
+        .filter(|(a, b)| !a.out_of_range && !b.out_of_range)
+        .map(|(a, _)| a.gain_db)
+        .collect();
+    let mean = in_range.iter().sum::<f32>() / in_range.len().max(1) as f32;
+    bands.iter().map(|b| b.gain_db - mean).collect()
+}
+
+fn rms_db(samples: &[f32]) -> f32 {
+    let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32;
+    10.0 * (mean_square + 1e-20).log10()
+}
@@ -0,0 +1,80 @@
+use super::profile::EQProfile;
+use serde::{Deserialize, Serialize};
+
+// Beyond this the 30% EQ scaling in the matcher can't close the gap
+const MISMATCH_RATIO: f32 = 0.7;
+const CROSSOVERS_HZ: [f32; 3] = [120.0, 1000.0, 6000.0];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultibandCompressionSuggestion {
+    pub crossovers_hz: Vec<f32>,
+    pub bands: Vec<CompressionBandSuggestion>,
+    pub reference_dynamic_range: f32,
+    pub input_dynamic_range: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionBandSuggestion {
+    pub low_hz: f32,
+    pub high_hz: f32,
+    pub gain_reduction_db: f32, // Target average GR
+    pub ratio: f32,
+}
+
+/// Suggests a 4-band compressor when the reference is much more compressed than the input.
+/// Per-band targets come from how much more the input's band levels move between windows.
+pub fn suggest_multiband_compression(
+    reference: &EQProfile,
+    input: &EQProfile,
+) -> Option<MultibandCompressionSuggestion> {
+    let ref_dr = reference.dynamic_range;
+    let inp_dr = input.dynamic_range;
+
+    if inp_dr <= 0.0 || ref_dr / inp_dr >= MISMATCH_RATIO {
+        return None;
+    }
+
+    let edges: Vec<f32> = std::iter::once(20.0)
+        .chain(CROSSOVERS_HZ)
+        .chain(std::iter::once(20000.0))
+        .collect();
+
+    let bands = edges
+        .windows(2)
+        .map(|edge| {
+            let (low_hz, high_hz) = (edge[0], edge[1]);
+            let spread = |profile: &EQProfile| {
+                let values: Vec<f32> = profile
+                    .bands
+                    .iter()
+                    .filter(|b| b.frequency >= low_hz && b.frequency < high_hz)
+                    .map(|b| b.temporal_spread_db)
+                    .collect();
+                values.iter().sum::<f32>() / values.len().max(1) as f32
+            };
+
+            let ref_spread = spread(reference);
+            let inp_spread = spread(input);
+            let gain_reduction_db = (inp_spread - ref_spread).max(0.0);
+            let ratio = if ref_spread > 0.0 {
+                (inp_spread / ref_spread).clamp(1.0, 4.0)
+            } else {
+                1.0
+            };
+
+            CompressionBandSuggestion {
+                low_hz,
+                high_hz,
+                gain_reduction_db,
+                ratio,
+            }
+        })
+        .collect();
+
+    Some(MultibandCompressionSuggestion {
+        crossovers_hz: CROSSOVERS_HZ.to_vec(),
+        bands,
+        reference_dynamic_range: ref_dr,
+        input_dynamic_range: inp_dr,
+    })
+}
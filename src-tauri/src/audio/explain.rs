@@ -0,0 +1,158 @@
+use super::matcher::{CorrectionStage, MatchResult};
+use serde::{Deserialize, Serialize};
+
+// Stage changes smaller than this aren't worth a mention
+const MENTION_DB: f32 = 0.05;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageAdjustment {
+    pub stage: CorrectionStage,
+    pub change_db: f32,
+}
+
+/// Why the matcher chose a band's gain: the difference it started from
+/// and what each stage did to it, with the same as one readable sentence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BandExplanation {
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub difference_db: f32,            // Normalized reference minus input
+    pub loudness_contribution_db: f32, // Overall level change from this band alone
+    pub adjustments: Vec<StageAdjustment>,
+    pub edited: bool, // Gain was set by hand after matching
+    pub text: String,
+}
+
+/// Per-band rationale for `result`'s correction, e.g. "+2.4 dB at 250 Hz
+/// because input is 3.8 dB below reference; reduced by smoothing (0.9 dB)
+/// and confidence 0.70 (0.5 dB)". Results saved before stages were
+/// recorded only get the starting difference.
+pub fn explain_correction(result: &MatchResult) -> Vec<BandExplanation> {
+    result
+        .correction_profile
+        .bands
+        .iter()
+        .enumerate()
+        .map(|(i, band)| {
+            let difference_db = match (
+                result.reference_normalized.get(i),
+                result.input_normalized.get(i),
+            ) {
+                (Some(r), Some(n)) => r - n,
+                _ => 0.0,
+            };
+
+            let mut before = difference_db;
+            let mut adjustments = Vec::new();
+            for step in &result.correction_steps {
+                let Some(&after) = step.gains_db.get(i) else {
+                    continue;
+                };
+                if step.stage != CorrectionStage::Difference && (after - before).abs() >= MENTION_DB
+                {
+                    adjustments.push(StageAdjustment {
+                        stage: step.stage,
+                        change_db: after - before,
+                    });
+                }
+                before = after;
+            }
+            let matched_db = before;
+            let edited =
+                !result.correction_steps.is_empty() && (band.gain_db - matched_db).abs() >= 0.01;
+
+            let mut text = format!("{:+.1} dB at {} Hz ", band.gain_db, band.frequency);
+            if band.out_of_range {
+                text.push_str(
+                    "left alone: the band lies above one of the files' Nyquist frequency",
+                );
+            } else {
+                text.push_str(&reason(difference_db));
+                let (reduced, raised): (Vec<_>, Vec<_>) = adjustments
+                    .iter()
+                    .scan(difference_db, |gain, adjustment| {
+                        let shrank = (*gain + adjustment.change_db).abs() < gain.abs();
+                        *gain += adjustment.change_db;
+                        Some((shrank, adjustment))
+                    })
+                    .partition(|(shrank, _)| *shrank);
+                for (verb, list) in [("reduced", reduced), ("increased", raised)] {
+                    if !list.is_empty() {
+                        let names: Vec<String> = list
+                            .iter()
+                            .map(|(_, a)| {
+                                format!(
+                                    "{} ({:.1} dB)",
+                                    stage_name(a.stage, band.confidence),
+                                    a.change_db.abs()
+                                )
+                            })
+                            .collect();
+                        text.push_str(&format!("; {} by {}", verb, join_and(&names)));
+                    }
+                }
+            }
+            if edited {
+                text.push_str(&format!(
+                    "; set by hand, the matcher chose {:+.1} dB",
+                    matched_db
+                ));
+            }
+
+            BandExplanation {
+                frequency: band.frequency,
+                gain_db: band.gain_db,
+                difference_db,
+                loudness_contribution_db: result
+                    .band_loudness_contribution_db
+                    .get(i)
+                    .copied()
+                    .unwrap_or(0.0),
+                adjustments,
+                edited,
+                text,
+            }
+        })
+        .collect()
+}
+
+fn reason(difference_db: f32) -> String {
+    if difference_db.abs() < 0.1 {
+        "because input already matches reference".to_string()
+    } else {
+        let side = if difference_db > 0.0 {
+            "below"
+        } else {
+            "above"
+        };
+        format!(
+            "because input is {:.1} dB {} reference",
+            difference_db.abs(),
+            side
+        )
+    }
+}
+
+fn stage_name(stage: CorrectionStage, confidence: f32) -> String {
+    match stage {
+        CorrectionStage::Difference => "the level difference".to_string(),
+        CorrectionStage::Psychoacoustic => "psychoacoustic weighting".to_string(),
+        CorrectionStage::Masking => "masking".to_string(),
+        CorrectionStage::Confidence => format!("confidence {:.2}", confidence),
+        CorrectionStage::Room => "room limits".to_string(),
+        CorrectionStage::Smoothing => "smoothing".to_string(),
+        CorrectionStage::Intensity => "intensity".to_string(),
+        CorrectionStage::Limit => "the correction limit".to_string(),
+        CorrectionStage::Speech => "speech limits".to_string(),
+        CorrectionStage::Dynamics => "dynamics preservation".to_string(),
+    }
+}
+
+// "a", "a and b", "a, b and c"
+fn join_and(items: &[String]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
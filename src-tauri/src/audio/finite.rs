@@ -0,0 +1,143 @@
+// Floors and checks that keep silence and degenerate input from turning
+// into -inf dB and NaN further down the pipeline
+
+use super::analyzer::FrequencySpectrum;
+use super::matcher::MatchResult;
+use super::profile::EQProfile;
+
+/// Level reported for digital silence, 20 * log10(1e-10).
+pub const SILENCE_DB: f32 = -200.0;
+
+// Anything at or below the silence floor (or NaN) reads as SILENCE_DB
+pub fn amplitude_db(amplitude: f32) -> f32 {
+    if amplitude > 1e-10 {
+        (20.0 * amplitude.log10()).max(SILENCE_DB)
+    } else {
+        SILENCE_DB
+    }
+}
+
+pub fn power_db(power: f32) -> f32 {
+    if power > 1e-20 {
+        (10.0 * power.log10()).max(SILENCE_DB)
+    } else {
+        SILENCE_DB
+    }
+}
+
+pub fn finite_or(value: f32, fallback: f32) -> f32 {
+    if value.is_finite() {
+        value
+    } else {
+        fallback
+    }
+}
+
+/// Fields of `profile` holding NaN or infinity, e.g. `bands[3].gain_db`.
+pub fn non_finite_in_profile(profile: &EQProfile) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut check = |name: String, value: f32| {
+        if !value.is_finite() {
+            fields.push(name);
+        }
+    };
+    check("overall_loudness".into(), profile.overall_loudness);
+    check("dynamic_range".into(), profile.dynamic_range);
+    check("spectral_centroid".into(), profile.spectral_centroid);
+    check("spectral_rolloff".into(), profile.spectral_rolloff);
+    for (i, band) in profile.bands.iter().enumerate() {
+        check(format!("bands[{}].gain_db", i), band.gain_db);
+        check(format!("bands[{}].confidence", i), band.confidence);
+        check(
+            format!("bands[{}].temporal_spread_db", i),
+            band.temporal_spread_db,
+        );
+    }
+    if let Some(channels) = &profile.channels {
+        for (side, profile) in [("first", &channels.first), ("second", &channels.second)] {
+            fields.extend(
+                non_finite_in_profile(profile)
+                    .into_iter()
+                    .map(|f| format!("channels.{}.{}", side, f)),
+            );
+        }
+    }
+    fields
+}
+
+pub fn non_finite_in_spectrum(spectrum: &FrequencySpectrum) -> Vec<String> {
+    let series = [
+        ("magnitudes", &spectrum.magnitudes),
+        ("spread_db", &spectrum.spread_db),
+    ];
+    series
+        .iter()
+        .flat_map(|(name, values)| {
+            values
+                .iter()
+                .enumerate()
+                .filter(|(_, v)| !v.is_finite())
+                .map(move |(i, _)| format!("{}[{}]", name, i))
+        })
+        .collect()
+}
+
+pub fn non_finite_in_result(result: &MatchResult) -> Vec<String> {
+    let mut fields: Vec<String> = non_finite_in_profile(&result.correction_profile)
+        .into_iter()
+        .map(|f| format!("correction_profile.{}", f))
+        .collect();
+    let series = [
+        ("reference_normalized", &result.reference_normalized),
+        ("input_normalized", &result.input_normalized),
+        ("predicted_normalized", &result.predicted_normalized),
+        ("residual_db", &result.residual_db),
+        ("band_uncertainty_db", &result.band_uncertainty_db),
+        (
+            "band_loudness_contribution_db",
+            &result.band_loudness_contribution_db,
+        ),
+    ];
+    for (name, values) in series {
+        for (i, value) in values.iter().enumerate() {
+            if !value.is_finite() {
+                fields.push(format!("{}[{}]", name, i));
+            }
+        }
+    }
+    for (name, value) in [
+        ("residual_rms_db", result.residual_rms_db),
+        (
+            "correction_loudness_delta_db",
+            result.correction_loudness_delta_db,
+        ),
+        ("output_gain_db", result.output_gain_db),
+        ("quality_score", result.quality_score),
+    ] {
+        if !value.is_finite() {
+            fields.push(name.to_string());
+        }
+    }
+    fields
+}
+
+/// Reports non-finite `fields` found in `what`. Debug builds treat them as
+/// a bug and assert; release builds log them and carry on.
+pub fn flag_non_finite(what: &str, fields: &[String]) {
+    if fields.is_empty() {
+        return;
+    }
+    let shown = fields
+        .iter()
+        .take(8)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    log::warn!(
+        "non-finite values in {} ({} fields): {}",
+        what,
+        fields.len(),
+        shown
+    );
+    debug_assert!(false, "non-finite values in {}: {}", what, shown);
+}
@@ -0,0 +1,141 @@
+use super::loader::MultiChannelAudio;
+use super::profile::FrequencyBand;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+const FADE_SECS: f64 = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TestSignal {
+    WhiteNoise,
+    PinkNoise,
+    LogSweep {
+        start_hz: f32,
+        end_hz: f32,
+    },
+    // Sine whose pitch wobbles around the centre; avoids standing-wave nulls
+    Warble {
+        center_hz: f32,
+        width_octaves: f32,
+        rate_hz: f32,
+    },
+}
+
+impl TestSignal {
+    // Warble covering one correction band, for auditioning it on speakers
+    pub fn for_band(band: &FrequencyBand) -> Self {
+        let low = (band.frequency - band.bandwidth / 2.0).max(1.0);
+        let high = band.frequency + band.bandwidth / 2.0;
+        TestSignal::Warble {
+            center_hz: band.frequency,
+            width_octaves: (high / low).log2().clamp(0.1, 2.0),
+            rate_hz: 6.0,
+        }
+    }
+}
+
+/// Renders `signal` to identical channels, peak-normalized to `level_db`
+/// dBFS with short fades so playback starts and stops without clicks.
+pub fn generate(
+    signal: &TestSignal,
+    sample_rate: u32,
+    duration_secs: f64,
+    level_db: f32,
+    channels: usize,
+) -> MultiChannelAudio {
+    let fs = sample_rate.max(1) as f64;
+    let frames = (duration_secs.max(0.0) * fs) as usize;
+    let mut rng = Xorshift32::new(0x1234_5678);
+
+    let mut samples: Vec<f64> = match *signal {
+        TestSignal::WhiteNoise => (0..frames).map(|_| rng.bipolar()).collect(),
+        TestSignal::PinkNoise => pink_noise(frames, &mut rng),
+        TestSignal::LogSweep { start_hz, end_hz } => {
+            let f1 = (start_hz as f64).max(1.0);
+            let f2 = (end_hz as f64).clamp(f1 + 1.0, fs / 2.0);
+            let k = (f2 / f1).ln();
+            (0..frames)
+                .map(|n| {
+                    let t = n as f64 / fs;
+                    let phase =
+                        2.0 * PI * f1 * duration_secs / k * ((t / duration_secs * k).exp() - 1.0);
+                    phase.sin()
+                })
+                .collect()
+        }
+        TestSignal::Warble {
+            center_hz,
+            width_octaves,
+            rate_hz,
+        } => {
+            let mut phase = 0.0f64;
+            (0..frames)
+                .map(|n| {
+                    let t = n as f64 / fs;
+                    let wobble = (2.0 * PI * rate_hz as f64 * t).sin();
+                    let freq = center_hz as f64 * 2f64.powf(width_octaves as f64 / 2.0 * wobble);
+                    phase = (phase + 2.0 * PI * freq / fs) % (2.0 * PI);
+                    phase.sin()
+                })
+                .collect()
+        }
+    };
+
+    let peak = samples.iter().fold(0.0f64, |m, s| m.max(s.abs()));
+    let gain = if peak > 0.0 {
+        10f64.powf(level_db as f64 / 20.0) / peak
+    } else {
+        0.0
+    };
+    let fade = ((FADE_SECS * fs) as usize).min(frames / 2).max(1);
+    for (n, sample) in samples.iter_mut().enumerate() {
+        let ramp = (n.min(frames - 1 - n) as f64 / fade as f64).min(1.0);
+        *sample *= gain * ramp;
+    }
+
+    let channel: Vec<f32> = samples.into_iter().map(|s| s as f32).collect();
+    MultiChannelAudio {
+        channels: vec![channel; channels.max(1)],
+        sample_rate,
+        duration_secs: frames as f64 / fs,
+    }
+}
+
+// Paul Kellet's economy filter: white noise through three one-pole stages
+fn pink_noise(frames: usize, rng: &mut Xorshift32) -> Vec<f64> {
+    let (mut b0, mut b1, mut b2) = (0.0, 0.0, 0.0);
+    (0..frames)
+        .map(|_| {
+            let white = rng.bipolar();
+            b0 = 0.99765 * b0 + white * 0.0990460;
+            b1 = 0.96300 * b1 + white * 0.2965164;
+            b2 = 0.57000 * b2 + white * 1.0526913;
+            b0 + b1 + b2 + white * 0.1848
+        })
+        .collect()
+}
+
+// Small deterministic PRNG, good enough for noise and dither
+pub(crate) struct Xorshift32 {
+    state: u32,
+}
+
+impl Xorshift32 {
+    pub(crate) fn new(seed: u32) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    // Uniform in [0, 1]
+    pub(crate) fn uniform(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f64 / u32::MAX as f64
+    }
+
+    // Uniform in [-1, 1]
+    pub(crate) fn bipolar(&mut self) -> f64 {
+        self.uniform() * 2.0 - 1.0
+    }
+}
@@ -0,0 +1,147 @@
+use super::curve::ResponseCurve;
+use super::loudness::REFERENCE_LISTENING_LEVEL_DB;
+use super::matcher::{match_profiles, MatchConfig, MatchResult};
+use serde::{Deserialize, Serialize};
+
+// Typical limits of headphone DSP apps (Wavelet, Qudelix, Peace)
+pub const MAX_HEADPHONE_BANDS: usize = 10;
+pub const MAX_HEADPHONE_GAIN_DB: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeadphoneTarget {
+    HarmanOverEar2018,
+    HarmanInEar2019,
+    DiffuseField,
+}
+
+impl HeadphoneTarget {
+    // Approximations of the published curves in ear-simulator coordinates, 0 dB at 1 kHz
+    pub fn curve(&self) -> ResponseCurve {
+        let points = match self {
+            HeadphoneTarget::HarmanOverEar2018 => vec![
+                (20.0, 6.0),
+                (50.0, 5.5),
+                (100.0, 3.8),
+                (200.0, 1.0),
+                (400.0, 0.0),
+                (1000.0, 0.0),
+                (2000.0, 5.0),
+                (3000.0, 9.0),
+                (4000.0, 8.0),
+                (6000.0, 4.0),
+                (10000.0, -1.0),
+                (16000.0, -6.0),
+                (20000.0, -10.0),
+            ],
+            HeadphoneTarget::HarmanInEar2019 => vec![
+                (20.0, 9.0),
+                (60.0, 9.0),
+                (150.0, 5.0),
+                (300.0, 1.0),
+                (1000.0, 0.0),
+                (2000.0, 6.0),
+                (3000.0, 11.0),
+                (5000.0, 8.0),
+                (8000.0, 3.0),
+                (10000.0, 0.0),
+                (16000.0, -4.0),
+                (20000.0, -8.0),
+            ],
+            HeadphoneTarget::DiffuseField => vec![
+                (20.0, 0.0),
+                (400.0, 0.0),
+                (1000.0, 0.0),
+                (2000.0, 5.0),
+                (3000.0, 12.0),
+                (5000.0, 9.0),
+                (8000.0, 4.0),
+                (10000.0, 2.0),
+                (20000.0, -2.0),
+            ],
+        };
+
+        ResponseCurve::new(points)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeadphoneMatch {
+    pub result: MatchResult,
+    pub preamp_db: f32, // Negative headroom for the largest boost
+}
+
+impl MatchConfig {
+    pub fn headphone() -> Self {
+        Self {
+            intensity: 1.0,
+            max_correction: MAX_HEADPHONE_GAIN_DB,
+            max_correction_curve: Vec::new(),
+            smoothing_factor: 0.2,
+            use_psychoacoustic: false,
+            preserve_dynamics: false,
+            use_masking: false,
+            room_correction: None,
+            speech: None,
+            listening_level_db: REFERENCE_LISTENING_LEVEL_DB,
+        }
+    }
+}
+
+/// Parses a frequency response export (AutoEQ / REW style): one "freq,gain" pair
+/// per line, separated by commas, semicolons, tabs or spaces. Headers and
+/// comments are skipped.
+pub fn parse_measurement(text: &str) -> Result<ResponseCurve, String> {
+    let points: Vec<(f32, f32)> = text
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split([',', ';', '\t', ' ']).filter(|f| !f.is_empty());
+            let freq = fields.next()?.trim().parse::<f32>().ok()?;
+            let gain = fields.next()?.trim().parse::<f32>().ok()?;
+            Some((freq, gain))
+        })
+        .collect();
+
+    if points.len() < 2 {
+        return Err("Measurement has fewer than two frequency/gain points".to_string());
+    }
+
+    Ok(ResponseCurve::new(points))
+}
+
+pub fn match_headphone(
+    measurement: &ResponseCurve,
+    target: &ResponseCurve,
+    band_centers: &[f32],
+) -> HeadphoneMatch {
+    let centers = headphone_centers(band_centers);
+    let reference = target.to_profile(&centers);
+    let input = measurement.to_profile(&centers);
+
+    let result = match_profiles(&reference, &input, &MatchConfig::headphone());
+    let max_boost = result
+        .correction_profile
+        .bands
+        .iter()
+        .map(|b| b.gain_db)
+        .fold(0.0f32, f32::max);
+
+    HeadphoneMatch {
+        result,
+        preamp_db: -max_boost,
+    }
+}
+
+// At most MAX_HEADPHONE_BANDS centers, log-spaced over the grid's whole
+// range so a dense grid doesn't lose the treble
+fn headphone_centers(band_centers: &[f32]) -> Vec<f32> {
+    if band_centers.len() <= MAX_HEADPHONE_BANDS {
+        return band_centers.to_vec();
+    }
+    let low = band_centers.iter().copied().fold(f32::MAX, f32::min);
+    let high = band_centers.iter().copied().fold(0.0f32, f32::max);
+    let steps = (MAX_HEADPHONE_BANDS - 1) as f32;
+    (0..MAX_HEADPHONE_BANDS)
+        .map(|i| low * (high / low).powf(i as f32 / steps))
+        .collect()
+}
@@ -1,26 +1,34 @@
-use std::fs::File;
+use super::mapped::open_media_source;
+use crate::jobs::CancelToken;
+use std::ops::Range;
 use std::path::Path;
 use symphonia::core::audio::{AudioBuffer, AudioBufferRef, Signal};
-use symphonia::core::codecs::DecoderOptions;
-use symphonia::core::formats::FormatOptions;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader};
 use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
+use symphonia::core::meta::{MetadataOptions, Value};
 use symphonia::core::probe::Hint;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum AudioError {
-    #[error("Dosya açılamadı: {0}")]
+    #[error("Cannot open file: {0}")]
     FileOpen(#[from] std::io::Error),
 
-    #[error("Format desteklenmiyor")]
+    #[error("Unsupported format")]
     UnsupportedFormat,
 
-    #[error("Decode hatası: {0}")]
+    #[error("Decode error: {0}")]
     DecodeError(String),
 
-    #[error("Ses verisi bulunamadı")]
+    #[error("No audio data")]
     NoAudioData,
+
+    #[error("Invalid analysis settings: {0}")]
+    InvalidConfig(String),
+
+    #[error("Cancelled")]
+    Cancelled,
 }
 
 pub struct AudioData {
@@ -40,61 +48,262 @@ pub enum ResamplePolicy {
     Never,
 }
 
+pub struct MultiChannelAudio {
+    pub channels: Vec<Vec<f32>>, // Planar, normalized -1.0 to 1.0
+    pub sample_rate: u32,
+    pub duration_secs: f64,
+}
+
 pub fn load_audio_file<P: AsRef<Path>>(path: P) -> Result<AudioData, AudioError> {
-    let file = File::open(&path)?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    load_audio_file_cancellable(path, &CancelToken::new())
+}
+
+/// `load_audio_file` that stops with `AudioError::Cancelled` at the next
+/// packet once `cancel` is set.
+pub fn load_audio_file_cancellable<P: AsRef<Path>>(
+    path: P,
+    cancel: &CancelToken,
+) -> Result<AudioData, AudioError> {
+    let mut all_samples = Vec::new();
 
-    // Format hint from extension
-    let mut hint = Hint::new();
-    if let Some(ext) = path.as_ref().extension() {
-        hint.with_extension(ext.to_str().unwrap_or(""));
+    let (sample_rate, channels, trim) = decode_audio_file(path, cancel, |decoded, channels| {
+        // Convert to f32 and mix to mono
+        let samples = convert_to_mono_f32(decoded, channels);
+        all_samples.extend(samples);
+    })?;
+    trim.apply(&mut all_samples);
+    zero_non_finite(&mut all_samples);
+
+    let duration_secs = all_samples.len() as f64 / sample_rate as f64;
+
+    Ok(AudioData {
+        samples: all_samples,
+        sample_rate,
+        duration_secs,
+        channels,
+    })
+}
+
+// Keeps every channel separate instead of mixing to mono
+pub fn load_audio_channels<P: AsRef<Path>>(path: P) -> Result<MultiChannelAudio, AudioError> {
+    load_audio_channels_cancellable(path, &CancelToken::new())
+}
+
+pub fn load_audio_channels_cancellable<P: AsRef<Path>>(
+    path: P,
+    cancel: &CancelToken,
+) -> Result<MultiChannelAudio, AudioError> {
+    let mut planar: Vec<Vec<f32>> = Vec::new();
+
+    let (sample_rate, _, trim) = decode_audio_file(path, cancel, |decoded, channels| {
+        if planar.is_empty() {
+            planar = vec![Vec::new(); channels.max(1) as usize];
+        }
+        append_planar_f32(decoded, &mut planar);
+    })?;
+    for channel in &mut planar {
+        trim.apply(channel);
+        zero_non_finite(channel);
     }
 
-    // Probe format
-    let format_opts = FormatOptions::default();
-    let metadata_opts = MetadataOptions::default();
-    let decoder_opts = DecoderOptions::default();
+    let frames = planar.first().map(|c| c.len()).unwrap_or(0);
 
-    let probed = symphonia::default::get_probe()
-        .format(&hint, mss, &format_opts, &metadata_opts)
-        .map_err(|_| AudioError::UnsupportedFormat)?;
+    Ok(MultiChannelAudio {
+        channels: planar,
+        sample_rate,
+        duration_secs: frames as f64 / sample_rate as f64,
+    })
+}
 
-    let mut format = probed.format;
-    let track = format.default_track().ok_or(AudioError::NoAudioData)?;
+/// Encoder delay and padding still in the decoded audio. With gapless
+/// decoding enabled symphonia trims MP3 (LAME/Xing) and Vorbis itself; AAC
+/// in MP4 carries them in the iTunes `iTunSMPB` tag, applied here, so lossy
+/// references line up with their lossless masters in length and level.
+#[derive(Debug, Clone, Copy, Default)]
+struct GaplessTrim {
+    delay: usize,          // Frames to drop from the start
+    frames: Option<usize>, // Frames of real audio after the delay
+}
 
-    let mut decoder = symphonia::default::get_codecs()
-        .make(&track.codec_params, &decoder_opts)
-        .map_err(|e| AudioError::DecodeError(e.to_string()))?;
+impl GaplessTrim {
+    // " 00000000 <delay> <padding> <frames> ..." in hex
+    fn from_itunes(value: &str) -> Option<Self> {
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        let hex = |i: usize| usize::from_str_radix(fields.get(i)?, 16).ok();
+        Some(Self {
+            delay: hex(1)?,
+            frames: hex(3).filter(|&f| f > 0),
+        })
+    }
 
-    let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
-    let channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(2) as u16;
+    fn apply(&self, samples: &mut Vec<f32>) {
+        samples.drain(..self.delay.min(samples.len()));
+        if let Some(frames) = self.frames {
+            samples.truncate(frames);
+        }
+    }
 
-    let mut all_samples = Vec::new();
+    // The part to keep of `len` frames starting `offset` frames into the
+    // decoded stream
+    fn range(&self, offset: usize, len: usize) -> Range<usize> {
+        let start = self.delay.saturating_sub(offset).min(len);
+        let end = match self.frames {
+            Some(frames) => (self.delay + frames).saturating_sub(offset).min(len),
+            None => len,
+        };
+        start..end.max(start)
+    }
+}
+
+fn itunes_gapless_trim(format: &mut dyn FormatReader) -> Option<GaplessTrim> {
+    let mut metadata = format.metadata();
+    let revision = metadata.skip_to_latest()?;
+    revision
+        .tags()
+        .iter()
+        .filter(|tag| tag.key.ends_with("iTunSMPB"))
+        .find_map(|tag| match &tag.value {
+            Value::String(value) => GaplessTrim::from_itunes(value),
+            _ => None,
+        })
+}
+
+// An open file, decoded one packet at a time
+struct PacketDecoder {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    sample_rate: u32,
+    channels: u16,
+    trim: GaplessTrim, // Still to apply to the decoded samples
+}
 
-    // Decode all packets
-    loop {
-        let packet = match format.next_packet() {
-            Ok(packet) => packet,
-            Err(_) => break,
+impl PacketDecoder {
+    fn open(path: &Path) -> Result<Self, AudioError> {
+        let source = open_media_source(path)?;
+        let mss = MediaSourceStream::new(source, Default::default());
+
+        // Format hint from extension
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension() {
+            hint.with_extension(ext.to_str().unwrap_or(""));
+        }
+
+        // Probe format
+        let format_opts = FormatOptions {
+            enable_gapless: true,
+            ..Default::default()
         };
+        let metadata_opts = MetadataOptions::default();
+        let decoder_opts = DecoderOptions::default();
 
-        let decoded = decoder
-            .decode(&packet)
+        let probed = symphonia::default::get_probe()
+            .format(&hint, mss, &format_opts, &metadata_opts)
+            .map_err(|_| AudioError::UnsupportedFormat)?;
+
+        let mut format = probed.format;
+        let track = format.default_track().ok_or(AudioError::NoAudioData)?;
+
+        let decoder = symphonia::default::get_codecs()
+            .make(&track.codec_params, &decoder_opts)
             .map_err(|e| AudioError::DecodeError(e.to_string()))?;
 
-        // Convert to f32 and mix to mono
-        let samples = convert_to_mono_f32(&decoded, channels);
-        all_samples.extend(samples);
+        let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
+        let channels = track.codec_params.channels.map(|c| c.count()).unwrap_or(2) as u16;
+        // A demuxer that knows the delay trims packets already
+        let trim = match track.codec_params.delay {
+            Some(_) => GaplessTrim::default(),
+            None => itunes_gapless_trim(format.as_mut()).unwrap_or_default(),
+        };
+
+        Ok(Self {
+            format,
+            decoder,
+            sample_rate,
+            channels,
+            trim,
+        })
     }
 
-    let duration_secs = all_samples.len() as f64 / sample_rate as f64;
+    // None at the end of the stream
+    fn next(&mut self, cancel: &CancelToken) -> Result<Option<AudioBufferRef<'_>>, AudioError> {
+        let Ok(packet) = self.format.next_packet() else {
+            return Ok(None);
+        };
+        if cancel.is_cancelled() {
+            return Err(AudioError::Cancelled);
+        }
+        self.decoder
+            .decode(&packet)
+            .map(Some)
+            .map_err(|e| AudioError::DecodeError(e.to_string()))
+    }
+}
 
-    Ok(AudioData {
-        samples: all_samples,
-        sample_rate,
-        duration_secs,
-        channels,
-    })
+// Decodes every packet and hands the buffers to `on_buffer`, checking
+// `cancel` between packets; returns (sample_rate, channels, trim still to
+// apply to the collected samples)
+fn decode_audio_file<P, F>(
+    path: P,
+    cancel: &CancelToken,
+    mut on_buffer: F,
+) -> Result<(u32, u16, GaplessTrim), AudioError>
+where
+    P: AsRef<Path>,
+    F: FnMut(&AudioBufferRef, u16),
+{
+    let mut packets = PacketDecoder::open(path.as_ref())?;
+    let channels = packets.channels;
+    while let Some(decoded) = packets.next(cancel)? {
+        on_buffer(&decoded, channels);
+    }
+
+    Ok((packets.sample_rate, channels, packets.trim))
+}
+
+/// The mono mixdown of `load_audio_file`, decoded a packet at a time with
+/// the gapless trim applied on the way, for analyses that never need the
+/// whole file in memory.
+pub struct MonoStream {
+    packets: PacketDecoder,
+    position: usize, // Frames decoded so far, before trimming
+}
+
+impl MonoStream {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, AudioError> {
+        Ok(Self {
+            packets: PacketDecoder::open(path.as_ref())?,
+            position: 0,
+        })
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.packets.sample_rate
+    }
+
+    /// The next packet's samples (possibly none, inside the encoder delay),
+    /// or None at the end of the file.
+    pub fn next_chunk(&mut self, cancel: &CancelToken) -> Result<Option<Vec<f32>>, AudioError> {
+        let channels = self.packets.channels;
+        let Some(decoded) = self.packets.next(cancel)? else {
+            return Ok(None);
+        };
+        let mut samples = convert_to_mono_f32(&decoded, channels);
+
+        let keep = self.packets.trim.range(self.position, samples.len());
+        self.position += samples.len();
+        samples.truncate(keep.end);
+        samples.drain(..keep.start);
+        zero_non_finite(&mut samples);
+        Ok(Some(samples))
+    }
+}
+
+// Float files can carry NaN or infinite samples; one is enough to turn
+// every FFT frame it touches into NaN
+fn zero_non_finite(samples: &mut [f32]) {
+    for sample in samples.iter_mut().filter(|s| !s.is_finite()) {
+        *sample = 0.0;
+    }
 }
 
 fn convert_to_mono_f32(audio_buf: &AudioBufferRef, channels: u16) -> Vec<f32> {
@@ -103,7 +312,12 @@ fn convert_to_mono_f32(audio_buf: &AudioBufferRef, channels: u16) -> Vec<f32> {
         AudioBufferRef::S32(buf) => convert_buffer_to_mono(buf, channels),
         AudioBufferRef::S16(buf) => convert_buffer_to_mono(buf, channels),
         AudioBufferRef::U8(buf) => convert_buffer_to_mono(buf, channels),
-        _ => Vec::new(),
+        AudioBufferRef::U16(buf) => convert_buffer_to_mono(buf, channels),
+        AudioBufferRef::U24(buf) => convert_buffer_to_mono(buf, channels),
+        AudioBufferRef::U32(buf) => convert_buffer_to_mono(buf, channels),
+        AudioBufferRef::S8(buf) => convert_buffer_to_mono(buf, channels),
+        AudioBufferRef::S24(buf) => convert_buffer_to_mono(buf, channels),
+        AudioBufferRef::F64(buf) => convert_buffer_to_mono(buf, channels),
     }
 }
 
@@ -136,73 +350,162 @@ where
     mono
 }
 
+fn append_planar_f32(audio_buf: &AudioBufferRef, out: &mut [Vec<f32>]) {
+    match audio_buf {
+        AudioBufferRef::F32(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::S32(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::S16(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::U8(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::U16(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::U24(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::U32(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::S8(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::S24(buf) => append_buffer_planar(buf, out),
+        AudioBufferRef::F64(buf) => append_buffer_planar(buf, out),
+    }
+}
+
+fn append_buffer_planar<S>(buf: &AudioBuffer<S>, out: &mut [Vec<f32>])
+where
+    S: symphonia::core::sample::Sample,
+    f32: symphonia::core::conv::FromSample<S>,
+{
+    use symphonia::core::conv::FromSample;
+
+    let available = buf.spec().channels.count();
+    for (ch, channel) in out.iter_mut().enumerate().take(available) {
+        channel.extend(buf.chan(ch).iter().map(|&s| f32::from_sample(s)));
+    }
+}
+
 // Resampling for matching sample rates
 pub fn resample_audio(
     samples: &[f32],
     from_rate: u32,
     to_rate: u32,
+) -> Result<Vec<f32>, AudioError> {
+    resample_audio_cancellable(samples, from_rate, to_rate, &CancelToken::new())
+}
+
+// `resample_audio`, checking `cancel` before each chunk
+pub fn resample_audio_cancellable(
+    samples: &[f32],
+    from_rate: u32,
+    to_rate: u32,
+    cancel: &CancelToken,
 ) -> Result<Vec<f32>, AudioError> {
     if from_rate == to_rate {
         return Ok(samples.to_vec());
     }
 
-    use rubato::{
-        Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
-    };
+    let chunk_size = RESAMPLE_CHUNK.min(samples.len().max(1));
+    let mut resampler = StreamResampler::new(from_rate, to_rate, chunk_size)?;
+    let mut out = Vec::with_capacity(
+        ((samples.len() as u64 * to_rate as u64) / from_rate as u64 + 1024) as usize,
+    );
+    for chunk in samples.chunks(chunk_size) {
+        if cancel.is_cancelled() {
+            return Err(AudioError::Cancelled);
+        }
+        resampler.push(chunk, &mut out)?;
+    }
+    resampler.finish(&mut out)?;
 
-    let params = SincInterpolationParameters {
-        sinc_len: 256,
-        f_cutoff: 0.95,
-        interpolation: SincInterpolationType::Linear,
-        oversampling_factor: 256,
-        window: WindowFunction::BlackmanHarris2,
-    };
+    Ok(out)
+}
 
-    // Process in chunks to avoid allocating/copying the full file into rubato buffers.
-    // `SincFixedIn` requires fixed-size input chunks.
-    let chunk_size = 16_384usize.min(samples.len().max(1));
+// Input frames rubato takes per call
+pub const RESAMPLE_CHUNK: usize = 16_384;
+
+/// Mono resampler fed in pieces of any size, for audio that never sits in
+/// memory whole. Output matches `resample_audio` on the concatenated input
+/// when `chunk_size` is the same.
+pub struct StreamResampler {
+    resampler: rubato::SincFixedIn<f32>,
+    pending: Vec<f32>, // Input short of a full chunk
+    wave_in: Vec<Vec<f32>>,
+    wave_out: Vec<Vec<f32>>,
+}
 
-    let mut resampler = SincFixedIn::<f32>::new(
-        to_rate as f64 / from_rate as f64,
-        2.0,
-        params,
-        chunk_size,
-        1,
-    )
-    .map_err(|e| AudioError::DecodeError(format!("Resampling error: {}", e)))?;
+impl StreamResampler {
+    pub fn new(from_rate: u32, to_rate: u32, chunk_size: usize) -> Result<Self, AudioError> {
+        use rubato::{
+            Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType,
+            WindowFunction,
+        };
 
-    let mut wave_in = vec![vec![0.0f32; resampler.input_frames_next()]];
-    let mut wave_out = vec![vec![0.0f32; resampler.output_frames_max()]];
+        let params = SincInterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: SincInterpolationType::Linear,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+        };
 
-    let mut out = Vec::with_capacity(
-        ((samples.len() as u64 * to_rate as u64) / from_rate as u64 + 1024) as usize,
-    );
+        // `SincFixedIn` requires fixed-size input chunks
+        let resampler = SincFixedIn::<f32>::new(
+            to_rate as f64 / from_rate as f64,
+            2.0,
+            params,
+            chunk_size,
+            1,
+        )
+        .map_err(|e| AudioError::DecodeError(format!("Resampling error: {}", e)))?;
 
-    let mut offset = 0usize;
-    while offset + wave_in[0].len() <= samples.len() {
-        let in_len = wave_in[0].len();
-        wave_in[0].copy_from_slice(&samples[offset..offset + in_len]);
-        let (_, out_len) = resampler
-            .process_into_buffer(&wave_in, &mut wave_out, None)
-            .map_err(|e| AudioError::DecodeError(format!("Resampling error: {}", e)))?;
-        out.extend_from_slice(&wave_out[0][..out_len]);
-        offset += in_len;
+        Ok(Self {
+            wave_in: vec![vec![0.0f32; resampler.input_frames_next()]],
+            wave_out: vec![vec![0.0f32; resampler.output_frames_max()]],
+            pending: Vec::with_capacity(chunk_size),
+            resampler,
+        })
     }
 
-    // Handle the last partial chunk and flush delayed frames.
-    let remaining = &samples[offset..];
-    if !remaining.is_empty() {
-        let (_, out_len) = resampler
-            .process_partial_into_buffer(Some(&[remaining]), &mut wave_out, None)
+    pub fn push(&mut self, samples: &[f32], out: &mut Vec<f32>) -> Result<(), AudioError> {
+        use rubato::Resampler;
+
+        self.pending.extend_from_slice(samples);
+        let in_len = self.wave_in[0].len();
+        let mut offset = 0usize;
+        while offset + in_len <= self.pending.len() {
+            self.wave_in[0].copy_from_slice(&self.pending[offset..offset + in_len]);
+            let (_, out_len) = self
+                .resampler
+                .process_into_buffer(&self.wave_in, &mut self.wave_out, None)
+                .map_err(|e| AudioError::DecodeError(format!("Resampling error: {}", e)))?;
+            out.extend_from_slice(&self.wave_out[0][..out_len]);
+            offset += in_len;
+        }
+        self.pending.drain(..offset);
+        Ok(())
+    }
+
+    // The last partial chunk and the frames still delayed in the filter
+    pub fn finish(mut self, out: &mut Vec<f32>) -> Result<(), AudioError> {
+        use rubato::Resampler;
+
+        if !self.pending.is_empty() {
+            let (_, out_len) = self
+                .resampler
+                .process_partial_into_buffer(Some(&[&self.pending]), &mut self.wave_out, None)
+                .map_err(|e| AudioError::DecodeError(format!("Resampling error: {}", e)))?;
+            out.extend_from_slice(&self.wave_out[0][..out_len]);
+        }
+        let (_, out_len) = self
+            .resampler
+            .process_partial_into_buffer::<Vec<f32>, Vec<f32>>(None, &mut self.wave_out, None)
             .map_err(|e| AudioError::DecodeError(format!("Resampling error: {}", e)))?;
-        out.extend_from_slice(&wave_out[0][..out_len]);
+        out.extend_from_slice(&self.wave_out[0][..out_len]);
+        Ok(())
     }
-    let (_, out_len) = resampler
-        .process_partial_into_buffer::<Vec<f32>, Vec<f32>>(None, &mut wave_out, None)
-        .map_err(|e| AudioError::DecodeError(format!("Resampling error: {}", e)))?;
-    out.extend_from_slice(&wave_out[0][..out_len]);
+}
 
-    Ok(out)
+/// The rate `policy` analyzes audio at `from_rate` in.
+pub fn analysis_rate(from_rate: u32, target_rate: u32, policy: ResamplePolicy) -> u32 {
+    match policy {
+        ResamplePolicy::Never => from_rate,
+        ResamplePolicy::DownsampleOnly => from_rate.min(target_rate),
+        ResamplePolicy::Always => target_rate,
+    }
 }
 
 pub fn prepare_audio_for_analysis(
@@ -210,28 +513,15 @@ pub fn prepare_audio_for_analysis(
     from_rate: u32,
     target_rate: u32,
     policy: ResamplePolicy,
+    cancel: &CancelToken,
 ) -> Result<(Vec<f32>, u32), AudioError> {
-    match policy {
-        ResamplePolicy::Never => Ok((samples, from_rate)),
-        ResamplePolicy::DownsampleOnly => {
-            if from_rate > target_rate {
-                Ok((
-                    resample_audio(&samples, from_rate, target_rate)?,
-                    target_rate,
-                ))
-            } else {
-                Ok((samples, from_rate))
-            }
-        }
-        ResamplePolicy::Always => {
-            if from_rate != target_rate {
-                Ok((
-                    resample_audio(&samples, from_rate, target_rate)?,
-                    target_rate,
-                ))
-            } else {
-                Ok((samples, from_rate))
-            }
-        }
+    let rate = analysis_rate(from_rate, target_rate, policy);
+    if rate == from_rate {
+        Ok((samples, from_rate))
+    } else {
+        Ok((
+            resample_audio_cancellable(&samples, from_rate, rate, cancel)?,
+            rate,
+        ))
     }
 }
@@ -0,0 +1,93 @@
+use super::curve::ResponseCurve;
+
+/// Monitoring level the fixed weighting table was tuned for (dB SPL).
+pub const REFERENCE_LISTENING_LEVEL_DB: f32 = 83.0;
+
+// ISO 226:2003 only defines contours between these loudness levels
+const MIN_PHON: f32 = 20.0;
+const MAX_PHON: f32 = 90.0;
+
+// ISO 226:2003 table 1: frequency, exponent a_f, magnitude L_U, threshold T_f
+const ISO_226: [(f32, f32, f32, f32); 29] = [
+    (20.0, 0.532, -31.6, 78.5),
+    (25.0, 0.506, -27.2, 68.7),
+    (31.5, 0.480, -23.0, 59.5),
+    (40.0, 0.455, -19.1, 51.1),
+    (50.0, 0.432, -15.9, 44.0),
+    (63.0, 0.409, -13.0, 37.5),
+    (80.0, 0.387, -10.3, 31.5),
+    (100.0, 0.367, -8.1, 26.5),
+    (125.0, 0.349, -6.2, 22.1),
+    (160.0, 0.330, -4.5, 17.9),
+    (200.0, 0.315, -3.1, 14.4),
+    (250.0, 0.301, -2.0, 11.4),
+    (315.0, 0.288, -1.1, 8.6),
+    (400.0, 0.276, -0.4, 6.2),
+    (500.0, 0.267, 0.0, 4.4),
+    (630.0, 0.259, 0.3, 3.0),
+    (800.0, 0.253, 0.5, 2.2),
+    (1000.0, 0.250, 0.0, 2.4),
+    (1250.0, 0.246, -2.7, 3.5),
+    (1600.0, 0.244, -4.1, 1.7),
+    (2000.0, 0.243, -1.0, -1.3),
+    (2500.0, 0.243, 1.7, -4.2),
+    (3150.0, 0.243, 2.5, -6.0),
+    (4000.0, 0.242, 1.2, -5.4),
+    (5000.0, 0.242, -2.1, -1.5),
+    (6300.0, 0.245, -7.1, 6.0),
+    (8000.0, 0.254, -11.2, 12.6),
+    (10000.0, 0.271, -10.7, 13.9),
+    (12500.0, 0.301, -3.1, 12.3),
+];
+
+// Correction weights at the reference level, from the equal-loudness
+// contours around it: most weight where the ear is most sensitive
+const REFERENCE_WEIGHTS: [(f32, f32); 10] = [
+    (31.5, 0.6),
+    (63.0, 0.7),
+    (125.0, 0.85),
+    (250.0, 0.95),
+    (500.0, 1.1),
+    (1000.0, 1.3),  // Most sensitive
+    (2000.0, 1.35), // Presence
+    (4000.0, 1.25), // Sibilance range
+    (8000.0, 1.0),
+    (16000.0, 0.7),
+];
+
+/// SPL (dB) a tone at the table row needs to sound as loud as `phon`.
+fn contour_spl((_, af, lu, tf): (f32, f32, f32, f32), phon: f32) -> f32 {
+    let af_total = 4.47e-3 * (10f32.powf(0.025 * phon) - 1.15)
+        + (0.4 * 10f32.powf((tf + lu) / 10.0 - 9.0)).powf(af);
+    10.0 / af * af_total.log10() - lu + 94.0
+}
+
+/// How many dB louder than a 1 kHz tone `freq` has to be to sound equally
+/// loud at `phon`. Follows ISO 226:2003, interpolated on a log-frequency
+/// axis and held flat past its 20 Hz - 12.5 kHz range.
+pub fn contour_elevation_db(freq: f32, phon: f32) -> f32 {
+    let phon = if phon.is_finite() {
+        phon.clamp(MIN_PHON, MAX_PHON)
+    } else {
+        REFERENCE_LISTENING_LEVEL_DB
+    };
+    let at_1k = contour_spl(ISO_226[17], phon);
+    let curve = ResponseCurve::new(
+        ISO_226
+            .iter()
+            .map(|&row| (row.0, contour_spl(row, phon) - at_1k))
+            .collect(),
+    );
+    curve.gain_at(freq)
+}
+
+/// Weight for a correction at `freq` heard at `listening_level_db` SPL.
+/// Quieter monitoring raises the contours in the lows and highs, so the
+/// weight there falls by the extra elevation relative to the reference
+/// level; louder monitoring flattens them and the weight rises.
+pub fn psychoacoustic_weight(freq: f32, listening_level_db: f32) -> f32 {
+    let base = ResponseCurve::new(REFERENCE_WEIGHTS.to_vec()).gain_at(freq);
+    let extra_db = contour_elevation_db(freq, listening_level_db)
+        - contour_elevation_db(freq, REFERENCE_LISTENING_LEVEL_DB);
+    base * 10f32.powf(-extra_db / 20.0)
+}
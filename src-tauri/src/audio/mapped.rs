@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::io::{self, Cursor};
+use std::path::Path;
+use symphonia::core::io::MediaSource;
+
+// Smaller files are read through the page cache as before; mapping only
+// pays off once copying into the stream buffer adds up
+const MAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Decoder input for `path`. Large files are memory-mapped so the decoder
+/// reads straight from the page cache and the OS pages the file in and out
+/// during long decodes; small files, and platforms or files that can't be
+/// mapped, are read normally.
+pub fn open_media_source(path: &Path) -> io::Result<Box<dyn MediaSource>> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    if len >= MAP_THRESHOLD {
+        match MappedFile::map(&file, len) {
+            Ok(map) => return Ok(Box::new(Cursor::new(map))),
+            Err(e) => log::debug!("{}: not memory-mapped: {}", path.display(), e),
+        }
+    }
+    Ok(Box::new(file))
+}
+
+/// A read-only, private mapping of a whole file. Truncating the file while
+/// it is mapped makes reads past the new end fault, as with any mapping.
+pub struct MappedFile {
+    ptr: *const u8,
+    len: usize,
+}
+
+// The mapping is read-only and owned; nothing aliases it mutably
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+#[cfg(unix)]
+impl MappedFile {
+    fn map(file: &File, len: u64) -> io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = usize::try_from(len).map_err(|_| io::Error::other("file too large to map"))?;
+        // SAFETY: a fresh read-only private mapping of an open descriptor;
+        // the result is checked before use
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        // Decoding reads front to back; a failed hint is harmless
+        // SAFETY: advises on the mapping created above
+        unsafe { libc::madvise(ptr, len, libc::MADV_SEQUENTIAL) };
+
+        Ok(Self {
+            ptr: ptr as *const u8,
+            len,
+        })
+    }
+}
+
+#[cfg(not(unix))]
+impl MappedFile {
+    fn map(_file: &File, _len: u64) -> io::Result<Self> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        // SAFETY: `ptr` maps `len` readable bytes until drop
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        // SAFETY: unmaps exactly the mapping created in `map`
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.len);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use super::profile::FrequencyBand;
+
+// Simplified spreading function in dB per octave of distance from the masker
+const UPWARD_SPREAD_DB_PER_OCT: f32 = 12.0; // Masker below the band
+const DOWNWARD_SPREAD_DB_PER_OCT: f32 = 24.0; // Masker above the band
+const MASKING_OFFSET_DB: f32 = 6.0;
+const MIN_FACTOR: f32 = 0.25;
+
+/// Masking threshold per band produced by all other bands of the signal (dB, same scale as `levels`).
+pub fn masking_thresholds(bands: &[FrequencyBand], levels: &[f32]) -> Vec<f32> {
+    bands
+        .iter()
+        .enumerate()
+        .map(|(i, band)| {
+            bands
+                .iter()
+                .zip(levels)
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, (masker, &masker_level))| {
+                    let octaves = (band.frequency / masker.frequency).log2();
+                    let spread = if octaves > 0.0 {
+                        octaves * UPWARD_SPREAD_DB_PER_OCT
+                    } else {
+                        -octaves * DOWNWARD_SPREAD_DB_PER_OCT
+                    };
+                    masker_level - spread - MASKING_OFFSET_DB
+                })
+                .fold(f32::NEG_INFINITY, f32::max)
+        })
+        .collect()
+}
+
+// Corrections to bands sitting under the masking threshold are scaled down
+pub fn apply_masking_attenuation(bands: &mut [FrequencyBand], input_levels: &[f32]) {
+    let thresholds = masking_thresholds(bands, input_levels);
+
+    for ((band, &level), &threshold) in bands.iter_mut().zip(input_levels).zip(&thresholds) {
+        let masked_by = threshold - level;
+        if masked_by > 0.0 {
+            band.gain_db *= (1.0 - masked_by / 10.0).max(MIN_FACTOR);
+        }
+    }
+}
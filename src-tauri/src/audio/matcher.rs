@@ -1,13 +1,39 @@
-use super::profile::{EQProfile, FrequencyBand};
+use super::curve::ResponseCurve;
+use super::dynamics::{suggest_multiband_compression, MultibandCompressionSuggestion};
+use super::finite::{finite_or, flag_non_finite, non_finite_in_result, SILENCE_DB};
+use super::loudness::{psychoacoustic_weight, REFERENCE_LISTENING_LEVEL_DB};
+use super::masking::apply_masking_attenuation;
+use super::metering::k_weighting_db;
+use super::profile::{ChannelProfiles, EQProfile, FrequencyBand};
+use super::provenance::{settings_mismatch, ProfileSource};
+use super::resonance::{suggest_narrow_cuts, NarrowBandSuggestion};
+use super::room::{apply_room_limits, guard_nulls, RoomCorrectionConfig};
+use super::speech::{apply_speech_limits, SpeechConfig};
+use super::warnings::{MatchWarning, SuggestedAction, WarningCode, WarningSeverity};
+use crate::dsp::filters::FilterType;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchConfig {
-    pub intensity: f32,        // 0.0 - 1.0
-    pub max_correction: f32,   // Max ±dB per band
+    pub intensity: f32,      // 0.0 - 1.0
+    pub max_correction: f32, // Max ±dB per band
+    #[serde(default)]
+    pub max_correction_curve: Vec<(f32, f32)>, // (Hz, max ±dB) anchors; replaces max_correction when set
     pub smoothing_factor: f32, // 0.0 - 1.0
     pub use_psychoacoustic: bool,
     pub preserve_dynamics: bool, // Don't compress dynamic range
+    #[serde(default)]
+    pub use_masking: bool, // Attenuate corrections to masked bands
+    #[serde(default)]
+    pub room_correction: Option<RoomCorrectionConfig>, // Speaker/room mode
+    #[serde(default)]
+    pub speech: Option<SpeechConfig>, // Voice/podcast mode
+    #[serde(default = "default_listening_level_db")]
+    pub listening_level_db: f32, // Monitoring level (dB SPL) for psychoacoustic weighting
+}
+
+fn default_listening_level_db() -> f32 {
+    REFERENCE_LISTENING_LEVEL_DB
 }
 
 impl Default for MatchConfig {
@@ -15,9 +41,66 @@ impl Default for MatchConfig {
         Self {
             intensity: 0.7,
             max_correction: 6.0,
+            max_correction_curve: Vec::new(),
             smoothing_factor: 0.5,
             use_psychoacoustic: true,
             preserve_dynamics: true,
+            use_masking: false,
+            room_correction: None,
+            speech: None,
+            listening_level_db: default_listening_level_db(),
+        }
+    }
+}
+
+impl MatchConfig {
+    /// Correction limit at `frequency`: the curve interpolated in log
+    /// frequency and held flat past its ends, or `max_correction` without one.
+    pub fn max_correction_at(&self, frequency: f32) -> f32 {
+        if self.max_correction_curve.is_empty() {
+            return self.max_correction;
+        }
+        ResponseCurve::new(self.max_correction_curve.clone())
+            .gain_at(frequency)
+            .abs()
+    }
+
+    fn correction_limits(&self, bands: &[FrequencyBand]) -> Vec<f32> {
+        bands
+            .iter()
+            .map(|b| self.max_correction_at(b.frequency))
+            .collect()
+    }
+
+    // Preset for speaker/room use: Schroeder-aware limits, no null boosting
+    pub fn room_correction() -> Self {
+        Self {
+            intensity: 1.0,
+            max_correction: 10.0,
+            max_correction_curve: Vec::new(),
+            smoothing_factor: 0.3,
+            use_psychoacoustic: false,
+            preserve_dynamics: false,
+            use_masking: false,
+            room_correction: Some(RoomCorrectionConfig::default()),
+            speech: None,
+            listening_level_db: default_listening_level_db(),
+        }
+    }
+
+    // Preset for matching a voice to a produced speech reference
+    pub fn speech() -> Self {
+        Self {
+            intensity: 0.8,
+            max_correction: 6.0,
+            max_correction_curve: Vec::new(),
+            smoothing_factor: 0.6,
+            use_psychoacoustic: false,
+            preserve_dynamics: true,
+            use_masking: true,
+            room_correction: None,
+            speech: Some(SpeechConfig::default()),
+            listening_level_db: default_listening_level_db(),
         }
     }
 }
@@ -27,8 +110,67 @@ pub struct MatchResult {
     pub correction_profile: EQProfile,
     pub reference_normalized: Vec<f32>,
     pub input_normalized: Vec<f32>,
-    pub quality_score: f32, // 0.0 - 1.0
-    pub warnings: Vec<String>,
+    pub predicted_normalized: Vec<f32>, // Input after correction
+    pub residual_db: Vec<f32>,          // Target - predicted, per band
+    pub residual_rms_db: f32,
+    #[serde(default)]
+    pub band_uncertainty_db: Vec<f32>, // ± dB per correction band
+    pub correction_loudness_delta_db: f32, // Level change introduced by the correction
+    #[serde(default)]
+    pub band_loudness_contribution_db: Vec<f32>, // Per band: its share of the level change
+    pub output_gain_db: f32,               // Trim to match the reference level
+    pub quality_score: f32,                // 0.0 - 1.0
+    #[serde(default)]
+    pub quality_breakdown: QualityBreakdown,
+    pub warnings: Vec<MatchWarning>,
+    #[serde(default)]
+    pub narrow_band_suggestions: Vec<NarrowBandSuggestion>, // Resonance / de-essing cuts
+    #[serde(default)]
+    pub compression_suggestion: Option<MultibandCompressionSuggestion>, // When EQ alone can't match dynamics
+    #[serde(default)]
+    pub suggested_high_pass_hz: Option<f32>,
+    #[serde(default)]
+    pub channel_corrections: Option<ChannelProfiles>, // L/R or M/S, when both sides have them
+    #[serde(default)]
+    pub correction_steps: Vec<CorrectionStep>, // Gains after each stage that ran, in order
+    #[serde(default)]
+    pub reference_source: Option<Box<ProfileSource>>,
+    #[serde(default)]
+    pub input_source: Option<Box<ProfileSource>>,
+}
+
+/// Matching stages that change the per-band gains, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CorrectionStage {
+    Difference, // Normalized reference minus input
+    Psychoacoustic,
+    Masking,
+    Confidence,
+    Room,
+    Smoothing,
+    Intensity,
+    Limit, // Clamp to max_correction, plus excess spilled into neighbours
+    Speech,
+    Dynamics,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrectionStep {
+    pub stage: CorrectionStage,
+    pub gains_db: Vec<f32>, // Per band, after this stage
+}
+
+// score = (1 - correction_penalty - slope_penalty) * confidence_factor, clamped to 0..1
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityBreakdown {
+    pub score: f32,
+    pub average_correction_db: f32,
+    pub correction_penalty: f32, // 0.0 - 0.4
+    pub slope_penalty: f32,      // 0.05 per band pair steeper than 6 dB
+    pub average_confidence: f32,
+    pub confidence_factor: f32, // 0.7 - 1.0
+    pub residual_rms_db: f32,   // Not scored; what the correction leaves unmatched
 }
 
 pub fn match_profiles(
@@ -37,6 +179,13 @@ pub fn match_profiles(
     config: &MatchConfig,
 ) -> MatchResult {
     let mut warnings = Vec::new();
+    let mut steps = Vec::new();
+    let record = |steps: &mut Vec<CorrectionStep>, stage, bands: &[FrequencyBand]| {
+        steps.push(CorrectionStep {
+            stage,
+            gains_db: bands.iter().map(|b| b.gain_db).collect(),
+        })
+    };
 
     // 1. Normalize both profiles to their mean
     let ref_normalized = normalize_profile(reference);
@@ -51,55 +200,118 @@ pub fn match_profiles(
         .zip(&inp_normalized)
         .map(|(((ref_band, inp_band), &ref_norm), &inp_norm)| {
             let raw_diff = ref_norm - inp_norm;
+            // Past Nyquist on either side there is nothing reliable to match
+            let out_of_range = ref_band.out_of_range || inp_band.out_of_range;
+            // Nor in a band whose level is NaN or infinite
+            let unusable =
+                out_of_range || !(ref_band.gain_db.is_finite() && inp_band.gain_db.is_finite());
 
             FrequencyBand {
                 frequency: ref_band.frequency,
-                gain_db: raw_diff,
+                gain_db: if unusable { 0.0 } else { raw_diff },
                 bandwidth: ref_band.bandwidth,
-                confidence: (ref_band.confidence + inp_band.confidence) / 2.0,
+                confidence: if unusable {
+                    0.0
+                } else {
+                    finite_or((ref_band.confidence + inp_band.confidence) / 2.0, 0.0)
+                },
+                temporal_spread_db: finite_or(
+                    (ref_band.temporal_spread_db.powi(2) + inp_band.temporal_spread_db.powi(2))
+                        .sqrt(),
+                    0.0,
+                ),
+                filter_type: FilterType::Peaking,
+                out_of_range,
             }
         })
         .collect();
+    record(&mut steps, CorrectionStage::Difference, &corrections);
 
     // 3. Apply psychoacoustic weighting
     if config.use_psychoacoustic {
-        apply_psychoacoustic_weighting(&mut corrections);
+        apply_psychoacoustic_weighting(&mut corrections, config.listening_level_db);
+        record(&mut steps, CorrectionStage::Psychoacoustic, &corrections);
+    }
+
+    // 3b. Attenuate corrections nobody can hear
+    if config.use_masking {
+        apply_masking_attenuation(&mut corrections, &inp_normalized);
+        record(&mut steps, CorrectionStage::Masking, &corrections);
     }
 
     // 4. Confidence-based attenuation
     apply_confidence_weighting(&mut corrections);
+    record(&mut steps, CorrectionStage::Confidence, &corrections);
+
+    // 4b. Room mode: Schroeder-aware limits
+    let mut nulls = Vec::new();
+    if let Some(room) = &config.room_correction {
+        nulls = apply_room_limits(&mut corrections, &inp_normalized, room);
+        record(&mut steps, CorrectionStage::Room, &corrections);
+    }
 
     // 5. Smoothing across frequency bands
     if config.smoothing_factor > 0.0 {
         smooth_corrections(&mut corrections, config.smoothing_factor);
+        guard_nulls(&mut corrections, &nulls);
+        record(&mut steps, CorrectionStage::Smoothing, &corrections);
     }
 
     // 6. Apply intensity scaling
     for band in &mut corrections {
         band.gain_db *= config.intensity;
     }
+    record(&mut steps, CorrectionStage::Intensity, &corrections);
+
+    // 7. Limiting, spilling part of the clipped excess into neighbouring bands
+    let limits = config.correction_limits(&corrections);
+    let excess: Vec<f32> = corrections
+        .iter_mut()
+        .zip(&limits)
+        .map(|(band, &limit)| {
+            let original = band.gain_db;
+            band.gain_db = band.gain_db.clamp(-limit, limit);
+            original - band.gain_db
+        })
+        .collect();
 
-    // 7. Limiting
-    for band in &mut corrections {
-        let original = band.gain_db;
-        band.gain_db = band
-            .gain_db
-            .clamp(-config.max_correction, config.max_correction);
-
-        if (original - band.gain_db).abs() > 0.1 {
-            warnings.push(format!(
-                "{} Hz: Correction limited from {:.1} dB to {:.1} dB",
-                band.frequency, original, band.gain_db
-            ));
+    for (band, &clipped) in corrections.iter().zip(&excess) {
+        if clipped.abs() > 0.1 {
+            let half_bw = band.bandwidth / 2.0;
+            warnings.push(
+                MatchWarning::new(
+                    WarningCode::CorrectionLimited,
+                    WarningSeverity::Warning,
+                    format!(
+                        "{} Hz: Correction limited from {:.1} dB to {:.1} dB",
+                        band.frequency,
+                        band.gain_db + clipped,
+                        band.gain_db
+                    ),
+                )
+                .with_range(band.frequency - half_bw, band.frequency + half_bw)
+                .with_action(SuggestedAction::RaiseMaxCorrection),
+            );
         }
     }
+    redistribute_excess(&mut corrections, &excess, &limits);
+    guard_nulls(&mut corrections, &nulls);
+    record(&mut steps, CorrectionStage::Limit, &corrections);
+
+    // 7b. Speech mode: band limits, importance weighting, intelligibility protection
+    if let Some(speech) = &config.speech {
+        apply_speech_limits(&mut corrections, speech);
+        record(&mut steps, CorrectionStage::Speech, &corrections);
+    }
 
     // 8. Check for extreme corrections
     check_for_extreme_corrections(&corrections, &mut warnings);
 
     // 9. Dynamic range preservation
     let correction_profile = if config.preserve_dynamics {
-        preserve_dynamic_range(reference, input, corrections)
+        let profile = preserve_dynamic_range(reference, input, corrections);
+        record(&mut steps, CorrectionStage::Dynamics, &profile.bands);
+        profile
     } else {
         EQProfile {
             bands: corrections,
@@ -107,53 +319,367 @@ pub fn match_profiles(
             dynamic_range: reference.dynamic_range,
             spectral_centroid: reference.spectral_centroid,
             spectral_rolloff: reference.spectral_rolloff,
+            channels: None,
+            resonance: Default::default(),
+            source: None,
         }
     };
 
-    // 10. Calculate quality score
-    let quality_score = calculate_match_quality(&correction_profile);
+    // 9b. EQ can't fix a large dynamics gap; say what would
+    let compression_suggestion = if config.preserve_dynamics {
+        suggest_multiband_compression(reference, input)
+    } else {
+        None
+    };
+    if let Some(suggestion) = &compression_suggestion {
+        warnings.push(MatchWarning::new(
+            WarningCode::DynamicsMismatch,
+            WarningSeverity::Info,
+            format!(
+                "Reference dynamic range ({:.1} dB) is much smaller than the input's ({:.1} dB); \
+                 EQ alone won't close the gap, see the multiband compression suggestion.",
+                suggestion.reference_dynamic_range, suggestion.input_dynamic_range
+            ),
+        ));
+    }
+
+    // 9c. Band levels from different FFT settings or rates differ by themselves
+    let mismatch = settings_mismatch(reference, input);
+    if !mismatch.is_empty() {
+        warnings.push(MatchWarning::new(
+            WarningCode::AnalysisMismatch,
+            WarningSeverity::Warning,
+            format!(
+                "Reference and input were analyzed with different settings ({}); \
+                 reload one of them so both use the same analysis.",
+                mismatch.join(", ")
+            ),
+        ));
+    }
+
+    // 10-12. What is left, the level change and the quality score
+    let Derived {
+        predicted_normalized,
+        residual_db,
+        residual_rms_db,
+        correction_loudness_delta_db,
+        band_loudness_contribution_db,
+        output_gain_db,
+        quality_breakdown,
+    } = derive(
+        reference,
+        input,
+        &ref_normalized,
+        &inp_normalized,
+        &correction_profile,
+    );
+    let quality_score = quality_breakdown.score;
+
+    // 10b. How speculative each move is
+    let band_uncertainty_db = estimate_uncertainty(&correction_profile, config);
+
+    // 13. Independent corrections per channel pair
+    let channel_corrections = match (&reference.channels, &input.channels) {
+        (Some(ref_ch), Some(inp_ch)) if ref_ch.mode == inp_ch.mode => Some(ChannelProfiles {
+            mode: ref_ch.mode,
+            first: match_profiles(&ref_ch.first, &inp_ch.first, config).correction_profile,
+            second: match_profiles(&ref_ch.second, &inp_ch.second, config).correction_profile,
+        }),
+        _ => None,
+    };
 
-    MatchResult {
+    let result = MatchResult {
         correction_profile,
         reference_normalized: ref_normalized,
         input_normalized: inp_normalized,
+        predicted_normalized,
+        residual_db,
+        residual_rms_db,
+        band_uncertainty_db,
+        correction_loudness_delta_db,
+        band_loudness_contribution_db,
+        output_gain_db,
         quality_score,
+        quality_breakdown,
         warnings,
+        narrow_band_suggestions: suggest_narrow_cuts(&reference.resonance, &input.resonance),
+        compression_suggestion,
+        suggested_high_pass_hz: config.speech.as_ref().map(|s| s.high_pass_hz),
+        channel_corrections,
+        correction_steps: steps,
+        reference_source: reference.source.clone(),
+        input_source: input.source.clone(),
+    };
+    flag_non_finite("match result", &non_finite_in_result(&result));
+    result
+}
+
+// Everything that follows from the correction gains
+struct Derived {
+    predicted_normalized: Vec<f32>,
+    residual_db: Vec<f32>,
+    residual_rms_db: f32,
+    correction_loudness_delta_db: f32,
+    band_loudness_contribution_db: Vec<f32>,
+    output_gain_db: f32,
+    quality_breakdown: QualityBreakdown,
+}
+
+fn derive(
+    reference: &EQProfile,
+    input: &EQProfile,
+    ref_normalized: &[f32],
+    inp_normalized: &[f32],
+    correction_profile: &EQProfile,
+) -> Derived {
+    // 10. Predict the corrected input and measure what is left
+    let predicted_normalized = predict_corrected(inp_normalized, correction_profile);
+    let residual_db: Vec<f32> = ref_normalized
+        .iter()
+        .zip(&predicted_normalized)
+        .map(|(&target, &predicted)| target - predicted)
+        .collect();
+    let residual_rms_db = calculate_rms(&residual_db);
+
+    // 11. Level match against the reference
+    let band_loudness_contribution_db = band_loudness_contributions(input, correction_profile);
+    let correction_loudness_delta_db = band_loudness_contribution_db.iter().sum::<f32>();
+    // Silence on either side has no level to match, only a huge trim
+    let output_gain_db = if reference.overall_loudness <= SILENCE_DB
+        || input.overall_loudness <= SILENCE_DB
+    {
+        0.0
+    } else {
+        finite_or(
+            k_weighted_level(reference) - (k_weighted_level(input) + correction_loudness_delta_db),
+            0.0,
+        )
+    };
+
+    // 12. Calculate quality score
+    let quality_breakdown = calculate_match_quality(correction_profile, residual_rms_db);
+
+    Derived {
+        predicted_normalized,
+        residual_db,
+        residual_rms_db,
+        correction_loudness_delta_db,
+        band_loudness_contribution_db,
+        output_gain_db,
+        quality_breakdown,
     }
 }
 
+/// Recomputes the prediction, residual, level match and quality after the
+/// correction gains were changed by hand. `reference` and `input` are the
+/// profiles the result was matched from.
+pub fn refresh_derived(result: &mut MatchResult, reference: &EQProfile, input: &EQProfile) {
+    let derived = derive(
+        reference,
+        input,
+        &result.reference_normalized,
+        &result.input_normalized,
+        &result.correction_profile,
+    );
+    result.predicted_normalized = derived.predicted_normalized;
+    result.residual_db = derived.residual_db;
+    result.residual_rms_db = derived.residual_rms_db;
+    result.correction_loudness_delta_db = derived.correction_loudness_delta_db;
+    result.band_loudness_contribution_db = derived.band_loudness_contribution_db;
+    result.output_gain_db = derived.output_gain_db;
+    result.quality_score = derived.quality_breakdown.score;
+    result.quality_breakdown = derived.quality_breakdown;
+}
+
+/// Weighted blend of two references ("70% A, 30% B"). Band shapes are mixed after
+/// normalization so a louder reference doesn't dominate the tonal balance.
+pub fn blend_profiles(a: &EQProfile, b: &EQProfile, weight_a: f32) -> Result<EQProfile, String> {
+    if a.bands.len() != b.bands.len()
+        || a.bands
+            .iter()
+            .zip(&b.bands)
+            .any(|(x, y)| (x.frequency - y.frequency).abs() > 0.01)
+    {
+        return Err("References were analyzed with different band grids".to_string());
+    }
+
+    let wa = weight_a.clamp(0.0, 1.0);
+    let wb = 1.0 - wa;
+    let mix = |x: f32, y: f32| x * wa + y * wb;
+
+    let a_norm = normalize_profile(a);
+    let b_norm = normalize_profile(b);
+    let mean_level = mix(mean_gain(a), mean_gain(b));
+
+    let bands = a
+        .bands
+        .iter()
+        .zip(&b.bands)
+        .zip(a_norm.iter().zip(&b_norm))
+        .map(|((band_a, band_b), (&norm_a, &norm_b))| FrequencyBand {
+            frequency: band_a.frequency,
+            gain_db: mix(norm_a, norm_b) + mean_level,
+            bandwidth: band_a.bandwidth,
+            confidence: mix(band_a.confidence, band_b.confidence),
+            temporal_spread_db: mix(band_a.temporal_spread_db, band_b.temporal_spread_db),
+            filter_type: band_a.filter_type,
+            out_of_range: band_a.out_of_range || band_b.out_of_range,
+        })
+        .collect();
+
+    Ok(EQProfile {
+        bands,
+        overall_loudness: mix(a.overall_loudness, b.overall_loudness),
+        dynamic_range: mix(a.dynamic_range, b.dynamic_range),
+        spectral_centroid: mix(a.spectral_centroid, b.spectral_centroid),
+        spectral_rolloff: mix(a.spectral_rolloff, b.spectral_rolloff),
+        channels: None,
+        resonance: Default::default(),
+        source: None,
+    })
+}
+
+fn mean_gain(profile: &EQProfile) -> f32 {
+    profile.bands.iter().map(|b| b.gain_db).sum::<f32>() / profile.bands.len().max(1) as f32
+}
+
+// Out-of-range bands would drag the mean down, so it comes from the rest
 fn normalize_profile(profile: &EQProfile) -> Vec<f32> {
-    let gains: Vec<f32> = profile.bands.iter().map(|b| b.gain_db).collect();
-    let mean = gains.iter().sum::<f32>() / gains.len() as f32;
+    // NaN or infinite bands read as the mean and stay out of it
+    let finite = profile.bands.iter().filter(|b| b.gain_db.is_finite());
+    let in_range: Vec<f32> = finite
+        .clone()
+        .filter(|b| !b.out_of_range)
+        .map(|b| b.gain_db)
+        .collect();
+    let basis = if in_range.is_empty() {
+        finite.map(|b| b.gain_db).collect()
+    } else {
+        in_range
+    };
+    let mean = basis.iter().sum::<f32>() / basis.len().max(1) as f32;
 
-    gains.iter().map(|&g| g - mean).collect()
+    profile
+        .bands
+        .iter()
+        .map(|b| finite_or(b.gain_db - mean, 0.0))
+        .collect()
 }
 
-// Fletcher-Munson inspired weighting
-fn apply_psychoacoustic_weighting(bands: &mut [FrequencyBand]) {
-    let weights = calculate_psychoacoustic_weights();
+fn predict_corrected(input_normalized: &[f32], correction: &EQProfile) -> Vec<f32> {
+    input_normalized
+        .iter()
+        .zip(&correction.bands)
+        .map(|(&inp, band)| inp + band.gain_db)
+        .collect()
+}
 
-    for (band, weight) in bands.iter_mut().zip(weights.iter()) {
-        // More weight to frequencies we're sensitive to
-        band.gain_db *= weight;
+// Combined level spread of both signals, widened for low-confidence bands
+fn estimate_uncertainty(correction: &EQProfile, config: &MatchConfig) -> Vec<f32> {
+    correction
+        .bands
+        .iter()
+        .map(|band| {
+            let confidence = band.confidence.clamp(0.0, 1.0);
+            (band.temporal_spread_db * (2.0 - confidence) * config.intensity)
+                .min(config.max_correction_at(band.frequency))
+        })
+        .collect()
+}
+
+fn calculate_rms(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    (values.iter().map(|v| v * v).sum::<f32>() / values.len() as f32).sqrt()
+}
+
+// Change in K-weighted level, in LU
+fn estimate_loudness_delta(input: &EQProfile, correction: &EQProfile) -> f32 {
+    let mut before = 0.0;
+    let mut after = 0.0;
+
+    for (inp, corr) in input.bands.iter().zip(&correction.bands) {
+        let energy = k_weighted_energy(inp);
+        before += energy;
+        after += energy * 10.0f32.powf(corr.gain_db / 10.0);
     }
+
+    if before <= 0.0 {
+        return 0.0;
+    }
+
+    finite_or(10.0 * (after / before).log10(), 0.0)
+}
+
+// Each band's share of the level change, in proportion to the K-weighted
+// energy its gain adds or removes; the shares add up to the whole change. A
+// big boost where the input has little energy barely registers.
+fn band_loudness_contributions(input: &EQProfile, correction: &EQProfile) -> Vec<f32> {
+    let changes: Vec<f32> = correction
+        .bands
+        .iter()
+        .enumerate()
+        .map(|(i, corr)| match input.bands.get(i) {
+            Some(inp) => k_weighted_energy(inp) * (10.0f32.powf(corr.gain_db / 10.0) - 1.0),
+            None => 0.0,
+        })
+        .collect();
+    let before: f32 = input
+        .bands
+        .iter()
+        .zip(&correction.bands)
+        .map(|(inp, _)| k_weighted_energy(inp))
+        .sum();
+    if before <= 0.0 {
+        return vec![0.0; changes.len()];
+    }
+
+    // dB per unit of energy; boosts and cuts that cancel out take the slope at 0
+    let total: f32 = changes.iter().sum();
+    let delta = estimate_loudness_delta(input, correction);
+    let per_energy = if total.abs() > before * 1e-4 {
+        delta / total
+    } else {
+        10.0 / (std::f32::consts::LN_10 * before)
+    };
+    changes
+        .iter()
+        .map(|change| finite_or(change * per_energy, 0.0))
+        .collect()
+}
+
+fn band_energy(band: &FrequencyBand) -> f32 {
+    10.0f32.powf(band.gain_db / 10.0)
 }
 
-fn calculate_psychoacoustic_weights() -> Vec<f32> {
-    // Based on ISO 226:2003 equal-loudness contours
-    // Frequencies: 31, 63, 125, 250, 500, 1k, 2k, 4k, 8k, 16k
-    vec![
-        0.6,  // 31 Hz - less sensitive
-        0.7,  // 63 Hz
-        0.85, // 125 Hz
-        0.95, // 250 Hz
-        1.1,  // 500 Hz - more sensitive
-        1.3,  // 1 kHz - most sensitive
-        1.35, // 2 kHz - most sensitive (presence)
-        1.25, // 4 kHz - sibilance range
-        1.0,  // 8 kHz
-        0.7,  // 16 kHz - less sensitive
-    ]
+// Band levels are a mean over the band, so the energy scales with its width;
+// the K curve then weights it as a loudness meter would
+fn k_weighted_energy(band: &FrequencyBand) -> f32 {
+    band_energy(band)
+        * band.bandwidth.max(0.0)
+        * 10.0f32.powf(k_weighting_db(band.frequency) / 10.0)
+}
+
+// Level the loudness meter would read, up to a constant offset that cancels
+// out between two profiles
+fn k_weighted_level(profile: &EQProfile) -> f32 {
+    10.0 * profile
+        .bands
+        .iter()
+        .map(k_weighted_energy)
+        .sum::<f32>()
+        .max(1e-20)
+        .log10()
+}
+
+// Equal-loudness weighting at the monitoring level
+fn apply_psychoacoustic_weighting(bands: &mut [FrequencyBand], listening_level_db: f32) {
+    for band in bands.iter_mut() {
+        // More weight to frequencies we're sensitive to
+        band.gain_db *= psychoacoustic_weight(band.frequency, listening_level_db);
+    }
 }
 
 fn apply_confidence_weighting(bands: &mut [FrequencyBand]) {
@@ -176,6 +702,9 @@ fn smooth_corrections(bands: &mut [FrequencyBand], factor: f32) {
         let weight = factor * (0.7f32).powi(pass); // Decreasing weight each pass
 
         for i in 1..bands.len() - 1 {
+            if bands[i].out_of_range {
+                continue;
+            }
             let prev = original[i - 1];
             let curr = original[i];
             let next = original[i + 1];
@@ -187,7 +716,32 @@ fn smooth_corrections(bands: &mut [FrequencyBand], factor: f32) {
     }
 }
 
-fn check_for_extreme_corrections(bands: &[FrequencyBand], warnings: &mut Vec<String>) {
+// Half of each band's clipped excess goes to its neighbours (a quarter each, within the
+// limit); the clipped band is widened so the boost/cut covers more of the target region.
+// `limits` holds each band's own ±dB limit
+fn redistribute_excess(bands: &mut [FrequencyBand], excess: &[f32], limits: &[f32]) {
+    for (i, &clipped) in excess.iter().enumerate() {
+        if clipped.abs() <= 0.1 {
+            continue;
+        }
+
+        let mut spilled = false;
+        for j in [i.checked_sub(1), Some(i + 1)].into_iter().flatten() {
+            // Nothing may be added past Nyquist
+            if let Some(neighbour) = bands.get_mut(j).filter(|n| !n.out_of_range) {
+                let before = neighbour.gain_db;
+                neighbour.gain_db = (before + clipped * 0.25).clamp(-limits[j], limits[j]);
+                spilled |= (neighbour.gain_db - before).abs() > 0.01;
+            }
+        }
+
+        if spilled {
+            bands[i].bandwidth *= 1.5;
+        }
+    }
+}
+
+fn check_for_extreme_corrections(bands: &[FrequencyBand], warnings: &mut Vec<MatchWarning>) {
     // Check for steep slopes
     for window in bands.windows(2) {
         let slope = (window[1].gain_db - window[0].gain_db).abs();
@@ -195,20 +749,35 @@ fn check_for_extreme_corrections(bands: &[FrequencyBand], warnings: &mut Vec<Str
         let slope_per_octave = slope / freq_ratio.log2();
 
         if slope_per_octave > 6.0 {
-            warnings.push(format!(
-                "Steep slope between {} Hz and {} Hz ({:.1} dB/octave)",
-                window[0].frequency, window[1].frequency, slope_per_octave
-            ));
+            warnings.push(
+                MatchWarning::new(
+                    WarningCode::SteepSlope,
+                    WarningSeverity::Warning,
+                    format!(
+                        "Steep slope between {} Hz and {} Hz ({:.1} dB/octave)",
+                        window[0].frequency, window[1].frequency, slope_per_octave
+                    ),
+                )
+                .with_range(window[0].frequency, window[1].frequency)
+                .with_action(SuggestedAction::IncreaseSmoothing),
+            );
         }
     }
 
     // Check for excessive total correction
     let total_correction: f32 = bands.iter().map(|b| b.gain_db.abs()).sum();
     if total_correction > 30.0 {
-        warnings.push(format!(
-            "High total correction: {:.1} dB. Consider lower intensity.",
-            total_correction
-        ));
+        warnings.push(
+            MatchWarning::new(
+                WarningCode::HighTotalCorrection,
+                WarningSeverity::Critical,
+                format!(
+                    "High total correction: {:.1} dB. Consider lower intensity.",
+                    total_correction
+                ),
+            )
+            .with_action(SuggestedAction::LowerIntensity),
+        );
     }
 }
 
@@ -237,31 +806,125 @@ fn preserve_dynamic_range(
         dynamic_range: reference.dynamic_range,
         spectral_centroid: reference.spectral_centroid,
         spectral_rolloff: reference.spectral_rolloff,
+        channels: None,
+        resonance: Default::default(),
+        source: None,
     }
 }
 
-fn calculate_match_quality(profile: &EQProfile) -> f32 {
+fn calculate_match_quality(profile: &EQProfile, residual_rms_db: f32) -> QualityBreakdown {
     let mut score = 1.0;
 
     // Penalty for large corrections
     let avg_correction: f32 =
         profile.bands.iter().map(|b| b.gain_db.abs()).sum::<f32>() / profile.bands.len() as f32;
 
-    score -= (avg_correction / 10.0).min(0.4); // Max -0.4 penalty
+    let correction_penalty = (avg_correction / 10.0).min(0.4); // Max -0.4 penalty
+    score -= correction_penalty;
 
     // Penalty for steep slopes
+    let mut slope_penalty = 0.0;
     for window in profile.bands.windows(2) {
         let slope = (window[1].gain_db - window[0].gain_db).abs();
         if slope > 6.0 {
-            score -= 0.05;
+            slope_penalty += 0.05;
         }
     }
+    score -= slope_penalty;
 
     // Bonus for high confidence
     let avg_confidence: f32 =
         profile.bands.iter().map(|b| b.confidence).sum::<f32>() / profile.bands.len() as f32;
 
-    score *= 0.7 + 0.3 * avg_confidence;
+    let confidence_factor = 0.7 + 0.3 * avg_confidence;
+    score *= confidence_factor;
+
+    QualityBreakdown {
+        score: score.clamp(0.0, 1.0),
+        average_correction_db: avg_correction,
+        correction_penalty,
+        slope_penalty,
+        average_confidence: avg_confidence,
+        confidence_factor,
+        residual_rms_db,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_correction_without_a_curve_is_flat() {
+        let config = MatchConfig::default();
+        assert_eq!(config.max_correction_at(20.0), config.max_correction);
+        assert_eq!(config.max_correction_at(15_000.0), config.max_correction);
+    }
+
+    #[test]
+    fn max_correction_curve_interpolates_in_log_frequency() {
+        let config = MatchConfig {
+            max_correction_curve: vec![(1000.0, 2.0), (100.0, 8.0)],
+            ..Default::default()
+        };
+        // Held flat past the ends, whatever order the anchors came in
+        assert_eq!(config.max_correction_at(20.0), 8.0);
+        assert_eq!(config.max_correction_at(100.0), 8.0);
+        assert_eq!(config.max_correction_at(20_000.0), 2.0);
+        // 316 Hz is halfway between the anchors on a log axis
+        assert!((config.max_correction_at(316.227_77) - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn max_correction_curve_limits_are_magnitudes() {
+        let config = MatchConfig {
+            max_correction_curve: vec![(100.0, -4.0), (1000.0, -4.0)],
+            ..Default::default()
+        };
+        assert_eq!(config.max_correction_at(500.0), 4.0);
+    }
 
-    score.clamp(0.0, 1.0)
+    // A reference bump around each of `nulls`, where the input has a deep
+    // null, so the raw correction boosts into it and its neighbours
+    fn room_profiles(nulls: &[usize]) -> (EQProfile, EQProfile) {
+        let centers: Vec<f32> = (0..28).map(|i| 25.0 * 2f32.powf(i as f32 / 3.0)).collect();
+        let mut reference = ResponseCurve::new(vec![(20.0, 0.0)]).to_profile(&centers);
+        let mut input = reference.clone();
+        for &i in nulls {
+            for band in &mut reference.bands[i - 1..=i + 1] {
+                band.gain_db = 6.0;
+            }
+            input.bands[i].gain_db = -16.0;
+        }
+        (reference, input)
+    }
+
+    #[test]
+    fn room_mode_never_boosts_deep_nulls() {
+        // 80 Hz in the modal region, 2 kHz above the transition
+        let nulls = [5, 19];
+        let (reference, input) = room_profiles(&nulls);
+        let config = MatchConfig {
+            intensity: 1.0,
+            smoothing_factor: 0.8,
+            max_correction: 4.0,
+            ..MatchConfig::room_correction()
+        };
+        let result = match_profiles(&reference, &input, &config);
+
+        let room = result
+            .correction_steps
+            .iter()
+            .position(|s| s.stage == CorrectionStage::Room)
+            .unwrap();
+        for &i in &nulls {
+            assert!(result.correction_steps[room - 1].gains_db[i] > 0.0);
+            for step in &result.correction_steps[room..] {
+                assert!(step.gains_db[i] <= 0.0, "{:?} at {}", step.stage, i);
+            }
+            assert!(result.correction_profile.bands[i].gain_db <= 0.0);
+        }
+        // The neighbours are still corrected
+        assert!(result.correction_profile.bands[nulls[0] + 1].gain_db > 0.0);
+    }
 }
@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+// ITU-R BS.1770-4 gating and EBU Tech 3342 loudness range
+const STEP_SECS: f64 = 0.1;
+const MOMENTARY_STEPS: usize = 4; // 400 ms blocks, 75% overlap
+const SHORT_TERM_STEPS: usize = 30; // 3 s windows for the loudness range
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+const RELATIVE_GATE_LU: f64 = -10.0;
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+const LRA_PERCENTILES: (f64, f64) = (0.10, 0.95);
+const SURROUND_WEIGHT: f64 = 1.41;
+
+/// Delivery loudness figures. Integrated loudness and range are None when
+/// the audio is too short or too quiet to pass the gates.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoudnessStats {
+    pub integrated_lufs: Option<f32>,
+    pub loudness_range_lu: Option<f32>,
+    pub true_peak_dbtp: f32,
+    pub sample_peak_dbfs: f32,
+    pub duration_secs: f32,
+}
+
+/// Measures planar `channels`. Five or more channels are taken as 5.1
+/// (L R C LFE Ls Rs): the LFE is skipped and surrounds weighted +1.5 dB.
+pub fn measure_loudness(channels: &[Vec<f32>], sample_rate: u32) -> LoudnessStats {
+    let frames = channels.iter().map(|c| c.len()).min().unwrap_or(0);
+    let step = ((sample_rate as f64 * STEP_SECS) as usize).max(1);
+    let steps = frames / step;
+
+    // Weighted mean square of the K-weighted signal per 100 ms step
+    let mut energy = vec![0.0f64; steps];
+    for (index, channel) in channels.iter().enumerate() {
+        let weight = channel_weight(index, channels.len());
+        if weight == 0.0 {
+            continue;
+        }
+        let mut filter = KWeighting::new(sample_rate as f64);
+        for (s, chunk) in channel[..steps * step].chunks(step).enumerate() {
+            let sum: f64 = chunk
+                .iter()
+                .map(|&x| filter.process(x as f64).powi(2))
+                .sum();
+            energy[s] += weight * sum / step as f64;
+        }
+    }
+
+    let momentary = windowed(&energy, MOMENTARY_STEPS);
+    let short_term = windowed(&energy, SHORT_TERM_STEPS);
+
+    let integrated_lufs = gated_mean(&momentary, RELATIVE_GATE_LU).map(|(loudness, _)| loudness);
+    let loudness_range_lu = gated_mean(&short_term, LRA_RELATIVE_GATE_LU).and_then(|(_, kept)| {
+        let mut kept: Vec<f64> = kept.into_iter().map(to_lufs).collect();
+        if kept.len() < 2 {
+            return None;
+        }
+        kept.sort_by(f64::total_cmp);
+        let at = |p: f64| kept[((kept.len() - 1) as f64 * p).round() as usize];
+        Some(at(LRA_PERCENTILES.1) - at(LRA_PERCENTILES.0))
+    });
+
+    let as_f64: Vec<Vec<f64>> = channels
+        .iter()
+        .map(|c| c.iter().map(|&s| s as f64).collect())
+        .collect();
+    let sample_peak = channels
+        .iter()
+        .flatten()
+        .fold(0.0f32, |peak, s| peak.max(s.abs()));
+
+    LoudnessStats {
+        integrated_lufs: integrated_lufs.map(|l| l as f32),
+        loudness_range_lu: loudness_range_lu.map(|l| l as f32),
+        true_peak_dbtp: linear_to_db(true_peak(&as_f64)) as f32,
+        sample_peak_dbfs: linear_to_db(sample_peak as f64) as f32,
+        duration_secs: frames as f32 / sample_rate.max(1) as f32,
+    }
+}
+
+/// Highest sample or inter-sample peak (linear) over all channels.
+pub fn true_peak(channels: &[Vec<f64>]) -> f64 {
+    channels
+        .iter()
+        .flat_map(|c| (0..c.len()).map(move |n| inter_sample_peak(c, n)))
+        .fold(0.0, f64::max)
+}
+
+// Sample peak plus Catmull-Rom estimates at the 1/4, 1/2 and 3/4 positions
+// between samples, a cheap stand-in for 4x oversampled peak metering
+pub(crate) fn inter_sample_peak(channel: &[f64], n: usize) -> f64 {
+    let at = |i: isize| -> f64 {
+        channel
+            .get(i.clamp(0, channel.len() as isize - 1) as usize)
+            .copied()
+            .unwrap_or(0.0)
+    };
+    let i = n as isize;
+    let (p0, p1, p2, p3) = (at(i - 1), at(i), at(i + 1), at(i + 2));
+
+    [0.25, 0.5, 0.75].iter().fold(p1.abs(), |peak, &t| {
+        let value = 0.5
+            * (2.0 * p1
+                + (p2 - p0) * t
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t);
+        peak.max(value.abs())
+    })
+}
+
+fn channel_weight(index: usize, count: usize) -> f64 {
+    match (count >= 5, index) {
+        (true, 3) => 0.0,
+        (true, 4 | 5) => SURROUND_WEIGHT,
+        _ => 1.0,
+    }
+}
+
+// Mean energy of each `len`-step window, one per step
+fn windowed(energy: &[f64], len: usize) -> Vec<f64> {
+    energy
+        .windows(len)
+        .map(|w| w.iter().sum::<f64>() / len as f64)
+        .collect()
+}
+
+// Absolute gate, then a gate `relative_lu` below the mean of what passed.
+// Returns the gated loudness and the blocks kept
+fn gated_mean(blocks: &[f64], relative_lu: f64) -> Option<(f64, Vec<f64>)> {
+    let loud: Vec<f64> = blocks
+        .iter()
+        .copied()
+        .filter(|&e| to_lufs(e) > ABSOLUTE_GATE_LUFS)
+        .collect();
+    if loud.is_empty() {
+        return None;
+    }
+    let threshold = to_lufs(loud.iter().sum::<f64>() / loud.len() as f64) + relative_lu;
+    let kept: Vec<f64> = loud
+        .into_iter()
+        .filter(|&e| to_lufs(e) > threshold)
+        .collect();
+    if kept.is_empty() {
+        return None;
+    }
+    Some((to_lufs(kept.iter().sum::<f64>() / kept.len() as f64), kept))
+}
+
+fn to_lufs(energy: f64) -> f64 {
+    -0.691 + 10.0 * (energy + 1e-20).log10()
+}
+
+fn linear_to_db(value: f64) -> f64 {
+    20.0 * (value + 1e-20).log10()
+}
+
+/// Gain of the K-weighting curve at `frequency_hz`, in dB, at the 48 kHz
+/// rate the standard specifies it for. Weights band energies the way the
+/// meter weights samples.
+pub fn k_weighting_db(frequency_hz: f32) -> f32 {
+    const RATE: f64 = 48_000.0;
+    let w = 2.0 * PI * (frequency_hz as f64).clamp(0.0, RATE / 2.0) / RATE;
+    let gain: f64 = KWeighting::new(RATE)
+        .stages
+        .iter()
+        .map(|stage| stage.magnitude(w))
+        .product();
+    linear_to_db(gain) as f32
+}
+
+/// BS.1770 K-weighting: the head-related high shelf, then the RLB high-pass,
+/// designed for the actual sample rate.
+struct KWeighting {
+    stages: [Biquad; 2],
+}
+
+impl KWeighting {
+    fn new(sample_rate: f64) -> Self {
+        let shelf = {
+            let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+            let k = (PI * f0 / sample_rate).tan();
+            let vh = 10f64.powf(gain_db / 20.0);
+            let vb = vh.powf(0.4996667741545416);
+            let a0 = 1.0 + k / q + k * k;
+            Biquad::new(
+                [
+                    (vh + vb * k / q + k * k) / a0,
+                    2.0 * (k * k - vh) / a0,
+                    (vh - vb * k / q + k * k) / a0,
+                ],
+                [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            )
+        };
+        let high_pass = {
+            let (f0, q) = (38.13547087602444, 0.5003270373238773);
+            let k = (PI * f0 / sample_rate).tan();
+            let a0 = 1.0 + k / q + k * k;
+            Biquad::new(
+                [1.0, -2.0, 1.0],
+                [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            )
+        };
+        Self {
+            stages: [shelf, high_pass],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        self.stages
+            .iter_mut()
+            .fold(x, |acc, stage| stage.process(acc))
+    }
+}
+
+// Transposed direct form II
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z: [0.0; 2] }
+    }
+
+    // |H(e^jw)|
+    fn magnitude(&self, w: f64) -> f64 {
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        let num =
+            (self.b[0] + self.b[1] * c1 + self.b[2] * c2).hypot(self.b[1] * s1 + self.b[2] * s2);
+        let den = (1.0 + self.a[0] * c1 + self.a[1] * c2).hypot(self.a[0] * s1 + self.a[1] * s2);
+        num / den
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.z[0];
+        self.z[0] = self.b[1] * x - self.a[0] * y + self.z[1];
+        self.z[1] = self.b[2] * x - self.a[1] * y;
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, amplitude: f64, sample_rate: u32, secs: f64) -> Vec<f32> {
+        (0..(sample_rate as f64 * secs) as usize)
+            .map(|i| {
+                (amplitude * (2.0 * PI * frequency * i as f64 / sample_rate as f64).sin()) as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn full_scale_1khz_sine_reads_minus_3_01_lufs() {
+        for sample_rate in [44_100, 48_000, 96_000] {
+            let stats = measure_loudness(&[sine(1000.0, 1.0, sample_rate, 10.0)], sample_rate);
+            let integrated = stats.integrated_lufs.unwrap();
+            assert!(
+                (integrated + 3.01).abs() < 0.05,
+                "{} Hz: {}",
+                sample_rate,
+                integrated
+            );
+            assert!(stats.loudness_range_lu.unwrap() < 0.1);
+            assert!(stats.sample_peak_dbfs.abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn level_changes_move_loudness_by_the_same_amount() {
+        let loud = measure_loudness(&[sine(1000.0, 1.0, 48_000, 5.0)], 48_000);
+        let quiet = measure_loudness(&[sine(1000.0, 0.1, 48_000, 5.0)], 48_000);
+        let difference = loud.integrated_lufs.unwrap() - quiet.integrated_lufs.unwrap();
+        assert!((difference - 20.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn silence_does_not_pass_the_gate() {
+        let stats = measure_loudness(&[vec![0.0; 48_000 * 2]], 48_000);
+        assert!(stats.integrated_lufs.is_none());
+        assert!(stats.loudness_range_lu.is_none());
+    }
+
+    #[test]
+    fn lfe_is_ignored_in_surround() {
+        let tone = sine(1000.0, 0.5, 48_000, 5.0);
+        let silent = vec![0.0; tone.len()];
+        let mut channels = vec![silent; 6];
+        channels[2] = tone.clone();
+        let centre = measure_loudness(&channels, 48_000);
+        channels[3] = tone;
+        let with_lfe = measure_loudness(&channels, 48_000);
+        assert_eq!(centre.integrated_lufs, with_lfe.integrated_lufs);
+    }
+
+    #[test]
+    fn k_weighting_matches_the_filter_response() {
+        assert!((k_weighting_db(1000.0) - 0.698).abs() < 0.01);
+        assert!(k_weighting_db(20.0) < -12.0);
+        assert!((k_weighting_db(10_000.0) - 4.0).abs() < 0.5);
+    }
+}
@@ -1,4 +1,32 @@
 pub mod analyzer;
+pub mod arithmetic;
+pub mod compare;
+pub mod curve;
+pub mod deviation;
+pub mod dynamics;
+pub mod explain;
+pub mod finite;
+pub mod generator;
+pub mod headphone;
 pub mod loader;
+pub mod loudness;
+pub mod mapped;
+pub mod masking;
 pub mod matcher;
+pub mod metering;
+pub mod null_test;
+pub mod optimizer;
+pub mod pipeline;
+pub mod predict;
+pub mod presets;
 pub mod profile;
+pub mod provenance;
+pub mod resonance;
+pub mod room;
+pub mod sections;
+pub mod spectrogram;
+pub mod speech;
+pub mod validation;
+pub mod warnings;
+pub mod waveform;
+pub mod writer;
@@ -0,0 +1,178 @@
+use super::analyzer::{analyze_bands, AnalysisConfig};
+use super::loader::{load_audio_channels_cancellable, resample_audio_cancellable, AudioError};
+use crate::dsp::fft;
+use crate::jobs::CancelToken;
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const MAX_OFFSET_SECS: f64 = 1.0; // Largest misalignment searched for
+const ALIGN_WINDOW_SECS: f64 = 10.0; // Audio used to find the offset
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NullBand {
+    pub frequency: f32,
+    pub depth_db: f32, // Residual level relative to A in this band
+}
+
+/// How far B is from A once aligned: 0 dB depth means nothing cancelled,
+/// below -90 dB the files are effectively identical.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NullTestResult {
+    pub sample_rate: u32,
+    pub offset_samples: i64, // Positive: B starts later than A
+    pub offset_ms: f64,
+    pub polarity_inverted: bool, // B was flipped before summing
+    pub compared_secs: f64,
+    pub reference_rms_db: f32, // A, dBFS
+    pub residual_rms_db: f32,
+    pub residual_peak_db: f32,
+    pub null_depth_db: f32, // Residual RMS relative to A
+    pub bands: Vec<NullBand>,
+}
+
+/// Aligns B to A by cross-correlation, subtracts it and measures what is
+/// left. B is resampled to A's rate if needed; channels beyond the smaller
+/// count are ignored. Gain differences are not compensated. Decoding and
+/// resampling stop with `AudioError::Cancelled` once `cancel` is set.
+pub fn null_test<P: AsRef<Path>>(
+    path_a: P,
+    path_b: P,
+    cancel: &CancelToken,
+) -> Result<NullTestResult, AudioError> {
+    let a = load_audio_channels_cancellable(path_a, cancel)?;
+    let b = load_audio_channels_cancellable(path_b, cancel)?;
+    let b_channels = b
+        .channels
+        .iter()
+        .map(|channel| resample_audio_cancellable(channel, b.sample_rate, a.sample_rate, cancel))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let channels = a.channels.len().min(b_channels.len());
+    if channels == 0 {
+        return Err(AudioError::NoAudioData);
+    }
+    let a_channels = &a.channels[..channels];
+    let b_channels = &b_channels[..channels];
+    let sample_rate = a.sample_rate;
+
+    let (offset, inverted) = find_offset(
+        &mixdown(a_channels),
+        &mixdown(b_channels),
+        (MAX_OFFSET_SECS * sample_rate as f64) as usize,
+        (ALIGN_WINDOW_SECS * sample_rate as f64) as usize,
+    );
+    let sign = if inverted { -1.0 } else { 1.0 };
+
+    // Overlap of A with shifted B
+    let start = (-offset).max(0) as usize;
+    let end = a_channels[0]
+        .len()
+        .min((b_channels[0].len() as i64 - offset).max(0) as usize);
+    if end <= start {
+        return Err(AudioError::NoAudioData);
+    }
+
+    let aligned_a: Vec<&[f32]> = a_channels.iter().map(|c| &c[start..end]).collect();
+    let residual: Vec<Vec<f32>> = a_channels
+        .iter()
+        .zip(b_channels)
+        .map(|(a, b)| {
+            (start..end)
+                .map(|i| a[i] - sign * b[(i as i64 + offset) as usize])
+                .collect()
+        })
+        .collect();
+
+    let reference_rms_db = to_db(rms(aligned_a.iter().copied()));
+    let residual_rms_db = to_db(rms(residual.iter().map(|c| c.as_slice())));
+    let peak = residual
+        .iter()
+        .flatten()
+        .fold(0.0f32, |peak, s| peak.max(s.abs()));
+
+    let config = AnalysisConfig::default();
+    let bands = |channels: &[&[f32]]| {
+        analyze_bands(&mixdown(channels), sample_rate, &config).map_err(AudioError::InvalidConfig)
+    };
+    let reference_bands = bands(&aligned_a)?;
+    let residual_bands = bands(&residual.iter().map(|c| c.as_slice()).collect::<Vec<_>>())?;
+
+    Ok(NullTestResult {
+        sample_rate,
+        offset_samples: offset,
+        offset_ms: offset as f64 * 1000.0 / sample_rate as f64,
+        polarity_inverted: inverted,
+        compared_secs: (end - start) as f64 / sample_rate as f64,
+        reference_rms_db,
+        residual_rms_db,
+        residual_peak_db: to_db(peak),
+        null_depth_db: residual_rms_db - reference_rms_db,
+        bands: reference_bands
+            .iter()
+            .zip(&residual_bands)
+            .map(|(a, r)| NullBand {
+                frequency: a.frequency,
+                depth_db: r.gain_db - a.gain_db,
+            })
+            .collect(),
+    })
+}
+
+fn mixdown<C: AsRef<[f32]>>(channels: &[C]) -> Vec<f32> {
+    let len = channels.iter().map(|c| c.as_ref().len()).min().unwrap_or(0);
+    let scale = 1.0 / channels.len().max(1) as f32;
+    (0..len)
+        .map(|i| channels.iter().map(|c| c.as_ref()[i]).sum::<f32>() * scale)
+        .collect()
+}
+
+// Lag maximizing |sum a[i] * b[i + lag]| over the opening `window`
+// samples, and whether the best match is polarity-inverted
+fn find_offset(a: &[f32], b: &[f32], max_lag: usize, window: usize) -> (i64, bool) {
+    let len = a.len().max(b.len()).min(window + max_lag);
+    let size = (2 * len).next_power_of_two();
+    let forward = fft::forward(size);
+    let inverse = fft::inverse(size);
+
+    let spectrum = |signal: &[f32]| {
+        let mut buffer: Vec<Complex<f32>> = (0..size)
+            .map(|i| {
+                Complex::new(
+                    signal.get(i).copied().filter(|_| i < len).unwrap_or(0.0),
+                    0.0,
+                )
+            })
+            .collect();
+        forward.process(&mut buffer);
+        buffer
+    };
+    let (fa, fb) = (spectrum(a), spectrum(b));
+    let mut correlation: Vec<Complex<f32>> =
+        fa.iter().zip(&fb).map(|(x, y)| x.conj() * y).collect();
+    inverse.process(&mut correlation);
+
+    let max_lag = max_lag.min(size / 2 - 1) as i64;
+    let (lag, value) = (-max_lag..=max_lag)
+        .map(|lag| {
+            let index = if lag >= 0 { lag } else { size as i64 + lag } as usize;
+            (lag, correlation[index].re)
+        })
+        .max_by(|x, y| x.1.abs().total_cmp(&y.1.abs()))
+        .unwrap_or((0, 0.0));
+    (lag, value < 0.0)
+}
+
+fn rms<'a>(channels: impl Iterator<Item = &'a [f32]>) -> f32 {
+    let (sum, count) = channels.fold((0.0f64, 0usize), |(sum, count), c| {
+        (
+            sum + c.iter().map(|&s| (s as f64).powi(2)).sum::<f64>(),
+            count + c.len(),
+        )
+    });
+    (sum / count.max(1) as f64).sqrt() as f32
+}
+
+fn to_db(value: f32) -> f32 {
+    20.0 * value.max(1e-10).log10()
+}
@@ -0,0 +1,128 @@
+use super::matcher::{match_profiles, MatchConfig, MatchResult};
+use super::profile::EQProfile;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// dB of residual error we accept per dB of average correction
+const CORRECTION_PENALTY: f32 = 0.25;
+
+const SMOOTHING_CANDIDATES: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+const INTENSITY_STEPS: usize = 20; // 0.05 - 1.0
+                                   // Objective differences below this are ties
+const OBJECTIVE_TOLERANCE: f32 = 1e-3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoMatchResult {
+    pub config: MatchConfig,
+    pub result: MatchResult,
+    pub objective: f32, // Lower is better
+    pub explanation: String,
+}
+
+/// Sweeps `intensity` (and optionally `smoothing_factor`) and keeps the
+/// setting with the best trade-off between residual error and correction size.
+pub fn auto_match(
+    reference: &EQProfile,
+    input: &EQProfile,
+    base: &MatchConfig,
+    search_smoothing: bool,
+) -> AutoMatchResult {
+    let smoothing_values: Vec<f32> = if search_smoothing {
+        SMOOTHING_CANDIDATES.to_vec()
+    } else {
+        vec![base.smoothing_factor]
+    };
+
+    let candidates: Vec<MatchConfig> = smoothing_values
+        .iter()
+        .flat_map(|&smoothing_factor| {
+            (1..=INTENSITY_STEPS).map(move |step| MatchConfig {
+                intensity: step as f32 / INTENSITY_STEPS as f32,
+                smoothing_factor,
+                ..base.clone()
+            })
+        })
+        .collect();
+
+    let mut scored: Vec<(MatchConfig, MatchResult, f32)> = candidates
+        .into_par_iter()
+        .map(|config| {
+            let result = match_profiles(reference, input, &config);
+            let objective = match_objective(&result);
+            (config, result, objective)
+        })
+        .collect();
+    // First of equal scores, so ties go to the gentlest setting
+    let best = (0..scored.len())
+        .min_by(|&a, &b| scored[a].2.total_cmp(&scored[b].2))
+        .expect("candidate list is never empty");
+
+    let explanation = explain(&scored, best, search_smoothing);
+    let (config, result, objective) = scored.swap_remove(best);
+
+    AutoMatchResult {
+        config,
+        result,
+        objective,
+        explanation,
+    }
+}
+
+// Says why the sweep stopped where it did, from the neighbouring scores at
+// the chosen smoothing
+fn explain(
+    scored: &[(MatchConfig, MatchResult, f32)],
+    best: usize,
+    search_smoothing: bool,
+) -> String {
+    let (config, result, objective) = &scored[best];
+    let summary = format!(
+        "Intensity {:.2} with smoothing {:.2} leaves {:.1} dB RMS residual for {:.1} dB average correction",
+        config.intensity,
+        config.smoothing_factor,
+        result.residual_rms_db,
+        average_correction(result),
+    );
+
+    let spread = scored.iter().map(|s| s.2).fold(f32::NEG_INFINITY, f32::max) - objective;
+    let step = best % INTENSITY_STEPS;
+    let reason = if spread < OBJECTIVE_TOLERANCE {
+        "no setting did better than another, so the gentlest one was kept".to_string()
+    } else if step == INTENSITY_STEPS - 1 {
+        "it is the strongest setting tried: each step up still removed more residual than it \
+         added correction, so the input may need more than full intensity"
+            .to_string()
+    } else if step == 0 {
+        "it is the weakest setting tried: any stronger correction added more than it removed \
+         from the residual"
+            .to_string()
+    } else {
+        let (weaker, stronger) = (&scored[best - 1].1, &scored[best + 1].1);
+        format!(
+            "the next weaker setting leaves {:.1} dB residual, the next stronger one {:.1} dB \
+             for {:.1} dB average correction, and both score worse",
+            weaker.residual_rms_db,
+            stronger.residual_rms_db,
+            average_correction(stronger)
+        )
+    };
+    let smoothing = if search_smoothing {
+        " Smoothing was searched too; this value scored best."
+    } else {
+        ""
+    };
+    format!("{}; {}.{}", summary, reason, smoothing)
+}
+
+fn match_objective(result: &MatchResult) -> f32 {
+    result.residual_rms_db + CORRECTION_PENALTY * average_correction(result)
+}
+
+fn average_correction(result: &MatchResult) -> f32 {
+    let bands = &result.correction_profile.bands;
+    if bands.is_empty() {
+        return 0.0;
+    }
+
+    bands.iter().map(|b| b.gain_db.abs()).sum::<f32>() / bands.len() as f32
+}
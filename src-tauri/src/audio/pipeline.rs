@@ -0,0 +1,299 @@
+use super::analyzer::FrequencySpectrum;
+use super::analyzer::{
+    analyze_spectrum, analyze_spectrum_streaming, AnalysisConfig, BandAccumulator,
+};
+use super::loader::{
+    analysis_rate, load_audio_channels_cancellable, load_audio_file_cancellable,
+    prepare_audio_for_analysis, AudioError, MonoStream, ResamplePolicy, StreamResampler,
+    RESAMPLE_CHUNK,
+};
+use super::profile::{band_profile, extract_eq_profile, ChannelMode, ChannelProfiles, EQProfile};
+use super::provenance::ProfileSource;
+use crate::jobs::CancelToken;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const STANDARD_RATE: u32 = 48000;
+
+// Resample policy:
+// - Default: avoid unnecessary upsampling (big speed win for 44.1kHz sources).
+// - "legacy": keep previous behavior (always resample to 48kHz).
+// - "no-resample": never resample.
+pub fn policy_from_preset(preset: Option<&str>) -> ResamplePolicy {
+    match preset {
+        Some("legacy") => ResamplePolicy::Always,
+        Some("no-resample") => ResamplePolicy::Never,
+        _ => ResamplePolicy::DownsampleOnly,
+    }
+}
+
+/// Full-resolution spectra behind a profile. Band extraction is cheap, so a
+/// profile on another band grid comes from these without decoding or
+/// transforming the file again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyzedSpectra {
+    pub mix: FrequencySpectrum,
+    #[serde(default)]
+    pub channels: Option<ChannelSpectra>,
+    #[serde(default)]
+    pub source: Option<ProfileSource>, // Filled in by the caller that knows the file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelSpectra {
+    pub mode: ChannelMode,
+    pub first: FrequencySpectrum,
+    pub second: FrequencySpectrum,
+}
+
+impl AnalyzedSpectra {
+    pub fn profile(&self, config: &AnalysisConfig) -> EQProfile {
+        let mut profile = extract_eq_profile(&self.mix, config);
+        profile.source = self.source.clone().map(|mut source| {
+            source.analysis.band_count = config.frequency_bands.len();
+            Box::new(source)
+        });
+        profile.channels = self.channels.as_ref().map(|c| {
+            Box::new(ChannelProfiles {
+                mode: c.mode,
+                first: extract_eq_profile(&c.first, config),
+                second: extract_eq_profile(&c.second, config),
+            })
+        });
+        profile
+    }
+}
+
+// Load, resample and analyze a file into an EQ profile
+pub fn analyze_audio_file<P: AsRef<Path>>(
+    path: P,
+    policy: ResamplePolicy,
+) -> Result<EQProfile, AudioError> {
+    analyze_audio_file_cancellable(path, policy, &CancelToken::new())
+}
+
+/// `analyze_audio_file` that stops with `AudioError::Cancelled` once
+/// `cancel` is set.
+pub fn analyze_audio_file_cancellable<P: AsRef<Path>>(
+    path: P,
+    policy: ResamplePolicy,
+    cancel: &CancelToken,
+) -> Result<EQProfile, AudioError> {
+    let config = AnalysisConfig::default();
+    let spectrum = analyze_file_spectrum(path, policy, &config, cancel, 0, &mut |_, _| {})?;
+    Ok(extract_eq_profile(&spectrum, &config))
+}
+
+/// The mono spectrum `analyze_audio_file` extracts its profile from, with
+/// the running averages passed to `on_partial`, using the FFT
+/// size, window and overlap of `config`. Decoding and resampling stop early
+/// with `AudioError::Cancelled` once `cancel` is set.
+pub fn analyze_file_spectrum<P: AsRef<Path>>(
+    path: P,
+    policy: ResamplePolicy,
+    config: &AnalysisConfig,
+    cancel: &CancelToken,
+    updates: usize,
+    on_partial: &mut dyn FnMut(FrequencySpectrum, f32),
+) -> Result<FrequencySpectrum, AudioError> {
+    let (samples, analyzed_rate) = load_mono_for_analysis(path, policy, cancel)?;
+
+    analyze_spectrum_streaming(&samples, analyzed_rate, config, updates, on_partial)
+        .map_err(AudioError::InvalidConfig)
+}
+
+/// Band levels of a file, from `analyze_file_bands`.
+#[derive(Debug, Clone)]
+pub struct BandAnalysis {
+    pub profile: EQProfile,
+    pub sample_rate: u32, // Analyzed rate
+    pub duration_secs: f32,
+}
+
+/// The bands of `analyze_file_spectrum`'s profile without the full-resolution
+/// spectrum: decoded packets are resampled and fed to a `BandAccumulator` as
+/// they come, so memory stays flat however long the file is. The profile
+/// has no centroid, rolloff or resonances (see `band_profile`).
+pub fn analyze_file_bands<P: AsRef<Path>>(
+    path: P,
+    policy: ResamplePolicy,
+    config: &AnalysisConfig,
+    cancel: &CancelToken,
+) -> Result<BandAnalysis, AudioError> {
+    let mut stream = MonoStream::open(path)?;
+    let rate = analysis_rate(stream.sample_rate(), STANDARD_RATE, policy);
+    let mut accumulator = BandAccumulator::new(rate, config).map_err(AudioError::InvalidConfig)?;
+    let mut resampler = if rate != stream.sample_rate() {
+        Some(StreamResampler::new(
+            stream.sample_rate(),
+            rate,
+            RESAMPLE_CHUNK,
+        )?)
+    } else {
+        None
+    };
+
+    let mut frames = 0usize;
+    let mut resampled = Vec::new();
+    while let Some(chunk) = stream.next_chunk(cancel)? {
+        let samples = match resampler.as_mut() {
+            Some(resampler) => {
+                resampled.clear();
+                resampler.push(&chunk, &mut resampled)?;
+                &resampled
+            }
+            None => &chunk,
+        };
+        frames += samples.len();
+        accumulator.push(samples);
+    }
+    if let Some(resampler) = resampler {
+        resampled.clear();
+        resampler.finish(&mut resampled)?;
+        frames += resampled.len();
+        accumulator.push(&resampled);
+    }
+
+    Ok(BandAnalysis {
+        profile: band_profile(accumulator.finish()),
+        sample_rate: rate,
+        duration_secs: frames as f32 / rate as f32,
+    })
+}
+
+// Mono mixdown at the rate `policy` picks for analysis
+pub fn load_mono_for_analysis<P: AsRef<Path>>(
+    path: P,
+    policy: ResamplePolicy,
+    cancel: &CancelToken,
+) -> Result<(Vec<f32>, u32), AudioError> {
+    let audio = load_audio_file_cancellable(path, cancel)?;
+    prepare_audio_for_analysis(
+        audio.samples,
+        audio.sample_rate,
+        STANDARD_RATE,
+        policy,
+        cancel,
+    )
+}
+
+// Like `analyze_audio_file`, but also attaches L/R or M/S profiles for stereo files
+pub fn analyze_audio_file_with_channels<P: AsRef<Path>>(
+    path: P,
+    policy: ResamplePolicy,
+    mode: ChannelMode,
+) -> Result<EQProfile, AudioError> {
+    let config = AnalysisConfig::default();
+    Ok(
+        analyze_file_channel_spectra(path, policy, mode, &config, &CancelToken::new())?
+            .profile(&config),
+    )
+}
+
+// Spectra behind `analyze_audio_file_with_channels`; mono files get no channel spectra
+pub fn analyze_file_channel_spectra<P: AsRef<Path>>(
+    path: P,
+    policy: ResamplePolicy,
+    mode: ChannelMode,
+    config: &AnalysisConfig,
+    cancel: &CancelToken,
+) -> Result<AnalyzedSpectra, AudioError> {
+    let audio = load_audio_channels_cancellable(path, cancel)?;
+
+    let analyze = |samples: Vec<f32>| -> Result<FrequencySpectrum, AudioError> {
+        let (samples, analyzed_rate) =
+            prepare_audio_for_analysis(samples, audio.sample_rate, STANDARD_RATE, policy, cancel)?;
+        analyze_spectrum(&samples, analyzed_rate, config).map_err(AudioError::InvalidConfig)
+    };
+
+    let (left, right) = match audio.channels.as_slice() {
+        [] => return Err(AudioError::NoAudioData),
+        [mono] => {
+            return Ok(AnalyzedSpectra {
+                mix: analyze(mono.clone())?,
+                channels: None,
+                source: None,
+            })
+        }
+        [left, right, ..] => (left, right),
+    };
+
+    let mono: Vec<f32> = left.iter().zip(right).map(|(l, r)| (l + r) * 0.5).collect();
+    let (first, second) = match mode {
+        ChannelMode::LeftRight => (left.clone(), right.clone()),
+        ChannelMode::MidSide => {
+            let side = left.iter().zip(right).map(|(l, r)| (l - r) * 0.5).collect();
+            (mono.clone(), side)
+        }
+    };
+
+    // The three analyses are independent; run them on the rayon pool
+    let (mix, (first, second)) = rayon::join(
+        || analyze(mono),
+        || rayon::join(|| analyze(first), || analyze(second)),
+    );
+
+    Ok(AnalyzedSpectra {
+        mix: mix?,
+        channels: Some(ChannelSpectra {
+            mode,
+            first: first?,
+            second: second?,
+        }),
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::writer::{write_wav, BitDepth};
+
+    #[test]
+    fn streamed_bands_match_the_full_analysis() {
+        let dir = std::env::temp_dir().join(format!("eq-matcher-bands-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = AnalysisConfig::default();
+        let cancel = CancelToken::new();
+
+        // 96 kHz goes through the resampler, 48 kHz straight to the bands
+        for rate in [48_000u32, 96_000] {
+            let frames = rate as usize * 3 / 2;
+            let channels: Vec<Vec<f64>> = (0..2)
+                .map(|c| {
+                    (0..frames)
+                        .map(|i| {
+                            let t = i as f64 / rate as f64;
+                            0.3 * (2.0 * std::f64::consts::PI * 440.0 * t).sin()
+                                + 0.1
+                                    * (2.0 * std::f64::consts::PI * (3_000.0 + c as f64) * t).sin()
+                        })
+                        .collect()
+                })
+                .collect();
+            let path = dir.join(format!("{}.wav", rate));
+            write_wav(&path, &channels, rate, BitDepth::Float32, false).unwrap();
+
+            let policy = ResamplePolicy::DownsampleOnly;
+            let spectrum =
+                analyze_file_spectrum(&path, policy, &config, &cancel, 0, &mut |_, _| {}).unwrap();
+            let full = extract_eq_profile(&spectrum, &config);
+            let streamed = analyze_file_bands(&path, policy, &config, &cancel).unwrap();
+
+            assert_eq!(streamed.sample_rate, spectrum.sample_rate);
+            assert_eq!(streamed.profile.bands.len(), full.bands.len());
+            assert!((streamed.duration_secs - spectrum.duration_secs).abs() < 1e-4);
+            for (a, b) in streamed.profile.bands.iter().zip(&full.bands) {
+                assert!(
+                    (a.gain_db - b.gain_db).abs() < 1e-3,
+                    "{} Hz at {} Hz: {} vs {}",
+                    a.frequency,
+                    rate,
+                    a.gain_db,
+                    b.gain_db
+                );
+            }
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
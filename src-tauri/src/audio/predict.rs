@@ -0,0 +1,50 @@
+use super::profile::EQProfile;
+use crate::dsp::response::band_power_gain_db;
+
+pub const DEFAULT_PREDICTION_RATE: u32 = 48000;
+
+/// Estimates the profile `input` would have after rendering through
+/// `correction`, without touching audio: each band level moves by the
+/// composite filter's power gain over that band. Loudness follows the
+/// summed band energy; everything else is carried over from `input`.
+pub fn predict_corrected_profile(
+    input: &EQProfile,
+    correction: &EQProfile,
+    sample_rate: u32,
+) -> EQProfile {
+    let mut predicted = input.clone();
+    apply_response(&mut predicted, correction, sample_rate);
+
+    if let Some(channels) = predicted.channels.as_mut() {
+        apply_response(&mut channels.first, correction, sample_rate);
+        apply_response(&mut channels.second, correction, sample_rate);
+    }
+    predicted
+}
+
+fn apply_response(profile: &mut EQProfile, correction: &EQProfile, sample_rate: u32) {
+    let spans: Vec<(f32, f32)> = profile
+        .bands
+        .iter()
+        .map(|b| (b.frequency, b.bandwidth))
+        .collect();
+    let gains = band_power_gain_db(&correction.bands, sample_rate, &spans);
+
+    let before = band_energy(profile);
+    for (band, gain) in profile.bands.iter_mut().zip(gains) {
+        band.gain_db += gain;
+    }
+    let after = band_energy(profile);
+
+    if before > 0.0 && after > 0.0 {
+        profile.overall_loudness += 10.0 * (after / before).log10();
+    }
+}
+
+fn band_energy(profile: &EQProfile) -> f32 {
+    profile
+        .bands
+        .iter()
+        .map(|b| 10f32.powf(b.gain_db / 10.0))
+        .sum()
+}
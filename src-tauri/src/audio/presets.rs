@@ -0,0 +1,96 @@
+use super::matcher::MatchConfig;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchPreset {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub config: MatchConfig,
+}
+
+fn preset(id: &str, name: &str, description: &str, config: MatchConfig) -> MatchPreset {
+    MatchPreset {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+        config,
+    }
+}
+
+pub fn list_match_presets() -> Vec<MatchPreset> {
+    vec![
+        preset(
+            "subtle",
+            "Subtle",
+            "Gentle tonal nudge, safe on finished mixes",
+            MatchConfig {
+                intensity: 0.4,
+                max_correction: 3.0,
+                smoothing_factor: 0.8,
+                use_masking: true,
+                ..MatchConfig::default()
+            },
+        ),
+        preset(
+            "moderate",
+            "Moderate",
+            "Balanced default for most material",
+            MatchConfig::default(),
+        ),
+        preset(
+            "aggressive",
+            "Aggressive",
+            "Close match to the reference, review the warnings",
+            MatchConfig {
+                intensity: 1.0,
+                max_correction: 12.0,
+                smoothing_factor: 0.2,
+                preserve_dynamics: false,
+                ..MatchConfig::default()
+            },
+        ),
+        preset(
+            "mastering",
+            "Mastering",
+            "Broad, low-gain moves across the full mix",
+            MatchConfig {
+                intensity: 0.6,
+                max_correction: 4.0,
+                smoothing_factor: 0.9,
+                use_masking: true,
+                ..MatchConfig::default()
+            },
+        ),
+        preset(
+            "mixbus",
+            "Mix bus",
+            "Stronger tonal shaping while keeping dynamics intact",
+            MatchConfig {
+                intensity: 0.75,
+                max_correction: 6.0,
+                smoothing_factor: 0.6,
+                use_masking: true,
+                ..MatchConfig::default()
+            },
+        ),
+        preset(
+            "speech",
+            "Voice / podcast",
+            "Speech band only, protects intelligibility, suggests a high-pass",
+            MatchConfig::speech(),
+        ),
+        preset(
+            "room",
+            "Room correction",
+            "Full correction below the Schroeder frequency, no null boosting",
+            MatchConfig::room_correction(),
+        ),
+        preset(
+            "headphone",
+            "Headphone",
+            "Headphone DSP limits (±12 dB) and light smoothing only; matches the loaded reference, use match_headphone for a headphone target",
+            MatchConfig::headphone(),
+        ),
+    ]
+}
@@ -1,4 +1,8 @@
 use super::analyzer::{AnalysisConfig, FrequencySpectrum};
+use super::finite::{flag_non_finite, non_finite_in_profile, power_db, SILENCE_DB};
+use super::provenance::ProfileSource;
+use super::resonance::{detect_resonances, ResonanceSummary};
+use crate::dsp::filters::FilterType;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use statrs::statistics::Statistics;
@@ -10,6 +14,27 @@ pub struct EQProfile {
     pub dynamic_range: f32,     // dB
     pub spectral_centroid: f32, // Hz
     pub spectral_rolloff: f32,  // Hz
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Box<ChannelProfiles>>,
+    #[serde(default)]
+    pub resonance: ResonanceSummary, // Narrow peaks found in the full-resolution spectrum
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<Box<ProfileSource>>, // File and settings it was analyzed from
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelMode {
+    LeftRight,
+    MidSide,
+}
+
+// Per-channel profiles: (left, right) or (mid, side) depending on `mode`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelProfiles {
+    pub mode: ChannelMode,
+    pub first: EQProfile,
+    pub second: EQProfile,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +43,18 @@ pub struct FrequencyBand {
     pub gain_db: f32,
     pub bandwidth: f32,  // For Q calculation
     pub confidence: f32, // 0.0 - 1.0
+    #[serde(default)]
+    pub temporal_spread_db: f32, // Variation of the band level across analysis windows
+    #[serde(default)]
+    pub filter_type: FilterType,
+    #[serde(default)]
+    pub out_of_range: bool, // Band reaches past Nyquist; its level is partial or missing
+}
+
+impl FrequencyBand {
+    pub fn q(&self) -> f32 {
+        self.frequency / self.bandwidth
+    }
 }
 
 pub fn extract_eq_profile(spectrum: &FrequencySpectrum, config: &AnalysisConfig) -> EQProfile {
@@ -32,23 +69,54 @@ pub fn extract_eq_profile(spectrum: &FrequencySpectrum, config: &AnalysisConfig)
     let spectral_centroid = calculate_spectral_centroid(spectrum);
     let spectral_rolloff = calculate_spectral_rolloff(spectrum, 0.85);
 
-    EQProfile {
+    let profile = EQProfile {
         bands,
         overall_loudness,
         dynamic_range,
         spectral_centroid,
         spectral_rolloff,
+        channels: None,
+        resonance: detect_resonances(spectrum),
+        source: None,
+    };
+    flag_non_finite("profile", &non_finite_in_profile(&profile));
+    profile
+}
+
+/// A profile from band levels alone, as `BandAccumulator` gives them.
+/// Loudness and dynamic range are taken over the bands instead of the bins;
+/// centroid, rolloff and resonances need the full spectrum and stay empty.
+pub fn band_profile(bands: Vec<FrequencyBand>) -> EQProfile {
+    let levels: Vec<f32> = bands.iter().map(|b| b.gain_db).collect();
+    EQProfile {
+        overall_loudness: calculate_overall_loudness(&levels),
+        dynamic_range: calculate_dynamic_range(&levels),
+        bands,
+        spectral_centroid: 0.0,
+        spectral_rolloff: 0.0,
+        channels: None,
+        resonance: Default::default(),
+        source: None,
     }
 }
 
-fn extract_band_info(spectrum: &FrequencySpectrum, center_freq: f32) -> FrequencyBand {
+pub(crate) fn third_octave_edges(center_freq: f32) -> (f32, f32) {
+    (
+        center_freq / 2.0f32.powf(1.0 / 6.0),
+        center_freq * 2.0f32.powf(1.0 / 6.0),
+    )
+}
+
+pub(crate) fn extract_band_info(spectrum: &FrequencySpectrum, center_freq: f32) -> FrequencyBand {
     // 1/3 octave bandwidth
     let bandwidth = center_freq * 0.23;
-    let lower = center_freq / 2.0f32.powf(1.0 / 6.0);
-    let upper = center_freq * 2.0f32.powf(1.0 / 6.0);
+    let (lower, upper) = third_octave_edges(center_freq);
+
+    let nyquist = spectrum.sample_rate as f32 / 2.0;
+    let out_of_range = upper > nyquist;
 
     // Find bins in this range
-    let bin_indices: Vec<usize> = spectrum
+    let mut bin_indices: Vec<usize> = spectrum
         .frequencies
         .iter()
         .enumerate()
@@ -61,12 +129,20 @@ fn extract_band_info(spectrum: &FrequencySpectrum, center_freq: f32) -> Frequenc
         })
         .collect();
 
+    // Narrow low bands (20 Hz at 48 kHz) can fall between two bins
+    if bin_indices.is_empty() && center_freq < nyquist {
+        bin_indices.extend(nearest_bin(&spectrum.frequencies, center_freq));
+    }
+
     if bin_indices.is_empty() {
         return FrequencyBand {
             frequency: center_freq,
             gain_db: -80.0,
             bandwidth,
             confidence: 0.0,
+            temporal_spread_db: 0.0,
+            filter_type: FilterType::Peaking,
+            out_of_range,
         };
     }
 
@@ -78,35 +154,75 @@ fn extract_band_info(spectrum: &FrequencySpectrum, center_freq: f32) -> Frequenc
 
     let gain_db = band_magnitudes.clone().mean() as f32;
 
-    // Confidence based on consistency
-    let std_dev = band_magnitudes.clone().std_dev() as f32;
+    // Confidence based on consistency; a lone bin (low bands at 44.1 kHz)
+    // has no sample deviation, so don't let it turn into NaN
+    let std_dev = if band_magnitudes.len() > 1 {
+        band_magnitudes.clone().std_dev() as f32
+    } else {
+        0.0
+    };
     let confidence = (1.0 / (1.0 + std_dev / 10.0)).clamp(0.0, 1.0);
 
+    let temporal_spread_db = bin_indices
+        .iter()
+        .map(|&i| spectrum.spread_db[i])
+        .sum::<f32>()
+        / bin_indices.len() as f32;
+
     FrequencyBand {
         frequency: center_freq,
         gain_db,
         bandwidth,
         confidence,
+        temporal_spread_db,
+        filter_type: FilterType::Peaking,
+        out_of_range,
     }
 }
 
-fn calculate_overall_loudness(magnitudes: &[f32]) -> f32 {
-    // A-weighting approximation
-    let rms: f32 = magnitudes
+pub(crate) fn nearest_bin(frequencies: &[f32], freq: f32) -> Option<usize> {
+    frequencies
         .iter()
-        .map(|&m| {
-            let linear = 10.0f32.powf(m / 20.0);
-            linear * linear
-        })
-        .sum::<f32>()
-        / magnitudes.len() as f32;
+        .enumerate()
+        .min_by(|a, b| (a.1 - freq).abs().total_cmp(&(b.1 - freq).abs()))
+        .map(|(i, _)| i)
+}
+
+// Spectrum bins as linear power; NaN bins count as silence
+fn bin_powers(magnitudes: &[f32]) -> impl Iterator<Item = f32> + '_ {
+    magnitudes.iter().map(|&m| {
+        if m.is_nan() {
+            0.0
+        } else {
+            10.0f32.powf(m / 10.0)
+        }
+    })
+}
 
-    20.0 * rms.sqrt().log10()
+fn calculate_overall_loudness(magnitudes: &[f32]) -> f32 {
+    // A-weighting approximation
+    if magnitudes.is_empty() {
+        return SILENCE_DB;
+    }
+    power_db(bin_powers(magnitudes).sum::<f32>() / magnitudes.len() as f32)
 }
 
 fn calculate_dynamic_range(magnitudes: &[f32]) -> f32 {
-    let mut sorted = magnitudes.to_vec();
-    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    // NaN and -inf bins sit at the silence floor so percentiles stay finite
+    let mut sorted: Vec<f32> = magnitudes
+        .iter()
+        .map(|&m| {
+            if m.is_nan() {
+                SILENCE_DB
+            } else {
+                m.max(SILENCE_DB)
+            }
+        })
+        .collect();
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    sorted.sort_by(f32::total_cmp);
 
     let percentile_95 = sorted[(sorted.len() as f32 * 0.95) as usize];
     let percentile_5 = sorted[(sorted.len() as f32 * 0.05) as usize];
@@ -118,13 +234,17 @@ fn calculate_spectral_centroid(spectrum: &FrequencySpectrum) -> f32 {
     let mut weighted_sum = 0.0;
     let mut total_magnitude = 0.0;
 
-    for (freq, mag) in spectrum.frequencies.iter().zip(&spectrum.magnitudes) {
-        let linear_mag = 10.0f32.powf(mag / 20.0);
+    for (freq, power) in spectrum
+        .frequencies
+        .iter()
+        .zip(bin_powers(&spectrum.magnitudes))
+    {
+        let linear_mag = power.sqrt();
         weighted_sum += freq * linear_mag;
         total_magnitude += linear_mag;
     }
 
-    if total_magnitude == 0.0 {
+    if !(total_magnitude > 0.0 && total_magnitude.is_finite()) {
         return 0.0;
     }
 
@@ -132,17 +252,17 @@ fn calculate_spectral_centroid(spectrum: &FrequencySpectrum) -> f32 {
 }
 
 fn calculate_spectral_rolloff(spectrum: &FrequencySpectrum, threshold: f32) -> f32 {
-    let total_energy: f32 = spectrum
-        .magnitudes
-        .iter()
-        .map(|&m| 10.0f32.powf(m / 10.0))
-        .sum();
+    let total_energy: f32 = bin_powers(&spectrum.magnitudes).sum();
 
     let threshold_energy = total_energy * threshold;
     let mut cumulative = 0.0;
 
-    for (freq, mag) in spectrum.frequencies.iter().zip(&spectrum.magnitudes) {
-        cumulative += 10.0f32.powf(mag / 10.0);
+    for (freq, power) in spectrum
+        .frequencies
+        .iter()
+        .zip(bin_powers(&spectrum.magnitudes))
+    {
+        cumulative += power;
         if cumulative >= threshold_energy {
             return *freq;
         }
@@ -0,0 +1,198 @@
+use super::analyzer::{AnalysisConfig, WindowType};
+use super::profile::EQProfile;
+use crate::jobs::CancelToken;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HASH_CHUNK: usize = 1 << 20;
+
+/// Where a profile came from: the file, how much of it was analyzed and
+/// with which settings. Profiles built by hand, blended or derived from a
+/// curve have none.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileSource {
+    pub path: String,
+    pub sha256: String, // Of the file's bytes, hex
+    pub duration_secs: f32,
+    pub sample_rate: u32, // Rate the analysis ran at, after resampling
+    pub analysis: AnalysisSummary,
+    pub created_at: u64, // Unix seconds
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisSummary {
+    pub fft_size: usize,
+    pub window_type: WindowType,
+    pub overlap: f32,
+    pub band_count: usize,
+    #[serde(default)]
+    pub preset: Option<String>,
+}
+
+impl ProfileSource {
+    /// Provenance of `spectrum`, analyzed from `path` with `config`.
+    pub fn new(
+        path: &str,
+        duration_secs: f32,
+        sample_rate: u32, // Analyzed rate
+        config: &AnalysisConfig,
+        preset: Option<&str>,
+        cancel: &CancelToken,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            path: path.to_string(),
+            sha256: file_sha256(Path::new(path), cancel)?,
+            duration_secs,
+            sample_rate,
+            analysis: AnalysisSummary {
+                fft_size: config.fft_size,
+                window_type: config.window_type,
+                overlap: config.overlap,
+                band_count: config.frequency_bands.len(),
+                preset: preset.map(str::to_string),
+            },
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        })
+    }
+
+    // One line for reports: file, length, rate and FFT settings
+    pub fn describe(&self) -> String {
+        let secs = self.duration_secs.max(0.0).round() as u64;
+        format!(
+            "{} ({}:{:02}, {} Hz, FFT {} {:?} {:.0}% overlap, sha256 {})",
+            self.path,
+            secs / 60,
+            secs % 60,
+            self.sample_rate,
+            self.analysis.fft_size,
+            self.analysis.window_type,
+            self.analysis.overlap * 100.0,
+            &self.sha256[..self.sha256.len().min(12)]
+        )
+    }
+}
+
+/// Differences in how two profiles were analyzed that make their band
+/// levels disagree on their own, e.g. "FFT size 8192 vs 4096". Empty when
+/// either profile has no provenance.
+pub fn settings_mismatch(a: &EQProfile, b: &EQProfile) -> Vec<String> {
+    let (Some(a), Some(b)) = (&a.source, &b.source) else {
+        return Vec::new();
+    };
+    let mut differences = Vec::new();
+    if a.analysis.fft_size != b.analysis.fft_size {
+        differences.push(format!(
+            "FFT size {} vs {}",
+            a.analysis.fft_size, b.analysis.fft_size
+        ));
+    }
+    if a.analysis.window_type != b.analysis.window_type {
+        differences.push(format!(
+            "window {:?} vs {:?}",
+            a.analysis.window_type, b.analysis.window_type
+        ));
+    }
+    if (a.analysis.overlap - b.analysis.overlap).abs() > 1e-3 {
+        differences.push(format!(
+            "overlap {:.0}% vs {:.0}%",
+            a.analysis.overlap * 100.0,
+            b.analysis.overlap * 100.0
+        ));
+    }
+    if a.sample_rate != b.sample_rate {
+        differences.push(format!(
+            "analysis rate {} Hz vs {} Hz",
+            a.sample_rate, b.sample_rate
+        ));
+    }
+    differences
+}
+
+/// Cheap identity of a file's contents for deduplicating requests: a
+/// SHA-256 over its size and its first and last `HASH_CHUNK` bytes. Unlike
+/// size and mtime it survives touching or copying the file, and it changes
+/// when the audio is rewritten in place within the same second.
+pub fn content_fingerprint(path: &Path) -> Result<String, String> {
+    let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+    let mut file = File::open(path).map_err(error)?;
+    let size = file.metadata().map_err(error)?.len();
+
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+    let mut buffer = Vec::with_capacity(HASH_CHUNK);
+    (&mut file)
+        .take(HASH_CHUNK as u64)
+        .read_to_end(&mut buffer)
+        .map_err(error)?;
+    hasher.update(&buffer);
+    // Files up to two chunks long are covered whole
+    let tail = size
+        .saturating_sub(HASH_CHUNK as u64)
+        .max(buffer.len() as u64);
+    if tail < size {
+        buffer.clear();
+        file.seek(SeekFrom::Start(tail)).map_err(error)?;
+        file.read_to_end(&mut buffer).map_err(error)?;
+        hasher.update(&buffer);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+// Streams the file, checking `cancel` between chunks
+pub fn file_sha256(path: &Path, cancel: &CancelToken) -> Result<String, String> {
+    let mut file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; HASH_CHUNK];
+    loop {
+        cancel.check()?;
+        let read = file.read(&mut buffer).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_fingerprint_follows_head_tail_and_size() {
+        let dir = std::env::temp_dir().join(format!("eq-matcher-fp-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audio.bin");
+        let mut bytes: Vec<u8> = (0..3 * HASH_CHUNK).map(|i| (i % 251) as u8).collect();
+        let fingerprint = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            content_fingerprint(&path).unwrap()
+        };
+
+        let original = fingerprint(&bytes);
+        assert_eq!(fingerprint(&bytes), original);
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert_ne!(fingerprint(&bytes), original);
+        bytes[last] ^= 1;
+        bytes.push(0);
+        assert_ne!(fingerprint(&bytes), original);
+        // Short files are hashed whole
+        assert_ne!(fingerprint(&bytes[..10]), fingerprint(&bytes[..11]));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
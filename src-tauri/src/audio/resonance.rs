@@ -0,0 +1,163 @@
+use super::analyzer::FrequencySpectrum;
+use serde::{Deserialize, Serialize};
+
+const MIN_PROMINENCE_DB: f32 = 6.0;
+const MAX_RESONANCES: usize = 8;
+const SEARCH_RANGE_HZ: (f32, f32) = (100.0, 12000.0);
+const SIBILANCE_RANGE_HZ: (f32, f32) = (5000.0, 9000.0);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResonanceSummary {
+    pub resonances: Vec<Resonance>,
+    pub sibilance_db: f32, // Sibilance band level relative to the full-band mean
+    pub sibilance_peak_hz: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resonance {
+    pub frequency: f32,
+    pub prominence_db: f32, // Height above the 1/3 octave smoothed spectrum
+    pub q: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NarrowBandKind {
+    Resonance,
+    Sibilance,
+}
+
+/// Targeted narrow cut, kept separate from the broad tonal correction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NarrowBandSuggestion {
+    pub kind: NarrowBandKind,
+    pub frequency: f32,
+    pub gain_db: f32,
+    pub q: f32,         // Always > 4
+    pub excess_db: f32, // How far the input exceeds the reference
+}
+
+pub fn detect_resonances(spectrum: &FrequencySpectrum) -> ResonanceSummary {
+    let mags = &spectrum.magnitudes;
+    let freqs = &spectrum.frequencies;
+    let smoothed = smooth_third_octave(freqs, mags);
+
+    let mut resonances: Vec<Resonance> = (1..mags.len().saturating_sub(1))
+        .filter(|&i| freqs[i] >= SEARCH_RANGE_HZ.0 && freqs[i] <= SEARCH_RANGE_HZ.1)
+        .filter(|&i| mags[i] > mags[i - 1] && mags[i] >= mags[i + 1])
+        .filter_map(|i| {
+            let prominence_db = mags[i] - smoothed[i];
+            (prominence_db >= MIN_PROMINENCE_DB).then(|| Resonance {
+                frequency: freqs[i],
+                prominence_db,
+                q: estimate_q(freqs, mags, i),
+            })
+        })
+        .collect();
+
+    resonances.sort_by(|a, b| b.prominence_db.total_cmp(&a.prominence_db));
+    resonances.truncate(MAX_RESONANCES);
+
+    let (sibilance_db, sibilance_peak_hz) = measure_sibilance(freqs, mags);
+
+    ResonanceSummary {
+        resonances,
+        sibilance_db,
+        sibilance_peak_hz,
+    }
+}
+
+// Resonances the reference doesn't share, plus excess sibilance
+pub fn suggest_narrow_cuts(
+    reference: &ResonanceSummary,
+    input: &ResonanceSummary,
+) -> Vec<NarrowBandSuggestion> {
+    let mut suggestions: Vec<NarrowBandSuggestion> = input
+        .resonances
+        .iter()
+        .filter_map(|res| {
+            let ref_prominence = reference
+                .resonances
+                .iter()
+                .filter(|r| (r.frequency / res.frequency).log2().abs() < 1.0 / 6.0)
+                .map(|r| r.prominence_db)
+                .fold(0.0f32, f32::max);
+            let excess_db = res.prominence_db - ref_prominence;
+
+            (excess_db >= MIN_PROMINENCE_DB / 2.0).then(|| NarrowBandSuggestion {
+                kind: NarrowBandKind::Resonance,
+                frequency: res.frequency,
+                gain_db: -(excess_db * 0.7).min(9.0),
+                q: res.q.clamp(4.5, 16.0),
+                excess_db,
+            })
+        })
+        .collect();
+
+    let sibilance_excess = input.sibilance_db - reference.sibilance_db;
+    if sibilance_excess > 2.0 && input.sibilance_peak_hz > 0.0 {
+        suggestions.push(NarrowBandSuggestion {
+            kind: NarrowBandKind::Sibilance,
+            frequency: input.sibilance_peak_hz,
+            gain_db: -sibilance_excess.min(8.0),
+            q: 4.5,
+            excess_db: sibilance_excess,
+        });
+    }
+
+    suggestions
+}
+
+// Moving average over ±1/6 octave using prefix sums
+fn smooth_third_octave(freqs: &[f32], mags: &[f32]) -> Vec<f32> {
+    let mut prefix = Vec::with_capacity(mags.len() + 1);
+    prefix.push(0.0f64);
+    for &m in mags {
+        prefix.push(prefix.last().unwrap() + m as f64);
+    }
+
+    let ratio = 2.0f32.powf(1.0 / 6.0);
+    freqs
+        .iter()
+        .map(|&f| {
+            let lo = freqs.partition_point(|&x| x < f / ratio);
+            let hi = freqs.partition_point(|&x| x <= f * ratio).max(lo + 1);
+            ((prefix[hi] - prefix[lo]) / (hi - lo) as f64) as f32
+        })
+        .collect()
+}
+
+// Q from the -3 dB width around the peak
+fn estimate_q(freqs: &[f32], mags: &[f32], peak: usize) -> f32 {
+    let threshold = mags[peak] - 3.0;
+    let lo = (0..peak).rev().find(|&i| mags[i] < threshold).unwrap_or(0);
+    let hi = (peak + 1..mags.len())
+        .find(|&i| mags[i] < threshold)
+        .unwrap_or(mags.len() - 1);
+    let width = (freqs[hi] - freqs[lo]).max(f32::EPSILON);
+
+    freqs[peak] / width
+}
+
+fn measure_sibilance(freqs: &[f32], mags: &[f32]) -> (f32, f32) {
+    let in_band: Vec<(f32, f32)> = freqs
+        .iter()
+        .zip(mags)
+        .filter(|(&f, _)| f >= SIBILANCE_RANGE_HZ.0 && f <= SIBILANCE_RANGE_HZ.1)
+        .map(|(&f, &m)| (f, m))
+        .collect();
+
+    if in_band.is_empty() || mags.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let overall = mags.iter().sum::<f32>() / mags.len() as f32;
+    let band = in_band.iter().map(|(_, m)| m).sum::<f32>() / in_band.len() as f32;
+    let peak_hz = in_band
+        .iter()
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(f, _)| *f)
+        .unwrap_or(0.0);
+
+    (band - overall, peak_hz)
+}
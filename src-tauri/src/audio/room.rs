@@ -0,0 +1,152 @@
+use super::profile::FrequencyBand;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_SCHROEDER_HZ: f32 = 300.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomCorrectionConfig {
+    pub schroeder_frequency: Option<f32>, // Hz, overrides the estimate
+    pub room_volume_m3: Option<f32>,
+    pub rt60_secs: Option<f32>,
+    pub above_schroeder_scale: f32, // 0.0 - 1.0, correction kept above the transition
+    pub null_depth_db: f32, // Dips deeper than this below their neighbours are never boosted
+}
+
+impl Default for RoomCorrectionConfig {
+    fn default() -> Self {
+        Self {
+            schroeder_frequency: None,
+            room_volume_m3: None,
+            rt60_secs: None,
+            above_schroeder_scale: 0.3,
+            null_depth_db: 6.0,
+        }
+    }
+}
+
+impl RoomCorrectionConfig {
+    // f_s ≈ 2000 * sqrt(RT60 / V), falling back to a typical small-room value
+    pub fn schroeder_hz(&self) -> f32 {
+        if let Some(freq) = self.schroeder_frequency {
+            return freq;
+        }
+
+        match (self.rt60_secs, self.room_volume_m3) {
+            (Some(rt60), Some(volume)) if rt60 > 0.0 && volume > 0.0 => {
+                2000.0 * (rt60 / volume).sqrt()
+            }
+            _ => DEFAULT_SCHROEDER_HZ,
+        }
+    }
+}
+
+/// Modal region gets full correction, boosts into deep nulls are removed and
+/// everything above the Schroeder frequency is scaled down and heavily smoothed.
+/// Returns the indices of the nulls, for `guard_nulls` after later stages.
+pub fn apply_room_limits(
+    bands: &mut [FrequencyBand],
+    input_normalized: &[f32],
+    config: &RoomCorrectionConfig,
+) -> Vec<usize> {
+    let schroeder = config.schroeder_hz();
+
+    let nulls: Vec<usize> = (0..bands.len())
+        .filter(|&i| is_deep_null(input_normalized, i, config.null_depth_db))
+        .collect();
+    guard_nulls(bands, &nulls);
+
+    let split = bands
+        .iter()
+        .position(|b| b.frequency > schroeder)
+        .unwrap_or(bands.len());
+    for band in &mut bands[split..] {
+        band.gain_db *= config.above_schroeder_scale;
+    }
+
+    // Heavy smoothing: pull each band towards the mean of the upper region.
+    // Nulls neither count towards the mean nor get pulled up by it
+    let smoothed: Vec<usize> = (split..bands.len())
+        .filter(|i| !nulls.contains(i))
+        .collect();
+    if !smoothed.is_empty() {
+        let mean = smoothed.iter().map(|&i| bands[i].gain_db).sum::<f32>() / smoothed.len() as f32;
+        for &i in &smoothed {
+            bands[i].gain_db = bands[i].gain_db * 0.3 + mean * 0.7;
+        }
+    }
+
+    nulls
+}
+
+/// Removes any boost at the `nulls` from `apply_room_limits`. Smoothing and
+/// the excess spill of later stages would otherwise bring some back.
+pub fn guard_nulls(bands: &mut [FrequencyBand], nulls: &[usize]) {
+    for &i in nulls {
+        if let Some(band) = bands.get_mut(i) {
+            band.gain_db = band.gain_db.min(0.0);
+        }
+    }
+}
+
+fn is_deep_null(levels: &[f32], index: usize, depth_db: f32) -> bool {
+    let neighbours: Vec<f32> = [index.checked_sub(1), Some(index + 1)]
+        .iter()
+        .flatten()
+        .filter_map(|&i| levels.get(i).copied())
+        .collect();
+
+    if neighbours.is_empty() {
+        return false;
+    }
+
+    let neighbour_mean = neighbours.iter().sum::<f32>() / neighbours.len() as f32;
+    levels
+        .get(index)
+        .map(|&level| neighbour_mean - level > depth_db)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::filters::FilterType;
+
+    fn bands(gains: &[f32], start_hz: f32) -> Vec<FrequencyBand> {
+        gains
+            .iter()
+            .enumerate()
+            .map(|(i, &gain_db)| {
+                let frequency = start_hz * 2f32.powf(i as f32 / 3.0);
+                FrequencyBand {
+                    frequency,
+                    gain_db,
+                    bandwidth: frequency * 0.23,
+                    confidence: 1.0,
+                    temporal_spread_db: 0.0,
+                    filter_type: FilterType::Peaking,
+                    out_of_range: false,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nulls_above_schroeder_are_not_pulled_up_by_the_mean() {
+        // Everything above the transition, a 16 dB null in the middle
+        let input = [0.0, 0.0, -16.0, 0.0, 0.0];
+        let mut corrections = bands(&[4.0, 4.0, 12.0, 4.0, 4.0], 1000.0);
+        let nulls = apply_room_limits(&mut corrections, &input, &RoomCorrectionConfig::default());
+
+        assert_eq!(nulls, vec![2]);
+        assert!(corrections[2].gain_db <= 0.0);
+        assert!(corrections[1].gain_db > 0.0);
+    }
+
+    #[test]
+    fn cuts_at_nulls_are_kept() {
+        let mut corrections = bands(&[2.0, -3.0, 5.0], 100.0);
+        guard_nulls(&mut corrections, &[1, 2, 7]);
+        let gains: Vec<f32> = corrections.iter().map(|b| b.gain_db).collect();
+        assert_eq!(gains, vec![2.0, -3.0, 0.0]);
+    }
+}
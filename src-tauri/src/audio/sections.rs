@@ -0,0 +1,128 @@
+use super::analyzer::{analyze_spectrum, AnalysisConfig};
+use super::deviation::{deviation_over_time, DeviationMap, DEFAULT_SEGMENT_SECS};
+use super::matcher::{match_profiles, MatchConfig};
+use super::profile::{extract_eq_profile, EQProfile};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// RMS distance (dB) from the running section's shape that starts a new one
+const SECTION_CHANGE_DB: f32 = 3.0;
+const MIN_SECTION_SEGMENTS: usize = 2; // A section lasts at least this long
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionCorrection {
+    pub start_secs: f32,
+    pub end_secs: f32,
+    pub correction: EQProfile,
+    pub quality_score: f32,
+}
+
+/// One correction per section of the input, in timeline order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionMatch {
+    pub duration_secs: f32,
+    pub sections: Vec<SectionCorrection>,
+}
+
+/// Matches each section of `samples` to `reference` on its own. Sections
+/// start at 0 and at each of `boundaries_secs`; with none given they are
+/// found from where the input's deviation from the reference changes.
+pub fn match_sections(
+    samples: &[f32],
+    sample_rate: u32,
+    reference: &EQProfile,
+    analysis: &AnalysisConfig,
+    config: &MatchConfig,
+    boundaries_secs: Option<&[f32]>,
+) -> Result<SectionMatch, String> {
+    if sample_rate == 0 {
+        return Err("Sample rate must be positive".to_string());
+    }
+    let duration_secs = samples.len() as f32 / sample_rate as f32;
+    let analysis = AnalysisConfig {
+        frequency_bands: reference.bands.iter().map(|b| b.frequency).collect(),
+        ..analysis.clone()
+    };
+
+    let mut starts = match boundaries_secs {
+        Some(boundaries) => boundaries.to_vec(),
+        None => {
+            let map = deviation_over_time(
+                samples,
+                sample_rate,
+                reference,
+                &analysis,
+                DEFAULT_SEGMENT_SECS,
+            )?;
+            find_boundaries(&map)
+        }
+    };
+    // Each section needs at least one analysis window
+    let min_secs = analysis.fft_size as f32 / sample_rate as f32;
+    starts.retain(|s| s.is_finite() && *s >= min_secs && *s <= duration_secs - min_secs);
+    starts.sort_by(f32::total_cmp);
+    starts.dedup_by(|b, a| *b - *a < min_secs);
+    starts.insert(0, 0.0);
+
+    let ends: Vec<f32> = starts
+        .iter()
+        .skip(1)
+        .copied()
+        .chain([duration_secs])
+        .collect();
+    let sections = starts
+        .par_iter()
+        .zip(&ends)
+        .map(|(&start, &end)| {
+            let from = (start * sample_rate as f32) as usize;
+            let to = ((end * sample_rate as f32) as usize).min(samples.len());
+            let spectrum = analyze_spectrum(&samples[from..to], sample_rate, &analysis)?;
+            let result =
+                match_profiles(reference, &extract_eq_profile(&spectrum, &analysis), config);
+            Ok(SectionCorrection {
+                start_secs: start,
+                end_secs: end,
+                correction: result.correction_profile,
+                quality_score: result.quality_score,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(SectionMatch {
+        duration_secs,
+        sections,
+    })
+}
+
+// Starts a section where a segment's deviation moves away from the mean of
+// the section so far; silent segments stay with the section they are in
+fn find_boundaries(map: &DeviationMap) -> Vec<f32> {
+    let mut boundaries = Vec::new();
+    let mut sum: Vec<f32> = vec![0.0; map.frequencies.len()];
+    let mut count = 0;
+
+    for (i, row) in map.deviation_db.iter().enumerate() {
+        if map.silent[i] {
+            continue;
+        }
+        if count >= MIN_SECTION_SEGMENTS {
+            let distance = (row
+                .iter()
+                .zip(&sum)
+                .map(|(v, s)| (v - s / count as f32).powi(2))
+                .sum::<f32>()
+                / row.len().max(1) as f32)
+                .sqrt();
+            if distance > SECTION_CHANGE_DB {
+                boundaries.push(map.start_secs[i]);
+                sum.iter_mut().for_each(|s| *s = 0.0);
+                count = 0;
+            }
+        }
+        for (s, v) in sum.iter_mut().zip(row) {
+            *s += v;
+        }
+        count += 1;
+    }
+    boundaries
+}
@@ -0,0 +1,114 @@
+use super::analyzer::AnalysisConfig;
+use crate::dsp::{fft, simd};
+use rayon::prelude::*;
+use rustfft::num_complex::Complex;
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_ROWS: usize = 240; // 24 per octave over 20 Hz - 20 kHz
+pub const DEFAULT_MAX_COLUMNS: usize = 1600;
+const LOWEST_HZ: f32 = 20.0;
+const HIGHEST_HZ: f32 = 20_000.0;
+const FLOOR_DB: f32 = -140.0;
+
+/// Short-time magnitudes on a log frequency axis, for display. Columns are
+/// FFT frames, rows log-spaced frequencies; values are dBFS (a full-scale
+/// sine reads 0 dB at its frequency).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spectrogram {
+    pub times_secs: Vec<f32>,         // Per column, frame center
+    pub frequencies: Vec<f32>,        // Per row, ascending
+    pub magnitudes_db: Vec<Vec<f32>>, // [column][row]
+    pub duration_secs: f32,
+    pub sample_rate: u32,
+}
+
+/// Spectrogram of mono `samples` with `config`'s FFT size and window. The
+/// hop grows past the configured one when needed to stay within
+/// `max_columns` frames, so long files stay cheap to compute and draw.
+pub fn compute_spectrogram(
+    samples: &[f32],
+    sample_rate: u32,
+    config: &AnalysisConfig,
+    rows: usize,
+    max_columns: usize,
+) -> Result<Spectrogram, String> {
+    config.validate()?;
+    if sample_rate == 0 {
+        return Err("Sample rate must be positive".to_string());
+    }
+    // The hop is spread over max_columns - 1 steps
+    if rows < 2 || max_columns < 2 {
+        return Err("A spectrogram needs at least two rows and two columns".to_string());
+    }
+    let size = config.fft_size;
+    let frames = samples.len().saturating_sub(size) / config.hop_size() + 1;
+    let hop = if frames > max_columns {
+        (samples.len() - size).div_ceil(max_columns - 1).max(1)
+    } else {
+        config.hop_size()
+    };
+    let columns = samples.len().saturating_sub(size) / hop + 1;
+
+    let high = HIGHEST_HZ.min(sample_rate as f32 / 2.0);
+    let ratio = (high / LOWEST_HZ).powf(1.0 / (rows - 1) as f32);
+    let frequencies: Vec<f32> = (0..rows)
+        .map(|r| LOWEST_HZ * ratio.powi(r as i32))
+        .collect();
+    // Bins from half a row below to half a row above each center, loudest
+    // wins; rows narrower than a bin take the nearest one
+    let bin_hz = sample_rate as f32 / size as f32;
+    let spans: Vec<(usize, usize)> = frequencies
+        .iter()
+        .map(|&f| {
+            let nearest = ((f / bin_hz).round() as usize).min(size / 2);
+            let low = ((f / ratio.sqrt() / bin_hz).ceil() as usize).min(size / 2);
+            let high = ((f * ratio.sqrt() / bin_hz).floor() as usize).min(size / 2);
+            if low <= high {
+                (low, high)
+            } else {
+                (nearest, nearest)
+            }
+        })
+        .collect();
+
+    let window = config.window_type.window(size);
+    // Amplitude of a full-scale sine through this window
+    let full_scale = window.iter().sum::<f32>() / 2.0;
+    let magnitudes_db = (0..columns)
+        .into_par_iter()
+        .map_init(
+            || {
+                let fft = fft::forward(size);
+                let scratch = vec![Complex::new(0.0f32, 0.0); fft.get_inplace_scratch_len()];
+                (fft, vec![Complex::new(0.0f32, 0.0); size], scratch)
+            },
+            |(fft, buffer, scratch), column| {
+                let start = column * hop;
+                let mut frame = samples[start..(start + size).min(samples.len())].to_vec();
+                frame.resize(size, 0.0);
+                simd::apply_window(&frame, &window, buffer);
+                fft.process_with_scratch(buffer, scratch);
+                spans
+                    .iter()
+                    .map(|&(low, high)| {
+                        let power = buffer[low..=high]
+                            .iter()
+                            .map(|c| c.norm_sqr())
+                            .fold(0.0f32, f32::max);
+                        (10.0 * (power / (full_scale * full_scale) + 1e-20).log10()).max(FLOOR_DB)
+                    })
+                    .collect()
+            },
+        )
+        .collect();
+
+    Ok(Spectrogram {
+        times_secs: (0..columns)
+            .map(|c| (c * hop + size / 2) as f32 / sample_rate as f32)
+            .collect(),
+        frequencies,
+        magnitudes_db,
+        duration_secs: samples.len() as f32 / sample_rate as f32,
+        sample_rate,
+    })
+}
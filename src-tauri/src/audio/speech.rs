@@ -0,0 +1,56 @@
+use super::curve::ResponseCurve;
+use super::profile::FrequencyBand;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeechConfig {
+    pub low_limit_hz: f32,   // No correction below
+    pub high_limit_hz: f32,  // No correction above
+    pub protect_low_hz: f32, // Intelligibility region that is never cut
+    pub protect_high_hz: f32,
+    pub high_pass_hz: f32, // Recommended rumble filter
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        Self {
+            low_limit_hz: 80.0,
+            high_limit_hz: 12000.0,
+            protect_low_hz: 1000.0,
+            protect_high_hz: 4000.0,
+            high_pass_hz: 80.0,
+        }
+    }
+}
+
+// ANSI S3.5 octave-band importance, normalized to the 2 kHz peak
+fn speech_importance() -> ResponseCurve {
+    ResponseCurve::new(vec![
+        (250.0, 0.23),
+        (500.0, 0.63),
+        (1000.0, 0.90),
+        (2000.0, 1.0),
+        (4000.0, 0.81),
+        (8000.0, 0.21),
+    ])
+}
+
+pub fn apply_speech_limits(bands: &mut [FrequencyBand], config: &SpeechConfig) {
+    let importance = speech_importance();
+
+    for band in bands.iter_mut() {
+        if band.frequency < config.low_limit_hz || band.frequency > config.high_limit_hz {
+            band.gain_db = 0.0;
+            continue;
+        }
+
+        // Less important bands get gentler moves
+        band.gain_db *= 0.4 + 0.6 * importance.gain_at(band.frequency);
+
+        let protected =
+            band.frequency >= config.protect_low_hz && band.frequency <= config.protect_high_hz;
+        if protected && band.gain_db < 0.0 {
+            band.gain_db = 0.0;
+        }
+    }
+}
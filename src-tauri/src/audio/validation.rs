@@ -0,0 +1,168 @@
+use super::analyzer::{AnalysisConfig, MAX_FFT_SIZE, MAX_OVERLAP, MIN_FFT_SIZE};
+use super::matcher::MatchConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const MAX_CORRECTION_LIMIT_DB: f32 = 48.0;
+
+/// One rejected setting: `field` is its path in the config as the frontend
+/// sends it (`room_correction.null_depth_db`, `frequency_bands[3]`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+/// Every problem found in a config, so a settings form can mark all the
+/// bad fields at once instead of one per attempt.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl ValidationErrors {
+    fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    // Checks a number lies in `min..=max`; NaN and infinities never do
+    fn range(&mut self, field: &str, value: f32, min: f32, max: f32) {
+        if !(value.is_finite() && (min..=max).contains(&value)) {
+            self.push(
+                field,
+                format!("Must be between {} and {}, got {}", min, max, value),
+            );
+        }
+    }
+
+    fn positive(&mut self, field: &str, value: f32) {
+        if !(value.is_finite() && value > 0.0) {
+            self.push(field, format!("Must be a positive number, got {}", value));
+        }
+    }
+
+    fn non_negative(&mut self, field: &str, value: f32) {
+        if !(value.is_finite() && value >= 0.0) {
+            self.push(field, format!("Must be zero or more, got {}", value));
+        }
+    }
+
+    fn into_result(self) -> Result<(), Self> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}: {}", error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+/// Rejects analysis settings that can't run: FFT sizes that aren't a power
+/// of two in range, overlaps that leave no hop, missing or bad band centers.
+pub fn validate_analysis_config(config: &AnalysisConfig) -> Result<(), ValidationErrors> {
+    let mut errors = ValidationErrors::default();
+    if !config.fft_size.is_power_of_two()
+        || !(MIN_FFT_SIZE..=MAX_FFT_SIZE).contains(&config.fft_size)
+    {
+        errors.push(
+            "fft_size",
+            format!(
+                "Must be a power of two between {} and {}, got {}",
+                MIN_FFT_SIZE, MAX_FFT_SIZE, config.fft_size
+            ),
+        );
+    }
+    errors.range("overlap", config.overlap, 0.0, MAX_OVERLAP);
+    if config.frequency_bands.is_empty() {
+        errors.push("frequency_bands", "At least one frequency band is required");
+    }
+    for (i, &freq) in config.frequency_bands.iter().enumerate() {
+        errors.positive(&format!("frequency_bands[{}]", i), freq);
+    }
+    errors.into_result()
+}
+
+/// Rejects match settings that would turn into NaN or runaway gains further
+/// down: negative or non-finite amounts, and inverted frequency ranges in
+/// the room and speech modes.
+pub fn validate_match_config(config: &MatchConfig) -> Result<(), ValidationErrors> {
+    let mut errors = ValidationErrors::default();
+    errors.range("intensity", config.intensity, 0.0, 1.0);
+    errors.range(
+        "max_correction",
+        config.max_correction,
+        0.0,
+        MAX_CORRECTION_LIMIT_DB,
+    );
+    for (i, &(frequency, limit)) in config.max_correction_curve.iter().enumerate() {
+        errors.positive(&format!("max_correction_curve[{}][0]", i), frequency);
+        errors.range(
+            &format!("max_correction_curve[{}][1]", i),
+            limit,
+            0.0,
+            MAX_CORRECTION_LIMIT_DB,
+        );
+    }
+    errors.range("smoothing_factor", config.smoothing_factor, 0.0, 1.0);
+    // Levels outside the ISO 226 contours are clamped when weighting
+    if !config.listening_level_db.is_finite() {
+        errors.push(
+            "listening_level_db",
+            format!("Must be a number, got {}", config.listening_level_db),
+        );
+    }
+
+    if let Some(room) = &config.room_correction {
+        for (field, value) in [
+            (
+                "room_correction.schroeder_frequency",
+                room.schroeder_frequency,
+            ),
+            ("room_correction.room_volume_m3", room.room_volume_m3),
+            ("room_correction.rt60_secs", room.rt60_secs),
+        ] {
+            if let Some(value) = value {
+                errors.positive(field, value);
+            }
+        }
+        errors.range(
+            "room_correction.above_schroeder_scale",
+            room.above_schroeder_scale,
+            0.0,
+            1.0,
+        );
+        errors.non_negative("room_correction.null_depth_db", room.null_depth_db);
+    }
+
+    if let Some(speech) = &config.speech {
+        errors.positive("speech.low_limit_hz", speech.low_limit_hz);
+        errors.positive("speech.high_limit_hz", speech.high_limit_hz);
+        errors.positive("speech.protect_low_hz", speech.protect_low_hz);
+        errors.positive("speech.protect_high_hz", speech.protect_high_hz);
+        errors.non_negative("speech.high_pass_hz", speech.high_pass_hz);
+        if speech.low_limit_hz >= speech.high_limit_hz {
+            errors.push("speech.high_limit_hz", "Must be above the low limit");
+        }
+        if speech.protect_low_hz > speech.protect_high_hz {
+            errors.push(
+                "speech.protect_high_hz",
+                "Must not be below the protected range's low end",
+            );
+        }
+    }
+    errors.into_result()
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningCode {
+    CorrectionLimited,
+    SteepSlope,
+    HighTotalCorrection,
+    DynamicsMismatch,
+    AnalysisMismatch, // Reference and input analyzed with different settings
+    InvalidBand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// One-click fixes the frontend can offer next to a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestedAction {
+    LowerIntensity,
+    RaiseMaxCorrection,
+    IncreaseSmoothing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchWarning {
+    pub code: WarningCode,
+    pub severity: WarningSeverity,
+    pub frequency_range: Option<(f32, f32)>, // Hz, None = whole spectrum
+    pub message: String,                     // English fallback text
+    pub suggested_action: Option<SuggestedAction>,
+}
+
+impl MatchWarning {
+    pub fn new(code: WarningCode, severity: WarningSeverity, message: String) -> Self {
+        Self {
+            code,
+            severity,
+            frequency_range: None,
+            message,
+            suggested_action: None,
+        }
+    }
+
+    pub fn with_range(mut self, low_hz: f32, high_hz: f32) -> Self {
+        self.frequency_range = Some((low_hz, high_hz));
+        self
+    }
+
+    pub fn with_action(mut self, action: SuggestedAction) -> Self {
+        self.suggested_action = Some(action);
+        self
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+pub const DEFAULT_WAVEFORM_POINTS: usize = 2000;
+pub const MAX_WAVEFORM_POINTS: usize = 100_000;
+
+/// Downsampled waveform for drawing: point i covers `secs_per_point` of
+/// audio from `i * secs_per_point`, with its lowest and highest sample and
+/// RMS level (linear, full scale = 1.0).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveformOverview {
+    pub sample_rate: u32,
+    pub duration_secs: f32,
+    pub secs_per_point: f32,
+    pub channels: Vec<WaveformPeaks>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveformPeaks {
+    pub min: Vec<f32>,
+    pub max: Vec<f32>,
+    pub rms: Vec<f32>,
+}
+
+/// Splits planar `channels` into at most `points` equal buckets (the last
+/// may be shorter) and summarizes each.
+pub fn waveform_overview(
+    channels: &[Vec<f32>],
+    sample_rate: u32,
+    points: usize,
+) -> Result<WaveformOverview, String> {
+    if !(1..=MAX_WAVEFORM_POINTS).contains(&points) {
+        return Err(format!(
+            "Points must be between 1 and {}, got {}",
+            MAX_WAVEFORM_POINTS, points
+        ));
+    }
+    if sample_rate == 0 {
+        return Err("Sample rate must be positive".to_string());
+    }
+    let frames = channels.iter().map(|c| c.len()).max().unwrap_or(0);
+    let bucket = frames.div_ceil(points).max(1);
+
+    let channels = channels
+        .iter()
+        .map(|channel| {
+            let mut peaks = WaveformPeaks {
+                min: Vec::with_capacity(points),
+                max: Vec::with_capacity(points),
+                rms: Vec::with_capacity(points),
+            };
+            for chunk in channel.chunks(bucket) {
+                let (low, high, squares) = chunk.iter().fold(
+                    (f32::INFINITY, f32::NEG_INFINITY, 0.0f64),
+                    |(low, high, squares), &s| {
+                        (low.min(s), high.max(s), squares + (s as f64) * (s as f64))
+                    },
+                );
+                peaks.min.push(low);
+                peaks.max.push(high);
+                peaks.rms.push((squares / chunk.len() as f64).sqrt() as f32);
+            }
+            peaks
+        })
+        .collect();
+
+    Ok(WaveformOverview {
+        sample_rate,
+        duration_secs: frames as f32 / sample_rate as f32,
+        secs_per_point: bucket as f32 / sample_rate as f32,
+        channels,
+    })
+}
@@ -0,0 +1,255 @@
+use super::generator::Xorshift32;
+use crate::atomic_file::AtomicFile;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+// KSDATAFORMAT_SUBTYPE_* GUIDs after their leading format tag
+const SUBFORMAT_TAIL: [u8; 14] = [
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B, 0x71,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BitDepth {
+    Pcm16,
+    #[default]
+    Pcm24,
+    Float32,
+}
+
+impl BitDepth {
+    fn bytes(self) -> u16 {
+        match self {
+            BitDepth::Pcm16 => 2,
+            BitDepth::Pcm24 => 3,
+            BitDepth::Float32 => 4,
+        }
+    }
+
+    // Largest positive integer code, None for float
+    fn full_scale(self) -> Option<f64> {
+        match self {
+            BitDepth::Pcm16 => Some(32767.0),
+            BitDepth::Pcm24 => Some(8_388_607.0),
+            BitDepth::Float32 => None,
+        }
+    }
+}
+
+/// Writes planar audio as a WAV file. Integer formats are quantized with
+/// optional TPDF dither (±1 LSB triangular noise); float output is never dithered.
+/// The file only appears under `path` once it is complete.
+pub fn write_wav<P: AsRef<Path>>(
+    path: P,
+    channels: &[Vec<f64>],
+    sample_rate: u32,
+    bit_depth: BitDepth,
+    dither: bool,
+) -> Result<(), String> {
+    if channels.is_empty() {
+        return Err("No channels to write".to_string());
+    }
+
+    let frames = channels[0].len();
+    let header = wav_header(frames, channels.len(), sample_rate, bit_depth)?;
+
+    let mut out = AtomicFile::create(path.as_ref())?;
+    out.write_all(&header).map_err(|e| e.to_string())?;
+
+    let mut noise = TpdfNoise::new();
+    for frame in 0..frames {
+        for channel in channels {
+            let sample = channel.get(frame).copied().unwrap_or(0.0);
+            match bit_depth.full_scale() {
+                None => out.write_all(&(sample as f32).to_le_bytes()),
+                Some(scale) => {
+                    let dithered = sample * scale + if dither { noise.sample() } else { 0.0 };
+                    let code = dithered.round().clamp(-scale - 1.0, scale) as i32;
+                    let bytes = code.to_le_bytes();
+                    out.write_all(&bytes[..bit_depth.bytes() as usize])
+                }
+            }
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    out.commit()
+}
+
+// The RIFF header for `frames` of interleaved audio. More than two channels,
+// 24-bit and float use WAVE_FORMAT_EXTENSIBLE, as the spec asks for formats
+// beyond plain 8/16-bit stereo; a size past the 4 GiB RIFF limit is an error.
+fn wav_header(
+    frames: usize,
+    channel_count: usize,
+    sample_rate: u32,
+    bit_depth: BitDepth,
+) -> Result<Vec<u8>, String> {
+    let too_large = || "Audio is too large for a WAV file (4 GiB limit)".to_string();
+    let channels = u16::try_from(channel_count).map_err(|_| too_large())?;
+    let block_align = channels
+        .checked_mul(bit_depth.bytes())
+        .ok_or_else(too_large)?;
+    let byte_rate = sample_rate
+        .checked_mul(block_align as u32)
+        .ok_or_else(too_large)?;
+    let format_tag = if bit_depth == BitDepth::Float32 {
+        WAVE_FORMAT_IEEE_FLOAT
+    } else {
+        WAVE_FORMAT_PCM
+    };
+    let extensible = channels > 2 || bit_depth != BitDepth::Pcm16;
+    let fmt_len: u32 = if extensible { 40 } else { 16 };
+
+    let data_len = (frames as u64)
+        .checked_mul(block_align as u64)
+        .and_then(|len| u32::try_from(len).ok())
+        .ok_or_else(too_large)?;
+    // "WAVE", the fmt chunk and the data chunk header
+    let riff_len = data_len
+        .checked_add(4 + 8 + fmt_len + 8)
+        .ok_or_else(too_large)?;
+
+    let mut header = Vec::with_capacity(20 + fmt_len as usize + 8);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&riff_len.to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&fmt_len.to_le_bytes());
+    let tag = if extensible {
+        WAVE_FORMAT_EXTENSIBLE
+    } else {
+        format_tag
+    };
+    header.extend_from_slice(&tag.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&(bit_depth.bytes() * 8).to_le_bytes());
+    if extensible {
+        header.extend_from_slice(&22u16.to_le_bytes());
+        header.extend_from_slice(&(bit_depth.bytes() * 8).to_le_bytes()); // Valid bits
+        header.extend_from_slice(&channel_mask(channels).to_le_bytes());
+        header.extend_from_slice(&format_tag.to_le_bytes());
+        header.extend_from_slice(&SUBFORMAT_TAIL);
+    }
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    Ok(header)
+}
+
+// Speaker positions for the usual layouts (5.1 as L R C LFE Ls Rs, like the
+// loudness meter); anything else is left unassigned
+fn channel_mask(channels: u16) -> u32 {
+    match channels {
+        1 => 0x4,
+        2 => 0x3,
+        4 => 0x33,
+        6 => 0x3F,
+        8 => 0x63F,
+        _ => 0,
+    }
+}
+
+// Difference of two uniform variables, in LSBs
+struct TpdfNoise {
+    rng: Xorshift32,
+}
+
+impl TpdfNoise {
+    fn new() -> Self {
+        Self {
+            rng: Xorshift32::new(0x9E37_79B9),
+        }
+    }
+
+    fn sample(&mut self) -> f64 {
+        self.rng.uniform() - self.rng.uniform()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::loader::load_audio_file;
+
+    fn u16_at(header: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes([header[at], header[at + 1]])
+    }
+
+    fn u32_at(header: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes([header[at], header[at + 1], header[at + 2], header[at + 3]])
+    }
+
+    #[test]
+    fn stereo_16_bit_uses_the_plain_header() {
+        let header = wav_header(1000, 2, 44_100, BitDepth::Pcm16).unwrap();
+        assert_eq!(header.len(), 44);
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(u32_at(&header, 4), 36 + 4000);
+        assert_eq!(&header[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&header, 16), 16);
+        assert_eq!(u16_at(&header, 20), WAVE_FORMAT_PCM);
+        assert_eq!(u16_at(&header, 22), 2);
+        assert_eq!(u32_at(&header, 24), 44_100);
+        assert_eq!(u32_at(&header, 28), 44_100 * 4);
+        assert_eq!(u16_at(&header, 32), 4);
+        assert_eq!(u16_at(&header, 34), 16);
+        assert_eq!(&header[36..40], b"data");
+        assert_eq!(u32_at(&header, 40), 4000);
+    }
+
+    #[test]
+    fn surround_float_uses_the_extensible_header() {
+        let header = wav_header(10, 6, 48_000, BitDepth::Float32).unwrap();
+        assert_eq!(header.len(), 68);
+        assert_eq!(u32_at(&header, 4), 60 + 240);
+        assert_eq!(u32_at(&header, 16), 40);
+        assert_eq!(u16_at(&header, 20), WAVE_FORMAT_EXTENSIBLE);
+        assert_eq!(u16_at(&header, 32), 24);
+        assert_eq!(u16_at(&header, 36), 22);
+        assert_eq!(u16_at(&header, 38), 32);
+        assert_eq!(u32_at(&header, 40), 0x3F);
+        assert_eq!(u16_at(&header, 44), WAVE_FORMAT_IEEE_FLOAT);
+        assert_eq!(header[46..60], SUBFORMAT_TAIL);
+        assert_eq!(&header[60..64], b"data");
+        assert_eq!(u32_at(&header, 64), 240);
+    }
+
+    #[test]
+    fn oversized_audio_is_an_error() {
+        assert!(wav_header(1 << 30, 2, 48_000, BitDepth::Pcm24).is_err());
+        assert!(wav_header(10, 70_000, 48_000, BitDepth::Pcm16).is_err());
+    }
+
+    #[test]
+    fn written_files_load_back() {
+        let dir = std::env::temp_dir().join(format!("eq-matcher-wav-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (channel_count, bit_depth) in [
+            (1, BitDepth::Pcm16),
+            (2, BitDepth::Pcm24),
+            (6, BitDepth::Float32),
+        ] {
+            let channels: Vec<Vec<f64>> = (0..channel_count)
+                .map(|c| {
+                    (0..4800)
+                        .map(|i| 0.25 * ((i + c) as f64 * 0.01).sin())
+                        .collect()
+                })
+                .collect();
+            let path = dir.join(format!("{}ch.wav", channel_count));
+            write_wav(&path, &channels, 48_000, bit_depth, false).unwrap();
+
+            let audio = load_audio_file(&path).unwrap();
+            assert_eq!(audio.channels, channel_count as u16);
+            assert_eq!(audio.sample_rate, 48_000);
+            assert_eq!(audio.samples.len(), 4800);
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,328 @@
+pub mod http;
+mod websocket;
+
+pub use http::{Request, Response};
+
+use serde::Serialize;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// How often the accept loop looks at the shutdown flag
+const ACCEPT_POLL: Duration = Duration::from_millis(50);
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+const TOKEN_BYTES: usize = 32;
+// Events queued per subscriber; one that falls this far behind is dropped
+const SUBSCRIBER_BACKLOG: usize = 256;
+pub const EVENTS_PATH: &str = "/events";
+
+pub type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// Local-only HTTP server for automation tools. Plain requests go to the
+/// handler, one per connection; a WebSocket upgrade on `/events` subscribes
+/// the client to everything passed to `broadcast`. Every request, upgrades
+/// included, needs `Authorization: Bearer <token>`.
+pub struct AutomationServer {
+    port: u16,
+    shutdown: Arc<AtomicBool>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+// A WebSocket client and the queue its writer thread sends from, so
+// `broadcast` never waits on a socket
+struct Subscriber {
+    stream: TcpStream,
+    queue: SyncSender<Arc<str>>,
+}
+
+impl Subscriber {
+    fn close(&self) {
+        let _ = self.stream.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+impl AutomationServer {
+    // Binds to loopback only; port 0 picks a free port
+    pub fn start(port: u16, token: String, handler: Handler) -> Result<Self, String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+            .map_err(|e| format!("Cannot listen on port {}: {}", port, e))?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let subscribers = Arc::new(Mutex::new(Vec::new()));
+
+        let flag = shutdown.clone();
+        let subs = subscribers.clone();
+        let token: Arc<str> = token.into();
+        thread::spawn(move || {
+            while !flag.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let (handler, subs, token) = (handler.clone(), subs.clone(), token.clone());
+                        thread::spawn(move || serve_connection(stream, &handler, &subs, &token));
+                    }
+                    // WouldBlock, or a transient accept error
+                    Err(_) => thread::sleep(ACCEPT_POLL),
+                }
+            }
+        });
+
+        Ok(Self {
+            port,
+            shutdown,
+            subscribers,
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    // Queues `event` as JSON for every subscriber without blocking, dropping
+    // ones that went away or stopped reading
+    pub fn broadcast<T: Serialize>(&self, event: &T) {
+        let Ok(text) = serde_json::to_string(event) else {
+            return;
+        };
+        let text: Arc<str> = text.into();
+        self.subscribers.lock().unwrap().retain(|subscriber| {
+            match subscriber.queue.try_send(text.clone()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => {
+                    subscriber.close();
+                    false
+                }
+            }
+        });
+    }
+
+    pub fn stop(&self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        for subscriber in self.subscribers.lock().unwrap().drain(..) {
+            subscriber.close();
+        }
+    }
+}
+
+impl Drop for AutomationServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A random token for `AutomationServer::start`, hex.
+pub fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("No random source: {}", e))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+// Web pages can reach loopback too: a form can post here, and a rebound
+// host name resolves to 127.0.0.1. Browsers always send their own Host and,
+// for cross-site and WebSocket requests, an Origin; neither knows the token.
+pub(crate) fn authorize(request: &Request, token: &str) -> Result<(), Response> {
+    if request.header("origin").is_some() {
+        return Err(Response::error(
+            403,
+            "Requests from web pages are not allowed",
+        ));
+    }
+    if !request.header("host").is_some_and(is_loopback_host) {
+        return Err(Response::error(403, "Host must be localhost or 127.0.0.1"));
+    }
+    let given = request
+        .header("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    match given {
+        Some(given) if constant_time_eq(given.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(Response::error(401, "Missing or wrong bearer token")),
+    }
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    let host = host.trim().to_ascii_lowercase();
+    let name = host
+        .rsplit_once(':')
+        .filter(|(_, port)| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()))
+        .map_or(host.as_str(), |(name, _)| name);
+    matches!(name, "localhost" | "127.0.0.1")
+}
+
+// Doesn't stop at the first differing byte, so timing says nothing about the token
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn serve_connection(
+    mut stream: TcpStream,
+    handler: &Handler,
+    subscribers: &Mutex<Vec<Subscriber>>,
+    token: &str,
+) {
+    let _ = stream.set_nonblocking(false);
+    let _ = stream.set_read_timeout(Some(IO_TIMEOUT));
+
+    let request = match http::read_request(&stream) {
+        Ok(request) => request,
+        Err(e) => {
+            let _ = http::write_response(&mut stream, &Response::error(400, &e.to_string()));
+            return;
+        }
+    };
+    if let Err(response) = authorize(&request, token) {
+        let _ = http::write_response(&mut stream, &response);
+        return;
+    }
+
+    let upgrade = request
+        .header("upgrade")
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    if upgrade && request.path == EVENTS_PATH {
+        let Some(key) = request.header("sec-websocket-key") else {
+            let _ = http::write_response(&mut stream, &Response::error(400, "Missing key"));
+            return;
+        };
+        if std::io::Write::write_all(&mut stream, websocket::handshake_response(key).as_bytes())
+            .is_err()
+        {
+            return;
+        }
+
+        // Subscribers stay until they disconnect; a stalled one gets dropped
+        let _ = stream.set_read_timeout(None);
+        let (Ok(writer), Ok(handle)) = (stream.try_clone(), stream.try_clone()) else {
+            return;
+        };
+        let _ = writer.set_write_timeout(Some(IO_TIMEOUT));
+        let (queue, events) = mpsc::sync_channel(SUBSCRIBER_BACKLOG);
+        thread::spawn(move || write_events(writer, events));
+        subscribers.lock().unwrap().push(Subscriber {
+            stream: handle,
+            queue,
+        });
+        websocket::drain_client(stream);
+        return;
+    }
+
+    let response = handler(&request);
+    let _ = http::write_response(&mut stream, &response);
+}
+
+// Runs until the subscriber is dropped or a write fails, which closes the
+// connection so the reader side ends too
+fn write_events(mut stream: TcpStream, events: Receiver<Arc<str>>) {
+    for text in events {
+        if websocket::write_text(&mut stream, &text).is_err() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request(headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: "/status".to_string(),
+            query: HashMap::new(),
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: Vec::new(),
+        }
+    }
+
+    fn status(request: &Request) -> Option<u16> {
+        authorize(request, "secret").err().map(|r| r.status)
+    }
+
+    #[test]
+    fn loopback_requests_with_the_token_pass() {
+        for host in ["127.0.0.1:8080", "localhost", "LOCALHOST:1"] {
+            let request = request(&[("host", host), ("authorization", "Bearer secret")]);
+            assert_eq!(status(&request), None, "{}", host);
+        }
+    }
+
+    #[test]
+    fn browser_and_rebound_requests_are_refused() {
+        let from_page = request(&[
+            ("host", "127.0.0.1"),
+            ("origin", "http://127.0.0.1"),
+            ("authorization", "Bearer secret"),
+        ]);
+        assert_eq!(status(&from_page), Some(403));
+        for host in ["evil.example", "localhost.evil.example:80", "127.0.0.1:"] {
+            let rebound = request(&[("host", host), ("authorization", "Bearer secret")]);
+            assert_eq!(status(&rebound), Some(403), "{}", host);
+        }
+    }
+
+    #[test]
+    fn missing_or_wrong_tokens_are_refused() {
+        assert_eq!(status(&request(&[("host", "localhost")])), Some(401));
+        let wrong = request(&[("host", "localhost"), ("authorization", "Bearer secre")]);
+        assert_eq!(status(&wrong), Some(401));
+    }
+
+    fn subscribe(server: &AutomationServer) -> TcpStream {
+        let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, server.port())).unwrap();
+        let upgrade = "GET /events HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\n\
+             Upgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n";
+        std::io::Write::write_all(&mut stream, upgrade.as_bytes()).unwrap();
+        let mut response = Vec::new();
+        let mut byte = [0u8; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            std::io::Read::read_exact(&mut stream, &mut byte).unwrap();
+            response.push(byte[0]);
+        }
+        assert!(response.starts_with(b"HTTP/1.1 101"));
+        // The server registers the subscriber after the handshake
+        while server.subscribers.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(5));
+        }
+        stream
+    }
+
+    fn server() -> AutomationServer {
+        let handler: Handler = Arc::new(|_: &Request| Response::error(404, "Not found"));
+        AutomationServer::start(0, "secret".to_string(), handler).unwrap()
+    }
+
+    #[test]
+    fn subscribers_receive_broadcasts() {
+        let server = server();
+        let mut client = subscribe(&server);
+        server.broadcast(&"hello");
+
+        let mut frame = [0u8; 9];
+        std::io::Read::read_exact(&mut client, &mut frame).unwrap();
+        assert_eq!(&frame[..2], &[0x81, 7]);
+        assert_eq!(&frame[2..], b"\"hello\"");
+    }
+
+    #[test]
+    fn a_stalled_subscriber_does_not_block_broadcasts() {
+        let server = server();
+        // Never reads, so its socket buffers fill up
+        let _stalled = subscribe(&server);
+
+        let event = "x".repeat(64 * 1024);
+        let started = std::time::Instant::now();
+        for _ in 0..2 * SUBSCRIBER_BACKLOG {
+            server.broadcast(&event);
+        }
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(server.subscribers.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,284 @@
+use crate::atomic_file::write_atomic;
+use crate::audio::loader::ResamplePolicy;
+use crate::audio::matcher::{match_profiles, MatchConfig, MatchResult};
+use crate::audio::pipeline::analyze_audio_file_cancellable;
+use crate::audio::profile::EQProfile;
+use crate::export::{export_result, file_extension};
+use crate::jobs::CancelToken;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+const AUDIO_EXTENSIONS: [&str; 8] = ["wav", "mp3", "flac", "ogg", "aac", "m4a", "aif", "aiff"];
+pub const DEFAULT_BATCH_MEMORY_MB: u64 = 2048;
+
+// Rough peak memory of analyzing a file, per byte on disk: decoded f32
+// channels, the mono mix and resampling buffers. Compressed formats expand
+// by their compression ratio on top of that.
+const UNCOMPRESSED_EXPANSION: u64 = 6;
+const LOSSLESS_EXPANSION: u64 = 12;
+const LOSSY_EXPANSION: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemResult {
+    pub path: String,
+    pub output_path: Option<String>,
+    pub quality_score: Option<f32>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub items: Vec<BatchItemResult>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BatchEvent {
+    Started {
+        total: usize,
+        workers: usize,
+    },
+    ItemStarted {
+        index: usize,
+        total: usize,
+        path: String,
+    },
+    // Files finish in any order; `completed` counts all done so far
+    ItemFinished {
+        index: usize,
+        completed: usize,
+        total: usize,
+        item: BatchItemResult,
+    },
+    Finished {
+        summary: BatchSummary,
+    },
+}
+
+pub struct BatchJob<'a> {
+    pub reference: &'a EQProfile,
+    pub config: &'a MatchConfig,
+    pub export_format: &'a str,
+    pub out_dir: &'a Path,
+    pub policy: ResamplePolicy,
+    pub cancel: &'a CancelToken, // Checked before each file
+    pub memory_budget: u64,      // Bytes the concurrent analyses may use together
+}
+
+/// Output files handed out to exports that may run at once. A source gets
+/// `<stem>.<ext>`, or `<stem>-2.<ext>` and up when another source already
+/// has that name, e.g. `a.wav` and `a.mp3`, or two `a.wav` from different
+/// folders. The same source always gets the same name back.
+#[derive(Debug, Default)]
+pub struct OutputNames {
+    taken: Mutex<HashMap<PathBuf, PathBuf>>, // Output -> source
+}
+
+impl OutputNames {
+    pub fn reserve(&self, file: &Path, extension: &str, out_dir: &Path) -> PathBuf {
+        let stem = file
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "output".to_string());
+        let mut taken = self.taken.lock().unwrap();
+        let mut n = 1;
+        loop {
+            let name = match n {
+                1 => format!("{}.{}", stem, extension),
+                n => format!("{}-{}.{}", stem, n, extension),
+            };
+            let path = out_dir.join(name);
+            match taken.get(&path) {
+                Some(source) if source != file => n += 1,
+                _ => {
+                    taken.insert(path.clone(), file.to_path_buf());
+                    return path;
+                }
+            }
+        }
+    }
+}
+
+// Directories are expanded (non-recursively) to the audio files they contain
+pub fn collect_audio_paths(paths: &[String]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for path in paths.iter().map(PathBuf::from) {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(&path)
+                .map(|dir| dir.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+                .unwrap_or_default();
+            entries.retain(|p| p.is_file() && is_audio_file(p));
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path);
+        }
+    }
+
+    files
+}
+
+/// How many files to analyze at once: one per core, fewer when the largest
+/// file's estimated footprint times the worker count would exceed the budget.
+pub fn worker_count(files: &[PathBuf], memory_budget: u64) -> usize {
+    let largest = files.iter().map(|f| estimated_memory(f)).max().unwrap_or(0);
+    let by_memory = (memory_budget / largest.max(1)).max(1) as usize;
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+
+    cores.min(by_memory).min(files.len()).max(1)
+}
+
+fn estimated_memory(path: &Path) -> u64 {
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let expansion = match extension.as_deref() {
+        Some("wav" | "aif" | "aiff") => UNCOMPRESSED_EXPANSION,
+        Some("flac") => LOSSLESS_EXPANSION,
+        _ => LOSSY_EXPANSION,
+    };
+    size * expansion
+}
+
+pub(crate) fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| AUDIO_EXTENSIONS.contains(&e.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn batch_match<F>(
+    paths: &[String],
+    job: &BatchJob,
+    mut on_event: F,
+) -> Result<BatchSummary, String>
+where
+    F: FnMut(BatchEvent),
+{
+    let extension = file_extension(job.export_format)
+        .ok_or_else(|| format!("Unknown format: {}", job.export_format))?;
+    fs::create_dir_all(job.out_dir).map_err(|e| e.to_string())?;
+
+    let files = collect_audio_paths(paths);
+    let total = files.len();
+    // Named in list order before any worker starts, so names don't depend
+    // on which file finishes first
+    let names = OutputNames::default();
+    let outputs: Vec<PathBuf> = files
+        .iter()
+        .map(|file| names.reserve(file, &extension, job.out_dir))
+        .collect();
+    let workers = worker_count(&files, job.memory_budget);
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(workers)
+        .build()
+        .map_err(|e| e.to_string())?;
+    on_event(BatchEvent::Started { total, workers });
+
+    // Workers report over a channel so `on_event` stays on this thread
+    let (sender, receiver) = mpsc::channel();
+    let mut finished: Vec<(usize, BatchItemResult)> = Vec::with_capacity(total);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            pool.install(|| {
+                files
+                    .par_iter()
+                    .enumerate()
+                    .for_each_with(sender, |sender, (index, file)| {
+                        if job.cancel.is_cancelled() {
+                            return;
+                        }
+                        let _ = sender.send((index, None));
+                        let item = process_file(file, &outputs[index], job);
+                        let _ = sender.send((index, Some(item)));
+                    })
+            })
+        });
+
+        for (index, item) in receiver {
+            match item {
+                None => on_event(BatchEvent::ItemStarted {
+                    index,
+                    total,
+                    path: files[index].to_string_lossy().into_owned(),
+                }),
+                Some(item) => {
+                    finished.push((index, item.clone()));
+                    on_event(BatchEvent::ItemFinished {
+                        index,
+                        completed: finished.len(),
+                        total,
+                        item,
+                    });
+                }
+            }
+        }
+    });
+    job.cancel.check()?;
+
+    finished.sort_by_key(|(index, _)| *index);
+    let items: Vec<BatchItemResult> = finished.into_iter().map(|(_, item)| item).collect();
+
+    let failed = items.iter().filter(|i| i.error.is_some()).count();
+    let summary = BatchSummary {
+        total,
+        succeeded: total - failed,
+        failed,
+        items,
+    };
+    on_event(BatchEvent::Finished {
+        summary: summary.clone(),
+    });
+
+    Ok(summary)
+}
+
+fn process_file(file: &Path, output_path: &Path, job: &BatchJob) -> BatchItemResult {
+    match match_and_export(file, output_path, job) {
+        Ok((output_path, quality_score)) => BatchItemResult {
+            path: file.to_string_lossy().into_owned(),
+            output_path: Some(output_path.to_string_lossy().into_owned()),
+            quality_score: Some(quality_score),
+            error: None,
+        },
+        Err(error) => BatchItemResult {
+            path: file.to_string_lossy().into_owned(),
+            output_path: None,
+            quality_score: None,
+            error: Some(error),
+        },
+    }
+}
+
+fn match_and_export(
+    file: &Path,
+    output_path: &Path,
+    job: &BatchJob,
+) -> Result<(PathBuf, f32), String> {
+    let input =
+        analyze_audio_file_cancellable(file, job.policy, job.cancel).map_err(|e| e.to_string())?;
+    let result = match_profiles(job.reference, &input, job.config);
+    write_export(&result, job.export_format, output_path)?;
+
+    Ok((output_path.to_path_buf(), result.quality_score))
+}
+
+// `output_path` comes from `OutputNames::reserve`
+pub(crate) fn write_export(
+    result: &MatchResult,
+    format: &str,
+    output_path: &Path,
+) -> Result<(), String> {
+    write_atomic(output_path, export_result(result, format)?)
+}
@@ -0,0 +1,153 @@
+use crate::audio::analyzer::{analyze_spectrum, AnalysisConfig};
+use crate::audio::generator::{generate, TestSignal};
+use crate::audio::loader::load_audio_channels_cancellable;
+use crate::audio::matcher::{match_profiles, MatchConfig};
+use crate::audio::profile::extract_eq_profile;
+use crate::audio::writer::{write_wav, BitDepth};
+use crate::jobs::CancelToken;
+use crate::render::{render_file, RenderConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+
+const SAMPLE_RATE: u32 = 48_000;
+const SIGNAL_SECS: f64 = 30.0;
+const SIGNAL_LEVEL_DB: f32 = -18.0;
+const MATCH_ROUNDS: usize = 50; // One match is too quick to time alone
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkStage {
+    pub name: String,
+    pub millis: f64,
+    pub realtime_factor: Option<f64>, // Seconds of audio per second of work
+}
+
+/// Timings of the main processing stages on generated signals, with what
+/// is needed to compare reports across machines and versions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+    pub debug_build: bool, // Debug builds are several times slower
+    pub signal_secs: f64,
+    pub sample_rate: u32,
+    pub stages: Vec<BenchmarkStage>,
+    pub total_millis: f64,
+}
+
+/// Times decode, analysis, matching and render on stereo pink and white
+/// noise written to `work_dir`. `on_stage` gets each stage's name as it
+/// starts; temporary files are removed afterwards, also on errors.
+pub fn run_benchmark<F>(
+    work_dir: &Path,
+    cancel: &CancelToken,
+    mut on_stage: F,
+) -> Result<BenchmarkReport, String>
+where
+    F: FnMut(&str),
+{
+    let files = TempFiles(vec![
+        work_dir.join(format!("benchmark-{}-in.wav", std::process::id())),
+        work_dir.join(format!("benchmark-{}-out.wav", std::process::id())),
+    ]);
+    let (input_path, output_path) = (&files.0[0], &files.0[1]);
+
+    let started = Instant::now();
+    let mut stages = Vec::new();
+
+    let signal =
+        |signal: TestSignal| generate(&signal, SAMPLE_RATE, SIGNAL_SECS, SIGNAL_LEVEL_DB, 2);
+    let pink = signal(TestSignal::PinkNoise);
+    let pink: Vec<Vec<f64>> = pink
+        .channels
+        .iter()
+        .map(|c| c.iter().map(|&s| s as f64).collect())
+        .collect();
+    write_wav(input_path, &pink, SAMPLE_RATE, BitDepth::Pcm24, false)?;
+    cancel.check()?;
+
+    on_stage("decode");
+    let t = Instant::now();
+    let decoded = load_audio_channels_cancellable(input_path, cancel).map_err(|e| e.to_string())?;
+    stages.push(stage("decode", t, 1, Some(decoded.duration_secs)));
+    cancel.check()?;
+
+    on_stage("analysis");
+    let config = AnalysisConfig::default();
+    let mono: Vec<f32> = decoded.channels[0]
+        .iter()
+        .zip(&decoded.channels[1])
+        .map(|(l, r)| (l + r) * 0.5)
+        .collect();
+    let t = Instant::now();
+    let reference = extract_eq_profile(&analyze_spectrum(&mono, SAMPLE_RATE, &config)?, &config);
+    stages.push(stage("analysis", t, 1, Some(SIGNAL_SECS)));
+    cancel.check()?;
+
+    let white = signal(TestSignal::WhiteNoise);
+    let input = extract_eq_profile(
+        &analyze_spectrum(&white.channels[0], SAMPLE_RATE, &config)?,
+        &config,
+    );
+    cancel.check()?;
+
+    on_stage("matching");
+    let match_config = MatchConfig::default();
+    let t = Instant::now();
+    let mut result = match_profiles(&reference, &input, &match_config);
+    for _ in 1..MATCH_ROUNDS {
+        result = match_profiles(&reference, &input, &match_config);
+    }
+    stages.push(stage("matching", t, MATCH_ROUNDS, None));
+    cancel.check()?;
+
+    on_stage("render");
+    let t = Instant::now();
+    render_file(
+        input_path,
+        output_path,
+        &result.correction_profile.bands,
+        &RenderConfig::default(),
+        cancel,
+        |_| {},
+    )
+    .map_err(|e| e.to_string())?;
+    stages.push(stage("render", t, 1, Some(SIGNAL_SECS)));
+
+    Ok(BenchmarkReport {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        cpus: thread::available_parallelism().map_or(1, |n| n.get()),
+        debug_build: cfg!(debug_assertions),
+        signal_secs: SIGNAL_SECS,
+        sample_rate: SAMPLE_RATE,
+        stages,
+        total_millis: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+// Time per round since `started`
+fn stage(name: &str, started: Instant, rounds: usize, audio_secs: Option<f64>) -> BenchmarkStage {
+    let secs = started.elapsed().as_secs_f64() / rounds as f64;
+    BenchmarkStage {
+        name: name.to_string(),
+        millis: secs * 1000.0,
+        realtime_factor: audio_secs.map(|a| a / secs.max(1e-9)),
+    }
+}
+
+// Removed on drop, so cancelled or failed runs leave nothing behind
+struct TempFiles(Vec<PathBuf>);
+
+impl Drop for TempFiles {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
@@ -1,8 +1,10 @@
 use eq_matcher::audio::{
     analyzer::{analyze_spectrum, AnalysisConfig},
     loader::{load_audio_file, prepare_audio_for_analysis, ResamplePolicy},
+    pipeline::{policy_from_preset, STANDARD_RATE},
     profile::{extract_eq_profile, EQProfile},
 };
+use eq_matcher::jobs::CancelToken;
 use std::env;
 use std::time::Instant;
 
@@ -10,11 +12,7 @@ fn parse_preset(args: &[String]) -> ResamplePolicy {
     let mut it = args.iter();
     while let Some(a) = it.next() {
         if a == "--preset" {
-            return match it.next().map(|s| s.as_str()) {
-                Some("legacy") => ResamplePolicy::Always,
-                Some("no-resample") => ResamplePolicy::Never,
-                _ => ResamplePolicy::DownsampleOnly,
-            };
+            return policy_from_preset(it.next().map(|s| s.as_str()));
         }
     }
     ResamplePolicy::DownsampleOnly
@@ -27,17 +25,21 @@ fn run_once(path: &str, policy: ResamplePolicy) -> Result<(EQProfile, Metrics),
     let audio = load_audio_file(path).map_err(|e| e.to_string())?;
     let load_ms = t0.elapsed().as_secs_f64() * 1000.0;
 
-    let standard_rate = 48_000u32;
     let t1 = Instant::now();
-    let (samples, analyzed_rate) =
-        prepare_audio_for_analysis(audio.samples, audio.sample_rate, standard_rate, policy)
-            .map_err(|e| e.to_string())?;
+    let (samples, analyzed_rate) = prepare_audio_for_analysis(
+        audio.samples,
+        audio.sample_rate,
+        STANDARD_RATE,
+        policy,
+        &CancelToken::new(),
+    )
+    .map_err(|e| e.to_string())?;
     let resample_ms = t1.elapsed().as_secs_f64() * 1000.0;
 
     let config = AnalysisConfig::default();
 
     let t2 = Instant::now();
-    let spectrum = analyze_spectrum(&samples, analyzed_rate, &config);
+    let spectrum = analyze_spectrum(&samples, analyzed_rate, &config)?;
     let analyze_ms = t2.elapsed().as_secs_f64() * 1000.0;
 
     let t3 = Instant::now();
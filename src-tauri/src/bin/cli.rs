@@ -0,0 +1,162 @@
+use eq_matcher::atomic_file::write_atomic;
+use eq_matcher::audio::{
+    matcher::{match_profiles, MatchConfig},
+    pipeline::{analyze_audio_file, policy_from_preset},
+    presets::list_match_presets,
+    validation::validate_match_config,
+};
+use eq_matcher::export::template::load_templates;
+use eq_matcher::export::{export_result_with_context, ExportContext};
+use eq_matcher::jobs::CancelToken;
+use eq_matcher::script::run_script;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: spectraforge-eq --reference <file> --input <file> [options]
+       spectraforge-eq --script <file>
+
+Options:
+  --format <reaper|json|txt|apo>   Export format (default: apo)
+  --out <file>                     Write the export here instead of stdout
+  --match-preset <id>              Start from a match preset (e.g. subtle)
+  --intensity <0-1>                Override the match intensity
+  --max-correction <dB>            Override the per-band correction limit
+  --max-correction-curve <Hz:dB,...>
+                                   Limit per frequency, e.g. 100:3,1000:6,10000:4
+  --resample <legacy|smart|no-resample>
+                                   Resampling policy for analysis
+  --script <file>                  Run a pipeline script (see src/script.rs)
+  --templates <dir>                Load export templates (<id>.<ext>.tmpl) as formats";
+
+// Value following `--name`, if the flag is present
+fn flag_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+fn parse_number(args: &[String], name: &str) -> Result<Option<f32>, String> {
+    flag_value(args, name)
+        .map(|v| {
+            v.parse::<f32>()
+                .map_err(|_| format!("Invalid value for {}: {}", name, v))
+        })
+        .transpose()
+}
+
+// "100:3,1000:6" into (Hz, dB) pairs
+fn parse_curve(value: &str) -> Option<Vec<(f32, f32)>> {
+    value
+        .split(',')
+        .map(|point| {
+            let (frequency, limit) = point.trim().split_once(':')?;
+            Some((frequency.parse().ok()?, limit.parse::<f32>().ok()?.abs()))
+        })
+        .collect()
+}
+
+fn match_config(args: &[String]) -> Result<MatchConfig, String> {
+    let mut config = match flag_value(args, "--match-preset") {
+        Some(id) => list_match_presets()
+            .into_iter()
+            .find(|p| p.id == id)
+            .map(|p| p.config)
+            .ok_or_else(|| format!("Unknown match preset: {}", id))?,
+        None => MatchConfig::default(),
+    };
+
+    if let Some(intensity) = parse_number(args, "--intensity")? {
+        config.intensity = intensity;
+    }
+    if let Some(max_correction) = parse_number(args, "--max-correction")? {
+        config.max_correction = max_correction.abs();
+    }
+    if let Some(curve) = flag_value(args, "--max-correction-curve") {
+        config.max_correction_curve = parse_curve(curve)
+            .ok_or_else(|| format!("Invalid value for --max-correction-curve: {}", curve))?;
+    }
+    // Same checks as the app, one line per field
+    validate_match_config(&config).map_err(|errors| {
+        let lines: Vec<String> = errors
+            .0
+            .iter()
+            .map(|e| format!("  {}: {}", e.field, e.message))
+            .collect();
+        format!("Invalid match settings:\n{}", lines.join("\n"))
+    })?;
+    Ok(config)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    if let Some(dir) = flag_value(args, "--templates") {
+        load_templates(Path::new(dir));
+    }
+    if let Some(path) = flag_value(args, "--script") {
+        return run_script_file(Path::new(path));
+    }
+
+    let reference_path = flag_value(args, "--reference").ok_or("Missing --reference")?;
+    let input_path = flag_value(args, "--input").ok_or("Missing --input")?;
+    let format = flag_value(args, "--format").unwrap_or("apo");
+    let policy = policy_from_preset(flag_value(args, "--resample"));
+    let config = match_config(args)?;
+
+    let reference = analyze_audio_file(reference_path, policy)
+        .map_err(|e| format!("{}: {}", reference_path, e))?;
+    let input =
+        analyze_audio_file(input_path, policy).map_err(|e| format!("{}: {}", input_path, e))?;
+    let result = match_profiles(&reference, &input, &config);
+    let context = ExportContext {
+        reference_path: Some(reference_path.to_string()),
+        input_path: Some(input_path.to_string()),
+    };
+    let exported = export_result_with_context(&result, format, &context)?;
+
+    // Progress and summary go to stderr so stdout stays clean for piping
+    match flag_value(args, "--out") {
+        Some(out) => {
+            write_atomic(Path::new(out), exported)?;
+            eprintln!("wrote {}", out);
+        }
+        None => print!("{}", exported),
+    }
+    eprintln!(
+        "quality: {:.2}  residual: {:.2} dB RMS  warnings: {}",
+        result.quality_score,
+        result.residual_rms_db,
+        result.warnings.len()
+    );
+
+    Ok(())
+}
+
+fn run_script_file(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+    let output = run_script(&source, base_dir, &CancelToken::new())?;
+    for line in output.log {
+        eprintln!("{}", line);
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+    }
+}
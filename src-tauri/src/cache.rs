@@ -0,0 +1,143 @@
+use crate::persist::{read_json, write_json};
+use crate::recent::fingerprint;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub const CACHE_DIR: &str = "cache/profiles";
+pub const DEFAULT_CACHE_LIMIT_MB: u64 = 512;
+const ENTRY_SUFFIX: &str = ".json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheInfo {
+    pub dir: Option<String>,
+    pub entries: usize,
+    pub total_bytes: u64,
+    pub limit_bytes: u64,
+}
+
+/// Analysis results on disk, one JSON file per source file and analysis
+/// variant. Entries are keyed by the source's size and mtime, so edited
+/// files miss; hits refresh the entry's mtime, which drives LRU eviction.
+/// Without a directory nothing is cached.
+#[derive(Debug)]
+pub struct ProfileCache {
+    dir: Option<PathBuf>,
+    limit_bytes: u64,
+}
+
+impl Default for ProfileCache {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            limit_bytes: DEFAULT_CACHE_LIMIT_MB * 1024 * 1024,
+        }
+    }
+}
+
+impl ProfileCache {
+    pub fn new(dir: PathBuf, limit_bytes: u64) -> Self {
+        Self {
+            dir: Some(dir),
+            limit_bytes,
+        }
+    }
+
+    // `variant` tells apart analyses of the same file with other options
+    pub fn get<T: DeserializeOwned>(&self, source: &Path, variant: &str) -> Option<T> {
+        let entry = self.entry_path(source, variant)?;
+        let profile = read_json(&entry).ok()?;
+        if let Ok(file) = File::options().append(true).open(&entry) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(profile)
+    }
+
+    pub fn put<T: Serialize>(&self, source: &Path, variant: &str, value: &T) -> Result<(), String> {
+        let Some(entry) = self.entry_path(source, variant) else {
+            return Ok(());
+        };
+        write_json(&entry, value)?;
+        self.evict();
+        Ok(())
+    }
+
+    pub fn info(&self) -> CacheInfo {
+        let entries = self.entries();
+        CacheInfo {
+            dir: self.dir.as_ref().map(|d| d.to_string_lossy().into_owned()),
+            entries: entries.len(),
+            total_bytes: entries.iter().map(|e| e.2).sum(),
+            limit_bytes: self.limit_bytes,
+        }
+    }
+
+    pub fn clear(&self) -> Result<CacheInfo, String> {
+        for (path, _, _) in self.entries() {
+            fs::remove_file(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(self.info())
+    }
+
+    pub fn set_limit(&mut self, limit_bytes: u64) -> CacheInfo {
+        self.limit_bytes = limit_bytes;
+        self.evict();
+        self.info()
+    }
+
+    // Least recently used first, until the total fits the limit
+    fn evict(&self) {
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|e| e.2).sum();
+        entries.sort_by_key(|e| e.1);
+
+        for (path, _, size) in entries {
+            if total <= self.limit_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= size;
+            }
+        }
+    }
+
+    // (path, last use, size) of every entry file
+    fn entries(&self) -> Vec<(PathBuf, SystemTime, u64)> {
+        let Some(dir) = &self.dir else {
+            return Vec::new();
+        };
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().ends_with(ENTRY_SUFFIX))
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                Some((e.path(), meta.modified().ok()?, meta.len()))
+            })
+            .collect()
+    }
+
+    fn entry_path(&self, source: &Path, variant: &str) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let (size, modified) = fingerprint(source)?;
+        let key = format!(
+            "{}|{}|{}|{}|{}",
+            source.display(),
+            size,
+            modified,
+            variant,
+            env!("CARGO_PKG_VERSION") // Analysis changes between versions
+        );
+        Some(dir.join(format!("{:016x}{}", fnv1a(key.as_bytes()), ENTRY_SUFFIX)))
+    }
+}
+
+// Stable across builds, unlike std's DefaultHasher
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
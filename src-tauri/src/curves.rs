@@ -0,0 +1,131 @@
+use crate::audio::curve::ResponseCurve;
+use crate::audio::profile::EQProfile;
+use crate::persist::{read_json, write_json};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const CURVES_FILE: &str = "target_curves.json";
+const MAX_POINTS: usize = 256;
+const FREQUENCY_RANGE: (f32, f32) = (10.0, 24_000.0);
+const GAIN_RANGE_DB: f32 = 40.0; // ± per anchor
+
+/// A target built from anchor points instead of a reference file, e.g. a
+/// studio's house curve. Matching uses it on the current band grid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetCurve {
+    pub id: String, // Derived from the name; saving the same name replaces it
+    pub name: String,
+    pub points: Vec<(f32, f32)>, // (Hz, dB), ascending
+    pub updated_at: u64,         // Unix seconds
+}
+
+impl TargetCurve {
+    // Interpolated on a log-frequency axis, flat past the outer anchors
+    pub fn profile(&self, band_centers: &[f32]) -> EQProfile {
+        ResponseCurve::new(self.points.clone()).to_profile(band_centers)
+    }
+}
+
+/// User target curves, persisted as JSON next to the settings.
+#[derive(Debug, Default)]
+pub struct CurveStore {
+    path: Option<PathBuf>,
+    curves: Vec<TargetCurve>,
+}
+
+impl CurveStore {
+    pub fn load(path: PathBuf) -> Self {
+        Self {
+            curves: read_json(&path).unwrap_or_default(),
+            path: Some(path),
+        }
+    }
+
+    pub fn list(&self) -> Vec<TargetCurve> {
+        self.curves.clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TargetCurve> {
+        self.curves.iter().find(|c| c.id == id)
+    }
+
+    /// Validates and stores `points` under `name`, replacing a curve of the
+    /// same name. Anchors may come in any order; duplicates are rejected.
+    pub fn save(&mut self, name: &str, points: Vec<(f32, f32)>) -> Result<TargetCurve, String> {
+        let name = name.trim();
+        let id = slug(name);
+        if id.is_empty() {
+            return Err("Curve name needs at least one letter or digit".to_string());
+        }
+        let points = validate_points(points)?;
+
+        let curve = TargetCurve {
+            id,
+            name: name.to_string(),
+            points,
+            updated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        };
+        match self.curves.iter_mut().find(|c| c.id == curve.id) {
+            Some(existing) => *existing = curve.clone(),
+            None => self.curves.push(curve.clone()),
+        }
+        self.persist()?;
+        Ok(curve)
+    }
+
+    // Returns whether a curve was removed
+    pub fn delete(&mut self, id: &str) -> Result<bool, String> {
+        let before = self.curves.len();
+        self.curves.retain(|c| c.id != id);
+        if self.curves.len() == before {
+            return Ok(false);
+        }
+        self.persist()?;
+        Ok(true)
+    }
+
+    fn persist(&self) -> Result<(), String> {
+        match &self.path {
+            Some(path) => write_json(path, &self.curves),
+            None => Ok(()),
+        }
+    }
+}
+
+fn validate_points(mut points: Vec<(f32, f32)>) -> Result<Vec<(f32, f32)>, String> {
+    if points.is_empty() || points.len() > MAX_POINTS {
+        return Err(format!(
+            "A curve needs 1 to {} anchor points, got {}",
+            MAX_POINTS,
+            points.len()
+        ));
+    }
+    if let Some(&(freq, gain)) = points.iter().find(|(f, g)| {
+        !(FREQUENCY_RANGE.0..=FREQUENCY_RANGE.1).contains(f)
+            || !g.is_finite()
+            || g.abs() > GAIN_RANGE_DB
+    }) {
+        return Err(format!(
+            "Anchor {} Hz / {} dB is outside {}-{} Hz, ±{} dB",
+            freq, gain, FREQUENCY_RANGE.0, FREQUENCY_RANGE.1, GAIN_RANGE_DB
+        ));
+    }
+
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    if let Some(pair) = points.windows(2).find(|w| w[0].0 == w[1].0) {
+        return Err(format!("Two anchors at {} Hz", pair[0].0));
+    }
+    Ok(points)
+}
+
+// "House Curve 2" -> "house-curve-2"
+fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("-")
+}
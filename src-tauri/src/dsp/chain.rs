@@ -0,0 +1,214 @@
+use biquad::Coefficients;
+
+const LANES: usize = 8;
+type Lanes = [f32; LANES];
+
+/// Cascade of transposed direct form II biquads, packed eight bands to a
+/// group. A block runs through each group as a wavefront: at step `t` lane
+/// `k` works on sample `t - k`, fed by lane `k - 1`'s output from the step
+/// before. The lanes don't depend on each other within a step, so the
+/// compiler can vectorize them instead of waiting on one band's feedback
+/// loop at a time. Unused lanes hold pass-through sections.
+#[derive(Clone, Default)]
+pub(crate) struct BiquadChain {
+    bands: usize,
+    b0: Vec<Lanes>,
+    b1: Vec<Lanes>,
+    b2: Vec<Lanes>,
+    a1: Vec<Lanes>,
+    a2: Vec<Lanes>,
+    s1: Vec<Lanes>,
+    s2: Vec<Lanes>,
+}
+
+impl BiquadChain {
+    pub(crate) fn new(coefficients: &[Coefficients<f32>]) -> Self {
+        let groups = coefficients.len().div_ceil(LANES);
+        let mut chain = Self {
+            s1: vec![[0.0; LANES]; groups],
+            s2: vec![[0.0; LANES]; groups],
+            ..Self::default()
+        };
+        chain.set_coefficients(coefficients);
+        chain
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.bands
+    }
+
+    // Keeps the state; callers make sure the band count matches
+    pub(crate) fn set_coefficients(&mut self, coefficients: &[Coefficients<f32>]) {
+        let pack = |field: fn(&Coefficients<f32>) -> f32, pass: f32| -> Vec<Lanes> {
+            coefficients
+                .chunks(LANES)
+                .map(|group| {
+                    let mut lanes = [pass; LANES];
+                    for (lane, c) in lanes.iter_mut().zip(group) {
+                        *lane = field(c);
+                    }
+                    lanes
+                })
+                .collect()
+        };
+
+        self.bands = coefficients.len();
+        self.b0 = pack(|c| c.b0, 1.0);
+        self.b1 = pack(|c| c.b1, 0.0);
+        self.b2 = pack(|c| c.b2, 0.0);
+        self.a1 = pack(|c| c.a1, 0.0);
+        self.a2 = pack(|c| c.a2, 0.0);
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.s1.fill([0.0; LANES]);
+        self.s2.fill([0.0; LANES]);
+    }
+
+    pub(crate) fn run(&mut self, sample: f32) -> f32 {
+        let mut x = sample;
+        for g in 0..self.b0.len() {
+            for k in 0..LANES {
+                let y = self.b0[g][k] * x + self.s1[g][k];
+                self.s1[g][k] = self.s2[g][k] + self.b1[g][k] * x - self.a1[g][k] * y;
+                self.s2[g][k] = self.b2[g][k] * x - self.a2[g][k] * y;
+                x = y;
+            }
+        }
+        x
+    }
+
+    pub(crate) fn run_block(&mut self, buffer: &mut [f32]) {
+        for g in 0..self.b0.len() {
+            self.run_group(g, buffer);
+        }
+    }
+
+    fn run_group(&mut self, g: usize, buffer: &mut [f32]) {
+        let len = buffer.len();
+        if len == 0 {
+            return;
+        }
+
+        let (b0, b1, b2, a1, a2) = (self.b0[g], self.b1[g], self.b2[g], self.a1[g], self.a2[g]);
+        let mut s1 = self.s1[g];
+        let mut s2 = self.s2[g];
+        let mut previous: Lanes = [0.0; LANES];
+
+        // Lane k is only live while its sample t - k lies inside the block, so
+        // the first and last LANES - 1 steps mask the state updates
+        let edge = |t: usize, k: usize| t >= k && t - k < len;
+        for t in 0..len + LANES - 1 {
+            let input = if t < len { buffer[t] } else { 0.0 };
+            let mut x: Lanes = [input; LANES];
+            x[1..].copy_from_slice(&previous[..LANES - 1]);
+
+            let mut y: Lanes = [0.0; LANES];
+            let mut next_s1: Lanes = [0.0; LANES];
+            let mut next_s2: Lanes = [0.0; LANES];
+            for k in 0..LANES {
+                y[k] = b0[k] * x[k] + s1[k];
+                next_s1[k] = s2[k] + b1[k] * x[k] - a1[k] * y[k];
+                next_s2[k] = b2[k] * x[k] - a2[k] * y[k];
+            }
+
+            if t >= LANES - 1 && t < len {
+                s1 = next_s1;
+                s2 = next_s2;
+                buffer[t - (LANES - 1)] = y[LANES - 1];
+            } else {
+                for k in 0..LANES {
+                    if edge(t, k) {
+                        s1[k] = next_s1[k];
+                        s2[k] = next_s2[k];
+                    }
+                }
+                if t >= LANES - 1 {
+                    buffer[t - (LANES - 1)] = y[LANES - 1];
+                }
+            }
+            previous = y;
+        }
+
+        self.s1[g] = s1;
+        self.s2[g] = s2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::generator::Xorshift32;
+    use crate::dsp::filters::{design_coefficients, FilterType};
+    use biquad::{Biquad, DirectForm2Transposed};
+
+    // Eleven bands: one full group and one with pass-through lanes
+    fn coefficients() -> Vec<Coefficients<f32>> {
+        let params: Vec<_> = (0..11)
+            .map(|i| {
+                let gain_db = if i % 2 == 0 { 6.0 } else { -4.0 };
+                (40.0 * 1.8f32.powi(i), gain_db, 1.4, FilterType::Peaking)
+            })
+            .collect();
+        design_coefficients(48_000.0, &params)
+    }
+
+    fn noise(len: usize) -> Vec<f32> {
+        let mut rng = Xorshift32::new(7);
+        (0..len).map(|_| rng.bipolar() as f32 * 0.5).collect()
+    }
+
+    #[test]
+    fn wavefront_blocks_match_a_sequential_cascade() {
+        let coefficients = coefficients();
+        assert_eq!(coefficients.len(), 11);
+        let input = noise(4096);
+
+        let mut cascade: Vec<DirectForm2Transposed<f32>> = coefficients
+            .iter()
+            .map(|&c| DirectForm2Transposed::<f32>::new(c))
+            .collect();
+        let expected: Vec<f32> = input
+            .iter()
+            .map(|&x| cascade.iter_mut().fold(x, |acc, f| f.run(acc)))
+            .collect();
+
+        // Uneven block sizes, including ones shorter than the wavefront
+        let mut chain = BiquadChain::new(&coefficients);
+        let mut output = input.clone();
+        let mut start = 0;
+        for size in [1, 3, 7, 8, 9, 64, 500].iter().cycle() {
+            if start >= output.len() {
+                break;
+            }
+            let end = (start + size).min(output.len());
+            chain.run_block(&mut output[start..end]);
+            start = end;
+        }
+
+        for (n, (a, b)) in output.iter().zip(&expected).enumerate() {
+            assert!((a - b).abs() < 1e-4, "sample {}: {} vs {}", n, a, b);
+        }
+    }
+
+    #[test]
+    fn per_sample_and_block_runs_share_state() {
+        let coefficients = coefficients();
+        let input = noise(1000);
+
+        let mut per_sample = BiquadChain::new(&coefficients);
+        let expected: Vec<f32> = input.iter().map(|&x| per_sample.run(x)).collect();
+
+        let mut mixed = BiquadChain::new(&coefficients);
+        let mut output = input.clone();
+        let (head, tail) = output.split_at_mut(500);
+        for sample in head.iter_mut() {
+            *sample = mixed.run(*sample);
+        }
+        mixed.run_block(tail);
+
+        for (a, b) in output.iter().zip(&expected) {
+            assert!((a - b).abs() < 1e-4);
+        }
+    }
+}
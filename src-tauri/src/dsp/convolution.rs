@@ -0,0 +1,241 @@
+use super::fft;
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealToComplex};
+use std::sync::Arc;
+
+/// Uniformly partitioned overlap-save convolution. The impulse response is
+/// split into `block_size` partitions whose spectra are multiplied against a
+/// delay line of past input spectra, so long linear-phase FIRs run in real
+/// time with only `block_size` samples of added latency.
+pub struct PartitionedConvolver {
+    block_size: usize,
+    forward: Arc<dyn RealToComplex<f32>>,
+    inverse: Arc<dyn ComplexToReal<f32>>,
+    partitions: Vec<Vec<Complex<f32>>>, // Spectrum of each IR partition
+    history: Vec<Vec<Complex<f32>>>,    // Ring of past input spectra
+    head: usize,
+    input: Vec<f32>, // Previous block followed by the one being filled
+    output: Vec<f32>,
+    fill: usize,
+    time_buffer: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    accumulator: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+}
+
+impl PartitionedConvolver {
+    pub fn new(impulse: &[f32], block_size: usize) -> Result<Self, String> {
+        if impulse.is_empty() {
+            return Err("Impulse response is empty".to_string());
+        }
+        if block_size == 0 {
+            return Err("Block size must be positive".to_string());
+        }
+
+        let fft_size = 2 * block_size;
+        let bins = block_size + 1;
+        let forward = fft::real_forward(fft_size);
+        let inverse = fft::real_inverse(fft_size);
+        let mut scratch =
+            vec![Complex::default(); forward.get_scratch_len().max(inverse.get_scratch_len())];
+        let partitions = transform_partitions(impulse, block_size, &forward, &mut scratch)?;
+
+        Ok(Self {
+            block_size,
+            history: vec![vec![Complex::default(); bins]; partitions.len()],
+            partitions,
+            forward,
+            inverse,
+            head: 0,
+            input: vec![0.0; fft_size],
+            output: vec![0.0; block_size],
+            fill: 0,
+            time_buffer: vec![0.0; fft_size],
+            spectrum: vec![Complex::default(); bins],
+            accumulator: vec![Complex::default(); bins],
+            scratch,
+        })
+    }
+
+    // Output lags the input by exactly one block
+    pub fn latency_samples(&self) -> usize {
+        self.block_size
+    }
+
+    /// Swaps in a new impulse response of the same partition count, keeping
+    /// the input history so playback continues without a gap.
+    pub fn set_impulse(&mut self, impulse: &[f32]) -> Result<(), String> {
+        if impulse.is_empty() || impulse.len().div_ceil(self.block_size) != self.partitions.len() {
+            return Err("Impulse response length changed".to_string());
+        }
+        self.partitions =
+            transform_partitions(impulse, self.block_size, &self.forward, &mut self.scratch)?;
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        for spectrum in &mut self.history {
+            spectrum.fill(Complex::default());
+        }
+        self.input.fill(0.0);
+        self.output.fill(0.0);
+        self.fill = 0;
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let output = self.output[self.fill];
+        self.input[self.block_size + self.fill] = sample;
+        self.fill += 1;
+
+        if self.fill == self.block_size {
+            self.process_block();
+            self.fill = 0;
+        }
+
+        output
+    }
+
+    pub fn process_buffer(&mut self, buffer: &mut [f32]) {
+        for sample in buffer.iter_mut() {
+            *sample = self.process(*sample);
+        }
+    }
+
+    fn process_block(&mut self) {
+        let partition_count = self.partitions.len();
+
+        self.time_buffer.copy_from_slice(&self.input);
+        // Buffer sizes are fixed at construction, so the transforms can't fail
+        let _ = self.forward.process_with_scratch(
+            &mut self.time_buffer,
+            &mut self.spectrum,
+            &mut self.scratch,
+        );
+
+        self.head = (self.head + partition_count - 1) % partition_count;
+        self.history[self.head].copy_from_slice(&self.spectrum);
+
+        self.accumulator.fill(Complex::default());
+        for (p, partition) in self.partitions.iter().enumerate() {
+            let past = &self.history[(self.head + p) % partition_count];
+            for ((acc, x), h) in self.accumulator.iter_mut().zip(past).zip(partition) {
+                *acc += x * h;
+            }
+        }
+
+        // Rounding can leave tiny imaginary parts where a real spectrum has none
+        self.accumulator[0].im = 0.0;
+        if let Some(last) = self.accumulator.last_mut() {
+            last.im = 0.0;
+        }
+        let _ = self.inverse.process_with_scratch(
+            &mut self.accumulator,
+            &mut self.time_buffer,
+            &mut self.scratch,
+        );
+
+        // Overlap-save: only the second half is free of wrap-around
+        self.output
+            .copy_from_slice(&self.time_buffer[self.block_size..]);
+        self.input.copy_within(self.block_size.., 0);
+    }
+}
+
+// Spectrum of each `block_size` partition, zero-padded to twice its length.
+// The 1/N of the inverse FFT is folded in.
+fn transform_partitions(
+    impulse: &[f32],
+    block_size: usize,
+    forward: &Arc<dyn RealToComplex<f32>>,
+    scratch: &mut [Complex<f32>],
+) -> Result<Vec<Vec<Complex<f32>>>, String> {
+    let fft_size = 2 * block_size;
+    let norm = 1.0 / fft_size as f32;
+    impulse
+        .chunks(block_size)
+        .map(|chunk| {
+            let mut padded = vec![0.0; fft_size];
+            for (dst, &src) in padded.iter_mut().zip(chunk) {
+                *dst = src * norm;
+            }
+            let mut spectrum = vec![Complex::default(); block_size + 1];
+            forward
+                .process_with_scratch(&mut padded, &mut spectrum, scratch)
+                .map_err(|e| e.to_string())?;
+            Ok(spectrum)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::generator::Xorshift32;
+
+    fn noise(len: usize, seed: u32) -> Vec<f32> {
+        let mut rng = Xorshift32::new(seed);
+        (0..len).map(|_| rng.bipolar() as f32).collect()
+    }
+
+    // Plain convolution, delayed by `latency`
+    fn direct(input: &[f32], impulse: &[f32], latency: usize) -> Vec<f32> {
+        (0..input.len())
+            .map(|n| {
+                let Some(n) = n.checked_sub(latency) else {
+                    return 0.0;
+                };
+                impulse
+                    .iter()
+                    .enumerate()
+                    .take_while(|(k, _)| *k <= n)
+                    .map(|(k, h)| h * input[n - k])
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_direct_convolution() {
+        // Lengths that leave a partial last partition, and a single partition
+        for (taps, block_size) in [(300, 64), (64, 64), (5, 16)] {
+            let impulse = noise(taps, 3);
+            let input = noise(2000, 11);
+            let mut convolver = PartitionedConvolver::new(&impulse, block_size).unwrap();
+            let mut output = input.clone();
+            convolver.process_buffer(&mut output);
+
+            let expected = direct(&input, &impulse, convolver.latency_samples());
+            for (n, (a, b)) in output.iter().zip(&expected).enumerate() {
+                assert!(
+                    (a - b).abs() < 1e-3,
+                    "{} taps, sample {}: {} vs {}",
+                    taps,
+                    n,
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn impulse_swaps_keep_the_history() {
+        let first = noise(200, 3);
+        let second: Vec<f32> = first.iter().map(|h| -0.5 * h).collect();
+        let input = noise(1024, 11);
+
+        let mut convolver = PartitionedConvolver::new(&first, 64).unwrap();
+        let mut output = input.clone();
+        convolver.process_buffer(&mut output[..512]);
+        convolver.set_impulse(&second).unwrap();
+        convolver.process_buffer(&mut output[512..]);
+
+        // Once the first block under the new impulse is out, the output is
+        // the new impulse over the whole input
+        let expected = direct(&input, &second, 64);
+        for n in 512 + 64..input.len() {
+            assert!((output[n] - expected[n]).abs() < 1e-3);
+        }
+        assert!(convolver.set_impulse(&noise(400, 3)).is_err());
+    }
+}
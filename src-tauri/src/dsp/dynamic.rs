@@ -0,0 +1,180 @@
+use super::filters::{biquad_type, FilterType};
+use crate::audio::dynamics::MultibandCompressionSuggestion;
+use biquad::*;
+use serde::{Deserialize, Serialize};
+
+// Gain is recomputed at this control rate rather than every sample
+const CONTROL_INTERVAL: usize = 16;
+// Smaller gain moves don't justify a coefficient update
+const GAIN_EPSILON_DB: f32 = 0.05;
+const DEFAULT_THRESHOLD_DB: f32 = -24.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DynamicBand {
+    pub frequency: f32,
+    pub q: f32,
+    #[serde(default)]
+    pub filter_type: FilterType,
+    #[serde(default)]
+    pub static_gain_db: f32, // Always applied
+    pub threshold_db: f32, // Detector level (dBFS) where the dynamic part starts
+    pub ratio: f32,
+    pub range_db: f32, // Most the dynamic part may move; negative cuts, positive boosts
+    #[serde(default = "default_attack_ms")]
+    pub attack_ms: f32,
+    #[serde(default = "default_release_ms")]
+    pub release_ms: f32,
+}
+
+fn default_attack_ms() -> f32 {
+    10.0
+}
+
+fn default_release_ms() -> f32 {
+    150.0
+}
+
+struct BandState {
+    band: DynamicBand,
+    detector: DirectForm2Transposed<f32>, // Band-pass around the band
+    filter: DirectForm2Transposed<f32>,
+    envelope: f32, // Linear detector level
+    attack_coeff: f32,
+    release_coeff: f32,
+    applied_gain_db: f32,
+}
+
+/// Peaking/shelf bands whose gain follows the level inside their own band:
+/// a band-pass detector feeds an envelope follower, and the overshoot above
+/// the threshold scaled by the ratio moves the gain, up to `range_db`.
+pub struct DynamicEQ {
+    sample_rate: f32,
+    bands: Vec<BandState>,
+    counter: usize,
+}
+
+impl DynamicEQ {
+    pub fn new(sample_rate: f32, bands: &[DynamicBand]) -> Self {
+        let coeff = |ms: f32| 1.0 - (-1.0 / (ms.max(0.1) * 0.001 * sample_rate)).exp();
+
+        let bands = bands
+            .iter()
+            .filter_map(|band| {
+                let detector = Coefficients::<f32>::from_params(
+                    Type::BandPass,
+                    sample_rate.hz(),
+                    band.frequency.hz(),
+                    band.q.max(0.3),
+                )
+                .ok()?;
+                let filter = design(sample_rate, band, band.static_gain_db)?;
+
+                Some(BandState {
+                    band: band.clone(),
+                    detector: DirectForm2Transposed::<f32>::new(detector),
+                    filter: DirectForm2Transposed::<f32>::new(filter),
+                    envelope: 0.0,
+                    attack_coeff: coeff(band.attack_ms),
+                    release_coeff: coeff(band.release_ms),
+                    applied_gain_db: band.static_gain_db,
+                })
+            })
+            .collect();
+
+        Self {
+            sample_rate,
+            bands,
+            counter: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bands.is_empty()
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let update = self.counter == 0;
+        self.counter = (self.counter + 1) % CONTROL_INTERVAL;
+
+        let mut output = sample;
+        for state in &mut self.bands {
+            let detected = state.detector.run(output).abs();
+            let coeff = if detected > state.envelope {
+                state.attack_coeff
+            } else {
+                state.release_coeff
+            };
+            state.envelope += (detected - state.envelope) * coeff;
+
+            if update {
+                let gain_db =
+                    state.band.static_gain_db + dynamic_gain_db(&state.band, state.envelope);
+                if (gain_db - state.applied_gain_db).abs() > GAIN_EPSILON_DB {
+                    if let Some(coeffs) = design(self.sample_rate, &state.band, gain_db) {
+                        state.filter.update_coefficients(coeffs);
+                        state.applied_gain_db = gain_db;
+                    }
+                }
+            }
+
+            output = state.filter.run(output);
+        }
+        output
+    }
+
+    pub fn reset(&mut self) {
+        for state in &mut self.bands {
+            state.detector.reset_state();
+            state.filter.reset_state();
+            state.envelope = 0.0;
+        }
+        self.counter = 0;
+    }
+}
+
+fn dynamic_gain_db(band: &DynamicBand, envelope: f32) -> f32 {
+    let level_db = 20.0 * envelope.max(1e-9).log10();
+    let over = level_db - band.threshold_db;
+    if over <= 0.0 {
+        return 0.0;
+    }
+
+    let amount = (over * (1.0 - 1.0 / band.ratio.max(1.0))).min(band.range_db.abs());
+    amount.copysign(band.range_db)
+}
+
+fn design(sample_rate: f32, band: &DynamicBand, gain_db: f32) -> Option<Coefficients<f32>> {
+    Coefficients::<f32>::from_params(
+        biquad_type(band.filter_type, gain_db),
+        sample_rate.hz(),
+        band.frequency.hz(),
+        band.q,
+    )
+    .ok()
+}
+
+// One dynamic cut per compressor band, centred geometrically in its range
+pub fn dynamic_bands_from_suggestion(
+    suggestion: &MultibandCompressionSuggestion,
+) -> Vec<DynamicBand> {
+    suggestion
+        .bands
+        .iter()
+        .filter(|b| b.gain_reduction_db > 0.0)
+        .map(|b| {
+            let frequency = (b.low_hz * b.high_hz).sqrt();
+            DynamicBand {
+                frequency,
+                q: (frequency / (b.high_hz - b.low_hz)).clamp(0.3, 4.0),
+                filter_type: FilterType::Peaking,
+                static_gain_db: 0.0,
+                threshold_db: DEFAULT_THRESHOLD_DB,
+                ratio: b.ratio,
+                // The suggestion is an average; leave room for peaks
+                range_db: -2.0 * b.gain_reduction_db,
+                attack_ms: default_attack_ms(),
+                release_ms: default_release_ms(),
+            }
+        })
+        .collect()
+}
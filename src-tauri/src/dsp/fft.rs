@@ -0,0 +1,32 @@
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use rustfft::{Fft, FftPlanner};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Planners keep every plan they made, so sharing them process-wide makes
+// repeated analyses and batch runs reuse plans instead of re-planning
+static PLANNER: OnceLock<Mutex<FftPlanner<f32>>> = OnceLock::new();
+static REAL_PLANNER: OnceLock<Mutex<RealFftPlanner<f32>>> = OnceLock::new();
+
+fn planner() -> &'static Mutex<FftPlanner<f32>> {
+    PLANNER.get_or_init(|| Mutex::new(FftPlanner::new()))
+}
+
+fn real_planner() -> &'static Mutex<RealFftPlanner<f32>> {
+    REAL_PLANNER.get_or_init(|| Mutex::new(RealFftPlanner::new()))
+}
+
+pub fn forward(size: usize) -> Arc<dyn Fft<f32>> {
+    planner().lock().unwrap().plan_fft_forward(size)
+}
+
+pub fn inverse(size: usize) -> Arc<dyn Fft<f32>> {
+    planner().lock().unwrap().plan_fft_inverse(size)
+}
+
+pub fn real_forward(size: usize) -> Arc<dyn RealToComplex<f32>> {
+    real_planner().lock().unwrap().plan_fft_forward(size)
+}
+
+pub fn real_inverse(size: usize) -> Arc<dyn ComplexToReal<f32>> {
+    real_planner().lock().unwrap().plan_fft_inverse(size)
+}
@@ -1,43 +1,224 @@
+use super::chain::BiquadChain;
 use crate::audio::profile::FrequencyBand;
+use crate::audio::warnings::{MatchWarning, WarningCode, WarningSeverity};
 use biquad::*;
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterType {
+    #[default]
+    Peaking,
+    LowShelf,
+    HighShelf,
+    HighPass, // Gain is ignored
+    LowPass,  // Gain is ignored
+}
+
+// (frequency Hz, gain dB, Q, type)
+pub type BandParams = (f32, f32, f32, FilterType);
+
+/// Biquad chain with independent filter state per channel. Channels either
+/// share one set of coefficients or get their own (e.g. L/R corrections).
 pub struct ParametricEQ {
-    filters: Vec<DirectForm2Transposed<f32>>,
+    filters: Vec<BiquadChain>, // One per channel
 }
 
 impl ParametricEQ {
     pub fn new(sample_rate: f32, bands: &[FrequencyBand]) -> Self {
-        let filters = bands
-            .iter()
-            .map(|band| {
-                let coeffs = Coefficients::<f32>::from_params(
-                    Type::PeakingEQ(band.gain_db),
-                    sample_rate.hz(),
-                    band.frequency.hz(),
-                    Q_BUTTERWORTH_F32, // Q = 0.707
-                )
-                .unwrap();
+        Self::from_params(sample_rate, &band_params(bands))
+    }
 
-                DirectForm2Transposed::<f32>::new(coeffs)
-            })
+    /// Builds the chain from explicit (frequency Hz, gain dB, Q, type) tuples.
+    pub fn from_params(sample_rate: f32, params: &[(f32, f32, f32, FilterType)]) -> Self {
+        Self::with_channels(sample_rate, 1, params)
+    }
+
+    // Same coefficients on every channel
+    pub fn with_channels(
+        sample_rate: f32,
+        channels: usize,
+        params: &[(f32, f32, f32, FilterType)],
+    ) -> Self {
+        let chain = build_chain(sample_rate, params);
+        Self {
+            filters: vec![chain; channels.max(1)],
+        }
+    }
+
+    // One parameter set per channel
+    pub fn from_channel_params(
+        sample_rate: f32,
+        channel_params: &[Vec<(f32, f32, f32, FilterType)>],
+    ) -> Self {
+        let mut filters: Vec<_> = channel_params
+            .iter()
+            .map(|params| build_chain(sample_rate, params))
             .collect();
+        if filters.is_empty() {
+            filters.push(BiquadChain::default());
+        }
 
         Self { filters }
     }
 
+    pub fn channels(&self) -> usize {
+        self.filters.len()
+    }
+
+    pub fn reset(&mut self) {
+        for chain in &mut self.filters {
+            chain.reset();
+        }
+    }
+
+    // Minimum-phase IIR, so nothing to compensate
+    pub fn latency_samples(&self) -> usize {
+        0
+    }
+
+    /// Swaps in new parameters while keeping the filter state, so a running
+    /// signal doesn't click. Rebuilds the chain when the band count changes.
+    /// Every channel gets the same parameters.
+    pub fn retune(&mut self, sample_rate: f32, params: &[(f32, f32, f32, FilterType)]) {
+        let coefficients = design_coefficients(sample_rate, params);
+        if self.filters.iter().any(|c| c.len() != coefficients.len()) {
+            *self = Self::with_channels(sample_rate, self.channels(), params);
+            return;
+        }
+
+        for chain in &mut self.filters {
+            chain.set_coefficients(&coefficients);
+        }
+    }
+
+    // Mono path, runs the first channel
     pub fn process(&mut self, sample: f32) -> f32 {
-        let mut output = sample;
-        for filter in &mut self.filters {
-            output = filter.run(output);
+        self.process_channel(0, sample)
+    }
+
+    pub fn process_channel(&mut self, channel: usize, sample: f32) -> f32 {
+        match self.filters.get_mut(channel) {
+            Some(chain) => chain.run(sample),
+            None => sample,
         }
-        output
     }
 
+    // Block paths below give the same output as calling `process` per sample
     pub fn process_buffer(&mut self, buffer: &mut [f32]) {
-        for sample in buffer.iter_mut() {
-            *sample = self.process(*sample);
+        self.filters[0].run_block(buffer);
+    }
+
+    // Frames of `channels()` samples; channels without a chain pass through.
+    // Each channel is gathered into a scratch block so it can run as one.
+    pub fn process_interleaved(&mut self, buffer: &mut [f32]) {
+        let channels = self.channels();
+        let mut scratch = Vec::with_capacity(buffer.len() / channels + 1);
+        for (ch, chain) in self.filters.iter_mut().enumerate() {
+            scratch.clear();
+            scratch.extend(buffer.iter().skip(ch).step_by(channels));
+            chain.run_block(&mut scratch);
+            for (dst, &src) in buffer.iter_mut().skip(ch).step_by(channels).zip(&scratch) {
+                *dst = src;
+            }
         }
     }
+
+    pub fn process_planar(&mut self, channels: &mut [Vec<f32>]) {
+        for (chain, channel) in self.filters.iter_mut().zip(channels.iter_mut()) {
+            chain.run_block(channel);
+        }
+    }
+}
+
+fn build_chain(sample_rate: f32, params: &[(f32, f32, f32, FilterType)]) -> BiquadChain {
+    BiquadChain::new(&design_coefficients(sample_rate, params))
+}
+
+pub fn band_params(bands: &[FrequencyBand]) -> Vec<(f32, f32, f32, FilterType)> {
+    bands
+        .iter()
+        .map(|band| (band.frequency, band.gain_db, band.q(), band.filter_type))
+        .collect()
+}
+
+// Highest usable centre frequency as a fraction of the sample rate
+const MAX_FREQUENCY_RATIO: f32 = 0.49;
+
+// Bands that can't be realised are skipped rather than panicking
+pub fn design_coefficients(
+    sample_rate: f32,
+    params: &[(f32, f32, f32, FilterType)],
+) -> Vec<Coefficients<f32>> {
+    sanitize_params(sample_rate, params)
+        .0
+        .iter()
+        .filter_map(|&(frequency, gain_db, q, filter_type)| {
+            let kind = biquad_type(filter_type, gain_db);
+            Coefficients::<f32>::from_params(kind, sample_rate.hz(), frequency.hz(), q).ok()
+        })
+        .collect()
+}
+
+/// Clamps bands above Nyquist down to just below it and drops bands with
+/// non-finite or non-positive values. Returns the usable parameters plus one
+/// warning per band that was changed or dropped.
+pub fn sanitize_params(
+    sample_rate: f32,
+    params: &[BandParams],
+) -> (Vec<BandParams>, Vec<MatchWarning>) {
+    let max_frequency = sample_rate * MAX_FREQUENCY_RATIO;
+    let mut valid = Vec::with_capacity(params.len());
+    let mut warnings = Vec::new();
+
+    for &(frequency, gain_db, q, filter_type) in params {
+        let usable = frequency.is_finite()
+            && frequency > 0.0
+            && gain_db.is_finite()
+            && q.is_finite()
+            && q > 0.0;
+        if !usable {
+            warnings.push(MatchWarning::new(
+                WarningCode::InvalidBand,
+                WarningSeverity::Warning,
+                format!(
+                    "Skipped band with invalid parameters ({} Hz, {} dB, Q {})",
+                    frequency, gain_db, q
+                ),
+            ));
+            continue;
+        }
+
+        if frequency > max_frequency {
+            warnings.push(
+                MatchWarning::new(
+                    WarningCode::InvalidBand,
+                    WarningSeverity::Info,
+                    format!(
+                        "Band at {:.0} Hz is above Nyquist for {:.0} Hz; moved to {:.0} Hz",
+                        frequency, sample_rate, max_frequency
+                    ),
+                )
+                .with_range(max_frequency, frequency),
+            );
+            valid.push((max_frequency, gain_db, q, filter_type));
+        } else {
+            valid.push((frequency, gain_db, q, filter_type));
+        }
+    }
+
+    (valid, warnings)
+}
+
+// Generic over the gain type so f64 chains can share the mapping
+pub fn biquad_type<G>(filter_type: FilterType, gain_db: G) -> Type<G> {
+    match filter_type {
+        FilterType::Peaking => Type::PeakingEQ(gain_db),
+        FilterType::LowShelf => Type::LowShelf(gain_db),
+        FilterType::HighShelf => Type::HighShelf(gain_db),
+        FilterType::HighPass => Type::HighPass,
+        FilterType::LowPass => Type::LowPass,
+    }
 }
 
 // Utility for audio preview with EQ applied
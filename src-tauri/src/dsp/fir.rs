@@ -0,0 +1,51 @@
+use super::fft;
+use super::filters::BandParams;
+use super::response::params_magnitude;
+use realfft::num_complex::Complex;
+use std::f64::consts::PI;
+
+// 8191 taps: ~6 Hz resolution and 85 ms of delay at 48 kHz
+pub const DEFAULT_FIR_TAPS: usize = 8191;
+const MIN_TAPS: usize = 3;
+
+/// Linear-phase FIR with the magnitude of the parametric `params` at
+/// `sample_rate`: the composite response sampled on an FFT grid with zero
+/// phase, centred and Hann-windowed. `taps` must be odd so the delay is a
+/// whole number of samples, `(taps - 1) / 2`.
+pub fn linear_phase_fir(
+    params: &[BandParams],
+    sample_rate: u32,
+    taps: usize,
+) -> Result<Vec<f32>, String> {
+    if taps < MIN_TAPS || taps.is_multiple_of(2) {
+        return Err(format!("FIR length must be odd and at least {}", MIN_TAPS));
+    }
+    if sample_rate == 0 {
+        return Err("Sample rate must be positive".to_string());
+    }
+
+    // Twice the length, so the zero-phase response has room to decay
+    let size = (2 * taps).next_power_of_two();
+    let bin_hz = sample_rate as f32 / size as f32;
+    let freqs: Vec<f32> = (0..=size / 2).map(|k| k as f32 * bin_hz).collect();
+    let mut spectrum: Vec<Complex<f32>> = params_magnitude(params, sample_rate, &freqs)
+        .into_iter()
+        .map(|m| Complex::new(m as f32, 0.0))
+        .collect();
+
+    let inverse = fft::real_inverse(size);
+    let mut response = vec![0.0f32; size];
+    inverse
+        .process(&mut spectrum, &mut response)
+        .map_err(|e| e.to_string())?;
+
+    // Zero phase puts the peak at index 0; shift it to the middle
+    let half = (taps - 1) / 2;
+    Ok((0..taps)
+        .map(|i| {
+            let source = (i + size - half) % size;
+            let window = 0.5 - 0.5 * (2.0 * PI * i as f64 / (taps - 1) as f64).cos();
+            response[source] / size as f32 * window as f32
+        })
+        .collect())
+}
@@ -0,0 +1,71 @@
+const THRESHOLD_DB: f32 = -3.0;
+const CEILING_DB: f32 = -0.3;
+const RELEASE_SECS: f32 = 0.08;
+
+/// Stereo-linked soft limiter for monitoring. Levels below the threshold
+/// pass untouched; above it the peak is bent smoothly (tanh knee) toward the
+/// ceiling, with instant attack so nothing ever exceeds it and a slow release
+/// so the gain doesn't pump on every transient.
+pub struct SoftLimiter {
+    threshold: f32,
+    ceiling: f32,
+    release_coeff: f32,
+    gain: f32,
+    max_reduction_db: f32, // Peak hold for metering, cleared by `take_max_reduction_db`
+}
+
+impl SoftLimiter {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            threshold: db_to_linear(THRESHOLD_DB),
+            ceiling: db_to_linear(CEILING_DB),
+            release_coeff: 1.0 - (-1.0 / (RELEASE_SECS * sample_rate.max(1) as f32)).exp(),
+            gain: 1.0,
+            max_reduction_db: 0.0,
+        }
+    }
+
+    // One sample per channel; every channel gets the same gain
+    pub fn process_frame(&mut self, frame: &mut [f32]) {
+        let peak = frame.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let target = self.target_gain(peak);
+
+        if target < self.gain {
+            self.gain = target;
+        } else {
+            self.gain += (target - self.gain) * self.release_coeff;
+        }
+
+        for sample in frame.iter_mut() {
+            *sample *= self.gain;
+        }
+        self.max_reduction_db = self.max_reduction_db.max(self.gain_reduction_db());
+    }
+
+    pub fn gain_reduction_db(&self) -> f32 {
+        -20.0 * self.gain.max(1e-6).log10()
+    }
+
+    pub fn take_max_reduction_db(&mut self) -> f32 {
+        std::mem::take(&mut self.max_reduction_db)
+    }
+
+    pub fn reset(&mut self) {
+        self.gain = 1.0;
+        self.max_reduction_db = 0.0;
+    }
+
+    fn target_gain(&self, peak: f32) -> f32 {
+        if peak <= self.threshold {
+            return 1.0;
+        }
+
+        let headroom = self.ceiling - self.threshold;
+        let limited = self.threshold + headroom * ((peak - self.threshold) / headroom).tanh();
+        limited / peak
+    }
+}
+
+fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
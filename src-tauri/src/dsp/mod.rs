@@ -1 +1,10 @@
+pub mod chain;
+pub mod convolution;
+pub mod dynamic;
+pub mod fft;
 pub mod filters;
+pub mod fir;
+pub mod limiter;
+pub mod oversampling;
+pub mod response;
+pub mod simd;
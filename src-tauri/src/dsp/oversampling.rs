@@ -0,0 +1,118 @@
+use super::filters::{FilterType, ParametricEQ};
+use biquad::*;
+
+// Section Qs of an 8th-order Butterworth low-pass
+pub(crate) const BUTTERWORTH_8_Q: [f32; 4] = [0.5098, 0.6013, 0.9000, 2.5629];
+// Anti-imaging/anti-aliasing cutoff as a fraction of the base sample rate
+pub(crate) const CUTOFF_RATIO: f32 = 0.45;
+
+pub const SUPPORTED_FACTORS: [usize; 3] = [1, 2, 4];
+
+/// Runs a `ParametricEQ` at a multiple of the host sample rate so bells near
+/// Nyquist keep their analog shape instead of cramping. Factor 1 is a plain
+/// pass-through to the EQ.
+pub struct OversampledEQ {
+    factor: usize,
+    base_rate: f32,
+    eq: ParametricEQ,
+    upsample: Vec<DirectForm2Transposed<f32>>,
+    downsample: Vec<DirectForm2Transposed<f32>>,
+    latency: usize, // Base-rate samples
+}
+
+impl OversampledEQ {
+    pub fn new(
+        sample_rate: f32,
+        factor: usize,
+        params: &[(f32, f32, f32, FilterType)],
+    ) -> Result<Self, String> {
+        if !SUPPORTED_FACTORS.contains(&factor) {
+            return Err(format!("Unsupported oversampling factor: {}", factor));
+        }
+
+        let oversampled_rate = sample_rate * factor as f32;
+        let anti_alias: Vec<Coefficients<f32>> = if factor == 1 {
+            Vec::new()
+        } else {
+            BUTTERWORTH_8_Q
+                .iter()
+                .filter_map(|&q| {
+                    Coefficients::<f32>::from_params(
+                        Type::LowPass,
+                        oversampled_rate.hz(),
+                        (sample_rate * CUTOFF_RATIO).hz(),
+                        q,
+                    )
+                    .ok()
+                })
+                .collect()
+        };
+
+        // The anti-aliasing filters aren't linear phase; their group delay at
+        // DC (up and down), in base-rate samples, is what the dry path needs
+        let delay: f32 = anti_alias.iter().map(dc_group_delay).sum::<f32>() * 2.0;
+        let cascade = || {
+            anti_alias
+                .iter()
+                .map(|&c| DirectForm2Transposed::<f32>::new(c))
+                .collect()
+        };
+
+        Ok(Self {
+            factor,
+            base_rate: sample_rate,
+            eq: ParametricEQ::from_params(oversampled_rate, params),
+            upsample: cascade(),
+            downsample: cascade(),
+            latency: (delay / factor as f32).round() as usize,
+        })
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    pub fn reset(&mut self) {
+        self.eq.reset();
+        for filter in self.upsample.iter_mut().chain(&mut self.downsample) {
+            filter.reset_state();
+        }
+    }
+
+    pub fn latency_samples(&self) -> usize {
+        self.latency + self.eq.latency_samples()
+    }
+
+    pub fn retune(&mut self, params: &[(f32, f32, f32, FilterType)]) {
+        self.eq.retune(self.base_rate * self.factor as f32, params);
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        if self.factor == 1 {
+            return self.eq.process(sample);
+        }
+
+        // Zero-stuff, filter images, run the EQ, filter, keep every Nth sample
+        let mut output = 0.0;
+        for k in 0..self.factor {
+            let stuffed = if k == 0 {
+                sample * self.factor as f32
+            } else {
+                0.0
+            };
+            let up = run_cascade(&mut self.upsample, stuffed);
+            let processed = self.eq.process(up);
+            output = run_cascade(&mut self.downsample, processed);
+        }
+        output
+    }
+}
+
+fn run_cascade(filters: &mut [DirectForm2Transposed<f32>], sample: f32) -> f32 {
+    filters.iter_mut().fold(sample, |acc, f| f.run(acc))
+}
+
+// tau(0) of a biquad, from the derivative of its phase at z = 1
+fn dc_group_delay(c: &Coefficients<f32>) -> f32 {
+    (c.b1 + 2.0 * c.b2) / (c.b0 + c.b1 + c.b2) - (c.a1 + 2.0 * c.a2) / (1.0 + c.a1 + c.a2)
+}
@@ -0,0 +1,107 @@
+use super::filters::{band_params, design_coefficients, sanitize_params, BandParams};
+use crate::audio::profile::FrequencyBand;
+use crate::audio::warnings::MatchWarning;
+use biquad::Coefficients;
+use num_complex::Complex;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterResponse {
+    pub freqs: Vec<f32>,
+    pub magnitude_db: Vec<f32>,
+    pub phase_deg: Vec<f32>,
+    #[serde(default)]
+    pub warnings: Vec<MatchWarning>, // Bands clamped or skipped at this sample rate
+}
+
+/// Evaluates the composite biquad transfer function on a log-spaced grid from
+/// 20 Hz to 20 kHz (or Nyquist), i.e. exactly the curve `ParametricEQ` applies.
+pub fn compute_filter_response(
+    bands: &[FrequencyBand],
+    sample_rate: u32,
+    n_points: usize,
+) -> FilterResponse {
+    let params = band_params(bands);
+    let coeffs = design_coefficients(sample_rate as f32, &params);
+    let (_, warnings) = sanitize_params(sample_rate as f32, &params);
+    let fs = sample_rate as f64;
+    let low = 20.0f64;
+    let high = 20000.0f64.min(fs / 2.0);
+    let n = n_points.max(2);
+
+    let freqs: Vec<f32> = (0..n)
+        .map(|i| (low * (high / low).powf(i as f64 / (n - 1) as f64)) as f32)
+        .collect();
+
+    let (magnitude_db, phase_deg) = freqs
+        .iter()
+        .map(|&freq| {
+            let h = transfer(&coeffs, freq, fs);
+            (
+                (20.0 * (h.norm() + 1e-12).log10()) as f32,
+                h.arg().to_degrees() as f32,
+            )
+        })
+        .unzip();
+
+    FilterResponse {
+        freqs,
+        magnitude_db,
+        phase_deg,
+        warnings,
+    }
+}
+
+/// Linear magnitude of the composite filter for `params` at each of `freqs`.
+pub fn params_magnitude(params: &[BandParams], sample_rate: u32, freqs: &[f32]) -> Vec<f64> {
+    let coeffs = design_coefficients(sample_rate as f32, params);
+    freqs
+        .iter()
+        .map(|&freq| transfer(&coeffs, freq, sample_rate as f64).norm())
+        .collect()
+}
+
+/// Power gain (dB) of the composite filter averaged over each
+/// `(center, bandwidth)` span, which is what a band level measured over
+/// that span would change by.
+pub fn band_power_gain_db(
+    bands: &[FrequencyBand],
+    sample_rate: u32,
+    spans: &[(f32, f32)],
+) -> Vec<f32> {
+    const POINTS_PER_SPAN: usize = 16;
+
+    let coeffs = design_coefficients(sample_rate as f32, &band_params(bands));
+    let fs = sample_rate as f64;
+    let nyquist = fs / 2.0;
+
+    spans
+        .iter()
+        .map(|&(center, bandwidth)| {
+            let low = (center as f64 - bandwidth as f64 / 2.0).max(1.0);
+            let high = (center as f64 + bandwidth as f64 / 2.0).clamp(low, nyquist * 0.999);
+            let mean_power = (0..POINTS_PER_SPAN)
+                .map(|i| {
+                    let t = i as f64 / (POINTS_PER_SPAN - 1) as f64;
+                    let freq = low * (high / low).powf(t);
+                    transfer(&coeffs, freq as f32, fs).norm_sqr()
+                })
+                .sum::<f64>()
+                / POINTS_PER_SPAN as f64;
+            (10.0 * (mean_power + 1e-24).log10()) as f32
+        })
+        .collect()
+}
+
+fn transfer(coeffs: &[Coefficients<f32>], freq: f32, fs: f64) -> Complex<f64> {
+    let w = 2.0 * PI * freq as f64 / fs;
+    let z1 = Complex::from_polar(1.0, -w);
+    let z2 = z1 * z1;
+
+    coeffs.iter().fold(Complex::new(1.0, 0.0), |acc, c| {
+        let num = c.b0 as f64 + z1 * c.b1 as f64 + z2 * c.b2 as f64;
+        let den = 1.0 + z1 * c.a1 as f64 + z2 * c.a2 as f64;
+        acc * num / den
+    })
+}
@@ -0,0 +1,63 @@
+use rustfft::num_complex::Complex;
+
+// Stable Rust has no portable SIMD, so the hot analysis loops work on
+// fixed-size lanes without bounds checks or early exits, which LLVM turns
+// into packed multiplies and square roots on every target.
+const LANES: usize = 8;
+
+/// `out[i] = frame[i] * window[i]` as a real-valued complex buffer.
+pub fn apply_window(frame: &[f32], window: &[f32], out: &mut [Complex<f32>]) {
+    let len = frame.len().min(window.len()).min(out.len());
+    let (frame, window, out) = (&frame[..len], &window[..len], &mut out[..len]);
+
+    let mut out_chunks = out.chunks_exact_mut(LANES);
+    let mut frame_chunks = frame.chunks_exact(LANES);
+    let mut window_chunks = window.chunks_exact(LANES);
+    for ((o, f), w) in (&mut out_chunks)
+        .zip(&mut frame_chunks)
+        .zip(&mut window_chunks)
+    {
+        for lane in 0..LANES {
+            o[lane] = Complex::new(f[lane] * w[lane], 0.0);
+        }
+    }
+    let tail = out_chunks.into_remainder().iter_mut();
+    for ((o, f), w) in tail
+        .zip(frame_chunks.remainder())
+        .zip(window_chunks.remainder())
+    {
+        *o = Complex::new(f * w, 0.0);
+    }
+}
+
+/// Adds `|c|` of each bin to `sums` and `|c|²` to `squares`.
+pub fn accumulate_magnitudes(bins: &[Complex<f32>], sums: &mut [f32], squares: &mut [f32]) {
+    let len = bins.len().min(sums.len()).min(squares.len());
+    let (bins, sums, squares) = (&bins[..len], &mut sums[..len], &mut squares[..len]);
+
+    let mut bin_chunks = bins.chunks_exact(LANES);
+    let mut sum_chunks = sums.chunks_exact_mut(LANES);
+    let mut square_chunks = squares.chunks_exact_mut(LANES);
+    for ((b, s), q) in (&mut bin_chunks)
+        .zip(&mut sum_chunks)
+        .zip(&mut square_chunks)
+    {
+        let mut power = [0.0f32; LANES];
+        for lane in 0..LANES {
+            power[lane] = b[lane].re * b[lane].re + b[lane].im * b[lane].im;
+        }
+        for lane in 0..LANES {
+            s[lane] += power[lane].sqrt();
+            q[lane] += power[lane];
+        }
+    }
+    let tail = sum_chunks.into_remainder().iter_mut();
+    for ((s, q), b) in tail
+        .zip(square_chunks.into_remainder())
+        .zip(bin_chunks.remainder())
+    {
+        let power = b.norm_sqr();
+        *s += power.sqrt();
+        *q += power;
+    }
+}
@@ -0,0 +1,152 @@
+use crate::audio::loader::AudioError;
+use crate::audio::validation::{FieldError, ValidationErrors};
+use crate::export::ExportError;
+use crate::i18n::{current_locale, message};
+use crate::jobs::Cancelled;
+use crate::render::RenderError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// Stable identifiers the frontend can branch on; the text shown to the
+/// user comes from the message catalog in `i18n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    FileOpen,
+    UnsupportedFormat,
+    DecodeFailed,
+    NoAudioData,
+    NotLoaded, // A reference, input, result or preview file is missing
+    NothingToUndo,
+    NothingToRedo,
+    InvalidArgument,
+    Cancelled,
+    Stale, // The state a job started from changed before it finished
+    Io,
+    AudioOutput, // No playback backend, or the device could not be opened
+    Internal,
+}
+
+/// Failure opening a file the user picked: `Read` when it couldn't be
+/// read at all, `Invalid` when it isn't a usable file of that kind.
+#[derive(Error, Debug)]
+pub enum FileError {
+    #[error("{0}")]
+    Read(String),
+
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Error returned by every command and carried by failed job events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String, // Localized for the current locale
+    #[serde(default)]
+    pub details: Option<String>, // Untranslated specifics: paths, decoder output
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>, // Rejected config fields, for invalid_argument
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self {
+            code,
+            message: message(code, current_locale()).to_string(),
+            details: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn with_details(code: ErrorCode, details: impl Into<String>) -> Self {
+        Self {
+            details: Some(details.into()),
+            ..Self::new(code)
+        }
+    }
+
+    // Prefixes the details with the file the error is about
+    pub fn in_file(mut self, path: &str) -> Self {
+        self.details = Some(match self.details {
+            Some(details) => format!("{}: {}", path, details),
+            None => path.to_string(),
+        });
+        self
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.details {
+            Some(details) => write!(f, "{} ({})", self.message, details),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<AudioError> for AppError {
+    fn from(error: AudioError) -> Self {
+        match error {
+            AudioError::FileOpen(e) => Self::with_details(ErrorCode::FileOpen, e.to_string()),
+            AudioError::UnsupportedFormat => Self::new(ErrorCode::UnsupportedFormat),
+            AudioError::DecodeError(e) => Self::with_details(ErrorCode::DecodeFailed, e),
+            AudioError::NoAudioData => Self::new(ErrorCode::NoAudioData),
+            AudioError::InvalidConfig(e) => Self::with_details(ErrorCode::InvalidArgument, e),
+            AudioError::Cancelled => Self::new(ErrorCode::Cancelled),
+        }
+    }
+}
+
+impl From<RenderError> for AppError {
+    fn from(error: RenderError) -> Self {
+        match error {
+            RenderError::InvalidConfig(e) => Self::with_details(ErrorCode::InvalidArgument, e),
+            RenderError::Audio(e) => e.into(),
+            RenderError::Write(e) => Self::with_details(ErrorCode::Io, e),
+            RenderError::Cancelled => Self::new(ErrorCode::Cancelled),
+        }
+    }
+}
+
+impl From<ExportError> for AppError {
+    fn from(error: ExportError) -> Self {
+        match error {
+            ExportError::Invalid(e) => Self::with_details(ErrorCode::InvalidArgument, e),
+            ExportError::Write(e) => Self::with_details(ErrorCode::Io, e),
+        }
+    }
+}
+
+impl From<FileError> for AppError {
+    fn from(error: FileError) -> Self {
+        match error {
+            FileError::Read(e) => Self::with_details(ErrorCode::FileOpen, e),
+            FileError::Invalid(e) => Self::with_details(ErrorCode::InvalidArgument, e),
+        }
+    }
+}
+
+impl From<Cancelled> for AppError {
+    fn from(_: Cancelled) -> Self {
+        Self::new(ErrorCode::Cancelled)
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        Self {
+            fields: errors.0.clone(),
+            ..Self::with_details(ErrorCode::InvalidArgument, errors.to_string())
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        Self::with_details(ErrorCode::Io, error.to_string())
+    }
+}
@@ -0,0 +1,83 @@
+use crate::audio::profile::{ChannelMode, ChannelProfiles, EQProfile, FrequencyBand};
+use crate::dsp::filters::FilterType;
+
+// Equalizer APO config (config.txt include)
+pub fn export_as_apo(profile: &EQProfile) -> Result<String, String> {
+    let mut output = String::from("# EQ Matcher correction\n");
+    output.push_str(&format!("Preamp: {:.1} dB\n", preamp_db(&[profile])));
+    push_filters(&mut output, profile, 1);
+
+    Ok(output)
+}
+
+// Independent filters per channel; M/S is encoded and decoded around the EQ with Copy
+pub fn export_channels_as_apo(channels: &ChannelProfiles) -> Result<String, String> {
+    let mut output = String::from("# EQ Matcher correction\n");
+    output.push_str(&format!(
+        "Preamp: {:.1} dB\n",
+        preamp_db(&[&channels.first, &channels.second])
+    ));
+
+    if channels.mode == ChannelMode::MidSide {
+        output.push_str("Copy: L=0.5*L+0.5*R R=0.5*L-0.5*R\n");
+    }
+
+    output.push_str("Channel: L\n");
+    let next = push_filters(&mut output, &channels.first, 1);
+    output.push_str("Channel: R\n");
+    push_filters(&mut output, &channels.second, next);
+    output.push_str("Channel: all\n");
+
+    if channels.mode == ChannelMode::MidSide {
+        output.push_str("Copy: L=L+R R=L-R\n");
+    }
+
+    Ok(output)
+}
+
+// Returns the next free filter index
+fn push_filters(output: &mut String, profile: &EQProfile, first_index: usize) -> usize {
+    for (i, band) in profile.bands.iter().enumerate() {
+        output.push_str(&format!(
+            "Filter {}: ON {}\n",
+            first_index + i,
+            apo_filter(band)
+        ));
+    }
+
+    first_index + profile.bands.len()
+}
+
+fn apo_filter(band: &FrequencyBand) -> String {
+    let (kind, has_gain) = match band.filter_type {
+        FilterType::Peaking => ("PK", true),
+        FilterType::LowShelf => ("LSC", true),
+        FilterType::HighShelf => ("HSC", true),
+        FilterType::HighPass => ("HPQ", false),
+        FilterType::LowPass => ("LPQ", false),
+    };
+
+    if has_gain {
+        format!(
+            "{} Fc {:.1} Hz Gain {:.2} dB Q {:.2}",
+            kind,
+            band.frequency,
+            band.gain_db,
+            band.q()
+        )
+    } else {
+        format!("{} Fc {:.1} Hz Q {:.2}", kind, band.frequency, band.q())
+    }
+}
+
+// Headroom for the largest boost so the correction can't clip
+pub(crate) fn preamp_db(profiles: &[&EQProfile]) -> f32 {
+    let max_boost = profiles
+        .iter()
+        .flat_map(|p| p.bands.iter())
+        .filter(|b| !matches!(b.filter_type, FilterType::HighPass | FilterType::LowPass))
+        .map(|b| b.gain_db)
+        .fold(0.0f32, f32::max);
+
+    -max_boost
+}
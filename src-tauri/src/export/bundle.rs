@@ -0,0 +1,140 @@
+use super::notes::{mastering_notes_as_text, MasteringNotes};
+use super::{export_result_with_context, file_extension, ExportContext, ExportError};
+use crate::atomic_file::write_atomic;
+use crate::audio::matcher::MatchResult;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+    pub format: String,
+    pub file: String, // Name inside the output folder
+    pub bytes: usize,
+}
+
+/// Written next to the exports as `<name>.manifest.json`, so whoever
+/// receives the folder knows what each file is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub app_version: String,
+    pub created_at: u64, // Unix seconds
+    pub name: String,
+    pub quality_score: f32,
+    pub output_gain_db: f32,
+    pub files: Vec<BundleFile>,
+}
+
+/// Writes `result` in every format of `formats` to `out_dir` as
+/// `<name>-<format>.<extension>`, the mastering notes when given as
+/// `<name>.notes.txt` and `.notes.json`, plus the manifest. Everything is
+/// rendered, then written to a staging folder inside `out_dir` and only
+/// moved into place once complete, so an unknown format, a failing template
+/// or a failed write leaves the folder untouched. A failure while moving the
+/// finished files in (rare: same folder, no copying) can leave part of the
+/// bundle behind.
+pub fn export_bundle(
+    result: &MatchResult,
+    formats: &[String],
+    out_dir: &Path,
+    name: &str,
+    context: &ExportContext,
+    notes: Option<&MasteringNotes>,
+) -> Result<BundleManifest, ExportError> {
+    if formats.is_empty() && notes.is_none() {
+        return Err(ExportError::Invalid(
+            "No export formats selected".to_string(),
+        ));
+    }
+
+    let mut rendered = Vec::new();
+    for format in formats {
+        let extension = file_extension(format)
+            .ok_or_else(|| ExportError::Invalid(format!("Unknown format: {}", format)))?;
+        let text = export_result_with_context(result, format, context)
+            .map_err(|e| ExportError::Invalid(format!("{}: {}", format, e)))?;
+        rendered.push((
+            format.clone(),
+            format!("{}-{}.{}", name, format, extension),
+            text,
+        ));
+    }
+    if let Some(notes) = notes {
+        let json =
+            serde_json::to_string_pretty(notes).map_err(|e| ExportError::Invalid(e.to_string()))?;
+        rendered.push((
+            "mastering_notes".to_string(),
+            format!("{}.notes.txt", name),
+            mastering_notes_as_text(notes),
+        ));
+        rendered.push((
+            "mastering_notes_json".to_string(),
+            format!("{}.notes.json", name),
+            json,
+        ));
+    }
+
+    fs::create_dir_all(out_dir)
+        .map_err(|e| ExportError::Write(format!("{}: {}", out_dir.display(), e)))?;
+    let staging = Staging::create(out_dir.join(format!(".{}.bundle.part", name)))
+        .map_err(ExportError::Write)?;
+    let mut files = Vec::new();
+    for (format, file, text) in rendered {
+        write_atomic(&staging.path.join(&file), &text).map_err(ExportError::Write)?;
+        files.push(BundleFile {
+            format,
+            file,
+            bytes: text.len(),
+        });
+    }
+
+    let manifest = BundleManifest {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        name: name.to_string(),
+        quality_score: result.quality_score,
+        output_gain_db: result.output_gain_db,
+        files,
+    };
+    let json =
+        serde_json::to_string_pretty(&manifest).map_err(|e| ExportError::Invalid(e.to_string()))?;
+    let manifest_file = format!("{}.manifest.json", name);
+    write_atomic(&staging.path.join(&manifest_file), json).map_err(ExportError::Write)?;
+
+    // The manifest goes last, so a folder that has it has the whole bundle
+    for file in manifest
+        .files
+        .iter()
+        .map(|f| &f.file)
+        .chain([&manifest_file])
+    {
+        let target = out_dir.join(file);
+        fs::rename(staging.path.join(file), &target)
+            .map_err(|e| ExportError::Write(format!("{}: {}", target.display(), e)))?;
+    }
+
+    Ok(manifest)
+}
+
+// Folder the bundle is written to first; removed with whatever is left in it
+struct Staging {
+    path: PathBuf,
+}
+
+impl Staging {
+    fn create(path: PathBuf) -> Result<Self, String> {
+        // Left over from a run that was killed mid-export
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self { path })
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
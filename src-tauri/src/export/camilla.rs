@@ -0,0 +1,129 @@
+use super::apo::preamp_db;
+use crate::audio::profile::{ChannelMode, ChannelProfiles, EQProfile, FrequencyBand};
+use crate::dsp::filters::FilterType;
+
+// -6.02 dB: the 0.5 of an M/S encode
+const HALF_DB: f32 = -6.0206;
+
+const HEADER: &str = "\
+# EQ Matcher correction for CamillaDSP 2.x
+# Merge the filters, mixers and pipeline into a config that has devices\n";
+
+/// Filters and pipeline for a CamillaDSP 2.x config, the same correction on
+/// both channels of a stereo stream.
+pub fn export_as_camilladsp(profile: &EQProfile) -> Result<String, String> {
+    let mut output = String::from(HEADER);
+    output.push_str("filters:\n");
+    push_gain(&mut output, "preamp", preamp_db(&[profile]));
+    let names = push_filters(&mut output, profile, "band");
+
+    output.push_str("pipeline:\n");
+    push_step(&mut output, &[0, 1], &names);
+    Ok(output)
+}
+
+// Independent filters per channel; M/S is encoded and decoded around them
+// with mixers
+pub fn export_channels_as_camilladsp(channels: &ChannelProfiles) -> Result<String, String> {
+    let mut output = String::from(HEADER);
+    output.push_str("filters:\n");
+    push_gain(
+        &mut output,
+        "preamp",
+        preamp_db(&[&channels.first, &channels.second]),
+    );
+    let (first, second) = match channels.mode {
+        ChannelMode::LeftRight => ("left", "right"),
+        ChannelMode::MidSide => ("mid", "side"),
+    };
+    let first_names = push_filters(&mut output, &channels.first, first);
+    let second_names = push_filters(&mut output, &channels.second, second);
+
+    let mid_side = channels.mode == ChannelMode::MidSide;
+    if mid_side {
+        output.push_str("mixers:\n");
+        push_mid_side_mixer(&mut output, "to_mid_side", HALF_DB);
+        push_mid_side_mixer(&mut output, "to_left_right", 0.0);
+    }
+
+    output.push_str("pipeline:\n");
+    if mid_side {
+        push_mixer_step(&mut output, "to_mid_side");
+    }
+    push_step(&mut output, &[0], &first_names);
+    push_step(&mut output, &[1], &second_names);
+    if mid_side {
+        push_mixer_step(&mut output, "to_left_right");
+    }
+    Ok(output)
+}
+
+fn push_gain(output: &mut String, name: &str, gain_db: f32) {
+    output.push_str(&format!(
+        "  {}:\n    type: Gain\n    parameters:\n      gain: {:.2}\n",
+        name, gain_db
+    ));
+}
+
+// Returns the filter names, the preamp first
+fn push_filters(output: &mut String, profile: &EQProfile, prefix: &str) -> Vec<String> {
+    let mut names = vec!["preamp".to_string()];
+    for (i, band) in profile.bands.iter().enumerate() {
+        let name = format!("{}_{}", prefix, i + 1);
+        output.push_str(&format!(
+            "  {}:\n    type: Biquad\n    parameters:\n{}",
+            name,
+            biquad_parameters(band)
+        ));
+        names.push(name);
+    }
+    names
+}
+
+fn biquad_parameters(band: &FrequencyBand) -> String {
+    let (kind, has_gain) = match band.filter_type {
+        FilterType::Peaking => ("Peaking", true),
+        FilterType::LowShelf => ("Lowshelf", true),
+        FilterType::HighShelf => ("Highshelf", true),
+        FilterType::HighPass => ("Highpass", false),
+        FilterType::LowPass => ("Lowpass", false),
+    };
+    let mut parameters = format!("      type: {}\n      freq: {:.1}\n", kind, band.frequency);
+    if has_gain {
+        parameters.push_str(&format!("      gain: {:.2}\n", band.gain_db));
+    }
+    parameters.push_str(&format!("      q: {:.3}\n", band.q()));
+    parameters
+}
+
+// L/R to M/S at -6 dB per source, or back at unity
+fn push_mid_side_mixer(output: &mut String, name: &str, gain_db: f32) {
+    output.push_str(&format!(
+        "  {}:\n    channels:\n      in: 2\n      out: 2\n    mapping:\n",
+        name
+    ));
+    for (dest, invert_second) in [(0, false), (1, true)] {
+        output.push_str(&format!("      - dest: {}\n        sources:\n", dest));
+        for (channel, inverted) in [(0, false), (1, invert_second)] {
+            output.push_str(&format!(
+                "          - channel: {}\n            gain: {:.4}\n            inverted: {}\n",
+                channel, gain_db, inverted
+            ));
+        }
+    }
+}
+
+fn push_step(output: &mut String, channels: &[usize], names: &[String]) {
+    let channels: Vec<String> = channels.iter().map(|c| c.to_string()).collect();
+    output.push_str(&format!(
+        "  - type: Filter\n    channels: [{}]\n    names:\n",
+        channels.join(", ")
+    ));
+    for name in names {
+        output.push_str(&format!("      - {}\n", name));
+    }
+}
+
+fn push_mixer_step(output: &mut String, name: &str) {
+    output.push_str(&format!("  - type: Mixer\n    name: {}\n", name));
+}
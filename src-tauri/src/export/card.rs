@@ -0,0 +1,168 @@
+use super::image::{text_width, Canvas, Rgb};
+use crate::audio::curve::ResponseCurve;
+use crate::audio::matcher::MatchResult;
+use serde::{Deserialize, Serialize};
+
+// Open Graph size, so links and posts show it uncropped
+const CARD_WIDTH: usize = 1200;
+const CARD_HEIGHT: usize = 630;
+const MARGIN: i64 = 56;
+const PLOT_LEFT: i64 = 112; // Room for the dB labels
+const PLOT_TOP: i64 = 176;
+const PLOT_BOTTOM: i64 = 540;
+const DB_STEP: f32 = 6.0;
+const MAX_RANGE_DB: f32 = 30.0;
+const FREQ_RANGE_HZ: (f32, f32) = (20.0, 20_000.0);
+
+const BACKGROUND: Rgb = [18, 20, 25];
+const PANEL: Rgb = [26, 29, 36];
+const GRID: Rgb = [48, 53, 63];
+const ZERO_LINE: Rgb = [78, 84, 96];
+const TEXT: Rgb = [232, 235, 240];
+const MUTED: Rgb = [130, 137, 150];
+const BEFORE: Rgb = [150, 156, 168];
+const TARGET: Rgb = [255, 184, 76];
+const AFTER: Rgb = [94, 176, 255];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareCardOptions {
+    pub branding: bool, // App name in the corner
+}
+
+/// A dark 1200x630 PNG of the match for posting: input (before), reference
+/// (target) and predicted (after) curves, with the quality score.
+pub fn render_share_card(
+    result: &MatchResult,
+    options: &ShareCardOptions,
+) -> Result<Vec<u8>, String> {
+    let bands = &result.correction_profile.bands;
+    let curve_of = |values: &[f32]| -> Vec<(f32, f32)> {
+        bands
+            .iter()
+            .zip(values)
+            .filter(|(b, _)| !b.out_of_range)
+            .map(|(b, &v)| (b.frequency, v))
+            .collect()
+    };
+    let curves = [
+        (curve_of(&result.input_normalized), BEFORE, 2),
+        (curve_of(&result.reference_normalized), TARGET, 3),
+        (curve_of(&result.predicted_normalized), AFTER, 3),
+    ];
+    if curves.iter().all(|(points, _, _)| points.is_empty()) {
+        return Err("The match has no bands in range to draw".to_string());
+    }
+
+    let peak = curves
+        .iter()
+        .flat_map(|(points, _, _)| points.iter().map(|p| p.1.abs()))
+        .filter(|v| v.is_finite())
+        .fold(0.0f32, f32::max);
+    let range_db = ((peak / DB_STEP).ceil() * DB_STEP).clamp(DB_STEP * 2.0, MAX_RANGE_DB);
+
+    let mut canvas = Canvas::new(CARD_WIDTH, CARD_HEIGHT, BACKGROUND);
+    let plot_right = canvas.width() as i64 - MARGIN;
+    let (low, high) = (FREQ_RANGE_HZ.0.ln(), FREQ_RANGE_HZ.1.ln());
+    let x_of = |freq: f32| {
+        PLOT_LEFT as f32 + (freq.ln() - low) / (high - low) * (plot_right - PLOT_LEFT) as f32
+    };
+    let y_of = |db: f32| {
+        let t = (db.clamp(-range_db, range_db) + range_db) / (2.0 * range_db);
+        PLOT_BOTTOM as f32 - t * (PLOT_BOTTOM - PLOT_TOP) as f32
+    };
+
+    // Header: title and legend left, score right
+    canvas.text(MARGIN, 48, "Frequency response", 5, TEXT);
+    let mut legend_x = MARGIN;
+    for (label, color) in [("Before", BEFORE), ("Target", TARGET), ("After", AFTER)] {
+        canvas.fill_rect(legend_x, 121, 28, 6, color);
+        legend_x += 40 + canvas.text(legend_x + 40, 117, label, 2, MUTED) + 32;
+    }
+    let score = format!("{:.0}%", result.quality_score.clamp(0.0, 1.0) * 100.0);
+    canvas.text(
+        plot_right - text_width(&score, 8) + 8,
+        40,
+        &score,
+        8,
+        score_color(result.quality_score),
+    );
+    let label = "Match quality";
+    canvas.text(plot_right - text_width(label, 2) + 2, 112, label, 2, MUTED);
+
+    // Plot area and grid
+    canvas.fill_rect(
+        PLOT_LEFT,
+        PLOT_TOP,
+        plot_right - PLOT_LEFT,
+        PLOT_BOTTOM - PLOT_TOP,
+        PANEL,
+    );
+    for (freq, label) in [(100.0, "100"), (1_000.0, "1k"), (10_000.0, "10k")] {
+        let x = x_of(freq).round() as i64;
+        canvas.fill_rect(x, PLOT_TOP, 1, PLOT_BOTTOM - PLOT_TOP, GRID);
+        let width = text_width(label, 2);
+        canvas.text(x - width / 2, PLOT_BOTTOM + 14, label, 2, MUTED);
+    }
+    let steps = (range_db / DB_STEP) as i32;
+    for step in -steps..=steps {
+        let db = step as f32 * DB_STEP;
+        let y = y_of(db).round() as i64;
+        let color = if step == 0 { ZERO_LINE } else { GRID };
+        canvas.fill_rect(PLOT_LEFT, y, plot_right - PLOT_LEFT, 1, color);
+        let label = if step == 0 {
+            "0".to_string()
+        } else {
+            format!("{:+.0}", db)
+        };
+        let width = text_width(&label, 2);
+        canvas.text(PLOT_LEFT - 14 - width, y - 7, &label, 2, MUTED);
+    }
+    canvas.text(
+        PLOT_LEFT - 14 - text_width("dB", 2),
+        PLOT_BOTTOM + 14,
+        "dB",
+        2,
+        MUTED,
+    );
+
+    // Curves, sampled per pixel column between the outermost bands
+    for (points, color, thickness) in curves {
+        let (Some(first), Some(last)) = (points.first().copied(), points.last().copied()) else {
+            continue;
+        };
+        let curve = ResponseCurve::new(points);
+        let (start, end) = (x_of(first.0).ceil() as i64, x_of(last.0).floor() as i64);
+        let line: Vec<(f32, f32)> = (start.max(PLOT_LEFT)..=end.min(plot_right))
+            .map(|x| {
+                let t = (x - PLOT_LEFT) as f32 / (plot_right - PLOT_LEFT) as f32;
+                let freq = (low + (high - low) * t).exp();
+                (x as f32, y_of(curve.gain_at(freq)))
+            })
+            .collect();
+        canvas.polyline(&line, thickness, color);
+    }
+
+    if options.branding {
+        let brand = "EQ Matcher";
+        canvas.text(
+            plot_right - text_width(brand, 2) + 2,
+            canvas.height() as i64 - 38,
+            brand,
+            2,
+            MUTED,
+        );
+    }
+
+    canvas.encode_png()
+}
+
+fn score_color(score: f32) -> Rgb {
+    if score >= 0.8 {
+        [102, 214, 140]
+    } else if score >= 0.6 {
+        [255, 184, 76]
+    } else {
+        [255, 107, 107]
+    }
+}
@@ -0,0 +1,26 @@
+use crate::audio::profile::EQProfile;
+use crate::dsp::filters::FilterType;
+
+// One band per row, for spreadsheets and scripts
+pub fn export_as_csv(profile: &EQProfile) -> Result<String, String> {
+    let mut output = String::from("frequency_hz,gain_db,q,filter_type\n");
+
+    for band in &profile.bands {
+        let filter_type = match band.filter_type {
+            FilterType::Peaking => "peaking",
+            FilterType::LowShelf => "low_shelf",
+            FilterType::HighShelf => "high_shelf",
+            FilterType::HighPass => "high_pass",
+            FilterType::LowPass => "low_pass",
+        };
+        output.push_str(&format!(
+            "{},{:.2},{:.3},{}\n",
+            band.frequency,
+            band.gain_db,
+            band.q(),
+            filter_type
+        ));
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,221 @@
+use super::apo::export_as_apo;
+use super::fit::{fit_bands_within, FitLimits, FIT_SAMPLE_RATE};
+use super::ExportError;
+use crate::atomic_file::write_atomic;
+use crate::audio::profile::{EQProfile, FrequencyBand};
+use crate::dsp::response::band_power_gain_db;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// What a hardware EQ accepts. The built-in ones follow each vendor's app;
+/// anything else can be described by the frontend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceConstraints {
+    pub id: String, // Used in file names: letters, digits, '-' and '_'
+    pub name: String,
+    pub max_bands: usize,
+    pub min_gain_db: f32,
+    pub max_gain_db: f32,
+    pub min_q: f32,
+    pub max_q: f32,
+    pub min_hz: f32,
+    pub max_hz: f32,
+    pub shelves: bool,
+}
+
+impl Default for DeviceConstraints {
+    fn default() -> Self {
+        Self {
+            id: "device".to_string(),
+            name: "Device".to_string(),
+            max_bands: 10,
+            min_gain_db: -12.0,
+            max_gain_db: 12.0,
+            min_q: 0.1,
+            max_q: 10.0,
+            min_hz: 20.0,
+            max_hz: 20_000.0,
+            shelves: true,
+        }
+    }
+}
+
+impl DeviceConstraints {
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_id = !self.id.is_empty()
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_id {
+            return Err(format!("Invalid device id: {:?}", self.id));
+        }
+        let ordered = |min: f32, max: f32| min.is_finite() && max.is_finite() && min < max;
+        if self.max_bands == 0
+            || !ordered(self.min_gain_db, self.max_gain_db)
+            || !ordered(self.min_q, self.max_q)
+            || !ordered(self.min_hz, self.max_hz)
+            || self.min_q <= 0.0
+        {
+            return Err(format!(
+                "{}: invalid band, gain, Q or frequency range",
+                self.id
+            ));
+        }
+        Ok(())
+    }
+
+    fn limits(&self) -> FitLimits {
+        FitLimits {
+            slots: self.max_bands,
+            gain_db: (self.min_gain_db, self.max_gain_db),
+            q: (self.min_q, self.max_q),
+            frequency_hz: (self.min_hz, self.max_hz),
+            shelves: self.shelves,
+        }
+    }
+}
+
+// PEQ limits as each vendor's app exposes them
+pub fn builtin_devices() -> Vec<DeviceConstraints> {
+    vec![
+        DeviceConstraints {
+            id: "qudelix_5k".to_string(),
+            name: "Qudelix-5K".to_string(),
+            ..Default::default()
+        },
+        DeviceConstraints {
+            id: "wiim".to_string(),
+            name: "WiiM".to_string(),
+            min_q: 0.25,
+            max_q: 8.0,
+            ..Default::default()
+        },
+        DeviceConstraints {
+            id: "minidsp_2x4hd".to_string(),
+            name: "miniDSP 2x4 HD".to_string(),
+            min_gain_db: -16.0,
+            max_gain_db: 16.0,
+            max_q: 20.0,
+            ..Default::default()
+        },
+    ]
+}
+
+/// How much of a correction one device can reproduce, with the bands that
+/// get closest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceFeasibility {
+    pub device: DeviceConstraints,
+    pub bands: Vec<FrequencyBand>,
+    pub residual_rms_db: f32, // Correction the bands miss, over the in-range bands
+    pub max_residual_db: f32,
+    pub represented: f32,     // Share of the correction's energy reproduced, 0-1
+    pub limited_bands: usize, // Bands held at the device's gain limit
+}
+
+/// Fits `profile` to each device and reports what is left over, best fit
+/// first.
+pub fn check_feasibility(
+    profile: &EQProfile,
+    devices: &[DeviceConstraints],
+) -> Result<Vec<DeviceFeasibility>, String> {
+    let mut reports = devices
+        .iter()
+        .map(|device| {
+            device.validate()?;
+            Ok(device_feasibility(profile, device))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    reports.sort_by(|a, b| a.residual_rms_db.total_cmp(&b.residual_rms_db));
+    Ok(reports)
+}
+
+fn device_feasibility(profile: &EQProfile, device: &DeviceConstraints) -> DeviceFeasibility {
+    let bands = fit_bands_within(profile, &device.limits());
+    let in_range: Vec<&FrequencyBand> = profile.bands.iter().filter(|b| !b.out_of_range).collect();
+    let target: Vec<f32> = in_range.iter().map(|b| b.gain_db).collect();
+    let applied = if bands.is_empty() {
+        vec![0.0; target.len()]
+    } else {
+        let spans: Vec<(f32, f32)> = in_range
+            .iter()
+            .map(|b| (b.frequency, b.bandwidth))
+            .collect();
+        band_power_gain_db(&bands, FIT_SAMPLE_RATE, &spans)
+    };
+    let residual: Vec<f32> = target.iter().zip(&applied).map(|(t, a)| t - a).collect();
+
+    let energy = |values: &[f32]| values.iter().map(|v| v * v).sum::<f32>();
+    let target_energy = energy(&target);
+    let residual_energy = energy(&residual);
+    let represented = if target_energy > 1e-6 {
+        (1.0 - residual_energy / target_energy).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let limited_bands = bands
+        .iter()
+        .filter(|b| {
+            (b.gain_db - device.min_gain_db).abs() < 1e-3
+                || (b.gain_db - device.max_gain_db).abs() < 1e-3
+        })
+        .count();
+
+    DeviceFeasibility {
+        residual_rms_db: (residual_energy / residual.len().max(1) as f32).sqrt(),
+        max_residual_db: residual.iter().fold(0.0f32, |m, r| m.max(r.abs())),
+        represented,
+        limited_bands,
+        bands,
+        device: device.clone(),
+    }
+}
+
+// The fitted bands as an Equalizer APO / AutoEQ parametric file, which the
+// vendors' apps import
+pub fn device_preset(profile: &EQProfile, report: &DeviceFeasibility) -> Result<String, String> {
+    let fitted = EQProfile {
+        bands: report.bands.clone(),
+        channels: None,
+        ..profile.clone()
+    };
+    export_as_apo(&fitted)
+}
+
+/// Writes one `<name>-<device id>.txt` per device to `out_dir`, plus the
+/// report as `<name>.devices.json`. Returns the report, best fit first.
+pub fn export_device_presets(
+    profile: &EQProfile,
+    devices: &[DeviceConstraints],
+    out_dir: &Path,
+    name: &str,
+) -> Result<Vec<DeviceFeasibility>, ExportError> {
+    if devices.is_empty() {
+        return Err(ExportError::Invalid("No devices selected".to_string()));
+    }
+    let reports = check_feasibility(profile, devices).map_err(ExportError::Invalid)?;
+    let rendered = reports
+        .iter()
+        .map(|r| {
+            Ok((
+                format!("{}-{}.txt", name, r.device.id),
+                device_preset(profile, r)?,
+            ))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(ExportError::Invalid)?;
+
+    std::fs::create_dir_all(out_dir)
+        .map_err(|e| ExportError::Write(format!("{}: {}", out_dir.display(), e)))?;
+    for (file, text) in rendered {
+        write_atomic(&out_dir.join(file), text).map_err(ExportError::Write)?;
+    }
+    let json =
+        serde_json::to_string_pretty(&reports).map_err(|e| ExportError::Invalid(e.to_string()))?;
+    write_atomic(&out_dir.join(format!("{}.devices.json", name)), json)
+        .map_err(ExportError::Write)?;
+
+    Ok(reports)
+}
@@ -0,0 +1,61 @@
+use super::apo::preamp_db;
+use super::ExportError;
+use crate::audio::matcher::MatchResult;
+use crate::audio::profile::{ChannelMode, EQProfile};
+use crate::audio::writer::{write_wav, BitDepth};
+use crate::dsp::filters::band_params;
+use crate::dsp::fir::linear_phase_fir;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// What `export_fir` wrote, for the convolver's settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirExport {
+    pub path: String,
+    pub sample_rate: u32,
+    pub taps: usize,
+    pub channels: usize,
+    pub channel_mode: Option<ChannelMode>, // Per-channel corrections; None: one filter
+    pub latency_samples: usize,            // Delay of the linear-phase filter
+    pub preamp_db: f32,                    // Already applied to the taps
+}
+
+/// Writes the correction as a linear-phase FIR impulse response, a 32-bit
+/// float WAV for convolution engines (CamillaDSP Conv, Equalizer APO
+/// Convolution, foobar2000). Per-channel corrections get one channel each;
+/// M/S filters must run between an M/S encode and decode, e.g. the mixers of
+/// the CamillaDSP export. The preamp is folded in so boosts can't clip.
+pub fn export_fir(
+    result: &MatchResult,
+    path: &Path,
+    sample_rate: u32,
+    taps: usize,
+) -> Result<FirExport, ExportError> {
+    let (profiles, channel_mode): (Vec<&EQProfile>, _) = match &result.channel_corrections {
+        Some(channels) => (vec![&channels.first, &channels.second], Some(channels.mode)),
+        None => (vec![&result.correction_profile], None),
+    };
+    let preamp_db = preamp_db(&profiles);
+    let scale = 10f64.powf(preamp_db as f64 / 20.0);
+
+    let channels = profiles
+        .iter()
+        .map(|profile| {
+            let impulse = linear_phase_fir(&band_params(&profile.bands), sample_rate, taps)?;
+            Ok(impulse.iter().map(|&s| s as f64 * scale).collect())
+        })
+        .collect::<Result<Vec<Vec<f64>>, String>>()
+        .map_err(ExportError::Invalid)?;
+    write_wav(path, &channels, sample_rate, BitDepth::Float32, false)
+        .map_err(ExportError::Write)?;
+
+    Ok(FirExport {
+        path: path.to_string_lossy().into_owned(),
+        sample_rate,
+        taps,
+        channels: channels.len(),
+        channel_mode,
+        latency_samples: (taps - 1) / 2,
+        preamp_db,
+    })
+}
@@ -0,0 +1,144 @@
+use crate::audio::profile::{EQProfile, FrequencyBand};
+use crate::dsp::filters::FilterType;
+use crate::dsp::response::band_power_gain_db;
+
+pub(crate) const FIT_SAMPLE_RATE: u32 = 48_000;
+pub(crate) const FIT_MIN_GAIN_DB: f32 = 0.5; // Residuals below this don't get a band
+const FIT_PASSES: usize = 4;
+const SHELF_Q: f32 = 0.71;
+
+/// What the target EQ can do: how many bands, and the gain, Q and
+/// frequency each one accepts.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FitLimits {
+    pub slots: usize,
+    pub gain_db: (f32, f32),
+    pub q: (f32, f32),
+    pub frequency_hz: (f32, f32),
+    pub shelves: bool, // Without, plateaus at the ends get a wide peak
+}
+
+impl FitLimits {
+    // Plugin EQs: any frequency, shelves, generous gain
+    fn plugin(slots: usize) -> Self {
+        Self {
+            slots,
+            gain_db: (-18.0, 18.0),
+            q: (0.3, 6.0),
+            frequency_hz: (0.0, f32::MAX),
+            shelves: true,
+        }
+    }
+}
+
+/// Approximates the correction with at most `slots` parametric bands, for
+/// plugins with a handful of them. Greedy: a band at the largest remaining
+/// error, then a few passes nudging every gain by the error left at its
+/// centre. Sorted by frequency.
+pub(crate) fn fit_bands(profile: &EQProfile, slots: usize) -> Vec<FrequencyBand> {
+    fit_bands_within(profile, &FitLimits::plugin(slots))
+}
+
+// `fit_bands` for a device: gains, Qs and frequencies stay inside `limits`
+pub(crate) fn fit_bands_within(profile: &EQProfile, limits: &FitLimits) -> Vec<FrequencyBand> {
+    let in_range: Vec<&FrequencyBand> = profile.bands.iter().filter(|b| !b.out_of_range).collect();
+    let target: Vec<f32> = in_range.iter().map(|b| b.gain_db).collect();
+    let spans: Vec<(f32, f32)> = in_range
+        .iter()
+        .map(|b| (b.frequency, b.bandwidth))
+        .collect();
+    let mut fitted: Vec<FrequencyBand> = Vec::new();
+
+    let residual = |fitted: &[FrequencyBand]| -> Vec<f32> {
+        if fitted.is_empty() {
+            return target.clone();
+        }
+        let applied = band_power_gain_db(fitted, FIT_SAMPLE_RATE, &spans);
+        target.iter().zip(applied).map(|(t, a)| t - a).collect()
+    };
+
+    for _ in 0..limits.slots {
+        let error = residual(&fitted);
+        let Some((peak, &gain)) = error
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()))
+        else {
+            break;
+        };
+        if gain.abs() < FIT_MIN_GAIN_DB {
+            break;
+        }
+
+        // Width: neighbours still above half the peak, same sign
+        let within =
+            |i: &usize| error[*i].signum() == gain.signum() && error[*i].abs() >= gain.abs() / 2.0;
+        let low = (0..peak).rev().take_while(within).last().unwrap_or(peak);
+        let high = (peak + 1..error.len())
+            .take_while(within)
+            .last()
+            .unwrap_or(peak);
+        // Edges halfway (in log frequency) to the next band outside; the
+        // outermost bands mirror their inner neighbour
+        let f = |i: usize| in_range[i].frequency;
+        let last = error.len() - 1;
+        let low_hz = match low {
+            0 if last > 0 => f(0) / (f(1) / f(0)).sqrt(),
+            0 => f(0) / 1.2,
+            i => (f(i - 1) * f(i)).sqrt(),
+        };
+        let high_hz = match high {
+            i if i < last => (f(i) * f(i + 1)).sqrt(),
+            i if i > 0 => f(i) * (f(i) / f(i - 1)).sqrt(),
+            i => f(i) * 1.2,
+        };
+        // Plateaus reaching an end of the range become shelves, turning
+        // over where the error falls to half
+        let (filter_type, frequency, q) = if limits.shelves && low == 0 && peak < error.len() / 4 {
+            (FilterType::LowShelf, in_range[high].frequency, SHELF_Q)
+        } else if limits.shelves && high == last && peak >= error.len() * 3 / 4 {
+            (FilterType::HighShelf, in_range[low].frequency, SHELF_Q)
+        } else {
+            let frequency = in_range[peak].frequency;
+            (
+                FilterType::Peaking,
+                frequency,
+                frequency / (high_hz - low_hz).max(1.0),
+            )
+        };
+        let frequency = frequency.clamp(limits.frequency_hz.0, limits.frequency_hz.1);
+        let q = q.clamp(limits.q.0, limits.q.1);
+        fitted.push(FrequencyBand {
+            frequency,
+            gain_db: gain.clamp(limits.gain_db.0, limits.gain_db.1),
+            bandwidth: frequency / q,
+            confidence: 1.0,
+            temporal_spread_db: 0.0,
+            filter_type,
+            out_of_range: false,
+        });
+
+        for _ in 0..FIT_PASSES {
+            for i in 0..fitted.len() {
+                let error = residual(&fitted);
+                // A shelf is judged on its plateau, not its turnover
+                let at = match fitted[i].filter_type {
+                    FilterType::LowShelf => 0,
+                    FilterType::HighShelf => spans.len() - 1,
+                    // Nearest centre, for peaks moved into the device's range
+                    _ => (0..spans.len())
+                        .min_by(|&a, &b| {
+                            let off = |j: usize| (spans[j].0 / fitted[i].frequency).ln().abs();
+                            off(a).total_cmp(&off(b))
+                        })
+                        .unwrap_or(0),
+                };
+                fitted[i].gain_db =
+                    (fitted[i].gain_db + error[at]).clamp(limits.gain_db.0, limits.gain_db.1);
+            }
+        }
+    }
+
+    fitted.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    fitted
+}
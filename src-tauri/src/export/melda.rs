@@ -0,0 +1,91 @@
+use super::apo::preamp_db;
+use super::fit::fit_bands;
+use crate::audio::curve::ResponseCurve;
+use crate::audio::profile::EQProfile;
+use crate::dsp::filters::FilterType;
+
+const MEQUALIZER_BANDS: usize = 6;
+const CURVE_POINTS_PER_OCTAVE: usize = 12; // MAutoEqualizer's high-resolution mode
+const CURVE_RANGE_HZ: (f32, f32) = (20.0, 20_000.0);
+
+/// MEqualizer preset: the correction fitted to its six bands.
+pub fn export_as_mequalizer(profile: &EQProfile) -> Result<String, String> {
+    let mut output = preset_header("MEqualizer");
+    output.push_str(&format!(
+        "  <Parameter Name=\"OutputGain\" Value=\"{:.2}\"/>\n",
+        preamp_db(&[profile])
+    ));
+    let bands = fit_bands(profile, MEQUALIZER_BANDS);
+    for n in 0..MEQUALIZER_BANDS {
+        match bands.get(n) {
+            Some(band) => output.push_str(&format!(
+                "  <Band Index=\"{}\" Enabled=\"1\" Shape=\"{}\" Frequency=\"{:.1}\" Gain=\"{:.2}\" Q=\"{:.3}\"/>\n",
+                n + 1,
+                shape(band.filter_type),
+                band.frequency,
+                band.gain_db,
+                band.q()
+            )),
+            None => output.push_str(&format!("  <Band Index=\"{}\" Enabled=\"0\"/>\n", n + 1)),
+        }
+    }
+    output.push_str("</MPreset>\n");
+
+    Ok(output)
+}
+
+/// MAutoEqualizer preset for its curve-based high-resolution mode: the
+/// correction resampled to 1/12 octave from 20 Hz to 20 kHz, so nothing is
+/// lost to a band fit.
+pub fn export_as_mautoequalizer(profile: &EQProfile) -> Result<String, String> {
+    let points: Vec<(f32, f32)> = profile
+        .bands
+        .iter()
+        .filter(|b| !b.out_of_range)
+        .filter(|b| !matches!(b.filter_type, FilterType::HighPass | FilterType::LowPass))
+        .map(|b| (b.frequency, b.gain_db))
+        .collect();
+    if points.is_empty() {
+        return Err("No bands in range to export".to_string());
+    }
+    let curve = ResponseCurve::new(points);
+
+    let mut output = preset_header("MAutoEqualizer");
+    output.push_str("  <Parameter Name=\"Mode\" Value=\"HighResolution\"/>\n");
+    output.push_str(&format!(
+        "  <Parameter Name=\"OutputGain\" Value=\"{:.2}\"/>\n",
+        preamp_db(&[profile])
+    ));
+    output.push_str("  <Curve Name=\"Equalization\">\n");
+    let (low, high) = CURVE_RANGE_HZ;
+    let steps = ((high / low).log2() * CURVE_POINTS_PER_OCTAVE as f32).round() as usize;
+    for i in 0..=steps {
+        let freq = low * (high / low).powf(i as f32 / steps as f32);
+        output.push_str(&format!(
+            "    <Point Frequency=\"{:.1}\" Gain=\"{:.2}\"/>\n",
+            freq,
+            curve.gain_at(freq)
+        ));
+    }
+    output.push_str("  </Curve>\n");
+    output.push_str("</MPreset>\n");
+
+    Ok(output)
+}
+
+fn preset_header(plugin: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<MPreset Plugin=\"{}\" Name=\"EQ Matcher correction\">\n",
+        plugin
+    )
+}
+
+fn shape(filter_type: FilterType) -> &'static str {
+    match filter_type {
+        FilterType::Peaking => "Peak",
+        FilterType::LowShelf => "LowShelf",
+        FilterType::HighShelf => "HighShelf",
+        FilterType::HighPass => "HighPass",
+        FilterType::LowPass => "LowPass",
+    }
+}
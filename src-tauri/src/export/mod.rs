@@ -0,0 +1,158 @@
+pub mod apo;
+pub mod bundle;
+pub mod camilla;
+pub mod card;
+pub mod csv;
+pub mod devices;
+pub mod fir;
+mod fit;
+pub(crate) mod image;
+pub mod melda;
+pub mod notes;
+pub mod nova;
+pub mod reaper;
+pub mod spectrogram;
+pub mod template;
+pub mod text;
+
+use crate::audio::matcher::MatchResult;
+use crate::audio::profile::EQProfile;
+use crate::audio::sections::SectionMatch;
+use serde::{Deserialize, Serialize};
+use template::{find_template, render_template, templates};
+use thiserror::Error;
+
+/// Failure of an export that writes files: `Invalid` before anything was
+/// written (unknown format, failing template, bad filter settings), `Write`
+/// when the files themselves couldn't be written.
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("{0}")]
+    Invalid(String),
+
+    #[error("{0}")]
+    Write(String),
+}
+
+// Where a result came from, for formats that describe the match
+#[derive(Debug, Clone, Default)]
+pub struct ExportContext {
+    pub reference_path: Option<String>,
+    pub input_path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportFormat {
+    pub id: String,
+    pub name: String,
+    pub extension: String,
+    pub builtin: bool, // False for user templates
+}
+
+const BUILTIN_FORMATS: [(&str, &str, &str); 12] = [
+    ("reaper", "Reaper FX chain", "RfxChain"),
+    (
+        "reaper_compensated",
+        "Reaper FX chain, loudness compensated",
+        "RfxChain",
+    ),
+    ("reaper_track", "Reaper track template", "RTrackTemplate"),
+    ("nova", "TDR Nova preset", "xml"),
+    ("mequalizer", "MeldaProduction MEqualizer preset", "mpreset"),
+    (
+        "mautoequalizer",
+        "MeldaProduction MAutoEqualizer curve",
+        "mpreset",
+    ),
+    ("json", "JSON", "json"),
+    ("txt", "Text", "txt"),
+    ("apo", "Equalizer APO", "txt"),
+    ("camilladsp", "CamillaDSP config", "yml"),
+    ("csv", "CSV", "csv"),
+    ("report", "Match report", "txt"),
+];
+
+// Built-in formats first, then loaded templates by id
+pub fn list_export_formats() -> Vec<ExportFormat> {
+    let builtin = BUILTIN_FORMATS
+        .iter()
+        .map(|&(id, name, extension)| ExportFormat {
+            id: id.to_string(),
+            name: name.to_string(),
+            extension: extension.to_string(),
+            builtin: true,
+        });
+    let user = templates().into_iter().map(|t| ExportFormat {
+        name: t.id.clone(),
+        id: t.id,
+        extension: t.extension,
+        builtin: false,
+    });
+    builtin.chain(user).collect()
+}
+
+pub fn export_profile(profile: &EQProfile, format: &str) -> Result<String, String> {
+    match format {
+        "reaper" => reaper::export_as_reaper_preset(profile),
+        // Without a result there is no level change to compensate
+        "reaper_compensated" => reaper::export_as_reaper_preset(profile),
+        "reaper_track" => Err("A track template needs a match result".to_string()),
+        "nova" => nova::export_as_nova(profile),
+        "mequalizer" => melda::export_as_mequalizer(profile),
+        "mautoequalizer" => melda::export_as_mautoequalizer(profile),
+        "json" => serde_json::to_string_pretty(profile).map_err(|e| e.to_string()),
+        "txt" => text::export_as_text(profile),
+        "apo" => apo::export_as_apo(profile),
+        "camilladsp" => camilla::export_as_camilladsp(profile),
+        "csv" => csv::export_as_csv(profile),
+        "report" => text::export_as_text(profile), // Without a result there is nothing else to report
+        _ => match find_template(format) {
+            Some(template) => render_template(&template.source, profile),
+            None => Err("Unknown format".to_string()),
+        },
+    }
+}
+
+// Formats that support channel-split EQ use the per-channel corrections when present
+pub fn export_result(result: &MatchResult, format: &str) -> Result<String, String> {
+    export_result_with_context(result, format, &ExportContext::default())
+}
+
+pub fn export_result_with_context(
+    result: &MatchResult,
+    format: &str,
+    context: &ExportContext,
+) -> Result<String, String> {
+    match (format, &result.channel_corrections) {
+        ("apo", Some(channels)) => apo::export_channels_as_apo(channels),
+        ("camilladsp", Some(channels)) => camilla::export_channels_as_camilladsp(channels),
+        ("txt", _) => text::export_result_as_text(result),
+        ("report", _) => text::export_report(result),
+        ("reaper_track", _) => reaper::export_as_track_template(result, context),
+        ("nova", _) => nova::export_result_as_nova(result),
+        ("reaper_compensated", _) => reaper::export_as_compensated_reaper(
+            &result.correction_profile,
+            -result.correction_loudness_delta_db,
+        ),
+        _ => export_profile(&result.correction_profile, format),
+    }
+}
+
+// Per-section corrections need automation; only the Reaper chain carries it so far
+pub fn export_sections(sections: &SectionMatch, format: &str) -> Result<String, String> {
+    match format {
+        "reaper" => reaper::export_sections_as_reaper(&sections.sections),
+        "json" => serde_json::to_string_pretty(sections).map_err(|e| e.to_string()),
+        _ => Err(format!(
+            "Format {} can't carry per-section automation",
+            format
+        )),
+    }
+}
+
+pub fn file_extension(format: &str) -> Option<String> {
+    list_export_formats()
+        .into_iter()
+        .find(|f| f.id == format)
+        .map(|f| f.extension)
+}
@@ -0,0 +1,131 @@
+use super::ExportContext;
+use crate::audio::loader::load_audio_channels_cancellable;
+use crate::audio::matcher::MatchResult;
+use crate::audio::metering::{measure_loudness, LoudnessStats};
+use crate::dsp::filters::{band_params, ParametricEQ};
+use crate::jobs::CancelToken;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Loudness figures for mastering deliverable notes, written next to an
+/// export. Stats are None for files that weren't given or couldn't be read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MasteringNotes {
+    pub created_at: u64, // Unix seconds
+    pub reference_file: Option<String>,
+    pub input_file: Option<String>,
+    pub reference: Option<LoudnessStats>,
+    pub input: Option<LoudnessStats>,
+    pub predicted: Option<LoudnessStats>, // Input through the correction and output gain
+    pub output_gain_db: f32,
+    pub quality_score: f32,
+}
+
+/// Decodes the reference and input named in `context` and measures them,
+/// plus the input rendered through `result`'s correction. Slow: both files
+/// are decoded in full, so run it as a job; `cancel` is checked while
+/// decoding and between steps.
+pub fn mastering_notes(
+    result: &MatchResult,
+    context: &ExportContext,
+    cancel: &CancelToken,
+) -> Result<MasteringNotes, String> {
+    let load = |path: &Option<String>| {
+        path.as_ref()
+            .map(|p| {
+                load_audio_channels_cancellable(p, cancel).map_err(|e| format!("{}: {}", p, e))
+            })
+            .transpose()
+    };
+
+    let reference = load(&context.reference_path)?
+        .map(|audio| measure_loudness(&audio.channels, audio.sample_rate));
+    cancel.check()?;
+    let (input, predicted) = match load(&context.input_path)? {
+        Some(mut audio) => {
+            let input = measure_loudness(&audio.channels, audio.sample_rate);
+            cancel.check()?;
+            let params = band_params(&result.correction_profile.bands);
+            ParametricEQ::with_channels(audio.sample_rate as f32, audio.channels.len(), &params)
+                .process_planar(&mut audio.channels);
+            let gain = 10f32.powf(result.output_gain_db / 20.0);
+            audio.channels.iter_mut().flatten().for_each(|s| *s *= gain);
+            let predicted = measure_loudness(&audio.channels, audio.sample_rate);
+            (Some(input), Some(predicted))
+        }
+        None => (None, None),
+    };
+
+    Ok(MasteringNotes {
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        reference_file: file_name(&context.reference_path),
+        input_file: file_name(&context.input_path),
+        reference,
+        input,
+        predicted,
+        output_gain_db: result.output_gain_db,
+        quality_score: result.quality_score,
+    })
+}
+
+// Plain table for a delivery sheet
+pub fn mastering_notes_as_text(notes: &MasteringNotes) -> String {
+    let mut output = String::from("Mastering Notes\n\n");
+    if let Some(reference) = &notes.reference_file {
+        output.push_str(&format!("Reference: {}\n", reference));
+    }
+    if let Some(input) = &notes.input_file {
+        output.push_str(&format!("Input: {}\n", input));
+    }
+    output.push_str(&format!(
+        "Match quality: {:.0}%\n",
+        notes.quality_score * 100.0
+    ));
+    output.push_str(&format!("Output gain: {:+.1} dB\n\n", notes.output_gain_db));
+
+    output.push_str(&format!(
+        "{:<12} {:>12} {:>10} {:>12} {:>10}\n",
+        "", "Integrated", "LRA", "True peak", "Length"
+    ));
+    for (label, stats) in [
+        ("Reference", &notes.reference),
+        ("Input", &notes.input),
+        ("Predicted", &notes.predicted),
+    ] {
+        let Some(stats) = stats else {
+            continue;
+        };
+        let or_dash = |value: Option<f32>, unit: &str| {
+            value.map_or("-".to_string(), |v| format!("{:.1} {}", v, unit))
+        };
+        output.push_str(&format!(
+            "{:<12} {:>12} {:>10} {:>12} {:>10}\n",
+            label,
+            or_dash(stats.integrated_lufs, "LUFS"),
+            or_dash(stats.loudness_range_lu, "LU"),
+            format!("{:.1} dBTP", stats.true_peak_dbtp),
+            format_duration(stats.duration_secs)
+        ));
+    }
+    if notes.predicted.is_some() {
+        output.push_str(
+            "\nPredicted: input through the correction and output gain, before limiting.\n",
+        );
+    }
+    output
+}
+
+fn file_name(path: &Option<String>) -> Option<String> {
+    path.as_deref()
+        .and_then(|p| Path::new(p).file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+}
+
+// m:ss
+fn format_duration(secs: f32) -> String {
+    let secs = secs.max(0.0).round() as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,162 @@
+use super::apo::preamp_db;
+use super::fit::{fit_bands, FIT_MIN_GAIN_DB};
+use crate::audio::matcher::MatchResult;
+use crate::audio::profile::EQProfile;
+use crate::audio::resonance::NarrowBandKind;
+use crate::dsp::dynamic::{dynamic_bands_from_suggestion, DynamicBand};
+use crate::dsp::filters::FilterType;
+
+const NOVA_BANDS: usize = 4;
+const MAX_DYNAMIC_BANDS: usize = 2; // The rest stay for the tonal fit
+const DEESS_THRESHOLD_DB: f32 = -30.0;
+const DEESS_RATIO: f32 = 4.0;
+
+// One Nova band: static gain plus optional dynamics
+struct NovaBand {
+    kind: &'static str,
+    frequency: f32,
+    gain_db: f32,
+    q: f32,
+    dynamics: Option<DynamicBand>,
+}
+
+/// TDR Nova preset with the correction fitted to Nova's four bands.
+pub fn export_as_nova(profile: &EQProfile) -> Result<String, String> {
+    let bands = fit_static_bands(profile, NOVA_BANDS);
+    Ok(nova_xml(&bands, None, preamp_db(&[profile])))
+}
+
+/// As `export_as_nova`, with the match's dynamic suggestions (multiband
+/// compression and de-essing) on dynamic bands and the suggested high-pass.
+/// Dynamic bands take up to two slots; the tonal fit gets the rest.
+pub fn export_result_as_nova(result: &MatchResult) -> Result<String, String> {
+    let mut dynamic: Vec<DynamicBand> = result
+        .compression_suggestion
+        .as_ref()
+        .map(dynamic_bands_from_suggestion)
+        .unwrap_or_default();
+    dynamic.extend(
+        result
+            .narrow_band_suggestions
+            .iter()
+            .filter(|s| s.kind == NarrowBandKind::Sibilance)
+            .map(|s| DynamicBand {
+                frequency: s.frequency,
+                q: s.q,
+                filter_type: FilterType::Peaking,
+                static_gain_db: 0.0,
+                threshold_db: DEESS_THRESHOLD_DB,
+                ratio: DEESS_RATIO,
+                range_db: s.gain_db,
+                attack_ms: 1.0,
+                release_ms: 60.0,
+            }),
+    );
+    dynamic.retain(|d| d.range_db.abs() >= FIT_MIN_GAIN_DB);
+    dynamic.sort_by(|a, b| b.range_db.abs().total_cmp(&a.range_db.abs()));
+    dynamic.truncate(MAX_DYNAMIC_BANDS);
+
+    let profile = &result.correction_profile;
+    let mut bands = fit_static_bands(profile, NOVA_BANDS - dynamic.len());
+    bands.extend(dynamic.into_iter().map(|d| NovaBand {
+        kind: "Bell",
+        frequency: d.frequency,
+        gain_db: d.static_gain_db,
+        q: d.q,
+        dynamics: Some(d),
+    }));
+    bands.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+
+    Ok(nova_xml(
+        &bands,
+        result.suggested_high_pass_hz,
+        preamp_db(&[profile]),
+    ))
+}
+
+fn fit_static_bands(profile: &EQProfile, slots: usize) -> Vec<NovaBand> {
+    fit_bands(profile, slots)
+        .into_iter()
+        .map(|b| NovaBand {
+            kind: match b.filter_type {
+                FilterType::LowShelf => "Low Shelf",
+                FilterType::HighShelf => "High Shelf",
+                _ => "Bell",
+            },
+            frequency: b.frequency,
+            gain_db: b.gain_db,
+            q: b.q(),
+            dynamics: None,
+        })
+        .collect()
+}
+
+// Nova's preset XML: one element, parameters as attributes named like the
+// plugin's own (`band<Param>_<n>`, `<param>_master`)
+fn nova_xml(bands: &[NovaBand], high_pass_hz: Option<f32>, output_gain_db: f32) -> String {
+    let mut attributes = vec![
+        ("bypass_master".to_string(), "Off".to_string()),
+        (
+            "outputGain_master".to_string(),
+            format!("{:.2}", output_gain_db),
+        ),
+        (
+            "hpActive_master".to_string(),
+            on_off(high_pass_hz.is_some()).to_string(),
+        ),
+        (
+            "hpFreq_master".to_string(),
+            format!("{:.1}", high_pass_hz.unwrap_or(20.0)),
+        ),
+        ("lpActive_master".to_string(), "Off".to_string()),
+    ];
+    for n in 1..=NOVA_BANDS {
+        let band = bands.get(n - 1);
+        let dynamics = band.and_then(|b| b.dynamics.as_ref());
+        let mut set = |param: &str, value: String| {
+            attributes.push((format!("band{}_{}", param, n), value));
+        };
+        set("Active", on_off(band.is_some()).to_string());
+        set("Type", band.map_or("Bell", |b| b.kind).to_string());
+        set(
+            "Freq",
+            format!("{:.1}", band.map_or(1000.0, |b| b.frequency)),
+        );
+        set("Gain", format!("{:.2}", band.map_or(0.0, |b| b.gain_db)));
+        set("Q", format!("{:.2}", band.map_or(0.7, |b| b.q)));
+        set("DynActive", on_off(dynamics.is_some()).to_string());
+        set(
+            "Threshold",
+            format!("{:.1}", dynamics.map_or(0.0, |d| d.threshold_db)),
+        );
+        set("Ratio", format!("{:.2}", dynamics.map_or(1.0, |d| d.ratio)));
+        // Nova's dynamic range is the gain reached at full compression
+        set(
+            "DynRange",
+            format!("{:.2}", dynamics.map_or(0.0, |d| d.range_db)),
+        );
+        set(
+            "Attack",
+            format!("{:.1}", dynamics.map_or(10.0, |d| d.attack_ms)),
+        );
+        set(
+            "Release",
+            format!("{:.1}", dynamics.map_or(150.0, |d| d.release_ms)),
+        );
+    }
+
+    let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<TDRNova");
+    for (name, value) in attributes {
+        output.push_str(&format!("\n  {}=\"{}\"", name, value));
+    }
+    output.push_str("\n/>\n");
+    output
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "On"
+    } else {
+        "Off"
+    }
+}
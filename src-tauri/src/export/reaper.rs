@@ -0,0 +1,315 @@
+use super::ExportContext;
+use crate::audio::matcher::MatchResult;
+use crate::audio::profile::{EQProfile, FrequencyBand};
+use crate::audio::sections::SectionCorrection;
+use crate::dsp::filters::FilterType;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const AUTOMATION_RAMP_SECS: f32 = 0.05; // Gain glide into each section, avoids clicks
+
+// VST2 state as REAPER stores it: a header naming the plugin and its pins,
+// the plugin's own chunk, then the program name
+const REAEQ_ID: u32 = 1919247729; // 'reeq'
+const REAPER_VST_MAGIC: u32 = 0xFEED5EEE;
+const BASE64_BLOCK: usize = 210; // Bytes per line, as REAPER wraps them
+
+// ReaEQ band types inside its chunk
+const REAEQ_LOW_SHELF: i32 = 0;
+const REAEQ_HIGH_SHELF: i32 = 1;
+const REAEQ_LOW_PASS: i32 = 3;
+const REAEQ_HIGH_PASS: i32 = 4;
+const REAEQ_BAND: i32 = 8;
+const REAEQ_PARAMS_PER_BAND: usize = 3; // Frequency, gain, bandwidth
+
+pub fn export_as_reaper_preset(profile: &EQProfile) -> Result<String, String> {
+    let mut output = String::from("<FXCHAIN\n");
+    output.push_str(&chain_header());
+    push_reaeq(&mut output, profile);
+    push_fx_footer(&mut output, "");
+    output.push_str(">\n");
+
+    Ok(output)
+}
+
+/// The ReaEQ chain followed by a JS volume plugin undoing the correction's
+/// level change, so bypassing the chain compares at equal loudness.
+pub fn export_as_compensated_reaper(
+    profile: &EQProfile,
+    compensation_db: f32,
+) -> Result<String, String> {
+    let mut output = String::from("<FXCHAIN\n");
+    output.push_str(&chain_header());
+    push_reaeq(&mut output, profile);
+    push_fx_footer(&mut output, "");
+    push_volume(&mut output, compensation_db);
+    push_fx_footer(&mut output, "");
+    output.push_str(">\n");
+
+    Ok(output)
+}
+
+/// A track template (.RTrackTemplate) holding the ReaEQ chain on a track
+/// named after the input, with an empty item whose notes describe the match.
+pub fn export_as_track_template(
+    result: &MatchResult,
+    context: &ExportContext,
+) -> Result<String, String> {
+    let file_name = |path: &Option<String>| {
+        path.as_deref()
+            .and_then(|p| std::path::Path::new(p).file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+    };
+    let name = file_name(&context.input_path)
+        .map(|input| format!("{} (EQ match)", input))
+        .unwrap_or_else(|| "EQ match".to_string());
+    let created_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+
+    let mut notes = vec![
+        format!(
+            "Reference: {}",
+            file_name(&context.reference_path).unwrap_or_else(|| "unknown".to_string())
+        ),
+        format!("Matched: {} UTC", format_date(created_at)),
+        format!("Quality score: {:.0}%", result.quality_score * 100.0),
+        format!("Output gain: {:+.1} dB", result.output_gain_db),
+    ];
+    notes.extend(
+        result
+            .warnings
+            .iter()
+            .map(|w| format!("Warning: {}", w.message)),
+    );
+
+    let mut chain = String::from("<FXCHAIN\n");
+    chain.push_str(&chain_header());
+    push_reaeq(&mut chain, &result.correction_profile);
+    push_fx_footer(&mut chain, "");
+    chain.push_str(">\n");
+
+    let mut output = format!("<TRACK {}\n", guid());
+    output.push_str(&format!("  NAME {}\n", quoted(&name)));
+    output.push_str("  VOLPAN 1 0 -1 -1 1\n");
+    output.push_str("  MUTESOLO 0 0 0\n");
+    output.push_str("  NCHAN 2\n");
+    output.push_str("  FX 1\n");
+    output.push_str(&format!("  TRACKID {}\n", guid()));
+    output.push_str("  MAINSEND 1 0\n");
+    for line in chain.lines() {
+        output.push_str(&format!("  {}\n", line));
+    }
+    output.push_str("  <ITEM\n");
+    output.push_str("    POSITION 0\n");
+    output.push_str("    LENGTH 4\n");
+    output.push_str(&format!("    IGUID {}\n", guid()));
+    output.push_str("    NAME \"EQ match notes\"\n");
+    output.push_str("    <NOTES\n");
+    for line in notes {
+        output.push_str(&format!("      |{}\n", line));
+    }
+    output.push_str("    >\n");
+    output.push_str("  >\n");
+    output.push_str(">\n");
+
+    Ok(output)
+}
+
+/// ReaEQ chain set to the first section's correction, with an envelope per
+/// band gain stepping through the rest at their start times (seconds from
+/// the item start). Saves as an .RfxChain like the static preset.
+pub fn export_sections_as_reaper(sections: &[SectionCorrection]) -> Result<String, String> {
+    let first = sections.first().ok_or("No sections to export")?;
+    let mut output = String::from("<FXCHAIN\n");
+    output.push_str(&chain_header());
+    push_reaeq(&mut output, &first.correction);
+
+    let mut envelopes = String::new();
+    for i in 0..first.correction.bands.len() {
+        let default = gain_norm(first.correction.bands[i].gain_db);
+        envelopes.push_str(&format!(
+            "<PARMENV {} 0 1 {}\n",
+            i * REAEQ_PARAMS_PER_BAND + 1,
+            default
+        ));
+        envelopes.push_str("ACT 1 -1\n");
+        envelopes.push_str("VIS 1 1 1\n");
+        envelopes.push_str("ARM 0\n");
+        envelopes.push_str("DEFSHAPE 0 -1 -1\n");
+
+        let mut previous: Option<f32> = None;
+        for section in sections {
+            let Some(band) = section.correction.bands.get(i) else {
+                return Err("Sections were matched on different band grids".to_string());
+            };
+            let value = gain_norm(band.gain_db);
+            if let Some(previous) = previous {
+                let ramp_start = (section.start_secs - AUTOMATION_RAMP_SECS).max(0.0);
+                envelopes.push_str(&format!("PT {:.3} {} 0\n", ramp_start, previous));
+            }
+            envelopes.push_str(&format!("PT {:.3} {} 0\n", section.start_secs, value));
+            previous = Some(value);
+        }
+        envelopes.push_str(">\n");
+    }
+    push_fx_footer(&mut output, &envelopes);
+    output.push_str(">\n");
+
+    Ok(output)
+}
+
+fn chain_header() -> String {
+    let mut output = String::from("WNDRECT 0 0 0 0\n");
+    output.push_str("SHOW 0\n");
+    output.push_str("LASTSEL 0\n");
+    output.push_str("DOCKED 0\n");
+    output
+}
+
+// Every band goes in; ReaEQ takes any number of them
+fn push_reaeq(output: &mut String, profile: &EQProfile) {
+    let chunk = reaeq_chunk(&profile.bands);
+
+    let mut header = Vec::new();
+    push_u32(&mut header, REAEQ_ID);
+    push_u32(&mut header, REAPER_VST_MAGIC);
+    // Stereo in and out, one pin per channel
+    for _ in 0..2 {
+        push_u32(&mut header, 2);
+        header.extend_from_slice(&1u64.to_le_bytes());
+        header.extend_from_slice(&2u64.to_le_bytes());
+    }
+    push_u32(&mut header, chunk.len() as u32);
+    push_u32(&mut header, 1);
+    push_u32(&mut header, 0x0010_0000);
+
+    output.push_str("BYPASS 0 0 0\n");
+    output.push_str(
+        "<VST \"VST: ReaEQ (Cockos)\" ReaEQ.vst.dylib 0 \"\" \
+         1919247729<56535472656571726561657100000000> \"\"\n",
+    );
+    output.push_str(&format!("  {}\n", BASE64.encode(&header)));
+    for block in chunk.chunks(BASE64_BLOCK) {
+        output.push_str(&format!("  {}\n", BASE64.encode(block)));
+    }
+    // Empty program name
+    output.push_str(&format!("  {}\n", BASE64.encode([0, 0, 0x10, 0, 0, 0])));
+    output.push_str(">\n");
+}
+
+// Band count, then per band: type, enabled, frequency (Hz), gain (linear),
+// bandwidth (octaves) and a flag byte; then tab visibility and output gain
+fn reaeq_chunk(bands: &[FrequencyBand]) -> Vec<u8> {
+    let mut chunk = Vec::new();
+    push_u32(&mut chunk, bands.len() as u32);
+    for band in bands {
+        let (kind, gain) = match band.filter_type {
+            FilterType::Peaking => (REAEQ_BAND, band.gain_db),
+            FilterType::LowShelf => (REAEQ_LOW_SHELF, band.gain_db),
+            FilterType::HighShelf => (REAEQ_HIGH_SHELF, band.gain_db),
+            FilterType::HighPass => (REAEQ_HIGH_PASS, 0.0),
+            FilterType::LowPass => (REAEQ_LOW_PASS, 0.0),
+        };
+        chunk.extend_from_slice(&kind.to_le_bytes());
+        push_u32(&mut chunk, 1);
+        push_f64(&mut chunk, band.frequency as f64);
+        push_f64(&mut chunk, 10f64.powf(gain as f64 / 20.0));
+        push_f64(&mut chunk, octaves(band.q()));
+        chunk.push(1);
+    }
+    push_u32(&mut chunk, 1);
+    push_f64(&mut chunk, 1.0);
+    chunk
+}
+
+// JS: utility/volume, first slider is the adjustment in dB
+fn push_volume(output: &mut String, gain_db: f32) {
+    output.push_str("BYPASS 0 0 0\n");
+    output.push_str("<JS utility/volume \"\"\n");
+    output.push_str(&format!("{:.2}{}\n", gain_db, " -".repeat(63)));
+    output.push_str(">\n");
+}
+
+// Envelopes belong to the FX, between its id and WAK
+fn push_fx_footer(output: &mut String, envelopes: &str) {
+    output.push_str("FLOATPOS 0 0 0 0\n");
+    output.push_str(&format!("FXID {}\n", guid()));
+    output.push_str(envelopes);
+    output.push_str("WAK 0 0\n");
+}
+
+// Random (version 4) GUID in REAPER's braced form
+fn guid() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    let random = |salt: u64| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(nanos ^ salt);
+        hasher.finish()
+    };
+    let (high, low) = (random(0), random(u64::MAX));
+    let high = (high & !0xF000) | 0x4000;
+    let low = (low & 0x3FFF_FFFF_FFFF_FFFF) | 0x8000_0000_0000_0000;
+    format!(
+        "{{{:08X}-{:04X}-{:04X}-{:04X}-{:012X}}}",
+        high >> 32,
+        (high >> 16) & 0xFFFF,
+        high & 0xFFFF,
+        low >> 48,
+        low & 0xFFFF_FFFF_FFFF
+    )
+}
+
+// REAPER quotes with whichever of " ' ` the text doesn't contain
+fn quoted(text: &str) -> String {
+    match ['"', '\'', '`'].into_iter().find(|q| !text.contains(*q)) {
+        Some(q) => format!("{}{}{}", q, text, q),
+        None => format!("\"{}\"", text.replace('"', "'")),
+    }
+}
+
+// Unix seconds as YYYY-MM-DD HH:MM
+fn format_date(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let minutes = secs % 86_400 / 60;
+    // Days to civil date, proleptic Gregorian
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+fn octaves(q: f32) -> f64 {
+    let q = (q as f64).max(0.01);
+    2.0 / std::f64::consts::LN_2 * (1.0 / (2.0 * q)).asinh()
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_f64(bytes: &mut Vec<u8>, value: f64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn gain_norm(gain_db: f32) -> f32 {
+    (gain_db + 18.0) / 36.0
+}
@@ -0,0 +1,201 @@
+use super::image::{text_width, Canvas, Rgb};
+use crate::audio::spectrogram::Spectrogram;
+use serde::{Deserialize, Serialize};
+
+const LEFT: i64 = 84; // Frequency labels
+const RIGHT: i64 = 120; // Color bar and its labels
+const TOP: i64 = 24;
+const BOTTOM: i64 = 56; // Time labels
+const MIN_SIZE: (usize, usize) = (400, 200);
+const MAX_SIZE: (usize, usize) = (8000, 4000);
+const FREQ_LABELS: [(f32, &str); 10] = [
+    (20.0, "20"),
+    (50.0, "50"),
+    (100.0, "100"),
+    (200.0, "200"),
+    (500.0, "500"),
+    (1_000.0, "1k"),
+    (2_000.0, "2k"),
+    (5_000.0, "5k"),
+    (10_000.0, "10k"),
+    (20_000.0, "20k"),
+];
+const TIME_STEPS_SECS: [f32; 10] = [0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+const BACKGROUND: Rgb = [18, 20, 25];
+const TEXT: Rgb = [200, 205, 214];
+const TICK: Rgb = [96, 102, 114];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Colormap {
+    #[default]
+    Magma,
+    Viridis,
+    Grayscale,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectrogramImageOptions {
+    pub width: usize,
+    pub height: usize,
+    pub colormap: Colormap,
+    pub floor_db: f32,   // Drawn as the bottom of the colormap
+    pub ceiling_db: f32, // And the top
+}
+
+impl Default for SpectrogramImageOptions {
+    fn default() -> Self {
+        Self {
+            width: 1200,
+            height: 600,
+            colormap: Colormap::Magma,
+            floor_db: -100.0,
+            ceiling_db: 0.0,
+        }
+    }
+}
+
+/// PNG of `spectrogram` with a log frequency axis, a time axis and a dB
+/// color bar.
+pub fn render_spectrogram_png(
+    spectrogram: &Spectrogram,
+    options: &SpectrogramImageOptions,
+) -> Result<Vec<u8>, String> {
+    if !(MIN_SIZE.0..=MAX_SIZE.0).contains(&options.width)
+        || !(MIN_SIZE.1..=MAX_SIZE.1).contains(&options.height)
+    {
+        return Err(format!(
+            "Image size must be between {}x{} and {}x{}, got {}x{}",
+            MIN_SIZE.0, MIN_SIZE.1, MAX_SIZE.0, MAX_SIZE.1, options.width, options.height
+        ));
+    }
+    if !(options.floor_db.is_finite()
+        && options.ceiling_db.is_finite()
+        && options.floor_db < options.ceiling_db)
+    {
+        return Err("The dB floor must be below the ceiling".to_string());
+    }
+    let (columns, rows) = (
+        spectrogram.magnitudes_db.len(),
+        spectrogram.frequencies.len(),
+    );
+    if columns == 0 || rows < 2 {
+        return Err("The spectrogram is empty".to_string());
+    }
+
+    let mut canvas = Canvas::new(options.width, options.height, BACKGROUND);
+    let (right, bottom) = (options.width as i64 - RIGHT, options.height as i64 - BOTTOM);
+    let (plot_width, plot_height) = (right - LEFT, bottom - TOP);
+    let level = |db: f32| {
+        ((db - options.floor_db) / (options.ceiling_db - options.floor_db)).clamp(0.0, 1.0)
+    };
+
+    // Nearest frame and row per pixel; rows are already log-spaced
+    for x in 0..plot_width {
+        let column = ((x as f32 + 0.5) / plot_width as f32 * columns as f32) as usize;
+        let magnitudes = &spectrogram.magnitudes_db[column.min(columns - 1)];
+        for y in 0..plot_height {
+            let t = 1.0 - (y as f32 + 0.5) / plot_height as f32;
+            let row = ((t * (rows - 1) as f32).round() as usize).min(rows - 1);
+            let color = options.colormap.color(level(magnitudes[row]));
+            canvas.set(LEFT + x, TOP + y, color);
+        }
+    }
+
+    let (low, high) = (
+        spectrogram.frequencies[0].ln(),
+        spectrogram.frequencies[rows - 1].ln(),
+    );
+    for (freq, label) in FREQ_LABELS {
+        if freq.ln() < low - 1e-3 || freq.ln() > high + 1e-3 {
+            continue;
+        }
+        let y = bottom - ((freq.ln() - low) / (high - low) * plot_height as f32).round() as i64;
+        canvas.fill_rect(LEFT - 6, y, 6, 1, TICK);
+        canvas.text(LEFT - 12 - text_width(label, 2), y - 7, label, 2, TEXT);
+    }
+    canvas.text(LEFT - 12 - text_width("Hz", 2), bottom + 14, "Hz", 2, TEXT);
+
+    // About one time label per 150 px
+    let duration = spectrogram.duration_secs.max(1e-3);
+    let wanted = duration / (plot_width as f32 / 150.0).max(1.0);
+    let step = TIME_STEPS_SECS
+        .iter()
+        .copied()
+        .find(|&s| s >= wanted)
+        .unwrap_or(600.0);
+    for index in 0.. {
+        let time = index as f32 * step;
+        if time > duration + 1e-3 {
+            break;
+        }
+        let x = LEFT + (time / duration * plot_width as f32).round() as i64;
+        canvas.fill_rect(x, bottom, 1, 6, TICK);
+        let label = format_time(time, step);
+        let width = text_width(&label, 2);
+        let x = (x - width / 2).clamp(LEFT, right - width);
+        canvas.text(x, bottom + 14, &label, 2, TEXT);
+    }
+
+    // Color bar with the floor and ceiling
+    let (bar_left, bar_width) = (right + 20, 16);
+    for y in 0..plot_height {
+        let t = 1.0 - y as f32 / (plot_height - 1).max(1) as f32;
+        canvas.fill_rect(bar_left, TOP + y, bar_width, 1, options.colormap.color(t));
+    }
+    let label_x = bar_left + bar_width + 8;
+    canvas.text(label_x, TOP, &format!("{:.0}", options.ceiling_db), 2, TEXT);
+    canvas.text(
+        label_x,
+        bottom - 14,
+        &format!("{:.0}", options.floor_db),
+        2,
+        TEXT,
+    );
+    canvas.text(bar_left, bottom + 14, "dBFS", 2, TEXT);
+
+    canvas.encode_png()
+}
+
+impl Colormap {
+    // `t` in 0.0 - 1.0, quiet to loud
+    fn color(self, t: f32) -> Rgb {
+        let stops: &[Rgb] = match self {
+            Colormap::Magma => &[
+                [0, 0, 4],
+                [59, 15, 112],
+                [140, 41, 129],
+                [222, 73, 104],
+                [254, 159, 109],
+                [252, 253, 191],
+            ],
+            Colormap::Viridis => &[
+                [68, 1, 84],
+                [65, 68, 135],
+                [42, 120, 142],
+                [34, 168, 132],
+                [122, 209, 81],
+                [253, 231, 37],
+            ],
+            Colormap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        };
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f32;
+        let (a, b) = (stops[index], stops[index + 1]);
+        [0, 1, 2].map(|i| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * fraction).round() as u8)
+    }
+}
+
+// m:ss, with tenths when the labels are closer than a second
+fn format_time(secs: f32, step: f32) -> String {
+    if step < 1.0 {
+        let tenths = (secs * 10.0).round() as u64;
+        format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+    } else {
+        let secs = secs.round() as u64;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
@@ -0,0 +1,147 @@
+use super::apo::preamp_db;
+use crate::audio::profile::{EQProfile, FrequencyBand};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::RwLock;
+
+pub const TEMPLATE_DIR: &str = "export_templates";
+const TEMPLATE_SUFFIX: &str = ".tmpl";
+
+const BANDS_START: &str = "{{#bands}}";
+const BANDS_END: &str = "{{/bands}}";
+
+/// User export format loaded from `<id>.<extension>.tmpl`.
+///
+/// Templates are plain text with `{{name}}` placeholders; `{{name:2}}`
+/// fixes the decimals. One `{{#bands}} ... {{/bands}}` block repeats per band.
+/// Outside the loop: `preamp_db`, `band_count`. Inside it: `index`
+/// (from 1), `frequency`, `gain_db`, `q`, `bandwidth`, `confidence`,
+/// `filter_type`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportTemplate {
+    pub id: String,
+    pub extension: String,
+    pub source: String,
+}
+
+// Loaded from the plugins folder at startup and on reload
+static TEMPLATES: RwLock<Vec<ExportTemplate>> = RwLock::new(Vec::new());
+
+/// Replaces the registered templates with those in `dir` and returns them.
+/// Unreadable files are skipped; a missing folder means no templates.
+pub fn load_templates(dir: &Path) -> Vec<ExportTemplate> {
+    let mut templates: Vec<ExportTemplate> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let (id, extension) = name.strip_suffix(TEMPLATE_SUFFIX)?.split_once('.')?;
+            let source = fs::read_to_string(entry.path()).ok()?;
+            Some(ExportTemplate {
+                id: id.to_string(),
+                extension: extension.to_string(),
+                source,
+            })
+        })
+        .collect();
+    templates.sort_by(|a, b| a.id.cmp(&b.id));
+
+    *TEMPLATES.write().unwrap() = templates.clone();
+    templates
+}
+
+pub fn templates() -> Vec<ExportTemplate> {
+    TEMPLATES.read().unwrap().clone()
+}
+
+pub(crate) fn find_template(id: &str) -> Option<ExportTemplate> {
+    TEMPLATES
+        .read()
+        .unwrap()
+        .iter()
+        .find(|t| t.id == id)
+        .cloned()
+}
+
+pub fn render_template(source: &str, profile: &EQProfile) -> Result<String, String> {
+    let global = |name: &str| match name {
+        "preamp_db" => Some(preamp_db(&[profile])),
+        "band_count" => Some(profile.bands.len() as f32),
+        _ => None,
+    };
+
+    let Some((before, rest)) = source.split_once(BANDS_START) else {
+        return substitute(source, &|name| global(name).map(Value::Number));
+    };
+    let (body, after) = rest
+        .split_once(BANDS_END)
+        .ok_or_else(|| format!("{} without {}", BANDS_START, BANDS_END))?;
+
+    let mut output = substitute(before, &|name| global(name).map(Value::Number))?;
+    for (i, band) in profile.bands.iter().enumerate() {
+        output.push_str(&substitute(body, &|name| {
+            band_value(band, i, name).or_else(|| global(name).map(Value::Number))
+        })?);
+    }
+    output.push_str(&substitute(after, &|name| global(name).map(Value::Number))?);
+    Ok(output)
+}
+
+enum Value {
+    Number(f32),
+    Text(String),
+}
+
+fn band_value(band: &FrequencyBand, index: usize, name: &str) -> Option<Value> {
+    let number = match name {
+        "index" => (index + 1) as f32,
+        "frequency" => band.frequency,
+        "gain_db" => band.gain_db,
+        "q" => band.q(),
+        "bandwidth" => band.bandwidth,
+        "confidence" => band.confidence,
+        "filter_type" => {
+            let kind = serde_json::to_value(band.filter_type).ok()?;
+            return Some(Value::Text(kind.as_str()?.to_string()));
+        }
+        _ => return None,
+    };
+    Some(Value::Number(number))
+}
+
+fn substitute(text: &str, lookup: &dyn Fn(&str) -> Option<Value>) -> Result<String, String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let end = rest[start..].find("}}").ok_or("Unclosed {{ in template")?;
+        let tag = rest[start + 2..start + end].trim();
+        let (name, decimals) = match tag.split_once(':') {
+            Some((name, decimals)) => (
+                name.trim(),
+                Some(
+                    decimals
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("Bad precision in {{{{{}}}}}", tag))?,
+                ),
+            ),
+            None => (tag, None),
+        };
+
+        match lookup(name).ok_or_else(|| format!("Unknown template field: {}", name))? {
+            Value::Number(n) => match decimals {
+                Some(d) => output.push_str(&format!("{:.*}", d, n)),
+                None => output.push_str(&n.to_string()),
+            },
+            Value::Text(s) => output.push_str(&s),
+        }
+        rest = &rest[start + end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
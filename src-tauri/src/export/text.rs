@@ -0,0 +1,95 @@
+use crate::audio::explain::explain_correction;
+use crate::audio::matcher::MatchResult;
+use crate::audio::profile::EQProfile;
+use crate::dsp::filters::FilterType;
+
+pub fn export_as_text(profile: &EQProfile) -> Result<String, String> {
+    let mut output = String::from("EQ Settings:\n\n");
+
+    for band in &profile.bands {
+        let label = match band.filter_type {
+            FilterType::Peaking => "",
+            FilterType::LowShelf => " low shelf",
+            FilterType::HighShelf => " high shelf",
+            FilterType::HighPass => " high-pass",
+            FilterType::LowPass => " low-pass",
+        };
+        output.push_str(&format!(
+            "{:>6} Hz: {:>+6.2} dB (Q: {:.2}){}\n",
+            band.frequency as i32,
+            band.gain_db,
+            band.q(),
+            label
+        ));
+    }
+
+    Ok(output)
+}
+
+// Settings followed by why each band got its gain
+pub fn export_result_as_text(result: &MatchResult) -> Result<String, String> {
+    let mut output = export_as_text(&result.correction_profile)?;
+    output.push_str("\nRationale:\n\n");
+    for explanation in explain_correction(result) {
+        output.push_str(&explanation.text);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+// Summary and warnings for a client or a session log, then the settings and rationale
+pub fn export_report(result: &MatchResult) -> Result<String, String> {
+    let mut output = String::from("EQ Match Report\n\n");
+    output.push_str(&format!(
+        "Quality score: {:.0}%\n",
+        result.quality_score * 100.0
+    ));
+    output.push_str(&format!(
+        "Residual mismatch: {:.1} dB RMS\n",
+        result.residual_rms_db
+    ));
+    for (label, source) in [
+        ("Reference", &result.reference_source),
+        ("Input", &result.input_source),
+    ] {
+        if let Some(source) = source {
+            output.push_str(&format!("{}: {}\n", label, source.describe()));
+        }
+    }
+    output.push_str(&format!("Output gain: {:+.1} dB\n", result.output_gain_db));
+    output.push_str(&format!(
+        "Correction loudness change: {:+.1} dB\n",
+        result.correction_loudness_delta_db
+    ));
+
+    // The few bands that move the level most
+    let mut contributions: Vec<(f32, f32)> = result
+        .correction_profile
+        .bands
+        .iter()
+        .zip(&result.band_loudness_contribution_db)
+        .map(|(band, &db)| (band.frequency, db))
+        .filter(|(_, db)| db.abs() >= 0.1)
+        .collect();
+    contributions.sort_by(|a, b| b.1.abs().total_cmp(&a.1.abs()));
+    if !contributions.is_empty() {
+        let listed: Vec<String> = contributions
+            .iter()
+            .take(3)
+            .map(|(freq, db)| format!("{} Hz ({:+.1} dB)", freq, db))
+            .collect();
+        output.push_str(&format!("Mostly from: {}\n", listed.join(", ")));
+    }
+
+    if !result.warnings.is_empty() {
+        output.push_str("\nWarnings:\n\n");
+        for warning in &result.warnings {
+            output.push_str(&format!("- {}\n", warning.message));
+        }
+    }
+
+    output.push('\n');
+    output.push_str(&export_result_as_text(result)?);
+    Ok(output)
+}
@@ -0,0 +1,149 @@
+use crate::audio::matcher::{refresh_derived, MatchConfig, MatchResult};
+use crate::audio::profile::EQProfile;
+use serde::{Deserialize, Serialize};
+
+const MAX_HISTORY: usize = 100;
+// Overrides address bands by frequency; this covers float round-trips
+const FREQUENCY_TOLERANCE: f32 = 0.01;
+
+/// Pins a correction band to a hand-picked gain, whatever the match says.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BandOverride {
+    pub frequency: f32,
+    pub gain_db: f32,
+}
+
+// One step in the history: the config and overrides that produce a result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchEdit {
+    pub config: MatchConfig,
+    #[serde(default)]
+    pub band_overrides: Vec<BandOverride>,
+}
+
+impl MatchEdit {
+    pub fn with_override(&self, frequency: f32, gain_db: Option<f32>) -> Self {
+        let mut band_overrides: Vec<BandOverride> = self
+            .band_overrides
+            .iter()
+            .filter(|o| !same_frequency(o.frequency, frequency))
+            .cloned()
+            .collect();
+        if let Some(gain_db) = gain_db {
+            band_overrides.push(BandOverride { frequency, gain_db });
+        }
+
+        Self {
+            config: self.config.clone(),
+            band_overrides,
+        }
+    }
+
+    pub fn same_as(&self, other: &MatchEdit) -> bool {
+        serde_json::to_value(self).ok() == serde_json::to_value(other).ok()
+    }
+}
+
+/// Linear undo/redo over match edits. Recording a new edit drops the redo
+/// branch, like any editor.
+#[derive(Debug, Default)]
+pub struct EditHistory {
+    undo: Vec<MatchEdit>,
+    redo: Vec<MatchEdit>,
+    current: Option<MatchEdit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryStatus {
+    pub can_undo: bool,
+    pub can_redo: bool,
+    pub current: Option<MatchEdit>,
+}
+
+impl EditHistory {
+    pub fn current(&self) -> Option<&MatchEdit> {
+        self.current.as_ref()
+    }
+
+    // Re-recording the current edit (e.g. recalculating) is not a new step
+    pub fn record(&mut self, edit: MatchEdit) {
+        if self.current.as_ref().is_some_and(|c| c.same_as(&edit)) {
+            return;
+        }
+        if let Some(previous) = self.current.replace(edit) {
+            self.undo.push(previous);
+            if self.undo.len() > MAX_HISTORY {
+                self.undo.remove(0);
+            }
+        }
+        self.redo.clear();
+    }
+
+    // The edits undo and redo would restore, without moving
+    pub fn next_undo(&self) -> Option<&MatchEdit> {
+        self.undo.last()
+    }
+
+    pub fn next_redo(&self) -> Option<&MatchEdit> {
+        self.redo.last()
+    }
+
+    pub fn undo(&mut self) -> Option<&MatchEdit> {
+        let previous = self.undo.pop()?;
+        if let Some(current) = self.current.replace(previous) {
+            self.redo.push(current);
+        }
+        self.current.as_ref()
+    }
+
+    pub fn redo(&mut self) -> Option<&MatchEdit> {
+        let next = self.redo.pop()?;
+        if let Some(current) = self.current.replace(next) {
+            self.undo.push(current);
+        }
+        self.current.as_ref()
+    }
+
+    pub fn status(&self) -> HistoryStatus {
+        HistoryStatus {
+            can_undo: !self.undo.is_empty(),
+            can_redo: !self.redo.is_empty(),
+            current: self.current.clone(),
+        }
+    }
+}
+
+// Applied to the main correction and to any per-channel corrections; the
+// residual, level match and quality are recomputed from the new gains
+pub fn apply_band_overrides(
+    result: &mut MatchResult,
+    overrides: &[BandOverride],
+    reference: &EQProfile,
+    input: &EQProfile,
+) {
+    if overrides.is_empty() {
+        return;
+    }
+
+    apply_to_profile(&mut result.correction_profile, overrides);
+    if let Some(channels) = result.channel_corrections.as_mut() {
+        apply_to_profile(&mut channels.first, overrides);
+        apply_to_profile(&mut channels.second, overrides);
+    }
+    refresh_derived(result, reference, input);
+}
+
+fn apply_to_profile(profile: &mut EQProfile, overrides: &[BandOverride]) {
+    for band in &mut profile.bands {
+        if let Some(o) = overrides
+            .iter()
+            .find(|o| same_frequency(o.frequency, band.frequency))
+        {
+            band.gain_db = o.gain_db;
+        }
+    }
+}
+
+fn same_frequency(a: f32, b: f32) -> bool {
+    (a - b).abs() <= FREQUENCY_TOLERANCE * a.abs().max(b.abs())
+}
@@ -0,0 +1,76 @@
+use crate::error::ErrorCode;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Locale {
+    #[default]
+    En,
+    Tr,
+}
+
+// Read whenever an error is built, so it lives outside AppState
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_locale(locale: Locale) {
+    CURRENT.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn current_locale() -> Locale {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Locale::Tr,
+        _ => Locale::En,
+    }
+}
+
+/// User-facing text for `code`. Messages say what to do next; specifics
+/// such as the path or decoder output go in `AppError::details`.
+pub fn message(code: ErrorCode, locale: Locale) -> &'static str {
+    match locale {
+        Locale::En => english(code),
+        Locale::Tr => turkish(code),
+    }
+}
+
+fn english(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::FileOpen => {
+            "The file could not be opened. Check that it exists and is readable."
+        }
+        ErrorCode::UnsupportedFormat => {
+            "This audio format is not supported. Try WAV, FLAC, MP3 or OGG."
+        }
+        ErrorCode::DecodeFailed => "The audio could not be decoded. The file may be damaged.",
+        ErrorCode::NoAudioData => "The file contains no audio.",
+        ErrorCode::NotLoaded => "Load the required audio before running this step.",
+        ErrorCode::NothingToUndo => "There is nothing to undo.",
+        ErrorCode::NothingToRedo => "There is nothing to redo.",
+        ErrorCode::InvalidArgument => "A value passed to this command is not valid.",
+        ErrorCode::Cancelled => "The operation was cancelled.",
+        ErrorCode::Stale => "The loaded audio changed while this was running. Run it again.",
+        ErrorCode::Io => "Reading or writing a file failed.",
+        ErrorCode::AudioOutput => "Audio playback is not available. Check the output device.",
+        ErrorCode::Internal => "Something went wrong.",
+    }
+}
+
+fn turkish(code: ErrorCode) -> &'static str {
+    match code {
+        ErrorCode::FileOpen => {
+            "Dosya açılamadı. Dosyanın var olduğunu ve okunabildiğini kontrol edin."
+        }
+        ErrorCode::UnsupportedFormat => "Format desteklenmiyor. WAV, FLAC, MP3 veya OGG deneyin.",
+        ErrorCode::DecodeFailed => "Ses çözülemedi. Dosya bozuk olabilir.",
+        ErrorCode::NoAudioData => "Ses verisi bulunamadı.",
+        ErrorCode::NotLoaded => "Bu adımdan önce gerekli sesi yükleyin.",
+        ErrorCode::NothingToUndo => "Geri alınacak bir şey yok.",
+        ErrorCode::NothingToRedo => "Yinelenecek bir şey yok.",
+        ErrorCode::InvalidArgument => "Bu komuta geçersiz bir değer verildi.",
+        ErrorCode::Cancelled => "İşlem iptal edildi.",
+        ErrorCode::Stale => "Bu işlem sürerken yüklü ses değişti. Yeniden çalıştırın.",
+        ErrorCode::Io => "Dosya okunurken veya yazılırken hata oluştu.",
+        ErrorCode::AudioOutput => "Ses çalınamıyor. Çıkış aygıtını kontrol edin.",
+        ErrorCode::Internal => "Bir şeyler ters gitti.",
+    }
+}
@@ -0,0 +1,130 @@
+use crate::automation::{constant_time_eq, generate_token};
+use crate::persist::{read_json, write_json};
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+pub const INSTANCE_FILE: &str = "instance.json";
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+// Thousands of paths; anything longer isn't a second launch
+const MAX_MESSAGE_BYTES: u64 = 1024 * 1024;
+const ACCEPTED: &str = "ok";
+
+// Where the running instance listens; the token keeps other local
+// programs from pushing files into it
+#[derive(Serialize, Deserialize)]
+struct InstanceInfo {
+    port: u16,
+    token: String,
+}
+
+/// Hands `paths` to the instance registered in `dir`. True when it took
+/// them and this launch can exit; false when there is none: no file, or one
+/// left by an instance that has quit.
+pub fn forward_to_running(dir: &Path, paths: &[PathBuf]) -> bool {
+    let Ok(info) = read_json::<InstanceInfo>(&dir.join(INSTANCE_FILE)) else {
+        return false;
+    };
+    match send(&info, paths) {
+        Ok(()) => true,
+        Err(e) => {
+            log::info!("no running instance on port {}: {}", info.port, e);
+            false
+        }
+    }
+}
+
+// One line with the token, one with the paths as JSON; "ok" comes back
+fn send(info: &InstanceInfo, paths: &[PathBuf]) -> io::Result<()> {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, info.port));
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let paths = serde_json::to_string(paths)?;
+    write!(stream, "{}\n{}\n", info.token, paths)?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply)?;
+    if reply.trim_end() == ACCEPTED {
+        Ok(())
+    } else {
+        // The port was reused by something else
+        Err(io::Error::other("unexpected reply"))
+    }
+}
+
+/// Registers this process in `dir` as the running instance and passes the
+/// files of later launches to `on_open`, on a background thread for the
+/// life of the process. Two launches racing each other can both end up
+/// running; the later one takes over the registration.
+pub fn listen(dir: &Path, on_open: impl Fn(Vec<PathBuf>) + Send + 'static) -> Result<(), String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let token = generate_token()?;
+    write_json(
+        &dir.join(INSTANCE_FILE),
+        &InstanceInfo {
+            port,
+            token: token.clone(),
+        },
+    )?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Some(paths) = receive(stream, &token) {
+                on_open(paths);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn receive(mut stream: TcpStream, token: &str) -> Option<Vec<PathBuf>> {
+    stream.set_read_timeout(Some(IO_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(IO_TIMEOUT)).ok()?;
+    let mut reader = BufReader::new(stream.try_clone().ok()?.take(MAX_MESSAGE_BYTES));
+
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    if !constant_time_eq(line.trim_end().as_bytes(), token.as_bytes()) {
+        return None;
+    }
+    line.clear();
+    reader.read_line(&mut line).ok()?;
+    let paths = serde_json::from_str(&line).ok()?;
+    stream
+        .write_all(format!("{}\n", ACCEPTED).as_bytes())
+        .ok()?;
+    Some(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::mpsc;
+
+    #[test]
+    fn later_launches_hand_their_files_over() {
+        let dir = std::env::temp_dir().join(format!("eq-matcher-instance-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        assert!(!forward_to_running(&dir, &[]));
+
+        let (sender, receiver) = mpsc::channel();
+        listen(&dir, move |paths| sender.send(paths).unwrap()).unwrap();
+        let paths = vec![PathBuf::from("/music/mix.wav"), PathBuf::from("a b.sfeq")];
+        assert!(forward_to_running(&dir, &paths));
+        assert_eq!(receiver.recv_timeout(IO_TIMEOUT).unwrap(), paths);
+
+        // A wrong token is turned away without reaching the handler
+        let mut info: InstanceInfo = read_json(&dir.join(INSTANCE_FILE)).unwrap();
+        info.token = "guess".to_string();
+        assert!(send(&info, &paths).is_err());
+        assert!(receiver.try_recv().is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
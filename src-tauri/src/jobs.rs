@@ -0,0 +1,366 @@
+use crate::error::{AppError, ErrorCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+pub type JobId = u64;
+
+// How long a finished keyed job answers identical requests
+pub const FINISHED_TTL: Duration = Duration::from_secs(60);
+
+/// Returned from a checkpoint once the job has been cancelled.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Cancelled")]
+pub struct Cancelled;
+
+// Lets `?` pass it through library code that reports errors as text
+impl From<Cancelled> for String {
+    fn from(error: Cancelled) -> Self {
+        error.to_string()
+    }
+}
+
+/// Shared flag checked by long-running work at its natural checkpoints
+/// (per file, per chunk, between stages); cancelling never interrupts a
+/// step half-way.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    // `?`-friendly checkpoint
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobEvent {
+    Started {
+        id: JobId,
+        job: String,
+    },
+    Progress {
+        id: JobId,
+        stage: String,
+        fraction: f32, // 0.0 - 1.0 within the whole job
+    },
+    Partial {
+        id: JobId,
+        fraction: f32,
+        data: serde_json::Value, // Intermediate result, e.g. a profile so far
+    },
+    Finished {
+        id: JobId,
+        result: serde_json::Value,
+    },
+    Failed {
+        id: JobId,
+        error: AppError,
+    },
+    Cancelled {
+        id: JobId,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: JobId,
+    pub job: String,
+    pub cancelling: bool,
+}
+
+pub type JobEmitter = Arc<dyn Fn(JobEvent) + Send + Sync>;
+
+/// Handle given to the work closure: its id, cancellation and progress.
+#[derive(Clone)]
+pub struct JobContext {
+    pub id: JobId,
+    pub cancel: CancelToken,
+    emit: JobEmitter,
+    key: Option<String>, // Set for deduplicated jobs
+}
+
+impl JobContext {
+    // Reports progress and doubles as a cancellation checkpoint
+    pub fn progress(&self, stage: &str, fraction: f32) -> Result<(), Cancelled> {
+        self.cancel.check()?;
+        (self.emit)(JobEvent::Progress {
+            id: self.id,
+            stage: stage.to_string(),
+            fraction: fraction.clamp(0.0, 1.0),
+        });
+        Ok(())
+    }
+
+    // Streams an intermediate result; unserializable data is dropped
+    pub fn partial<T: Serialize>(&self, fraction: f32, data: &T) {
+        if let Ok(data) = serde_json::to_value(data) {
+            (self.emit)(JobEvent::Partial {
+                id: self.id,
+                fraction: fraction.clamp(0.0, 1.0),
+                data,
+            });
+        }
+    }
+}
+
+/// How `JobManager::start_keyed` handled a request.
+pub enum JobStart {
+    New(JobContext),
+    Running(JobId), // Same request already in flight; follow this id
+    Cached(JobId),  // Answered from a recent run; already finished
+}
+
+struct FinishedJob {
+    result: serde_json::Value,
+    stamp: u64,
+    at: Instant,
+}
+
+#[derive(Default)]
+pub struct JobManager {
+    next_id: AtomicU64,
+    active: Mutex<HashMap<JobId, (String, CancelToken)>>,
+    keys: Mutex<HashMap<String, JobId>>, // Request key -> running job
+    finished: Mutex<HashMap<String, FinishedJob>>, // Request key -> recent result
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Registers the job and announces it; ids are never reused
+    pub fn start(&self, job: &str, emit: JobEmitter) -> JobContext {
+        self.register(job, None, emit)
+    }
+
+    /// Like `start`, but a request whose `key` matches a job still running
+    /// joins that job instead, so a retried command doesn't redo the work
+    /// or race the first run. A job that succeeded within `FINISHED_TTL`
+    /// answers too, as a new job that finishes at once with its result, as
+    /// long as `stamp` is still what `finish_keyed` was given: the caller's
+    /// state hasn't changed since, so the result is as good as a rerun.
+    pub fn start_keyed(&self, job: &str, key: String, stamp: u64, emit: JobEmitter) -> JobStart {
+        // Held across the check and the insert so racing retries see each other
+        let mut keys = self.keys.lock().unwrap();
+        if let Some(&id) = keys.get(&key) {
+            return JobStart::Running(id);
+        }
+
+        let cached = {
+            let mut finished = self.finished.lock().unwrap();
+            finished.retain(|_, f| f.at.elapsed() < FINISHED_TTL);
+            finished
+                .get(&key)
+                .filter(|f| f.stamp == stamp)
+                .map(|f| f.result.clone())
+        };
+        if let Some(result) = cached {
+            let ctx = self.register(job, None, emit);
+            self.finish(&ctx, Ok(result));
+            return JobStart::Cached(ctx.id);
+        }
+
+        let ctx = self.register(job, Some(key.clone()), emit);
+        keys.insert(key, ctx.id);
+        JobStart::New(ctx)
+    }
+
+    fn register(&self, job: &str, key: Option<String>, emit: JobEmitter) -> JobContext {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = CancelToken::new();
+        self.active
+            .lock()
+            .unwrap()
+            .insert(id, (job.to_string(), cancel.clone()));
+
+        emit(JobEvent::Started {
+            id,
+            job: job.to_string(),
+        });
+        JobContext {
+            id,
+            cancel,
+            emit,
+            key,
+        }
+    }
+
+    /// Emits the terminal event for `ctx` and forgets the job. Errors after
+    /// a cancel request are reported as cancellation.
+    pub fn finish<T: Serialize>(&self, ctx: &JobContext, result: Result<T, AppError>) {
+        self.active.lock().unwrap().remove(&ctx.id);
+        if let Some(key) = &ctx.key {
+            self.keys.lock().unwrap().remove(key);
+        }
+
+        let event = match result {
+            _ if ctx.cancel.is_cancelled() => JobEvent::Cancelled { id: ctx.id },
+            Ok(value) => match serde_json::to_value(value) {
+                Ok(result) => JobEvent::Finished { id: ctx.id, result },
+                Err(e) => JobEvent::Failed {
+                    id: ctx.id,
+                    error: AppError::with_details(ErrorCode::Internal, e.to_string()),
+                },
+            },
+            Err(error) => JobEvent::Failed { id: ctx.id, error },
+        };
+        (ctx.emit)(event);
+    }
+
+    /// `finish` for jobs from `start_keyed`: a successful result is kept
+    /// for identical requests made while the caller's state is at `stamp`.
+    pub fn finish_keyed<T: Serialize>(
+        &self,
+        ctx: &JobContext,
+        result: Result<T, AppError>,
+        stamp: u64,
+    ) {
+        let result = result.and_then(|value| {
+            serde_json::to_value(value)
+                .map_err(|e| AppError::with_details(ErrorCode::Internal, e.to_string()))
+        });
+        if let (Some(key), Ok(value)) = (&ctx.key, &result) {
+            if !ctx.cancel.is_cancelled() {
+                self.finished.lock().unwrap().insert(
+                    key.clone(),
+                    FinishedJob {
+                        result: value.clone(),
+                        stamp,
+                        at: Instant::now(),
+                    },
+                );
+            }
+        }
+        self.finish(ctx, result);
+    }
+
+    // False when the job already finished or never existed
+    pub fn cancel(&self, id: JobId) -> bool {
+        match self.active.lock().unwrap().get(&id) {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn active(&self) -> Vec<JobInfo> {
+        let mut jobs: Vec<JobInfo> = self
+            .active
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&id, (job, token))| JobInfo {
+                id,
+                job: job.clone(),
+                cancelling: token.is_cancelled(),
+            })
+            .collect();
+        jobs.sort_by_key(|j| j.id);
+        jobs
+    }
+}
+
+/// Identity of a request for `JobManager::start_keyed`: a SHA-256 over the
+/// job name and the JSON of everything that determines its outcome.
+pub fn request_key<T: Serialize>(job: &str, parts: &T) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(job.as_bytes());
+    hasher.update([0]);
+    hasher.update(serde_json::to_vec(parts).unwrap_or_default());
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder() -> (JobEmitter, Arc<Mutex<Vec<JobEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        (
+            Arc::new(move |event| sink.lock().unwrap().push(event)),
+            events,
+        )
+    }
+
+    fn new_job(start: JobStart) -> JobContext {
+        match start {
+            JobStart::New(ctx) => ctx,
+            _ => panic!("expected a new job"),
+        }
+    }
+
+    #[test]
+    fn retries_join_the_running_job() {
+        let jobs = JobManager::new();
+        let (emit, _) = recorder();
+        let ctx = new_job(jobs.start_keyed("load", "a".into(), 1, emit.clone()));
+        assert!(matches!(
+            jobs.start_keyed("load", "a".into(), 1, emit.clone()),
+            JobStart::Running(id) if id == ctx.id
+        ));
+        jobs.finish_keyed(&ctx, Ok(1), 1);
+    }
+
+    #[test]
+    fn recent_results_answer_while_the_stamp_holds() {
+        let jobs = JobManager::new();
+        let (emit, events) = recorder();
+        let ctx = new_job(jobs.start_keyed("load", "a".into(), 1, emit.clone()));
+        jobs.finish_keyed(&ctx, Ok(42), 2);
+
+        let JobStart::Cached(id) = jobs.start_keyed("load", "a".into(), 2, emit.clone()) else {
+            panic!("expected the cached result");
+        };
+        assert_ne!(id, ctx.id);
+        assert!(matches!(
+            events.lock().unwrap().last(),
+            Some(JobEvent::Finished { id: finished, result }) if *finished == id && result == 42
+        ));
+        assert!(jobs.active().is_empty());
+
+        // The state moved on since: run again
+        new_job(jobs.start_keyed("load", "a".into(), 3, emit));
+    }
+
+    #[test]
+    fn failures_and_cancels_are_not_kept() {
+        let jobs = JobManager::new();
+        let (emit, _) = recorder();
+        let ctx = new_job(jobs.start_keyed("load", "a".into(), 1, emit.clone()));
+        jobs.finish_keyed::<u32>(&ctx, Err(AppError::new(ErrorCode::Internal)), 1);
+        let ctx = new_job(jobs.start_keyed("load", "a".into(), 1, emit.clone()));
+        ctx.cancel.cancel();
+        jobs.finish_keyed(&ctx, Ok(1), 1);
+        new_job(jobs.start_keyed("load", "a".into(), 1, emit));
+    }
+}
@@ -1,2 +1,29 @@
+pub mod abx;
+pub mod atomic_file;
 pub mod audio;
+pub mod automation;
+pub mod batch;
+pub mod benchmark;
+pub mod cache;
+pub mod curves;
 pub mod dsp;
+pub mod error;
+pub mod export;
+pub mod history;
+pub mod i18n;
+pub mod instance;
+pub mod jobs;
+pub mod logging;
+pub mod midi;
+pub mod open;
+pub mod osc;
+pub(crate) mod persist;
+pub mod preview;
+pub mod project;
+pub mod queue;
+pub mod recent;
+pub mod render;
+pub mod script;
+pub mod session;
+pub mod settings;
+pub mod share;
@@ -0,0 +1,158 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const LOG_FILE: &str = "spectraforge-eq.log";
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+const KEPT_LOG_FILES: usize = 3; // spectraforge-eq.log.1 .. .3
+const MAX_RECENT_ERRORS: usize = 50;
+
+/// One log line. Files hold these as JSON lines so support can grep or
+/// load them without a custom parser.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp_ms: u64, // Unix milliseconds
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+struct LogFile {
+    file: File,
+    written: u64,
+}
+
+struct FileLogger {
+    dir: PathBuf,
+    file: Mutex<Option<LogFile>>, // None after a failed rotation; lines are dropped
+    recent_errors: Mutex<VecDeque<LogEntry>>,
+}
+
+static LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+/// Installs the global logger writing to `dir`, rotating the file once it
+/// passes 5 MiB. Warnings and errors are also kept in memory for
+/// `recent_errors`. Calling it twice is an error.
+pub fn init(dir: &Path, level: LevelFilter) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let file = open_log(&dir.join(LOG_FILE))?;
+
+    let logger = LOGGER.get_or_init(|| FileLogger {
+        dir: dir.to_path_buf(),
+        file: Mutex::new(Some(file)),
+        recent_errors: Mutex::new(VecDeque::new()),
+    });
+    log::set_logger(logger).map_err(|e| e.to_string())?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+// Where the log files live, once `init` has run
+pub fn log_dir() -> Option<PathBuf> {
+    LOGGER.get().map(|logger| logger.dir.clone())
+}
+
+// Newest last
+pub fn recent_errors() -> Vec<LogEntry> {
+    LOGGER
+        .get()
+        .map(|logger| {
+            logger
+                .recent_errors
+                .lock()
+                .unwrap()
+                .iter()
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn open_log(path: &Path) -> Result<LogFile, String> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let written = file.metadata().map(|m| m.len()).unwrap_or(0);
+    Ok(LogFile { file, written })
+}
+
+impl FileLogger {
+    // Shifts .log -> .log.1 -> .log.2 ..., dropping the oldest
+    fn rotate(&self) -> Result<LogFile, String> {
+        let path = self.dir.join(LOG_FILE);
+        let numbered = |n: usize| self.dir.join(format!("{}.{}", LOG_FILE, n));
+
+        let _ = fs::remove_file(numbered(KEPT_LOG_FILES));
+        for n in (1..KEPT_LOG_FILES).rev() {
+            let _ = fs::rename(numbered(n), numbered(n + 1));
+        }
+        fs::rename(&path, numbered(1)).map_err(|e| e.to_string())?;
+        open_log(&path)
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = LogEntry {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+
+        if cfg!(debug_assertions) {
+            eprint!("{}", line);
+        }
+
+        if record.level() <= Level::Warn {
+            let mut recent = self.recent_errors.lock().unwrap();
+            if recent.len() == MAX_RECENT_ERRORS {
+                recent.pop_front();
+            }
+            recent.push_back(entry);
+        }
+
+        let mut file = self.file.lock().unwrap();
+        if file
+            .as_ref()
+            .is_some_and(|f| f.written + line.len() as u64 > MAX_LOG_BYTES)
+        {
+            // The open handle has to go before the rename on Windows
+            file.take();
+            *file = self.rotate().ok();
+        }
+        if let Some(log) = file.as_mut() {
+            if log.file.write_all(line.as_bytes()).is_ok() {
+                log.written += line.len() as u64;
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(log) = self.file.lock().unwrap().as_mut() {
+            let _ = log.file.flush();
+        }
+    }
+}
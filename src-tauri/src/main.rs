@@ -3,146 +3,2766 @@
     windows_subsystem = "windows"
 )]
 
-use eq_matcher::audio::analyzer::{analyze_spectrum, AnalysisConfig};
-use eq_matcher::audio::loader::{load_audio_file, prepare_audio_for_analysis, ResamplePolicy};
-use eq_matcher::audio::matcher::{match_profiles, MatchConfig, MatchResult};
-use eq_matcher::audio::profile::{extract_eq_profile, EQProfile};
-use std::sync::Mutex;
+use eq_matcher::abx::{AbxCandidate, AbxReport, AbxSession, AbxSide, AbxStatus};
+use eq_matcher::atomic_file::{init_journal, write_atomic, JOURNAL_FILE};
+use eq_matcher::audio::analyzer::AnalysisConfig;
+use eq_matcher::audio::arithmetic::{apply_profile_op, ProfileOp};
+use eq_matcher::audio::compare::{compare_profiles, ProfileComparison};
+use eq_matcher::audio::deviation::{
+    deviation_over_time as run_deviation, worst_region, DeviationMap, DEFAULT_SEGMENT_SECS,
+};
+use eq_matcher::audio::explain::{explain_correction as explain_bands, BandExplanation};
+use eq_matcher::audio::generator::{generate, TestSignal};
+use eq_matcher::audio::headphone::{
+    match_headphone as run_headphone_match, parse_measurement, HeadphoneTarget,
+};
+use eq_matcher::audio::loader::load_audio_channels_cancellable;
+use eq_matcher::audio::matcher::{blend_profiles, match_profiles, MatchConfig, MatchResult};
+use eq_matcher::audio::null_test::null_test as run_null_test;
+use eq_matcher::audio::optimizer::auto_match;
+use eq_matcher::audio::pipeline::{
+    analyze_file_bands, analyze_file_channel_spectra, analyze_file_spectrum,
+    load_mono_for_analysis, policy_from_preset, AnalyzedSpectra, STANDARD_RATE,
+};
+use eq_matcher::audio::predict::{
+    predict_corrected_profile as predict_profile, DEFAULT_PREDICTION_RATE,
+};
+use eq_matcher::audio::presets::{list_match_presets as all_match_presets, MatchPreset};
+use eq_matcher::audio::profile::{extract_eq_profile, ChannelMode, EQProfile, FrequencyBand};
+use eq_matcher::audio::provenance::{content_fingerprint, ProfileSource};
+use eq_matcher::audio::sections::{match_sections as run_section_match, SectionMatch};
+use eq_matcher::audio::spectrogram::{
+    compute_spectrogram, DEFAULT_MAX_COLUMNS, DEFAULT_ROWS as DEFAULT_SPECTROGRAM_ROWS,
+};
+use eq_matcher::audio::validation::{validate_analysis_config, validate_match_config};
+use eq_matcher::audio::warnings::MatchWarning;
+use eq_matcher::audio::waveform::{
+    waveform_overview as downsample_waveform, WaveformOverview, DEFAULT_WAVEFORM_POINTS,
+};
+use eq_matcher::automation::{generate_token, AutomationServer, Request, Response};
+use eq_matcher::batch::{
+    batch_match as run_batch_match, collect_audio_paths, BatchEvent, BatchJob,
+    DEFAULT_BATCH_MEMORY_MB,
+};
+use eq_matcher::benchmark::run_benchmark as run_self_test;
+use eq_matcher::cache::{CacheInfo, ProfileCache, CACHE_DIR, DEFAULT_CACHE_LIMIT_MB};
+use eq_matcher::curves::{CurveStore, TargetCurve, CURVES_FILE};
+use eq_matcher::dsp::dynamic::DynamicBand;
+use eq_matcher::dsp::fir::DEFAULT_FIR_TAPS;
+use eq_matcher::dsp::response::{compute_filter_response as filter_response, FilterResponse};
+use eq_matcher::error::{AppError, ErrorCode};
+use eq_matcher::export::bundle::export_bundle;
+use eq_matcher::export::card::{render_share_card, ShareCardOptions};
+use eq_matcher::export::devices::{
+    builtin_devices, check_feasibility, export_device_presets, DeviceConstraints, DeviceFeasibility,
+};
+use eq_matcher::export::fir::{export_fir as export_fir_wav, FirExport};
+use eq_matcher::export::notes::mastering_notes as measure_mastering_notes;
+use eq_matcher::export::spectrogram::{render_spectrogram_png, SpectrogramImageOptions};
+use eq_matcher::export::template::{load_templates, TEMPLATE_DIR};
+use eq_matcher::export::{
+    export_result_with_context, export_sections, file_extension,
+    list_export_formats as all_export_formats, ExportContext, ExportFormat,
+};
+use eq_matcher::history::{apply_band_overrides, EditHistory, HistoryStatus, MatchEdit};
+use eq_matcher::i18n::set_locale;
+use eq_matcher::instance::{forward_to_running, listen};
+use eq_matcher::jobs::{
+    request_key, JobContext, JobEmitter, JobEvent, JobId, JobInfo, JobManager, JobStart,
+};
+use eq_matcher::logging::{self, LogEntry};
+use eq_matcher::midi::{self, MidiConfig, MidiOutputInfo};
+#[cfg(target_os = "macos")]
+use eq_matcher::open::install_open_handler;
+use eq_matcher::open::{launch_paths, plan_drop, DropRoute, FileKind, OpenRequest};
+use eq_matcher::osc::{self, OscConfig};
+use eq_matcher::preview::device::{
+    default_backend, find_output_device, AudioBackend, AudioDeviceInfo,
+};
+use eq_matcher::preview::output::PreviewOutput;
+use eq_matcher::preview::{PreviewEngine, PreviewMeter, PreviewStatus};
+use eq_matcher::project::{Project, ProjectId, ProjectInfo, ProjectStore};
+use eq_matcher::queue::{BatchQueue, QueueAction, QueueItemId, QueueSnapshot, DEFAULT_WORKERS};
+use eq_matcher::recent::{FileRole, RecentFile, RecentFiles, ReferenceSuggestion, RECENT_FILE};
+use eq_matcher::render::{render_file, RenderConfig, RenderEvent};
+use eq_matcher::script::run_script;
+use eq_matcher::session::{load_session, save_session as write_session, Session, AUTOSAVE_FILE};
+use eq_matcher::settings::{Settings, SettingsStore, SETTINGS_FILE};
+use eq_matcher::share::{
+    create_shared_profile, read_shared_profile, write_shared_profile, ShareInfo, SharedProfile,
+    SHARE_SCHEMA,
+};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Manager;
 
 struct AppState {
-    reference_profile: Mutex<Option<EQProfile>>,
-    input_profile: Mutex<Option<EQProfile>>,
-    match_result: Mutex<Option<MatchResult>>,
+    projects: RwLock<ProjectStore>, // Profiles, result and edit history per open project
+    preview: Arc<Mutex<Option<PreviewEngine>>>, // Shared with the output thread
+    preview_output: Mutex<Option<PreviewOutput>>, // Opened on first play
+    audio_backend: Box<dyn AudioBackend>,
+    preview_device: Mutex<Option<AudioDeviceInfo>>, // None = system default
+    jobs: JobManager,
+    settings: Mutex<SettingsStore>,
+    recent: Mutex<RecentFiles>,
+    curves: Mutex<CurveStore>,      // User target curves
+    abx: Mutex<Option<AbxSession>>, // Running blind test, played through the preview
+    cache: Mutex<ProfileCache>,     // Analyzed profiles on disk, reused by loads
+    config_dir: OnceLock<PathBuf>,  // Set during setup when the platform has one
+    automation: Mutex<Option<AutomationServer>>,
+    queue: OnceLock<BatchQueue>, // Created during setup, once events can be emitted
+    open_requests: Mutex<Vec<OpenRequest>>, // Files handed over at launch, until the UI asks
 }
 
+/// Starts `work` on the blocking thread pool and returns its job id right
+/// away, so decoding and FFTs never stall the async runtime. Progress and
+/// the serialized result (or error) arrive as "job-event" events tagged
+/// with that id.
+fn spawn_job<T, F>(app: &tauri::AppHandle, job: &str, work: F) -> JobId
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce(&AppState, &JobContext) -> Result<T, AppError> + Send + 'static,
+{
+    spawn_keyed_job(app, job, None, work)
+}
+
+// With a request key, a retry of a request still running gets the running
+// job's id and `work` is dropped; so does one repeating a recent success
+// while the project it acted on is unchanged (see `JobManager::start_keyed`)
+fn spawn_keyed_job<T, F>(
+    app: &tauri::AppHandle,
+    job: &str,
+    key: Option<(ProjectId, String)>,
+    work: F,
+) -> JobId
+where
+    T: Serialize + Send + 'static,
+    F: FnOnce(&AppState, &JobContext) -> Result<T, AppError> + Send + 'static,
+{
+    let emitter = app.clone();
+    let emit: JobEmitter = Arc::new(move |event| {
+        log_job_event(&event);
+        if let Some(server) = emitter
+            .state::<AppState>()
+            .automation
+            .lock()
+            .unwrap()
+            .as_ref()
+        {
+            server.broadcast(&event);
+        }
+        let _ = emitter.emit_all("job-event", event);
+    });
+    let state = app.state::<AppState>();
+    let ctx = match &key {
+        None => state.jobs.start(job, emit),
+        Some((project, key)) => {
+            let stamp = project_stamp(&state, *project);
+            match state.jobs.start_keyed(job, key.clone(), stamp, emit) {
+                JobStart::New(ctx) => ctx,
+                JobStart::Running(id) => {
+                    log::info!("job {} retried while running: {}", id, job);
+                    return id;
+                }
+                JobStart::Cached(id) => {
+                    log::info!("job {} answered from a recent run: {}", id, job);
+                    return id;
+                }
+            }
+        }
+    };
+    let id = ctx.id;
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let (worker, worker_ctx) = (app.clone(), ctx.clone());
+        let result = tauri::async_runtime::spawn_blocking(move || {
+            work(&worker.state::<AppState>(), &worker_ctx)
+        })
+        .await
+        // A panicking job must still leave the registry
+        .unwrap_or_else(|e| Err(AppError::with_details(ErrorCode::Internal, e.to_string())));
+
+        let state = app.state::<AppState>();
+        match key {
+            Some((project, _)) => {
+                let stamp = project_stamp(&state, project);
+                state.jobs.finish_keyed(&ctx, result, stamp)
+            }
+            None => state.jobs.finish(&ctx, result),
+        }
+    });
+
+    id
+}
+
+// Every change to a project bumps its revision; a closed project never
+// matches again
+fn project_stamp(state: &AppState, project: ProjectId) -> u64 {
+    read_project(state, project, |p| p.revision).unwrap_or(u64::MAX)
+}
+
+fn log_job_event(event: &JobEvent) {
+    match event {
+        JobEvent::Started { id, job } => log::info!("job {} started: {}", id, job),
+        JobEvent::Finished { id, .. } => log::info!("job {} finished", id),
+        JobEvent::Failed { id, error } => log::warn!("job {} failed: {}", id, error),
+        JobEvent::Cancelled { id } => log::info!("job {} cancelled", id),
+        JobEvent::Progress { .. } | JobEvent::Partial { .. } => {}
+    }
+}
+
+fn not_loaded(details: impl Into<String>) -> AppError {
+    AppError::with_details(ErrorCode::NotLoaded, details)
+}
+
+// Partial profiles per load job, enough for a smooth build-up in the UI
+const ANALYSIS_UPDATES: usize = 20;
+
+// Analyzes with the FFT size, window and overlap of `config` and streams
+// partial profiles on its band grid as job events; channel analyses run in
+// parallel and only report the finished profile
+fn analyze_with_preset(
+    ctx: &JobContext,
+    path: &str,
+    preset: Option<&str>,
+    channel_mode: Option<ChannelMode>,
+    config: &AnalysisConfig,
+) -> Result<AnalyzedSpectra, AppError> {
+    let policy = policy_from_preset(preset);
+    match channel_mode {
+        Some(mode) => analyze_file_channel_spectra(path, policy, mode, config, &ctx.cancel),
+        None => analyze_file_spectrum(
+            path,
+            policy,
+            config,
+            &ctx.cancel,
+            ANALYSIS_UPDATES,
+            &mut |spectrum, done| ctx.partial(done, &extract_eq_profile(&spectrum, config)),
+        )
+        .map(|mix| AnalyzedSpectra {
+            mix,
+            channels: None,
+            source: None,
+        }),
+    }
+    .map_err(|e| AppError::from(e).in_file(path))
+}
+
+// The full-resolution analysis of `path`, from the cache when possible
+fn load_spectra(
+    state: &AppState,
+    ctx: &JobContext,
+    path: &str,
+    preset: Option<&str>,
+    channel_mode: Option<ChannelMode>,
+    config: &AnalysisConfig,
+) -> Result<AnalyzedSpectra, AppError> {
+    let source = Path::new(path);
+    // Band centers aren't part of it: profiles on any grid come from the spectra
+    let variant = format!(
+        "{:?}/{:?}/{}/{:?}/{}",
+        preset, channel_mode, config.fft_size, config.window_type, config.overlap
+    );
+    // Entries from before provenance was kept are analyzed again
+    let cached: Option<AnalyzedSpectra> = state.cache.lock().unwrap().get(source, &variant);
+    if let Some(spectra) = cached.filter(|s| s.source.is_some()) {
+        return Ok(spectra);
+    }
+
+    let mut spectra = analyze_with_preset(ctx, path, preset, channel_mode, config)?;
+    spectra.source = Some(
+        ProfileSource::new(
+            path,
+            spectra.mix.duration_secs,
+            spectra.mix.sample_rate,
+            config,
+            preset,
+            &ctx.cancel,
+        )
+        .map_err(io_error)?,
+    );
+    if let Err(e) = state.cache.lock().unwrap().put(source, &variant, &spectra) {
+        log::warn!("analysis not cached: {}", e);
+    }
+    Ok(spectra)
+}
+
+// Band levels only, streamed from the decoder into the band accumulator, for
+// files too long to hold with their full-resolution spectrum. Nothing is
+// cached, as the cache holds spectra
+fn load_bands(
+    ctx: &JobContext,
+    path: &str,
+    preset: Option<&str>,
+    config: &AnalysisConfig,
+) -> Result<EQProfile, AppError> {
+    let analysis = analyze_file_bands(path, policy_from_preset(preset), config, &ctx.cancel)
+        .map_err(|e| AppError::from(e).in_file(path))?;
+    let mut profile = analysis.profile;
+    profile.source = Some(Box::new(
+        ProfileSource::new(
+            path,
+            analysis.duration_secs,
+            analysis.sample_rate,
+            config,
+            preset,
+            &ctx.cancel,
+        )
+        .map_err(io_error)?,
+    ));
+    Ok(profile)
+}
+
+// Analysis settings from the stored settings: FFT size, window, overlap and
+// band grid for every file loaded
+fn analysis_config(state: &AppState) -> AnalysisConfig {
+    state.settings.lock().unwrap().get().analysis_config.clone()
+}
+
+fn active_project(state: &AppState) -> ProjectId {
+    state.projects.read().unwrap().active_id()
+}
+
+// Readers share the lock, so polling never queues behind other reads;
+// keep `f` short, writers wait for it
+fn read_active<T>(state: &AppState, f: impl FnOnce(&Project) -> T) -> T {
+    f(state.projects.read().unwrap().active())
+}
+
+fn read_project<T>(
+    state: &AppState,
+    id: ProjectId,
+    f: impl FnOnce(&Project) -> T,
+) -> Result<T, AppError> {
+    Ok(f(state.projects.read().unwrap().get(id).map_err(stale)?))
+}
+
+// Mutable access bumps the project's revision
+fn with_active<T>(state: &AppState, f: impl FnOnce(&mut Project) -> T) -> T {
+    f(state.projects.write().unwrap().active_mut())
+}
+
+// Jobs write back through this; a project closed meanwhile is an error
+fn with_project<T>(
+    state: &AppState,
+    id: ProjectId,
+    f: impl FnOnce(&mut Project) -> T,
+) -> Result<T, AppError> {
+    Ok(f(state
+        .projects
+        .write()
+        .unwrap()
+        .get_mut(id)
+        .map_err(stale)?))
+}
+
+fn stale(details: impl Into<String>) -> AppError {
+    AppError::with_details(ErrorCode::Stale, details)
+}
+
+fn invalid(details: impl Into<String>) -> AppError {
+    AppError::with_details(ErrorCode::InvalidArgument, details)
+}
+
+fn io_error(details: impl Into<String>) -> AppError {
+    AppError::with_details(ErrorCode::Io, details)
+}
+
+fn current_result(state: &AppState) -> Option<MatchResult> {
+    read_active(state, |project| project.match_result.clone())
+}
+
+// Files behind the active project, for exports that describe the match
+fn export_context(state: &AppState) -> ExportContext {
+    read_active(state, |project| ExportContext {
+        reference_path: project.reference_path.clone(),
+        input_path: project.input_path.clone(),
+    })
+}
+
+fn loaded_reference(state: &AppState, id: &str) -> Result<EQProfile, AppError> {
+    read_active(state, |project| project.references.get(id).cloned())
+        .ok_or_else(|| not_loaded(format!("Reference not loaded: {}", id)))
+}
+
+// Makes `profile` the project's reference or input and remembers the file
+fn set_loaded_profile(
+    state: &AppState,
+    project: ProjectId,
+    role: FileRole,
+    path: String,
+    profile: &EQProfile,
+    spectra: Option<Arc<AnalyzedSpectra>>, // None drops spectra kept from earlier loads
+) -> Result<(), AppError> {
+    with_project(state, project, |project| {
+        match spectra {
+            Some(spectra) => project.spectra.insert(path.clone(), spectra),
+            None => project.spectra.remove(&path),
+        };
+        match role {
+            FileRole::Reference => {
+                project.reference_profile = Some(profile.clone());
+                project.references.insert(path.clone(), profile.clone());
+                project.reference_path = Some(path.clone());
+            }
+            FileRole::Input => {
+                project.input_profile = Some(profile.clone());
+                project.input_path = Some(path.clone());
+            }
+        }
+        project.inputs_changed();
+    })?;
+
+    // Best effort, like autosave
+    let _ = state.recent.lock().unwrap().record(&path, role, profile);
+    autosave(state);
+    Ok(())
+}
+
+// Shared by the load commands and the automation API. Result: EQProfile.
+// `bands_only` skips the full-resolution spectrum (see `load_bands`); channel
+// profiles need it, so it doesn't apply with a channel mode
+fn start_load(
+    app: &tauri::AppHandle,
+    role: FileRole,
+    path: String,
+    preset: Option<String>,
+    channel_mode: Option<ChannelMode>,
+    bands_only: bool,
+) -> JobId {
+    let job = match role {
+        FileRole::Reference => "load_reference_audio",
+        FileRole::Input => "load_input_audio",
+    };
+    let state = app.state::<AppState>();
+    let project = active_project(&state);
+    // Same file at the same path (by content), options and analysis settings
+    let key = request_key(
+        job,
+        &(
+            project,
+            fs::canonicalize(&path).unwrap_or_else(|_| PathBuf::from(&path)),
+            content_fingerprint(Path::new(&path)).ok(),
+            &preset,
+            &channel_mode,
+            bands_only,
+            analysis_config(&state),
+        ),
+    );
+    spawn_keyed_job(app, job, Some((project, key)), move |state, ctx| {
+        ctx.progress("analyzing", 0.0)?;
+        let config = analysis_config(state);
+        let (profile, spectra) = if bands_only && channel_mode.is_none() {
+            (load_bands(ctx, &path, preset.as_deref(), &config)?, None)
+        } else {
+            let spectra =
+                load_spectra(state, ctx, &path, preset.as_deref(), channel_mode, &config)?;
+            (spectra.profile(&config), Some(Arc::new(spectra)))
+        };
+        ctx.cancel.check()?;
+
+        set_loaded_profile(state, project, role, path, &profile, spectra)?;
+
+        Ok(profile)
+    })
+}
+
+// Result: MatchResult, which also becomes the current result
+fn start_match(
+    app: &tauri::AppHandle,
+    reference: EQProfile,
+    input: EQProfile,
+    config: MatchConfig,
+) -> JobId {
+    let state = app.state::<AppState>();
+    let project = active_project(&state);
+    let basis = read_active(&state, |project| project.inputs_revision);
+    let key = request_key(
+        "calculate_eq_match",
+        &(project, basis, &reference, &input, &config),
+    );
+    spawn_keyed_job(
+        app,
+        "calculate_eq_match",
+        Some((project, key)),
+        move |state, ctx| {
+            validate_match_config(&config)?;
+            let mut result = match_profiles(&reference, &input, &config);
+            ctx.cancel.check()?;
+
+            with_project(state, project, |project| {
+                // A new config keeps the hand-set bands from the current edit
+                let edit = MatchEdit {
+                    config: config.clone(),
+                    band_overrides: project
+                        .history
+                        .current()
+                        .map(|e| e.band_overrides.clone())
+                        .unwrap_or_default(),
+                };
+                apply_band_overrides(&mut result, &edit.band_overrides, &reference, &input);
+                project
+                    .set_result(result.clone(), config, basis)
+                    .map(|_| project.history.record(edit))
+            })?
+            .map_err(stale)?;
+            autosave(state);
+            if let Some(source) = &reference.source {
+                // Best effort, like the recent list itself
+                let _ = state.recent.lock().unwrap().record_match(&source.path);
+            }
+
+            Ok(result)
+        },
+    )
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MatchChange {
+    result: MatchResult,
+    history: HistoryStatus,
+}
+
+// Where an edit lands in the history, once its result is stored
+enum HistoryMove {
+    Record(Vec<MatchEdit>), // Recorded in order; the edit itself comes last
+    Undo,
+    Redo,
+}
+
+impl HistoryMove {
+    // Undo and redo are stale once the history moved on since they were asked
+    fn applies(&self, history: &EditHistory, edit: &MatchEdit) -> bool {
+        let next = match self {
+            HistoryMove::Record(_) => return true,
+            HistoryMove::Undo => history.next_undo(),
+            HistoryMove::Redo => history.next_redo(),
+        };
+        next.is_some_and(|next| next.same_as(edit))
+    }
+
+    fn apply(self, history: &mut EditHistory) {
+        match self {
+            HistoryMove::Record(edits) => edits.into_iter().for_each(|e| history.record(e)),
+            HistoryMove::Undo => {
+                history.undo();
+            }
+            HistoryMove::Redo => {
+                history.redo();
+            }
+        }
+    }
+}
+
+// Recomputes the current result for `edit` against the loaded profiles, then
+// moves the history; a failed or cancelled job leaves both as they were.
+// Result: MatchChange
+fn start_edit(
+    app: &tauri::AppHandle,
+    job: &str,
+    project: ProjectId,
+    edit: MatchEdit,
+    step: HistoryMove,
+) -> Result<JobId, AppError> {
+    let (reference, input, basis) = read_project(&app.state::<AppState>(), project, |project| {
+        (
+            project.reference_profile.clone(),
+            project.input_profile.clone(),
+            project.inputs_revision,
+        )
+    })?;
+    let (Some(reference), Some(input)) = (reference, input) else {
+        return Err(not_loaded("Load a reference and an input first"));
+    };
+    validate_match_config(&edit.config)?;
+
+    Ok(spawn_job(app, job, move |state, ctx| {
+        let mut result = match_profiles(&reference, &input, &edit.config);
+        apply_band_overrides(&mut result, &edit.band_overrides, &reference, &input);
+        ctx.cancel.check()?;
+
+        let history = with_project(state, project, |project| {
+            if !step.applies(&project.history, &edit) {
+                return Err("The history changed while recalculating".to_string());
+            }
+            project.set_result(result.clone(), edit.config.clone(), basis)?;
+            step.apply(&mut project.history);
+            Ok(project.history.status())
+        })?
+        .map_err(stale)?;
+        autosave(state);
+
+        Ok(MatchChange { result, history })
+    }))
+}
+
+// Pins one band to `gain_db`, or releases it with None. Undoable.
+#[tauri::command]
+fn set_band_override(
+    frequency: f32,
+    gain_db: Option<f32>,
+    app: tauri::AppHandle,
+) -> Result<JobId, AppError> {
+    let state = app.state::<AppState>();
+    let project = active_project(&state);
+    let (edit, step) = read_active(&state, |project| {
+        // Seed with the plain match so the first override can be undone
+        let (base, seed) = match project.history.current().cloned() {
+            Some(current) => (current, None),
+            None => {
+                let seed = MatchEdit {
+                    config: project
+                        .match_config
+                        .clone()
+                        .ok_or_else(|| not_loaded("Calculate a match first"))?,
+                    band_overrides: Vec::new(),
+                };
+                (seed.clone(), Some(seed))
+            }
+        };
+        let edit = base.with_override(frequency, gain_db);
+        let steps = seed.into_iter().chain([edit.clone()]).collect();
+        Ok::<_, AppError>((edit, HistoryMove::Record(steps)))
+    })?;
+    start_edit(&app, "set_band_override", project, edit, step)
+}
+
+#[tauri::command]
+fn undo_match_change(app: tauri::AppHandle) -> Result<JobId, AppError> {
+    let state = app.state::<AppState>();
+    let project = active_project(&state);
+    let edit = read_active(&state, |project| project.history.next_undo().cloned())
+        .ok_or_else(|| AppError::new(ErrorCode::NothingToUndo))?;
+    start_edit(&app, "undo_match_change", project, edit, HistoryMove::Undo)
+}
+
+#[tauri::command]
+fn redo_match_change(app: tauri::AppHandle) -> Result<JobId, AppError> {
+    let state = app.state::<AppState>();
+    let project = active_project(&state);
+    let edit = read_active(&state, |project| project.history.next_redo().cloned())
+        .ok_or_else(|| AppError::new(ErrorCode::NothingToRedo))?;
+    start_edit(&app, "redo_match_change", project, edit, HistoryMove::Redo)
+}
+
+#[tauri::command]
+fn match_history(state: tauri::State<'_, AppState>) -> HistoryStatus {
+    read_active(&state, |project| project.history.status())
+}
+
+#[tauri::command]
+fn list_projects(state: tauri::State<'_, AppState>) -> Vec<ProjectInfo> {
+    state.projects.read().unwrap().list()
+}
+
+// Opens an empty project next to the others and makes it active
+#[tauri::command]
+fn create_project(name: Option<String>, state: tauri::State<'_, AppState>) -> Vec<ProjectInfo> {
+    {
+        let mut projects = state.projects.write().unwrap();
+        let id = projects.create(name.as_deref());
+        let _ = projects.set_active(id);
+    }
+    autosave(&state);
+    state.projects.read().unwrap().list()
+}
+
+#[tauri::command]
+fn set_active_project(
+    id: ProjectId,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ProjectInfo>, AppError> {
+    state
+        .projects
+        .write()
+        .unwrap()
+        .set_active(id)
+        .map_err(invalid)?;
+    autosave(&state);
+    Ok(state.projects.read().unwrap().list())
+}
+
+#[tauri::command]
+fn rename_project(
+    id: ProjectId,
+    name: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ProjectInfo>, AppError> {
+    let mut projects = state.projects.write().unwrap();
+    projects.rename(id, &name).map_err(invalid)?;
+    Ok(projects.list())
+}
+
+// Jobs still running for the project finish without storing their results
+#[tauri::command]
+fn close_project(
+    id: ProjectId,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ProjectInfo>, AppError> {
+    state.projects.write().unwrap().close(id).map_err(invalid)?;
+    autosave(&state);
+    Ok(state.projects.read().unwrap().list())
+}
+
+#[tauri::command]
+fn load_reference_audio(
+    path: String,
+    preset: Option<String>,
+    channel_mode: Option<ChannelMode>,
+    bands_only: Option<bool>,
+    app: tauri::AppHandle,
+) -> JobId {
+    start_load(
+        &app,
+        FileRole::Reference,
+        path,
+        preset,
+        channel_mode,
+        bands_only.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+fn load_input_audio(
+    path: String,
+    preset: Option<String>,
+    channel_mode: Option<ChannelMode>,
+    bands_only: Option<bool>,
+    app: tauri::AppHandle,
+) -> JobId {
+    start_load(
+        &app,
+        FileRole::Input,
+        path,
+        preset,
+        channel_mode,
+        bands_only.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+fn calculate_eq_match(
+    reference: EQProfile,
+    input: EQProfile,
+    config: MatchConfig,
+    app: tauri::AppHandle,
+) -> JobId {
+    start_match(&app, reference, input, config)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BandGridUpdate {
+    reference_profile: Option<EQProfile>,
+    input_profile: Option<EQProfile>,
+    match_result: Option<MatchResult>, // Redone with the current config, None if there was none
+    reanalyzed: Vec<String>,           // Loaded without spectra (sessions, recent files)
+}
+
+// Result: BandGridUpdate. Switches the band grid and re-extracts the loaded
+// profiles from their kept spectra; only files without spectra are analyzed
+// again (with the default preset)
+#[tauri::command]
+fn set_band_grid(bands: Vec<f32>, app: tauri::AppHandle) -> Result<JobId, AppError> {
+    let mut config = analysis_config(&app.state::<AppState>());
+    config.frequency_bands = bands;
+    apply_analysis_config(&app, "set_band_grid", config)
+}
+
+// Result: BandGridUpdate. Like `set_band_grid`, but a new FFT size, window
+// or overlap also drops the kept spectra, so the loaded files are analyzed
+// again with it
+#[tauri::command]
+fn set_analysis_config(config: AnalysisConfig, app: tauri::AppHandle) -> Result<JobId, AppError> {
+    apply_analysis_config(&app, "set_analysis_config", config)
+}
+
+#[tauri::command]
+fn get_analysis_config(state: tauri::State<'_, AppState>) -> AnalysisConfig {
+    analysis_config(&state)
+}
+
+// Stores `config` in the settings and brings the active project onto it
+fn apply_analysis_config(
+    app: &tauri::AppHandle,
+    job: &str,
+    config: AnalysisConfig,
+) -> Result<JobId, AppError> {
+    validate_analysis_config(&config)?;
+    let state = app.state::<AppState>();
+    let mut settings = state.settings.lock().unwrap().get().clone();
+    let previous = &settings.analysis_config;
+    let respectrum = previous.fft_size != config.fft_size
+        || previous.window_type != config.window_type
+        || previous.overlap != config.overlap;
+    settings.analysis_config = config.clone();
+    state
+        .settings
+        .lock()
+        .unwrap()
+        .update(settings)
+        .map_err(io_error)?;
+
+    let project = active_project(&state);
+    Ok(spawn_job(app, job, move |state, ctx| {
+        if respectrum {
+            with_project(state, project, |p| p.spectra.clear())?;
+        }
+        let reanalyzed = with_project(state, project, |p| p.rebuild_profiles(&config))?;
+        for (i, path) in reanalyzed.iter().enumerate() {
+            ctx.progress("analyzing", i as f32 / reanalyzed.len() as f32)?;
+            let spectra = load_spectra(state, ctx, path, None, None, &config)?;
+            with_project(state, project, |p| {
+                p.spectra.insert(path.clone(), Arc::new(spectra))
+            })?;
+        }
+        if !reanalyzed.is_empty() {
+            with_project(state, project, |p| p.rebuild_profiles(&config))?;
+        }
+
+        // Re-match from a snapshot, outside the lock
+        let (profiles, rematch, basis) = read_project(state, project, |p| {
+            let rematch = match (&p.reference_profile, &p.input_profile, &p.match_config) {
+                (Some(reference), Some(input), Some(config)) if p.match_result.is_some() => Some((
+                    reference.clone(),
+                    input.clone(),
+                    config.clone(),
+                    p.history.current().map(|e| e.band_overrides.clone()),
+                )),
+                _ => None,
+            };
+            (
+                (p.reference_profile.clone(), p.input_profile.clone()),
+                rematch,
+                p.inputs_revision,
+            )
+        })?;
+        let mut match_result = None;
+        if let Some((reference, input, config, overrides)) = rematch {
+            ctx.progress("matching", 0.9)?;
+            let mut result = match_profiles(&reference, &input, &config);
+            if let Some(overrides) = overrides {
+                apply_band_overrides(&mut result, &overrides, &reference, &input);
+            }
+            with_project(state, project, |p| {
+                p.set_result(result.clone(), config, basis)
+            })?
+            .map_err(stale)?;
+            match_result = Some(result);
+        }
+        let update = BandGridUpdate {
+            reference_profile: profiles.0,
+            input_profile: profiles.1,
+            match_result,
+            reanalyzed,
+        };
+        autosave(state);
+
+        Ok(update)
+    }))
+}
+
+#[tauri::command]
+fn calculate_blended_match(
+    reference_a: EQProfile,
+    reference_b: EQProfile,
+    weight_a: f32, // 0.0 - 1.0, share of reference A
+    input: EQProfile,
+    config: MatchConfig,
+    app: tauri::AppHandle,
+) -> JobId {
+    spawn_job(&app, "calculate_blended_match", move |_, _| {
+        validate_match_config(&config)?;
+        let target = blend_profiles(&reference_a, &reference_b, weight_a).map_err(invalid)?;
+        Ok(match_profiles(&target, &input, &config))
+    })
+}
+
+#[tauri::command]
+fn list_match_presets() -> Vec<MatchPreset> {
+    all_match_presets()
+}
+
+// Result: AutoMatchResult
+#[tauri::command]
+fn auto_match_intensity(
+    reference: EQProfile,
+    input: EQProfile,
+    config: MatchConfig,
+    search_smoothing: Option<bool>,
+    app: tauri::AppHandle,
+) -> JobId {
+    spawn_job(&app, "auto_match_intensity", move |_, _| {
+        validate_match_config(&config)?;
+        Ok(auto_match(
+            &reference,
+            &input,
+            &config,
+            search_smoothing.unwrap_or(false),
+        ))
+    })
+}
+
+// Result: HeadphoneMatch
+#[tauri::command]
+fn match_headphone(
+    measurement_path: String,
+    target: HeadphoneTarget,
+    app: tauri::AppHandle,
+) -> JobId {
+    spawn_job(&app, "match_headphone", move |_, _| {
+        let text = std::fs::read_to_string(&measurement_path).map_err(|e| {
+            AppError::with_details(ErrorCode::FileOpen, e.to_string()).in_file(&measurement_path)
+        })?;
+        let measurement =
+            parse_measurement(&text).map_err(|e| invalid(e).in_file(&measurement_path))?;
+        let bands = AnalysisConfig::default().frequency_bands;
+
+        Ok(run_headphone_match(&measurement, &target.curve(), &bands))
+    })
+}
+
+#[tauri::command]
+fn compute_filter_response(
+    bands: Vec<FrequencyBand>,
+    sample_rate: u32,
+    n_points: usize,
+) -> FilterResponse {
+    filter_response(&bands, sample_rate, n_points)
+}
+
+// Invert, add, subtract or scale, e.g. a match minus the room correction
+#[tauri::command]
+fn profile_arithmetic(profile: EQProfile, op: ProfileOp) -> Result<EQProfile, AppError> {
+    apply_profile_op(&profile, &op)
+        .map_err(|e| AppError::with_details(ErrorCode::InvalidArgument, e))
+}
+
+// "After" curve for the UI without rendering audio
+#[tauri::command]
+fn predict_corrected_profile(
+    input: EQProfile,
+    correction: EQProfile,
+    sample_rate: Option<u32>,
+) -> EQProfile {
+    predict_profile(
+        &input,
+        &correction,
+        sample_rate.unwrap_or(DEFAULT_PREDICTION_RATE),
+    )
+}
+
+// Per-band rationale for the chosen gains
+#[tauri::command]
+fn explain_correction(result: MatchResult) -> Vec<BandExplanation> {
+    explain_bands(&result)
+}
+
+#[tauri::command]
+async fn export_eq_settings(
+    result: MatchResult,
+    format: String, // Any id from `list_export_formats`
+    state: tauri::State<'_, AppState>,
+) -> Result<String, AppError> {
+    export_result_with_context(&result, &format, &export_context(&state)).map_err(invalid)
+}
+
+// Every requested format of the current result into `out_dir`, with a
+// manifest. Files are named after `name`, or the input file by default.
+// Result: BundleManifest
+#[tauri::command]
+fn export_all(
+    formats: Vec<String>,
+    out_dir: String,
+    name: Option<String>,
+    mastering_notes: Option<bool>, // Loudness sidecar; decodes input and reference
+    app: tauri::AppHandle,
+) -> JobId {
+    spawn_job(&app, "export_all", move |state, ctx| {
+        let result =
+            current_result(state).ok_or_else(|| not_loaded("No match result available"))?;
+        let name = export_name(state, name);
+
+        let context = export_context(state);
+        let notes = match mastering_notes {
+            Some(true) => {
+                ctx.progress("measuring", 0.0)?;
+                Some(
+                    measure_mastering_notes(&result, &context, &ctx.cancel)
+                        .map_err(|e| AppError::with_details(ErrorCode::DecodeFailed, e))?,
+                )
+            }
+            _ => None,
+        };
+        ctx.progress("writing", 0.9)?;
+
+        Ok(export_bundle(
+            &result,
+            &formats,
+            Path::new(&out_dir),
+            &name,
+            &context,
+            notes.as_ref(),
+        )?)
+    })
+}
+
+// Only a bare name, never a path out of the output folder
+fn export_name(state: &AppState, name: Option<String>) -> String {
+    let input_path = read_active(state, |project| project.input_path.clone());
+    match (name, input_path) {
+        (Some(name), _) => Path::new(&name).file_name().map(|n| n.to_owned()),
+        (None, Some(input)) => Path::new(&input).file_stem().map(|n| n.to_owned()),
+        (None, None) => None,
+    }
+    .map(|n| n.to_string_lossy().into_owned())
+    .unwrap_or_else(|| "correction".to_string())
+}
+
+// The current correction as a linear-phase FIR WAV for convolution engines;
+// 48 kHz and 8191 taps by default
+#[tauri::command]
+async fn export_fir(
+    path: String,
+    sample_rate: Option<u32>,
+    taps: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<FirExport, AppError> {
+    let result = current_result(&state).ok_or_else(|| not_loaded("No match result available"))?;
+    Ok(export_fir_wav(
+        &result,
+        Path::new(&path),
+        sample_rate.unwrap_or(STANDARD_RATE),
+        taps.unwrap_or(DEFAULT_FIR_TAPS),
+    )?)
+}
+
+#[tauri::command]
+fn list_devices() -> Vec<DeviceConstraints> {
+    builtin_devices()
+}
+
+// How much of the current correction each device can represent, best
+// first. Defaults to the built-in devices
+#[tauri::command]
+async fn check_device_feasibility(
+    devices: Option<Vec<DeviceConstraints>>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DeviceFeasibility>, AppError> {
+    let result = current_result(&state).ok_or_else(|| not_loaded("No match result available"))?;
+    let devices = devices.unwrap_or_else(builtin_devices);
+    check_feasibility(&result.correction_profile, &devices).map_err(invalid)
+}
+
+// One preset per device into `out_dir`, named like `export_all`, with the
+// feasibility report
+#[tauri::command]
+async fn export_device_bundle(
+    out_dir: String,
+    devices: Option<Vec<DeviceConstraints>>,
+    name: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<DeviceFeasibility>, AppError> {
+    let result = current_result(&state).ok_or_else(|| not_loaded("No match result available"))?;
+    let devices = devices.unwrap_or_else(builtin_devices);
+    Ok(export_device_presets(
+        &result.correction_profile,
+        &devices,
+        Path::new(&out_dir),
+        &export_name(&state, name),
+    )?)
+}
+
+// Pushes the correction bands to an OSC receiver. A given config becomes
+// the stored default. Result: number of messages sent
+#[tauri::command]
+async fn send_correction_osc(
+    result: MatchResult,
+    config: Option<OscConfig>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, AppError> {
+    let config = match config {
+        Some(config) => {
+            let mut store = state.settings.lock().unwrap();
+            let mut settings = store.get().clone();
+            settings.osc = config.clone();
+            store.update(settings).map_err(io_error)?;
+            config
+        }
+        None => state.settings.lock().unwrap().get().osc.clone(),
+    };
+    osc::send_profile(&result.correction_profile, &config).map_err(io_error)
+}
+
+// Same as `send_correction_osc`, through the MIDI mapping table
+#[tauri::command]
+async fn send_correction_midi(
+    result: MatchResult,
+    config: Option<MidiConfig>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, AppError> {
+    let config = match config {
+        Some(config) => {
+            let mut store = state.settings.lock().unwrap();
+            let mut settings = store.get().clone();
+            settings.midi = config.clone();
+            store.update(settings).map_err(io_error)?;
+            config
+        }
+        None => state.settings.lock().unwrap().get().midi.clone(),
+    };
+    midi::send_profile(&result.correction_profile, &config).map_err(io_error)
+}
+
+// Outputs `send_correction_midi` can target
+#[tauri::command]
+fn list_midi_outputs() -> Result<Vec<MidiOutputInfo>, AppError> {
+    midi::midi_outputs().map_err(|e| AppError::with_details(ErrorCode::Io, e))
+}
+
+#[tauri::command]
+fn list_export_formats() -> Vec<ExportFormat> {
+    all_export_formats()
+}
+
+// Rescans the templates folder, e.g. after the user dropped a new file in
+#[tauri::command]
+fn reload_export_templates(state: tauri::State<'_, AppState>) -> Vec<ExportFormat> {
+    if let Some(dir) = state.config_dir.get() {
+        load_templates(&dir.join(TEMPLATE_DIR));
+    }
+    all_export_formats()
+}
+
+// Result: BatchSummary. Per-file results also go out as "batch-progress"
+#[tauri::command]
+fn batch_match(
+    paths: Vec<String>,
+    reference_id: String,
+    config: MatchConfig,
+    export_format: String,
+    out_dir: String,
+    app: tauri::AppHandle,
+) -> Result<JobId, AppError> {
+    let state = app.state::<AppState>();
+    let reference = loaded_reference(&state, &reference_id)?;
+    let memory_budget = state
+        .settings
+        .lock()
+        .unwrap()
+        .get()
+        .batch_memory_mb
+        .unwrap_or(DEFAULT_BATCH_MEMORY_MB)
+        .saturating_mul(MB);
+
+    validate_match_config(&config)?;
+    if file_extension(&export_format).is_none() {
+        return Err(invalid(format!("Unknown format: {}", export_format)));
+    }
+
+    let emitter = app.clone();
+    Ok(spawn_job(&app, "batch_match", move |_, ctx| {
+        let job = BatchJob {
+            reference: &reference,
+            config: &config,
+            export_format: &export_format,
+            out_dir: Path::new(&out_dir),
+            policy: policy_from_preset(None),
+            cancel: &ctx.cancel,
+            memory_budget,
+        };
+
+        run_batch_match(&paths, &job, |event| {
+            if let BatchEvent::ItemFinished {
+                completed, total, ..
+            } = &event
+            {
+                let _ = ctx.progress("matching", *completed as f32 / *total as f32);
+            }
+            let _ = emitter.emit_all("batch-progress", event);
+        })
+        .map_err(io_error)
+    }))
+}
+
+// Result: BenchmarkReport. Times decode, analysis, matching and render on
+// generated signals, so slow machines can be told apart from regressions
+#[tauri::command]
+fn run_benchmark(app: tauri::AppHandle) -> JobId {
+    spawn_job(&app, "run_benchmark", move |_, ctx| {
+        let mut started = 0;
+        run_self_test(&std::env::temp_dir(), &ctx.cancel, |stage| {
+            let _ = ctx.progress(stage, started as f32 / 4.0);
+            started += 1;
+        })
+        .map_err(|e| AppError::with_details(ErrorCode::Internal, e))
+    })
+}
+
+// Result: RenderSummary
+#[tauri::command]
+fn render_processed_audio(
+    input_path: String,
+    output_path: String,
+    bands: Vec<FrequencyBand>,
+    config: RenderConfig,
+    app: tauri::AppHandle,
+) -> JobId {
+    spawn_job(&app, "render_processed_audio", move |_, ctx| {
+        render_file(
+            Path::new(&input_path),
+            Path::new(&output_path),
+            &bands,
+            &config,
+            &ctx.cancel,
+            |event| {
+                if let RenderEvent::Progress { stage, fraction } = event {
+                    let _ = ctx.progress(stage.label(), fraction);
+                }
+            },
+        )
+        .map_err(AppError::from)
+    })
+}
+
+// Result: ScriptOutput. Relative paths resolve against `base_dir`,
+// normally the script's own folder.
+#[tauri::command]
+fn run_pipeline_script(source: String, base_dir: String, app: tauri::AppHandle) -> JobId {
+    spawn_job(&app, "run_pipeline_script", move |_, ctx| {
+        run_script(&source, Path::new(&base_dir), &ctx.cancel).map_err(invalid)
+    })
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum QueueActionKind {
+    Analyze,
+    Match,
+    Export,
+}
+
+// Unset fields fall back to the saved settings
+#[derive(Deserialize)]
+struct QueueRequest {
+    action: QueueActionKind,
+    #[serde(default)]
+    reference_id: Option<String>, // Required for match and export
+    #[serde(default)]
+    config: Option<MatchConfig>,
+    #[serde(default)]
+    format: Option<String>,
+    #[serde(default)]
+    out_dir: Option<String>,
+}
+
+fn batch_queue(state: &AppState) -> Result<&BatchQueue, AppError> {
+    state
+        .queue
+        .get()
+        .ok_or_else(|| AppError::with_details(ErrorCode::Internal, "Queue not ready"))
+}
+
+fn queue_action(state: &AppState, request: QueueRequest) -> Result<QueueAction, AppError> {
+    if let QueueActionKind::Analyze = request.action {
+        return Ok(QueueAction::Analyze);
+    }
+
+    let reference_id = request
+        .reference_id
+        .ok_or_else(|| not_loaded("A reference is required to match"))?;
+    let reference = loaded_reference(state, &reference_id)?;
+    let settings = state.settings.lock().unwrap().get().clone();
+    let config = request.config.unwrap_or(settings.match_config);
+    validate_match_config(&config)?;
+
+    Ok(match request.action {
+        QueueActionKind::Export => QueueAction::Export {
+            reference: Arc::new(reference),
+            config,
+            format: request
+                .format
+                .or(settings.export_format)
+                .unwrap_or_else(|| "apo".to_string()),
+            out_dir: request.out_dir.or(settings.export_dir).ok_or_else(|| {
+                AppError::with_details(ErrorCode::InvalidArgument, "No export directory set")
+            })?,
+        },
+        _ => QueueAction::Match {
+            reference: Arc::new(reference),
+            config,
+        },
+    })
+}
+
+// Directories expand to the audio files inside them, like batch_match.
+// Progress arrives as "queue-event" events
+#[tauri::command]
+fn queue_enqueue(
+    paths: Vec<String>,
+    request: QueueRequest,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<QueueItemId>, AppError> {
+    let action = queue_action(&state, request)?;
+    let files: Vec<String> = collect_audio_paths(&paths)
+        .iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    Ok(batch_queue(&state)?.enqueue(&files, &action))
+}
+
+#[tauri::command]
+fn queue_status(state: tauri::State<'_, AppState>) -> Result<QueueSnapshot, AppError> {
+    Ok(batch_queue(&state)?.snapshot())
+}
+
+#[tauri::command]
+fn queue_set_paused(
+    paused: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<QueueSnapshot, AppError> {
+    let queue = batch_queue(&state)?;
+    if paused {
+        queue.pause();
+    } else {
+        queue.resume();
+    }
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+fn queue_set_workers(
+    workers: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<QueueSnapshot, AppError> {
+    let queue = batch_queue(&state)?;
+    queue.set_workers(workers);
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+fn queue_move(
+    id: QueueItemId,
+    position: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<QueueSnapshot, AppError> {
+    let queue = batch_queue(&state)?;
+    queue.move_item(id, position).map_err(invalid)?;
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+fn queue_cancel(
+    id: QueueItemId,
+    state: tauri::State<'_, AppState>,
+) -> Result<QueueSnapshot, AppError> {
+    let queue = batch_queue(&state)?;
+    queue.cancel(id).map_err(invalid)?;
+    Ok(queue.snapshot())
+}
+
+#[tauri::command]
+fn queue_clear_finished(state: tauri::State<'_, AppState>) -> Result<QueueSnapshot, AppError> {
+    let queue = batch_queue(&state)?;
+    queue.clear_finished();
+    Ok(queue.snapshot())
+}
+
+// False when the job already finished
 #[tauri::command]
-async fn load_reference_audio(path: String, preset: Option<String>) -> Result<EQProfile, String> {
-    // Load audio
-    let audio = load_audio_file(&path).map_err(|e| format!("Load error: {}", e))?;
+fn cancel_job(id: JobId, state: tauri::State<'_, AppState>) -> bool {
+    state.jobs.cancel(id)
+}
+
+#[tauri::command]
+fn list_jobs(state: tauri::State<'_, AppState>) -> Vec<JobInfo> {
+    state.jobs.active()
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Diagnostics {
+    app_version: &'static str,
+    tauri_version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    audio_backend: String,
+    log_dir: Option<String>,
+    analysis_config: AnalysisConfig,
+    match_config: Option<MatchConfig>, // Behind the current result
+    settings: Settings,
+    active_jobs: Vec<JobInfo>,
+    automation_port: Option<u16>,
+    recent_errors: Vec<LogEntry>, // Warnings and errors since launch, newest last
+}
+
+// Everything a bug report needs, in one JSON blob
+#[tauri::command]
+fn get_diagnostics(state: tauri::State<'_, AppState>) -> Diagnostics {
+    let settings = state.settings.lock().unwrap().get().clone();
+    Diagnostics {
+        app_version: env!("CARGO_PKG_VERSION"),
+        tauri_version: tauri::VERSION,
+        os: std::env::consts::OS,
+        arch: std::env::consts::ARCH,
+        audio_backend: state.audio_backend.name().to_string(),
+        log_dir: logging::log_dir().map(|d| d.to_string_lossy().into_owned()),
+        analysis_config: settings.analysis_config.clone(),
+        match_config: read_active(&state, |project| project.match_config.clone()),
+        settings,
+        active_jobs: state.jobs.active(),
+        automation_port: state.automation.lock().unwrap().as_ref().map(|s| s.port()),
+        recent_errors: logging::recent_errors(),
+    }
+}
+
+// Opens the log directory in the platform's file manager
+#[tauri::command]
+fn open_log_folder() -> Result<(), AppError> {
+    let dir = logging::log_dir()
+        .ok_or_else(|| AppError::with_details(ErrorCode::Internal, "Logging is not active"))?;
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(opener).arg(&dir).spawn()?;
+    Ok(())
+}
+
+#[tauri::command]
+fn list_audio_devices(state: tauri::State<'_, AppState>) -> Result<Vec<AudioDeviceInfo>, AppError> {
+    state
+        .audio_backend
+        .devices()
+        .map_err(|e| AppError::with_details(ErrorCode::AudioOutput, e))
+}
+
+// Passing None goes back to the system default output
+#[tauri::command]
+fn set_preview_device(
+    id: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<AudioDeviceInfo>, AppError> {
+    let device = id
+        .map(|id| find_output_device(state.audio_backend.as_ref(), &id))
+        .transpose()
+        .map_err(|e| AppError::with_details(ErrorCode::AudioOutput, e))?;
+
+    let mut store = state.settings.lock().unwrap();
+    let mut settings = store.get().clone();
+    settings.preview_device = device.as_ref().map(|d| d.id.clone());
+    store.update(settings).map_err(io_error)?;
+
+    *state.preview_device.lock().unwrap() = device.clone();
+    // Reopened on the new device at the next play
+    *state.preview_output.lock().unwrap() = None;
+
+    Ok(device)
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<'_, AppState>) -> Settings {
+    state.settings.lock().unwrap().get().clone()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SettingsUpdate {
+    settings: Settings,
+    analysis_job: Option<JobId>, // Re-analysis when the analysis config changed; Result: BandGridUpdate
+}
 
-    // Resample policy:
-    // - Default: avoid unnecessary upsampling (big speed win for 44.1kHz sources).
-    // - "legacy": keep previous behavior (always resample to 48kHz).
-    // - "no-resample": never resample.
-    let standard_rate = 48000;
-    let policy = match preset.as_deref() {
-        Some("legacy") => ResamplePolicy::Always,
-        Some("no-resample") => ResamplePolicy::Never,
-        _ => ResamplePolicy::DownsampleOnly,
+// Replaces the stored settings wholesale and persists them. A changed
+// analysis config also brings the active project onto it, as a job.
+#[tauri::command]
+fn update_settings(settings: Settings, app: tauri::AppHandle) -> Result<SettingsUpdate, AppError> {
+    validate_analysis_config(&settings.analysis_config)?;
+    validate_match_config(&settings.match_config)?;
+    let state = app.state::<AppState>();
+    let mut store = state.settings.lock().unwrap();
+    let current = store.get();
+    let analysis_config = settings.analysis_config.clone();
+    let analysis_changed = analysis_config != current.analysis_config;
+    // The token belongs to the running API, not to the settings form; a new
+    // analysis config is stored by apply_analysis_config below
+    let settings = Settings {
+        automation_token: current.automation_token.clone(),
+        analysis_config: current.analysis_config.clone(),
+        ..settings
+    };
+    store.update(settings.clone()).map_err(io_error)?;
+    drop(store);
+    set_locale(settings.locale);
+
+    let analysis_job = if analysis_changed {
+        Some(apply_analysis_config(
+            &app,
+            "update_settings",
+            analysis_config.clone(),
+        )?)
+    } else {
+        None
     };
-    let (samples, analyzed_rate) =
-        prepare_audio_for_analysis(audio.samples, audio.sample_rate, standard_rate, policy)
-            .map_err(|e| format!("Resample error: {}", e))?;
+    Ok(SettingsUpdate {
+        settings: Settings {
+            analysis_config,
+            ..settings
+        },
+        analysis_job,
+    })
+}
+
+// Sessions hold one project: the active one
+fn session_snapshot(state: &AppState) -> Session {
+    state.projects.read().unwrap().active().to_session()
+}
+
+fn apply_session(state: &AppState, session: Session) {
+    with_active(state, |project| project.load_session(session));
+}
+
+// Best effort: a failed autosave must not fail the command that triggered it
+fn autosave(state: &AppState) {
+    if let Some(dir) = state.config_dir.get() {
+        if let Err(e) = write_session(&dir.join(AUTOSAVE_FILE), &session_snapshot(state)) {
+            log::warn!("autosave failed: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+fn save_session(path: String, state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    write_session(Path::new(&path), &session_snapshot(&state)).map_err(io_error)
+}
+
+// Replaces the current state; the returned session lets the UI rehydrate
+#[tauri::command]
+fn open_session(path: String, state: tauri::State<'_, AppState>) -> Result<Session, AppError> {
+    let session = load_session(Path::new(&path))?;
+    apply_session(&state, session.clone());
+    autosave(&state);
+    Ok(session)
+}
+
+// What was restored from the autosave at launch, or the live state since
+#[tauri::command]
+fn get_session(state: tauri::State<'_, AppState>) -> Session {
+    session_snapshot(&state)
+}
+
+const MB: u64 = 1024 * 1024;
+
+#[tauri::command]
+fn get_cache_info(state: tauri::State<'_, AppState>) -> CacheInfo {
+    state.cache.lock().unwrap().info()
+}
+
+#[tauri::command]
+fn clear_cache(state: tauri::State<'_, AppState>) -> Result<CacheInfo, AppError> {
+    state.cache.lock().unwrap().clear().map_err(io_error)
+}
+
+// Evicts least recently used entries right away if over the new limit
+#[tauri::command]
+fn set_cache_limit(
+    limit_mb: u64,
+    state: tauri::State<'_, AppState>,
+) -> Result<CacheInfo, AppError> {
+    let mut store = state.settings.lock().unwrap();
+    let mut settings = store.get().clone();
+    settings.cache_limit_mb = Some(limit_mb);
+    store.update(settings).map_err(io_error)?;
+    Ok(state
+        .cache
+        .lock()
+        .unwrap()
+        .set_limit(limit_mb.saturating_mul(MB)))
+}
+
+#[tauri::command]
+fn get_recent_files(role: Option<FileRole>, state: tauri::State<'_, AppState>) -> Vec<RecentFile> {
+    state.recent.lock().unwrap().entries(role)
+}
+
+const DEFAULT_SUGGESTIONS: usize = 5;
+
+// Recent references ranked for `input_profile` by tonal similarity and how
+// often they were matched against; open one with `open_recent_file`
+#[tauri::command]
+fn suggest_references(
+    input_profile: EQProfile,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Vec<ReferenceSuggestion> {
+    state
+        .recent
+        .lock()
+        .unwrap()
+        .suggest_references(&input_profile, limit.unwrap_or(DEFAULT_SUGGESTIONS))
+}
+
+// Reopens from the profile cached for the same preset and channel mode; fails
+// if there is none or the file changed since, in which case the UI falls
+// back to a regular load
+#[tauri::command]
+fn open_recent_file(
+    path: String,
+    role: FileRole,
+    preset: Option<String>,
+    channel_mode: Option<ChannelMode>,
+    state: tauri::State<'_, AppState>,
+) -> Result<EQProfile, AppError> {
+    let profile = state
+        .recent
+        .lock()
+        .unwrap()
+        .find(&path, role, preset.as_deref(), channel_mode)
+        .map(|entry| entry.profile.clone())
+        .ok_or_else(|| not_loaded(format!("No up-to-date cached analysis for {}", path)))?;
 
-    // Analyze
-    let config = AnalysisConfig::default();
-    let spectrum = analyze_spectrum(&samples, analyzed_rate, &config);
-    let profile = extract_eq_profile(&spectrum, &config);
+    set_loaded_profile(&state, active_project(&state), role, path, &profile, None)?;
 
     Ok(profile)
 }
 
+// Looks `id` up as a loaded reference, the current input, then a cached
+// recent file
+fn resolve_profile(state: &AppState, id: &str) -> Option<EQProfile> {
+    let loaded = read_active(state, |project| {
+        project.references.get(id).cloned().or_else(|| {
+            (project.input_path.as_deref() == Some(id))
+                .then(|| project.input_profile.clone())
+                .flatten()
+        })
+    });
+    if loaded.is_some() {
+        return loaded;
+    }
+    let recent = state.recent.lock().unwrap();
+    [FileRole::Input, FileRole::Reference]
+        .into_iter()
+        .find_map(|role| recent.find_latest(id, role))
+        .map(|entry| entry.profile.clone())
+}
+
+// Spectrogram of `source` as a PNG at `path`, e.g. for reports. Uses the
+// analysis FFT size and window. Result: the path written
 #[tauri::command]
-async fn load_input_audio(path: String, preset: Option<String>) -> Result<EQProfile, String> {
-    load_reference_audio(path, preset).await // Same process
+fn export_spectrogram(
+    source: String,
+    path: String,
+    options: Option<SpectrogramImageOptions>,
+    app: tauri::AppHandle,
+) -> JobId {
+    spawn_job(&app, "export_spectrogram", move |state, ctx| {
+        ctx.progress("analyzing", 0.0)?;
+        let (samples, rate) =
+            load_mono_for_analysis(&source, policy_from_preset(None), &ctx.cancel)
+                .map_err(|e| AppError::from(e).in_file(&source))?;
+        ctx.cancel.check()?;
+
+        let spectrogram = compute_spectrogram(
+            &samples,
+            rate,
+            &analysis_config(state),
+            DEFAULT_SPECTROGRAM_ROWS,
+            DEFAULT_MAX_COLUMNS,
+        )
+        .map_err(invalid)?;
+        ctx.cancel.check()?;
+        ctx.progress("rendering", 0.9)?;
+        let png = render_spectrogram_png(&spectrogram, &options.unwrap_or_default())
+            .map_err(|e| AppError::with_details(ErrorCode::InvalidArgument, e))?;
+        write_atomic(Path::new(&path), png).map_err(io_error)?;
+        Ok(path)
+    })
 }
 
+// Checks e.g. a DAW bounce against the app's own render. Result: NullTestResult
 #[tauri::command]
-async fn calculate_eq_match(
-    reference: EQProfile,
-    input: EQProfile,
+fn null_test(path_a: String, path_b: String, app: tauri::AppHandle) -> JobId {
+    spawn_job(&app, "null_test", move |_, ctx| {
+        ctx.progress("comparing", 0.0)?;
+        run_null_test(&path_a, &path_b, &ctx.cancel).map_err(|e| AppError::from(e).in_file(&path_b))
+    })
+}
+
+// The project's input path with the given reference, or the current one
+fn input_and_reference(
+    state: &AppState,
+    project: ProjectId,
+    reference_id: Option<&str>,
+) -> Result<(String, EQProfile), AppError> {
+    let (path, reference) = read_project(state, project, |project| {
+        let reference = match reference_id {
+            Some(id) => project.references.get(id).cloned(),
+            None => project.reference_profile.clone(),
+        };
+        (project.input_path.clone(), reference)
+    })?;
+    let path = path.ok_or_else(|| not_loaded("No input loaded"))?;
+    let reference = reference.ok_or_else(|| not_loaded("No reference loaded"))?;
+    Ok((path, reference))
+}
+
+// Min/max/RMS peaks of the loaded reference or input, per channel, for
+// drawing its waveform. Result: WaveformOverview
+#[tauri::command]
+fn waveform_overview(role: FileRole, points: Option<usize>, app: tauri::AppHandle) -> JobId {
+    let project = active_project(&app.state::<AppState>());
+    spawn_job(&app, "waveform_overview", move |state, ctx| {
+        let path = read_project(state, project, |project| match role {
+            FileRole::Reference => project.reference_path.clone(),
+            FileRole::Input => project.input_path.clone(),
+        })?
+        .ok_or_else(|| not_loaded("No file loaded for that role"))?;
+
+        ctx.progress("decoding", 0.0)?;
+        let audio = load_audio_channels_cancellable(&path, &ctx.cancel)
+            .map_err(|e| AppError::from(e).in_file(&path))?;
+        ctx.cancel.check()?;
+        let overview: WaveformOverview = downsample_waveform(
+            &audio.channels,
+            audio.sample_rate,
+            points.unwrap_or(DEFAULT_WAVEFORM_POINTS),
+        )
+        .map_err(|e| AppError::with_details(ErrorCode::InvalidArgument, e))?;
+        Ok(overview)
+    })
+}
+
+// Where along the loaded input it departs from a reference (the current one
+// by default), per band and segment. Result: DeviationMap
+#[tauri::command]
+fn deviation_over_time(
+    reference_id: Option<String>,
+    segment_secs: Option<f32>,
+    app: tauri::AppHandle,
+) -> JobId {
+    let project = active_project(&app.state::<AppState>());
+    spawn_job(&app, "deviation_over_time", move |state, ctx| {
+        let (path, reference) = input_and_reference(state, project, reference_id.as_deref())?;
+
+        ctx.progress("analyzing", 0.0)?;
+        let (samples, rate) = load_mono_for_analysis(&path, policy_from_preset(None), &ctx.cancel)
+            .map_err(|e| AppError::from(e).in_file(&path))?;
+        ctx.cancel.check()?;
+
+        let map: DeviationMap = run_deviation(
+            &samples,
+            rate,
+            &reference,
+            &analysis_config(state),
+            segment_secs.unwrap_or(DEFAULT_SEGMENT_SECS),
+        )
+        .map_err(invalid)?;
+        Ok(map)
+    })
+}
+
+// A correction per section of the loaded input, for automation where one
+// static EQ can't fix it. Without boundaries, sections are found from the
+// deviation map. Result: SectionMatch
+#[tauri::command]
+fn match_sections(
+    reference_id: Option<String>,
+    boundaries_secs: Option<Vec<f32>>,
     config: MatchConfig,
-) -> Result<MatchResult, String> {
-    Ok(match_profiles(&reference, &input, &config))
+    app: tauri::AppHandle,
+) -> JobId {
+    let project = active_project(&app.state::<AppState>());
+    spawn_job(&app, "match_sections", move |state, ctx| {
+        validate_match_config(&config)?;
+        let (path, reference) = input_and_reference(state, project, reference_id.as_deref())?;
+
+        ctx.progress("analyzing", 0.0)?;
+        let (samples, rate) = load_mono_for_analysis(&path, policy_from_preset(None), &ctx.cancel)
+            .map_err(|e| AppError::from(e).in_file(&path))?;
+        ctx.cancel.check()?;
+
+        let sections: SectionMatch = run_section_match(
+            &samples,
+            rate,
+            &reference,
+            &analysis_config(state),
+            &config,
+            boundaries_secs.as_deref(),
+        )
+        .map_err(invalid)?;
+        Ok(sections)
+    })
 }
 
+// "reaper" (FX chain with gain envelopes) or "json"
 #[tauri::command]
-async fn export_eq_settings(
-    result: MatchResult,
-    format: String, // "reaper", "json", "txt"
-) -> Result<String, String> {
-    match format.as_str() {
-        "reaper" => export_as_reaper_preset(&result.correction_profile),
-        "json" => {
-            serde_json::to_string_pretty(&result.correction_profile).map_err(|e| e.to_string())
+async fn export_section_automation(
+    sections: SectionMatch,
+    format: String,
+) -> Result<String, AppError> {
+    export_sections(&sections, &format).map_err(invalid)
+}
+
+#[tauri::command]
+fn list_target_curves(state: tauri::State<'_, AppState>) -> Vec<TargetCurve> {
+    state.curves.lock().unwrap().list()
+}
+
+// Creates or replaces the curve called `name` from (Hz, dB) anchors
+#[tauri::command]
+fn save_target_curve(
+    name: String,
+    points: Vec<(f32, f32)>,
+    state: tauri::State<'_, AppState>,
+) -> Result<TargetCurve, AppError> {
+    state
+        .curves
+        .lock()
+        .unwrap()
+        .save(&name, points)
+        .map_err(|e| AppError::with_details(ErrorCode::InvalidArgument, e))
+}
+
+#[tauri::command]
+fn delete_target_curve(id: String, state: tauri::State<'_, AppState>) -> Result<bool, AppError> {
+    state.curves.lock().unwrap().delete(&id).map_err(io_error)
+}
+
+// The curve on the current band grid, usable as the reference of a match
+#[tauri::command]
+fn target_curve_profile(
+    id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<EQProfile, AppError> {
+    let bands = analysis_config(&state).frequency_bands;
+    state
+        .curves
+        .lock()
+        .unwrap()
+        .get(&id)
+        .map(|curve| curve.profile(&bands))
+        .ok_or_else(|| not_loaded(format!("No target curve: {}", id)))
+}
+
+// Writes `profile` as a shareable .sfprofile, with a chart unless told not to
+#[tauri::command]
+fn export_shared_profile(
+    profile: EQProfile,
+    info: ShareInfo,
+    include_preview: Option<bool>,
+    path: String,
+) -> Result<SharedProfile, AppError> {
+    let shared = create_shared_profile(&profile, info, include_preview.unwrap_or(true))
+        .map_err(|e| AppError::with_details(ErrorCode::InvalidArgument, e))?;
+    write_shared_profile(Path::new(&path), &shared).map_err(io_error)?;
+    Ok(shared)
+}
+
+// Verifies a downloaded profile; its `profile` comes back on the current
+// band grid, ready to use as a reference
+#[tauri::command]
+fn import_shared_profile(
+    path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<SharedProfile, AppError> {
+    let mut shared = read_shared_profile(Path::new(&path))?;
+    shared.profile = shared.profile_on_grid(&analysis_config(&state).frequency_bands);
+    Ok(shared)
+}
+
+#[tauri::command]
+fn shared_profile_schema() -> String {
+    SHARE_SCHEMA.to_string()
+}
+
+// Before/after/target chart of the current match as a PNG, for posting
+#[tauri::command]
+fn export_share_card(
+    path: String,
+    options: Option<ShareCardOptions>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    let result = current_result(&state).ok_or_else(|| not_loaded("No match result available"))?;
+    let png = render_share_card(&result, &options.unwrap_or_default())
+        .map_err(|e| AppError::with_details(ErrorCode::InvalidArgument, e))?;
+    write_atomic(Path::new(&path), png).map_err(io_error)?;
+    Ok(())
+}
+
+// N x N similarity, tilt and loudness differences, e.g. across an album
+#[tauri::command]
+fn compare_profile_set(
+    ids: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ProfileComparison, AppError> {
+    let profiles = ids
+        .iter()
+        .map(|id| {
+            resolve_profile(&state, id)
+                .ok_or_else(|| not_loaded(format!("Profile not loaded: {}", id)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let refs: Vec<&EQProfile> = profiles.iter().collect();
+
+    compare_profiles(ids, &refs).map_err(invalid)
+}
+
+#[derive(Deserialize)]
+struct LoadRequest {
+    path: String,
+    #[serde(default)]
+    preset: Option<String>,
+    #[serde(default)]
+    channel_mode: Option<ChannelMode>,
+    #[serde(default)]
+    bands_only: bool,
+}
+
+#[derive(Deserialize, Default)]
+struct MatchRequest {
+    #[serde(default)]
+    config: Option<MatchConfig>, // Defaults to the configured match settings
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(request: &Request) -> Result<T, Response> {
+    serde_json::from_slice(&request.body).map_err(|e| Response::error(400, &e.to_string()))
+}
+
+/// Routes of the local automation API. Long operations answer with a job
+/// id; their progress and results are pushed to `/events` subscribers.
+fn automation_route(app: &tauri::AppHandle, request: &Request) -> Response {
+    let state = app.state::<AppState>();
+    let job = |id: JobId| Response::json(&serde_json::json!({ "job_id": id }));
+
+    match (request.method.as_str(), request.segments().as_slice()) {
+        ("GET", ["status"]) => read_active(&state, |project| {
+            Response::json(&serde_json::json!({
+                "reference_path": project.reference_path,
+                "input_path": project.input_path,
+                "has_result": project.match_result.is_some(),
+                "result_stale": project.result_stale(),
+                "revision": project.revision,
+                "jobs": state.jobs.active(),
+            }))
+        }),
+        ("POST", [target @ ("reference" | "input")]) => {
+            let role = if *target == "reference" {
+                FileRole::Reference
+            } else {
+                FileRole::Input
+            };
+            match parse_body::<LoadRequest>(request) {
+                Ok(body) => job(start_load(
+                    app,
+                    role,
+                    body.path,
+                    body.preset,
+                    body.channel_mode,
+                    body.bands_only,
+                )),
+                Err(response) => response,
+            }
+        }
+        ("POST", ["match"]) => {
+            let body = if request.body.is_empty() {
+                MatchRequest::default()
+            } else {
+                match parse_body::<MatchRequest>(request) {
+                    Ok(body) => body,
+                    Err(response) => return response,
+                }
+            };
+            let (reference, input) = read_active(&state, |project| {
+                (
+                    project.reference_profile.clone(),
+                    project.input_profile.clone(),
+                )
+            });
+            let (Some(reference), Some(input)) = (reference, input) else {
+                return Response::error(409, "Load a reference and an input first");
+            };
+            let config = body
+                .config
+                .unwrap_or_else(|| state.settings.lock().unwrap().get().match_config.clone());
+            if let Err(e) = validate_match_config(&config) {
+                return Response::error(400, &e.to_string());
+            }
+            job(start_match(app, reference, input, config))
         }
-        "txt" => export_as_text(&result.correction_profile),
-        _ => Err("Unknown format".to_string()),
+        ("GET", ["result"]) => match current_result(&state) {
+            Some(result) => Response::json(&result),
+            None => Response::error(409, "No match result yet"),
+        },
+        ("GET", ["export"]) => {
+            let format = request
+                .query
+                .get("format")
+                .map(|f| f.as_str())
+                .unwrap_or("apo");
+            match current_result(&state) {
+                Some(result) => {
+                    match export_result_with_context(&result, format, &export_context(&state)) {
+                        Ok(text) => Response::text(text),
+                        Err(e) => Response::error(400, &e),
+                    }
+                }
+                None => Response::error(409, "No match result yet"),
+            }
+        }
+        ("GET", ["jobs"]) => Response::json(&state.jobs.active()),
+        ("POST", ["jobs", id, "cancel"]) => match id.parse::<JobId>() {
+            Ok(id) => Response::json(&serde_json::json!({ "cancelled": state.jobs.cancel(id) })),
+            Err(_) => Response::error(400, "Invalid job id"),
+        },
+        (_, ["status" | "reference" | "input" | "match" | "result" | "export" | "jobs", ..]) => {
+            Response::error(405, "Method not allowed")
+        }
+        _ => Response::not_found(),
+    }
+}
+
+// Replaces any running server; port 0 picks a free one
+fn start_automation(app: &tauri::AppHandle, port: u16) -> Result<u16, AppError> {
+    let state = app.state::<AppState>();
+    let mut slot = state.automation.lock().unwrap();
+    *slot = None;
+
+    let router = app.clone();
+    let server = AutomationServer::start(
+        port,
+        automation_token(&state)?,
+        Arc::new(move |request| automation_route(&router, request)),
+    )
+    .map_err(io_error)?;
+    let port = server.port();
+    *slot = Some(server);
+    Ok(port)
+}
+
+// The API's bearer token, created and saved on first use
+fn automation_token(state: &AppState) -> Result<String, AppError> {
+    let mut store = state.settings.lock().unwrap();
+    if let Some(token) = &store.get().automation_token {
+        return Ok(token.clone());
     }
+    let token = generate_token().map_err(|e| AppError::with_details(ErrorCode::Internal, e))?;
+    let mut settings = store.get().clone();
+    settings.automation_token = Some(token.clone());
+    store.update(settings).map_err(io_error)?;
+    Ok(token)
+}
+
+// Returns the port actually bound, which is remembered for next launch
+#[tauri::command]
+fn start_automation_server(port: u16, app: tauri::AppHandle) -> Result<u16, AppError> {
+    let bound = start_automation(&app, port)?;
+
+    let state = app.state::<AppState>();
+    let mut store = state.settings.lock().unwrap();
+    let mut settings = store.get().clone();
+    settings.automation_port = Some(bound);
+    store.update(settings).map_err(io_error)?;
+
+    Ok(bound)
 }
 
-fn export_as_reaper_preset(profile: &EQProfile) -> Result<String, String> {
-    let mut output = String::from("<FXCHAIN\n");
-    output.push_str("WNDRECT 0 0 0 0\n");
-    output.push_str("SHOW 0\n");
-    output.push_str("LASTSEL 0\n");
-    output.push_str("DOCKED 0\n");
-    output.push_str("<VST \"VST: ReaEQ (Cockos)\" ReaEQ.vst.dylib 0 \"\" 1919247729\n");
+#[tauri::command]
+fn stop_automation_server(state: tauri::State<'_, AppState>) -> Result<(), AppError> {
+    *state.automation.lock().unwrap() = None;
+
+    let mut store = state.settings.lock().unwrap();
+    let mut settings = store.get().clone();
+    settings.automation_port = None;
+    store.update(settings).map_err(io_error)
+}
 
-    // ReaEQ bands
-    for (i, band) in profile.bands.iter().enumerate().take(10) {
-        let base_param = i * 5;
+// Loads settings, recent files and the autosaved session from the config dir, and
+// re-selects the saved preview device if it is still present
+fn restore_app_state(app: &tauri::App) {
+    let Some(dir) = app.path_resolver().app_config_dir() else {
+        return;
+    };
+    let state = app.state::<AppState>();
+    if let Ok(session) = load_session(&dir.join(AUTOSAVE_FILE)) {
+        apply_session(&state, session);
+    }
 
-        // Enable band
-        output.push_str(&format!("  {} 1.0\n", base_param));
+    let store = SettingsStore::load(dir.join(SETTINGS_FILE));
 
-        // Frequency (normalized 0-1)
-        let freq_norm =
-            (band.frequency.log2() - 20.0f32.log2()) / (20000.0f32.log2() - 20.0f32.log2());
-        output.push_str(&format!("  {} {}\n", base_param + 1, freq_norm));
+    *state.preview_device.lock().unwrap() = store
+        .get()
+        .preview_device
+        .as_deref()
+        .and_then(|id| find_output_device(state.audio_backend.as_ref(), id).ok());
+    let automation_port = store.get().automation_port;
+    let removed = init_journal(dir.join(JOURNAL_FILE));
+    if !removed.is_empty() {
+        log::warn!(
+            "removed unfinished files from an interrupted run: {:?}",
+            removed
+        );
+    }
+    let cache_limit_mb = store.get().cache_limit_mb.unwrap_or(DEFAULT_CACHE_LIMIT_MB);
+    *state.cache.lock().unwrap() =
+        ProfileCache::new(dir.join(CACHE_DIR), cache_limit_mb.saturating_mul(MB));
+    set_locale(store.get().locale);
+    *state.settings.lock().unwrap() = store;
+    *state.recent.lock().unwrap() = RecentFiles::load(dir.join(RECENT_FILE));
+    *state.curves.lock().unwrap() = CurveStore::load(dir.join(CURVES_FILE));
+    let templates = load_templates(&dir.join(TEMPLATE_DIR));
+    log::info!("{} export template(s) loaded", templates.len());
+    let _ = state.config_dir.set(dir);
 
-        // Gain (normalized, ±18dB range)
-        let gain_norm = (band.gain_db + 18.0) / 36.0;
-        output.push_str(&format!("  {} {}\n", base_param + 2, gain_norm));
+    // A taken port shouldn't stop the app from starting
+    if let Some(port) = automation_port {
+        if let Err(e) = start_automation(&app.handle(), port) {
+            log::warn!("automation API not started: {}", e);
+        }
+    }
+}
 
-        // Q
-        output.push_str(&format!("  {} 0.5\n", base_param + 3));
+// Audio files load as the input, sessions replace the active project's
+// state; "open-requested" tells the UI to collect the results
+fn open_files(app: &tauri::AppHandle, paths: Vec<PathBuf>) {
+    let state = app.state::<AppState>();
+    for path in paths {
+        let mut request = OpenRequest::new(&path);
+        match request.kind {
+            FileKind::Audio => {
+                request.job_id = Some(start_load(
+                    app,
+                    FileRole::Input,
+                    request.path.clone(),
+                    None,
+                    None,
+                    false,
+                ));
+            }
+            FileKind::Session => match load_session(&path) {
+                Ok(session) => {
+                    apply_session(&state, session);
+                    autosave(&state);
+                }
+                Err(e) => request.error = Some(e.to_string()),
+            },
+            FileKind::Measurement | FileKind::Unsupported => {
+                request.error = Some(format!("Unsupported file: {}", request.path));
+            }
+        }
+        log::info!("opened: {} ({:?})", request.path, request.kind);
+        state.open_requests.lock().unwrap().push(request);
+    }
+    let _ = app.emit_all("open-requested", ());
+}
 
-        // Type (Bell)
-        output.push_str(&format!("  {} 0.4\n", base_param + 4));
+// Files from a later launch or from Finder, while the app is running
+fn open_forwarded_files(app: &tauri::AppHandle, paths: Vec<PathBuf>) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    if !paths.is_empty() {
+        open_files(app, paths);
     }
+}
+
+// Hands opened files to the UI once each; later calls return only newer ones
+#[tauri::command]
+fn take_open_requests(state: tauri::State<'_, AppState>) -> Vec<OpenRequest> {
+    std::mem::take(&mut *state.open_requests.lock().unwrap())
+}
+
+// Classifies dropped files so the UI can route each one; nothing is loaded
+#[tauri::command]
+fn plan_dropped_files(paths: Vec<String>, state: tauri::State<'_, AppState>) -> Vec<DropRoute> {
+    let has_reference = read_active(&state, |project| project.reference_profile.is_some());
+    plan_drop(&paths, has_reference)
+}
+
+fn with_preview<T>(
+    state: &AppState,
+    f: impl FnOnce(&mut PreviewEngine) -> T,
+) -> Result<T, AppError> {
+    state
+        .preview
+        .lock()
+        .unwrap()
+        .as_mut()
+        .map(f)
+        .ok_or_else(|| not_loaded("No file loaded for preview"))
+}
 
-    output.push_str(">\n");
-    output.push_str("FLOATPOS 0 0 0 0\n");
-    output.push_str("FXID {GUID}\n");
-    output.push_str("WAK 0 0\n");
-    output.push_str(">\n");
+// Replaces the preview, blind if an ABX test is running
+fn install_preview(state: &AppState, mut engine: PreviewEngine) -> PreviewStatus {
+    engine.set_blind(state.abx.lock().unwrap().is_some());
+    let status = engine.status();
+    *state.preview.lock().unwrap() = Some(engine);
+    status
+}
 
-    Ok(output)
+// Result: PreviewStatus
+#[tauri::command]
+fn preview_load(path: String, app: tauri::AppHandle) -> JobId {
+    spawn_job(&app, "preview_load", move |state, ctx| {
+        ctx.progress("decoding", 0.0)?;
+        let audio = load_audio_channels_cancellable(&path, &ctx.cancel)
+            .map_err(|e| AppError::from(e).in_file(&path))?;
+        ctx.cancel.check()?;
+        Ok(install_preview(state, PreviewEngine::new(audio)))
+    })
 }
 
-fn export_as_text(profile: &EQProfile) -> Result<String, String> {
-    let mut output = String::from("EQ Settings:\n\n");
+const TEST_SIGNAL_RATE: u32 = 48_000;
+const TEST_SIGNAL_LEVEL_DB: f32 = -18.0;
 
-    for band in &profile.bands {
-        output.push_str(&format!(
-            "{:>6} Hz: {:>+6.2} dB (Q: {:.2})\n",
-            band.frequency as i32,
-            band.gain_db,
-            calculate_q_from_bandwidth(band.frequency, band.bandwidth)
+// Generated signals play through the same engine as files, so bands, solo
+// and the limiter all apply to them
+#[tauri::command]
+async fn preview_load_test_signal(
+    signal: TestSignal,
+    duration_secs: f64,
+    level_db: Option<f32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    if !(duration_secs > 0.0 && duration_secs <= 600.0) {
+        return Err(AppError::with_details(
+            ErrorCode::InvalidArgument,
+            format!("Test signal duration: {}", duration_secs),
         ));
     }
 
-    Ok(output)
+    let sample_rate = state
+        .preview_device
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|d| {
+            let rates = d.sample_rates.as_ref()?;
+            rates
+                .iter()
+                .copied()
+                .find(|&r| r == TEST_SIGNAL_RATE)
+                .or_else(|| rates.first().copied())
+        })
+        .unwrap_or(TEST_SIGNAL_RATE);
+
+    let audio = tauri::async_runtime::spawn_blocking(move || {
+        generate(
+            &signal,
+            sample_rate,
+            duration_secs,
+            level_db.unwrap_or(TEST_SIGNAL_LEVEL_DB),
+            2,
+        )
+    })
+    .await
+    .map_err(|e| AppError::with_details(ErrorCode::Internal, e.to_string()))?;
+    Ok(install_preview(&state, PreviewEngine::new(audio)))
+}
+
+// Warble tone matching one band of the current correction
+#[tauri::command]
+fn test_signal_for_band(
+    index: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<TestSignal, AppError> {
+    let result = current_result(&state).ok_or_else(|| not_loaded("No match result available"))?;
+
+    result
+        .correction_profile
+        .bands
+        .get(index)
+        .map(TestSignal::for_band)
+        .ok_or_else(|| {
+            AppError::with_details(
+                ErrorCode::InvalidArgument,
+                format!("Band index out of range: {}", index),
+            )
+        })
+}
+
+// Opens the output stream for the loaded file, or reopens it when the file's
+// rate or channel count changed or the device went away
+fn ensure_preview_output(state: &AppState) -> Result<(), AppError> {
+    let (sample_rate, channels) =
+        with_preview(state, |engine| (engine.sample_rate(), engine.channels()))?;
+    let device = state.preview_device.lock().unwrap().clone();
+    // Mono plays on both sides; the device may take fewer channels than the file
+    let channels = match device.as_ref().and_then(|d| d.max_channels) {
+        Some(max_channels) => channels.max(2).min(max_channels.max(1) as usize),
+        None => channels.max(2),
+    };
+
+    let mut output = state.preview_output.lock().unwrap();
+    if output
+        .as_ref()
+        .is_some_and(|o| o.fits(sample_rate, channels))
+    {
+        return Ok(());
+    }
+    *output = None;
+    let sink = state
+        .audio_backend
+        .open_output(device.as_ref(), sample_rate, channels)
+        .map_err(|e| AppError::with_details(ErrorCode::AudioOutput, e))?;
+    *output = Some(PreviewOutput::start(
+        state.preview.clone(),
+        sink,
+        sample_rate,
+        channels,
+    ));
+    Ok(())
+}
+
+#[tauri::command]
+fn preview_play(state: tauri::State<'_, AppState>) -> Result<PreviewStatus, AppError> {
+    ensure_preview_output(&state)?;
+    with_preview(&state, |engine| {
+        engine.play();
+        engine.status()
+    })
+}
+
+#[tauri::command]
+fn preview_pause(state: tauri::State<'_, AppState>) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.pause();
+        engine.status()
+    })
+}
+
+#[tauri::command]
+fn preview_seek(
+    position_secs: f64,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.seek(position_secs);
+        engine.status()
+    })
+}
+
+#[tauri::command]
+fn preview_set_loop(
+    start_secs: Option<f64>,
+    end_secs: Option<f64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_loop(start_secs.zip(end_secs));
+        engine.status()
+    })
+}
+
+// Loop length when the caller doesn't give one; long enough for a phrase
+const PROBLEM_LOOP_SECS: f32 = 8.0;
+
+// Loops the stretch of the preview file that deviates most from a reference
+// (the current one by default). Result: PreviewStatus
+#[tauri::command]
+fn preview_loop_worst_section(
+    reference_id: Option<String>,
+    length_secs: Option<f32>,
+    app: tauri::AppHandle,
+) -> JobId {
+    spawn_job(&app, "preview_loop_worst_section", move |state, ctx| {
+        let reference = match &reference_id {
+            Some(id) => loaded_reference(state, id)?,
+            None => read_active(state, |project| project.reference_profile.clone())
+                .ok_or_else(|| not_loaded("No reference loaded"))?,
+        };
+        let (samples, rate) =
+            with_preview(state, |engine| (engine.mixdown(), engine.sample_rate()))?;
+
+        ctx.progress("analyzing", 0.0)?;
+        let map = run_deviation(
+            &samples,
+            rate,
+            &reference,
+            &analysis_config(state),
+            DEFAULT_SEGMENT_SECS,
+        )
+        .map_err(invalid)?;
+        ctx.cancel.check()?;
+
+        let (start, end) = worst_region(&map, length_secs.unwrap_or(PROBLEM_LOOP_SECS))
+            .ok_or_else(|| {
+                AppError::with_details(ErrorCode::NoAudioData, "The preview file is silent")
+            })?;
+        with_preview(state, |engine| {
+            engine.set_loop(Some((start as f64, end as f64)));
+            engine.status()
+        })
+    })
+}
+
+#[tauri::command]
+fn preview_set_bands(
+    bands: Vec<FrequencyBand>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<MatchWarning>, AppError> {
+    with_preview(&state, |engine| engine.set_bands(&bands))
+}
+
+#[tauri::command]
+fn preview_set_bypass(
+    bypassed: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_bypass(bypassed);
+        engine.status()
+    })
+}
+
+#[tauri::command]
+fn preview_set_delta(
+    delta: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_delta(delta);
+        engine.status()
+    })
+}
+
+#[tauri::command]
+fn preview_set_solo(
+    band: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_solo(band).map_err(invalid)?;
+        Ok(engine.status())
+    })?
+}
+
+#[tauri::command]
+fn preview_set_muted(
+    band: usize,
+    muted: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_muted(band, muted).map_err(invalid)?;
+        Ok(engine.status())
+    })?
+}
+
+#[tauri::command]
+fn preview_set_oversampling(
+    factor: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_oversampling(factor).map_err(invalid)?;
+        Ok(engine.status())
+    })?
+}
+
+#[tauri::command]
+fn preview_set_linear_phase(
+    enabled: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_linear_phase(enabled).map_err(invalid)?;
+        Ok(engine.status())
+    })?
+}
+
+// Without an explicit delta the last match result's correction loudness is used
+#[tauri::command]
+fn preview_set_loudness_compensation(
+    enabled: bool,
+    delta_db: Option<f32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    let delta_db = if enabled {
+        let from_result = current_result(&state).map(|r| r.correction_loudness_delta_db);
+        Some(
+            delta_db
+                .or(from_result)
+                .ok_or_else(|| not_loaded("No match result to compensate for"))?,
+        )
+    } else {
+        None
+    };
+
+    with_preview(&state, |engine| {
+        engine.set_loudness_compensation(delta_db);
+        engine.status()
+    })
+}
+
+// Defaults compare the current match's correction (A) against the original (B)
+#[tauri::command]
+fn abx_start(
+    a: Option<AbxCandidate>,
+    b: Option<AbxCandidate>,
+    state: tauri::State<'_, AppState>,
+) -> Result<AbxStatus, AppError> {
+    let a = match a {
+        Some(a) => a,
+        None => {
+            let result =
+                current_result(&state).ok_or_else(|| not_loaded("No match result to test"))?;
+            AbxCandidate {
+                bands: result.correction_profile.bands,
+                loudness_delta_db: result.correction_loudness_delta_db,
+            }
+        }
+    };
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(1);
+    let session = AbxSession::new(a, b.unwrap_or_default(), seed);
+    let status = session.status();
+    *state.abx.lock().unwrap() = Some(session);
+    // Nothing may be loaded yet; `install_preview` covers that
+    let _ = with_preview(&state, |engine| engine.set_blind(true));
+    Ok(status)
+}
+
+// Switches the preview to A, B or the hidden X, level-matched
+#[tauri::command]
+fn abx_select(side: AbxSide, state: tauri::State<'_, AppState>) -> Result<AbxStatus, AppError> {
+    let mut abx = state.abx.lock().unwrap();
+    let session = abx
+        .as_mut()
+        .ok_or_else(|| not_loaded("No ABX test running"))?;
+    let candidate = session.select(side).clone();
+    with_preview(&state, |engine| {
+        engine.set_bands(&candidate.bands);
+        engine.set_loudness_compensation(Some(candidate.loudness_delta_db));
+    })?;
+    Ok(session.status())
+}
+
+#[tauri::command]
+fn abx_guess(guess: AbxSide, state: tauri::State<'_, AppState>) -> Result<AbxStatus, AppError> {
+    let mut abx = state.abx.lock().unwrap();
+    let session = abx
+        .as_mut()
+        .ok_or_else(|| not_loaded("No ABX test running"))?;
+    session
+        .guess(guess)
+        .map_err(|e| AppError::with_details(ErrorCode::InvalidArgument, e))
+}
+
+// Ends the test and reveals every trial
+#[tauri::command]
+fn abx_finish(state: tauri::State<'_, AppState>) -> Result<AbxReport, AppError> {
+    let session = state
+        .abx
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| not_loaded("No ABX test running"))?;
+    let _ = with_preview(&state, |engine| engine.set_blind(false));
+    Ok(session.report())
+}
+
+#[tauri::command]
+fn preview_set_dynamic_bands(
+    bands: Vec<DynamicBand>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), AppError> {
+    with_preview(&state, |engine| engine.set_dynamic_bands(&bands))
+}
+
+#[tauri::command]
+fn preview_set_limiter(
+    enabled: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| {
+        engine.set_limiter(enabled);
+        engine.status()
+    })
+}
+
+#[tauri::command]
+fn preview_meter(state: tauri::State<'_, AppState>) -> Result<PreviewMeter, AppError> {
+    with_preview(&state, |engine| engine.meter())
 }
 
-fn calculate_q_from_bandwidth(center_freq: f32, bandwidth: f32) -> f32 {
-    center_freq / bandwidth
+#[tauri::command]
+fn preview_status(state: tauri::State<'_, AppState>) -> Result<PreviewStatus, AppError> {
+    with_preview(&state, |engine| engine.status())
 }
 
 fn main() {
+    let context = tauri::generate_context!();
+    let launch_files = launch_paths(std::env::args_os());
+    // A second launch hands its files to the running instance and quits
+    if let Some(dir) = tauri::api::path::app_config_dir(context.config()) {
+        if forward_to_running(&dir, &launch_files) {
+            return;
+        }
+    }
+
     tauri::Builder::default()
         .manage(AppState {
-            reference_profile: Mutex::new(None),
-            input_profile: Mutex::new(None),
-            match_result: Mutex::new(None),
+            projects: RwLock::new(ProjectStore::new()),
+            preview: Arc::new(Mutex::new(None)),
+            preview_output: Mutex::new(None),
+            audio_backend: default_backend(),
+            preview_device: Mutex::new(None),
+            jobs: JobManager::new(),
+            settings: Mutex::new(SettingsStore::default()),
+            recent: Mutex::new(RecentFiles::default()),
+            curves: Mutex::new(CurveStore::default()),
+            abx: Mutex::new(None),
+            cache: Mutex::new(ProfileCache::default()),
+            config_dir: OnceLock::new(),
+            automation: Mutex::new(None),
+            queue: OnceLock::new(),
+            open_requests: Mutex::new(Vec::new()),
+        })
+        .setup(|app| {
+            if let Some(dir) = app.path_resolver().app_log_dir() {
+                if let Err(e) = logging::init(&dir, LevelFilter::Info) {
+                    eprintln!("Logging disabled: {}", e);
+                }
+            }
+            log::info!("starting {}", env!("CARGO_PKG_VERSION"));
+
+            let emitter = app.handle();
+            let queue = BatchQueue::new(
+                DEFAULT_WORKERS,
+                Arc::new(move |event| {
+                    let _ = emitter.emit_all("queue-event", event);
+                }),
+            );
+            let _ = app.state::<AppState>().queue.set(queue);
+            restore_app_state(app);
+            if let Some(dir) = app.path_resolver().app_config_dir() {
+                let handle = app.handle();
+                if let Err(e) = listen(&dir, move |paths| open_forwarded_files(&handle, paths)) {
+                    log::warn!("later launches will start their own window: {}", e);
+                }
+            }
+            #[cfg(target_os = "macos")]
+            {
+                let handle = app.handle();
+                install_open_handler(move |paths| open_forwarded_files(&handle, paths));
+            }
+            open_files(&app.handle(), launch_files);
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            take_open_requests,
+            plan_dropped_files,
+            list_projects,
+            create_project,
+            set_active_project,
+            rename_project,
+            close_project,
             load_reference_audio,
             load_input_audio,
             calculate_eq_match,
+            set_band_grid,
+            set_analysis_config,
+            get_analysis_config,
+            set_band_override,
+            undo_match_change,
+            redo_match_change,
+            match_history,
+            get_diagnostics,
+            open_log_folder,
+            calculate_blended_match,
+            list_match_presets,
+            auto_match_intensity,
+            match_headphone,
+            compute_filter_response,
+            profile_arithmetic,
+            predict_corrected_profile,
+            explain_correction,
             export_eq_settings,
+            export_all,
+            list_devices,
+            check_device_feasibility,
+            export_device_bundle,
+            export_fir,
+            list_export_formats,
+            send_correction_osc,
+            send_correction_midi,
+            list_midi_outputs,
+            reload_export_templates,
+            batch_match,
+            render_processed_audio,
+            run_benchmark,
+            run_pipeline_script,
+            list_audio_devices,
+            set_preview_device,
+            cancel_job,
+            get_settings,
+            update_settings,
+            save_session,
+            open_session,
+            get_session,
+            get_recent_files,
+            get_cache_info,
+            clear_cache,
+            set_cache_limit,
+            open_recent_file,
+            suggest_references,
+            compare_profile_set,
+            list_target_curves,
+            save_target_curve,
+            delete_target_curve,
+            target_curve_profile,
+            export_shared_profile,
+            import_shared_profile,
+            shared_profile_schema,
+            export_share_card,
+            waveform_overview,
+            deviation_over_time,
+            match_sections,
+            export_section_automation,
+            null_test,
+            export_spectrogram,
+            start_automation_server,
+            stop_automation_server,
+            list_jobs,
+            queue_enqueue,
+            queue_status,
+            queue_set_paused,
+            queue_set_workers,
+            queue_move,
+            queue_cancel,
+            queue_clear_finished,
+            preview_load,
+            preview_load_test_signal,
+            test_signal_for_band,
+            preview_play,
+            preview_pause,
+            preview_seek,
+            preview_set_loop,
+            preview_loop_worst_section,
+            preview_set_bands,
+            preview_set_bypass,
+            preview_set_delta,
+            preview_set_loudness_compensation,
+            preview_set_solo,
+            preview_set_oversampling,
+            preview_set_linear_phase,
+            preview_set_muted,
+            preview_set_dynamic_bands,
+            abx_start,
+            abx_select,
+            abx_guess,
+            abx_finish,
+            preview_set_limiter,
+            preview_meter,
+            preview_status,
         ])
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }
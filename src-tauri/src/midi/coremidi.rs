@@ -0,0 +1,136 @@
+use crate::preview::coreaudio::{take_string, CfString, CfStringRef};
+use std::ffi::c_void;
+use std::ptr;
+
+type MidiObjectRef = u32;
+type OsStatus = i32;
+
+// Packet list header plus per-packet timestamp and length, rounded up
+const LIST_OVERHEAD: usize = 4;
+const PACKET_OVERHEAD: usize = 16;
+
+#[link(name = "CoreMIDI", kind = "framework")]
+extern "C" {
+    static kMIDIPropertyDisplayName: CfStringRef;
+
+    fn MIDIClientCreate(
+        name: CfStringRef,
+        notify: *const c_void,
+        notify_context: *mut c_void,
+        client: *mut MidiObjectRef,
+    ) -> OsStatus;
+    fn MIDIClientDispose(client: MidiObjectRef) -> OsStatus;
+    fn MIDIOutputPortCreate(
+        client: MidiObjectRef,
+        name: CfStringRef,
+        port: *mut MidiObjectRef,
+    ) -> OsStatus;
+    fn MIDIGetNumberOfDestinations() -> usize;
+    fn MIDIGetDestination(index: usize) -> MidiObjectRef;
+    fn MIDIObjectGetStringProperty(
+        object: MidiObjectRef,
+        property: CfStringRef,
+        value: *mut CfStringRef,
+    ) -> OsStatus;
+    fn MIDIPacketListInit(list: *mut c_void) -> *mut c_void;
+    fn MIDIPacketListAdd(
+        list: *mut c_void,
+        list_size: usize,
+        packet: *mut c_void,
+        time: u64,
+        length: usize,
+        data: *const u8,
+    ) -> *mut c_void;
+    fn MIDISend(port: MidiObjectRef, destination: MidiObjectRef, list: *const c_void) -> OsStatus;
+}
+
+fn destinations() -> Vec<(MidiObjectRef, String)> {
+    (0..unsafe { MIDIGetNumberOfDestinations() })
+        .filter_map(|index| {
+            let endpoint = unsafe { MIDIGetDestination(index) };
+            if endpoint == 0 {
+                return None;
+            }
+            let mut name: CfStringRef = ptr::null();
+            let status = unsafe {
+                MIDIObjectGetStringProperty(endpoint, kMIDIPropertyDisplayName, &mut name)
+            };
+            if status != 0 {
+                return None;
+            }
+            Some((endpoint, take_string(name)?))
+        })
+        .collect()
+}
+
+// Destinations by display name ("Device Port"), as Audio MIDI Setup shows them
+pub fn output_names() -> Vec<String> {
+    destinations().into_iter().map(|(_, name)| name).collect()
+}
+
+pub fn send(port: &str, messages: &[Vec<u8>]) -> Result<(), String> {
+    let destination = destinations()
+        .into_iter()
+        .find(|(_, name)| name == port)
+        .map(|(endpoint, _)| endpoint)
+        .ok_or_else(|| format!("MIDI port not found: {}", port))?;
+
+    let name = CfString::new("EQ Matcher");
+    let mut client: MidiObjectRef = 0;
+    let status = unsafe { MIDIClientCreate(name.0, ptr::null(), ptr::null_mut(), &mut client) };
+    if status != 0 {
+        return Err(format!(
+            "CoreMIDI: cannot create a client (status {})",
+            status
+        ));
+    }
+    let mut output: MidiObjectRef = 0;
+    let status = unsafe { MIDIOutputPortCreate(client, name.0, &mut output) };
+    let sent = if status != 0 {
+        Err(format!(
+            "CoreMIDI: cannot create a port (status {})",
+            status
+        ))
+    } else {
+        send_packets(output, destination, messages).map_err(|e| format!("{}: {}", port, e))
+    };
+    // Disposing the client also disposes its port
+    unsafe { MIDIClientDispose(client) };
+    sent
+}
+
+// All messages in one packet list, timestamped "now"
+fn send_packets(
+    port: MidiObjectRef,
+    destination: MidiObjectRef,
+    messages: &[Vec<u8>],
+) -> Result<(), String> {
+    let size = LIST_OVERHEAD
+        + messages
+            .iter()
+            .map(|m| m.len() + PACKET_OVERHEAD)
+            .sum::<usize>();
+    // u32 words keep the list aligned as CoreMIDI expects
+    let mut buffer = vec![0u32; size.div_ceil(4)];
+    let list = buffer.as_mut_ptr() as *mut c_void;
+    let mut packet = unsafe { MIDIPacketListInit(list) };
+    for message in messages {
+        packet = unsafe {
+            MIDIPacketListAdd(
+                list,
+                buffer.len() * 4,
+                packet,
+                0,
+                message.len(),
+                message.as_ptr(),
+            )
+        };
+        if packet.is_null() {
+            return Err("too much data for one packet list".to_string());
+        }
+    }
+    match unsafe { MIDISend(port, destination, list) } {
+        0 => Ok(()),
+        status => Err(format!("send failed (status {})", status)),
+    }
+}
@@ -0,0 +1,277 @@
+#[cfg(target_os = "macos")]
+mod coremidi;
+#[cfg(windows)]
+mod winmm;
+
+use crate::atomic_file::write_atomic;
+use crate::audio::profile::EQProfile;
+use crate::osc::{normalized_band, strongest_bands};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+const CONTROL_CHANGE: u8 = 0xB0;
+// Outputs naming a system MIDI port rather than a path
+const PORT_PREFIX: &str = "port:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BandParameter {
+    Frequency,
+    Gain,
+    Q,
+}
+
+/// How one parameter is sent. SysEx templates are hex bytes from `F0` to
+/// `F7`; `vv` is replaced by the 7-bit value, `vh`/`vl` by the high and low
+/// halves of the 14-bit value and `ch` by the channel (0-15).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MidiMessage {
+    ControlChange { controller: u8 },
+    SysEx { template: String },
+}
+
+/// One row of the mapping table: a parameter of a band slot (counted from
+/// 1 in frequency order) and the message it goes out as.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiMapping {
+    pub band: usize,
+    pub parameter: BandParameter,
+    pub message: MidiMessage,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiConfig {
+    pub output: Option<String>, // "port:<name>", a raw MIDI device (e.g. /dev/snd/midiC1D0) or a .syx file
+    pub channel: u8,            // 1 - 16
+    pub max_bands: Option<usize>, // Slots get the largest moves
+    pub mappings: Vec<MidiMapping>,
+}
+
+impl Default for MidiConfig {
+    // Four bands on the general-purpose controllers 20 - 31
+    fn default() -> Self {
+        let parameters = [
+            BandParameter::Frequency,
+            BandParameter::Gain,
+            BandParameter::Q,
+        ];
+        let mappings = (0..4)
+            .flat_map(|slot| {
+                parameters
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, &parameter)| MidiMapping {
+                        band: slot + 1,
+                        parameter,
+                        message: MidiMessage::ControlChange {
+                            controller: (20 + slot * 3 + i) as u8,
+                        },
+                    })
+            })
+            .collect();
+
+        Self {
+            output: None,
+            channel: 1,
+            max_bands: Some(4),
+            mappings,
+        }
+    }
+}
+
+/// Encodes the mapped parameters of the correction bands, one message per
+/// mapping row. Rows for slots past the last band are skipped.
+pub fn midi_messages(profile: &EQProfile, config: &MidiConfig) -> Result<Vec<Vec<u8>>, String> {
+    if !(1..=16).contains(&config.channel) {
+        return Err(format!("MIDI channel must be 1-16, got {}", config.channel));
+    }
+    let channel = config.channel - 1;
+    let bands = strongest_bands(profile, config.max_bands);
+
+    let mut messages = Vec::new();
+    for mapping in &config.mappings {
+        let Some(band) = mapping.band.checked_sub(1).and_then(|i| bands.get(i)) else {
+            continue;
+        };
+        let (frequency, gain, q) = normalized_band(band);
+        let value = match mapping.parameter {
+            BandParameter::Frequency => frequency,
+            BandParameter::Gain => gain,
+            BandParameter::Q => q,
+        };
+
+        messages.push(match &mapping.message {
+            MidiMessage::ControlChange { controller } => {
+                if *controller > 0x7F {
+                    return Err(format!("Controller must be 0-127, got {}", controller));
+                }
+                vec![
+                    CONTROL_CHANGE | channel,
+                    *controller,
+                    (value * 127.0).round() as u8,
+                ]
+            }
+            MidiMessage::SysEx { template } => encode_sysex(template, channel, value)?,
+        });
+    }
+    Ok(messages)
+}
+
+/// A MIDI output; `id` is what goes into `MidiConfig::output`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MidiOutputInfo {
+    pub id: String,
+    pub name: String,
+}
+
+/// The MIDI outputs of the system: winmm ports on Windows, CoreMIDI
+/// destinations on macOS and raw ALSA MIDI devices on Linux.
+pub fn midi_outputs() -> Result<Vec<MidiOutputInfo>, String> {
+    #[cfg(any(windows, target_os = "macos"))]
+    {
+        #[cfg(windows)]
+        let names = winmm::output_names();
+        #[cfg(target_os = "macos")]
+        let names = coremidi::output_names();
+        Ok(names
+            .into_iter()
+            .map(|name| MidiOutputInfo {
+                id: format!("{}{}", PORT_PREFIX, name),
+                name,
+            })
+            .collect())
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        raw_midi_devices()
+    }
+}
+
+/// Writes the messages to the configured output. Returns the number of
+/// messages written.
+pub fn send_profile(profile: &EQProfile, config: &MidiConfig) -> Result<usize, String> {
+    let output = config
+        .output
+        .as_deref()
+        .ok_or("No MIDI output configured")?;
+    let messages = midi_messages(profile, config)?;
+    match output.strip_prefix(PORT_PREFIX) {
+        Some(port) => send_to_port(port, &messages)?,
+        None => write_output(Path::new(output), &messages.concat())?,
+    }
+    Ok(messages.len())
+}
+
+// Device nodes take the bytes as they are; files are replaced whole so a
+// failed write doesn't leave half a .syx behind
+fn write_output(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    match fs::metadata(path) {
+        Ok(metadata) if !metadata.is_file() => {
+            fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+        }
+        _ => write_atomic(path, bytes),
+    }
+}
+
+#[cfg(windows)]
+fn send_to_port(port: &str, messages: &[Vec<u8>]) -> Result<(), String> {
+    winmm::send(port, messages)
+}
+
+#[cfg(target_os = "macos")]
+fn send_to_port(port: &str, messages: &[Vec<u8>]) -> Result<(), String> {
+    coremidi::send(port, messages)
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn send_to_port(port: &str, _messages: &[Vec<u8>]) -> Result<(), String> {
+    Err(format!(
+        "MIDI port {} not found: use the device path, e.g. /dev/snd/midiC1D0",
+        port
+    ))
+}
+
+// /dev/snd/midiC<card>D<device>, named after the card's rawmidi entry in
+// /proc/asound when there is one
+#[cfg(not(any(windows, target_os = "macos")))]
+fn raw_midi_devices() -> Result<Vec<MidiOutputInfo>, String> {
+    let entries = match fs::read_dir("/dev/snd") {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut devices: Vec<MidiOutputInfo> = entries
+        .filter_map(|entry| {
+            let file = entry.ok()?.file_name().into_string().ok()?;
+            let (card, device) = file.strip_prefix("midiC")?.split_once('D')?;
+            let info = format!("/proc/asound/card{}/midi{}", card, device);
+            let name = fs::read_to_string(info)
+                .ok()
+                .and_then(|text| text.lines().next().map(|l| l.trim().to_string()))
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| file.clone());
+            Some(MidiOutputInfo {
+                id: format!("/dev/snd/{}", file),
+                name,
+            })
+        })
+        .collect();
+    devices.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(devices)
+}
+
+fn encode_sysex(template: &str, channel: u8, value: f32) -> Result<Vec<u8>, String> {
+    let fine = (value * 16383.0).round() as u16;
+    let bytes = template
+        .split_whitespace()
+        .map(|token| match token.to_ascii_lowercase().as_str() {
+            "vv" => Ok((value * 127.0).round() as u8),
+            "vh" => Ok((fine >> 7) as u8),
+            "vl" => Ok((fine & 0x7F) as u8),
+            "ch" => Ok(channel),
+            _ => u8::from_str_radix(token, 16).map_err(|_| format!("Bad SysEx byte: {}", token)),
+        })
+        .collect::<Result<Vec<u8>, String>>()?;
+
+    let framed = bytes.len() >= 2 && bytes[0] == SYSEX_START && bytes[bytes.len() - 1] == SYSEX_END;
+    if !framed || bytes[1..bytes.len() - 1].iter().any(|&b| b > 0x7F) {
+        return Err(format!("Not a SysEx message: {}", template));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("eq-matcher-midi-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn syx_files_are_replaced_whole() {
+        let dir = test_dir("syx");
+        let path = dir.join("profile.syx");
+        fs::write(&path, [0u8; 64]).unwrap();
+
+        write_output(&path, &[SYSEX_START, 0x01, SYSEX_END]).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), vec![SYSEX_START, 0x01, SYSEX_END]);
+        // Nothing but the target is left in the directory
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(not(any(windows, target_os = "macos")))]
+    #[test]
+    fn ports_point_to_device_paths() {
+        let error = send_to_port("Nothing", &[]).unwrap_err();
+        assert!(error.contains("/dev/snd/"));
+    }
+}
@@ -0,0 +1,145 @@
+use std::ffi::c_void;
+use std::ptr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const MMSYSERR_NOERROR: u32 = 0;
+const CALLBACK_NULL: u32 = 0;
+const MHDR_DONE: u32 = 0x1;
+const SYSEX_START: u8 = 0xF0;
+// Slow interfaces take about 0.3 ms per byte; anything beyond this is stuck
+const SYSEX_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[repr(C)]
+struct MidiOutCaps {
+    mid: u16,
+    pid: u16,
+    driver_version: u32,
+    name: [u16; 32],
+    technology: u16,
+    voices: u16,
+    notes: u16,
+    channel_mask: u16,
+    support: u32,
+}
+
+#[repr(C)]
+struct MidiHdr {
+    data: *mut u8,
+    buffer_length: u32,
+    bytes_recorded: u32,
+    user: usize,
+    flags: u32,
+    next: *mut MidiHdr,
+    reserved: usize,
+    offset: u32,
+    reserved_driver: [usize; 8],
+}
+
+type HMidiOut = *mut c_void;
+
+#[link(name = "winmm")]
+extern "system" {
+    fn midiOutGetNumDevs() -> u32;
+    fn midiOutGetDevCapsW(device: usize, caps: *mut MidiOutCaps, size: u32) -> u32;
+    fn midiOutOpen(
+        handle: *mut HMidiOut,
+        device: u32,
+        callback: usize,
+        instance: usize,
+        flags: u32,
+    ) -> u32;
+    fn midiOutShortMsg(handle: HMidiOut, message: u32) -> u32;
+    fn midiOutPrepareHeader(handle: HMidiOut, header: *mut MidiHdr, size: u32) -> u32;
+    fn midiOutUnprepareHeader(handle: HMidiOut, header: *mut MidiHdr, size: u32) -> u32;
+    fn midiOutLongMsg(handle: HMidiOut, header: *mut MidiHdr, size: u32) -> u32;
+    fn midiOutReset(handle: HMidiOut) -> u32;
+    fn midiOutClose(handle: HMidiOut) -> u32;
+}
+
+// Output ports by index, as the system numbers them
+pub fn output_names() -> Vec<String> {
+    (0..unsafe { midiOutGetNumDevs() })
+        .map(|index| {
+            let mut caps: MidiOutCaps = unsafe { std::mem::zeroed() };
+            let size = std::mem::size_of::<MidiOutCaps>() as u32;
+            if unsafe { midiOutGetDevCapsW(index as usize, &mut caps, size) } != MMSYSERR_NOERROR {
+                return String::new();
+            }
+            let len = caps.name.iter().position(|&c| c == 0).unwrap_or(32);
+            String::from_utf16_lossy(&caps.name[..len])
+        })
+        .collect()
+}
+
+pub fn send(port: &str, messages: &[Vec<u8>]) -> Result<(), String> {
+    let index = output_names()
+        .iter()
+        .position(|name| name == port)
+        .ok_or_else(|| format!("MIDI port not found: {}", port))?;
+
+    let mut handle: HMidiOut = ptr::null_mut();
+    let result = unsafe { midiOutOpen(&mut handle, index as u32, 0, 0, CALLBACK_NULL) };
+    if result != MMSYSERR_NOERROR {
+        return Err(format!("{}: cannot open the port (error {})", port, result));
+    }
+    let sent = messages
+        .iter()
+        .try_for_each(|message| send_message(handle, message))
+        .map_err(|e| format!("{}: {}", port, e));
+    unsafe { midiOutClose(handle) };
+    sent
+}
+
+fn send_message(handle: HMidiOut, message: &[u8]) -> Result<(), String> {
+    if message.first() != Some(&SYSEX_START) {
+        // Status in the low byte, then the data bytes
+        let packed = message
+            .iter()
+            .take(3)
+            .enumerate()
+            .fold(0u32, |acc, (i, &b)| acc | (b as u32) << (8 * i));
+        return match unsafe { midiOutShortMsg(handle, packed) } {
+            MMSYSERR_NOERROR => Ok(()),
+            error => Err(format!("send failed (error {})", error)),
+        };
+    }
+
+    let mut bytes = message.to_vec();
+    let mut header = MidiHdr {
+        data: bytes.as_mut_ptr(),
+        buffer_length: bytes.len() as u32,
+        bytes_recorded: 0,
+        user: 0,
+        flags: 0,
+        next: ptr::null_mut(),
+        reserved: 0,
+        offset: 0,
+        reserved_driver: [0; 8],
+    };
+    let size = std::mem::size_of::<MidiHdr>() as u32;
+    let result = unsafe { midiOutPrepareHeader(handle, &mut header, size) };
+    if result != MMSYSERR_NOERROR {
+        return Err(format!("cannot prepare SysEx (error {})", result));
+    }
+    let result = unsafe { midiOutLongMsg(handle, &mut header, size) };
+
+    // The driver sends asynchronously and sets MHDR_DONE from its own thread;
+    // the buffer has to stay put until then
+    let deadline = Instant::now() + SYSEX_TIMEOUT;
+    let mut done = result != MMSYSERR_NOERROR;
+    while !done && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(1));
+        done = unsafe { ptr::read_volatile(&header.flags) } & MHDR_DONE != 0;
+    }
+    if !done {
+        // Hands the buffer back so it can be released
+        unsafe { midiOutReset(handle) };
+    }
+    unsafe { midiOutUnprepareHeader(handle, &mut header, size) };
+    match result {
+        MMSYSERR_NOERROR if done => Ok(()),
+        MMSYSERR_NOERROR => Err("SysEx timed out".to_string()),
+        error => Err(format!("SysEx failed (error {})", error)),
+    }
+}
@@ -0,0 +1,190 @@
+use crate::preview::coreaudio::{take_string, CfString, CfStringRef};
+use std::ffi::{c_char, c_void, CStr};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::OnceLock;
+
+type Id = *mut c_void;
+type Sel = *const c_void;
+
+// Four-character codes from AppleEvents.h and AEDataModel.h
+const CORE_EVENT_CLASS: u32 = u32::from_be_bytes(*b"aevt");
+const OPEN_DOCUMENTS: u32 = u32::from_be_bytes(*b"odoc");
+const DIRECT_OBJECT: u32 = u32::from_be_bytes(*b"----");
+const FILE_URL: u32 = u32::from_be_bytes(*b"furl");
+const POSIX_PATH_STYLE: isize = 0;
+const UTF8: u32 = 0x0800_0100;
+
+#[link(name = "objc")]
+extern "C" {
+    fn objc_getClass(name: *const c_char) -> Id;
+    fn objc_allocateClassPair(superclass: Id, name: *const c_char, extra_bytes: usize) -> Id;
+    fn objc_registerClassPair(class: Id);
+    fn class_addMethod(class: Id, name: Sel, imp: *const c_void, types: *const c_char) -> bool;
+    fn sel_registerName(name: *const c_char) -> Sel;
+    fn objc_msgSend();
+}
+
+// NSAppleEventManager and NSNotificationCenter
+#[link(name = "Foundation", kind = "framework")]
+extern "C" {}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFURLCreateWithBytes(
+        allocator: *const c_void,
+        bytes: *const u8,
+        length: isize,
+        encoding: u32,
+        base: *const c_void,
+    ) -> *const c_void;
+    fn CFURLCopyFileSystemPath(url: *const c_void, style: isize) -> CfStringRef;
+    fn CFRelease(object: *const c_void);
+}
+
+type OpenHandler = Box<dyn Fn(Vec<PathBuf>) + Send + Sync>;
+
+static ON_OPEN: OnceLock<OpenHandler> = OnceLock::new();
+
+/// Passes files opened from Finder (double-click, "Open With", drops on the
+/// Dock icon) to `on_open`, at launch and while running. macOS doesn't put
+/// these on the command line; they arrive as an "open documents" Apple
+/// Event. Only the first call installs anything.
+pub fn install_open_handler(on_open: impl Fn(Vec<PathBuf>) + Send + Sync + 'static) {
+    if ON_OPEN.set(Box::new(on_open)).is_err() {
+        return;
+    }
+    unsafe {
+        let handler = new_handler();
+        set_event_handler(handler);
+
+        // AppKit installs its own handlers as it finishes launching, over
+        // ours; installing again on this notification still catches the
+        // event for the files the app was launched with
+        let center = msg_id(class(c"NSNotificationCenter"), sel(c"defaultCenter"));
+        let name = CfString::new("NSApplicationWillFinishLaunchingNotification");
+        let add_observer: unsafe extern "C" fn(Id, Sel, Id, Sel, Id, Id) =
+            std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+        add_observer(
+            center,
+            sel(c"addObserver:selector:name:object:"),
+            handler,
+            sel(c"willFinishLaunching:"),
+            name.0 as Id,
+            ptr::null_mut(),
+        );
+    }
+}
+
+// An NSObject subclass with the two callbacks; never released
+unsafe fn new_handler() -> Id {
+    let superclass = class(c"NSObject");
+    let handler_class = objc_allocateClassPair(superclass, c"EQMatcherOpenHandler".as_ptr(), 0);
+    class_addMethod(
+        handler_class,
+        sel(c"handleOpenDocuments:withReplyEvent:"),
+        handle_open_documents as *const c_void,
+        c"v@:@@".as_ptr(),
+    );
+    class_addMethod(
+        handler_class,
+        sel(c"willFinishLaunching:"),
+        will_finish_launching as *const c_void,
+        c"v@:@".as_ptr(),
+    );
+    objc_registerClassPair(handler_class);
+    msg_id(msg_id(handler_class, sel(c"alloc")), sel(c"init"))
+}
+
+unsafe fn set_event_handler(handler: Id) {
+    let manager = msg_id(
+        class(c"NSAppleEventManager"),
+        sel(c"sharedAppleEventManager"),
+    );
+    let set_handler: unsafe extern "C" fn(Id, Sel, Id, Sel, u32, u32) =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+    set_handler(
+        manager,
+        sel(c"setEventHandler:andSelector:forEventClass:andEventID:"),
+        handler,
+        sel(c"handleOpenDocuments:withReplyEvent:"),
+        CORE_EVENT_CLASS,
+        OPEN_DOCUMENTS,
+    );
+}
+
+extern "C" fn will_finish_launching(this: Id, _: Sel, _notification: Id) {
+    unsafe { set_event_handler(this) };
+}
+
+extern "C" fn handle_open_documents(_: Id, _: Sel, event: Id, _reply: Id) {
+    let paths = unsafe { document_paths(event) };
+    if let Some(on_open) = ON_OPEN.get() {
+        on_open(paths);
+    }
+}
+
+// The direct object is a list of file URL descriptors
+unsafe fn document_paths(event: Id) -> Vec<PathBuf> {
+    let with_code: unsafe extern "C" fn(Id, Sel, u32) -> Id =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+    let at_index: unsafe extern "C" fn(Id, Sel, isize) -> Id =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+    let count: unsafe extern "C" fn(Id, Sel) -> isize =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+
+    let list = with_code(event, sel(c"paramDescriptorForKeyword:"), DIRECT_OBJECT);
+    if list.is_null() {
+        return Vec::new();
+    }
+    // Descriptor lists are 1-based
+    (1..=count(list, sel(c"numberOfItems")))
+        .filter_map(|index| {
+            let item = at_index(list, sel(c"descriptorAtIndex:"), index);
+            let url = with_code(item, sel(c"coerceToDescriptorType:"), FILE_URL);
+            if url.is_null() {
+                return None;
+            }
+            file_url_path(msg_id(url, sel(c"data")))
+        })
+        .collect()
+}
+
+// NSData holding a file:// URL, to a POSIX path
+unsafe fn file_url_path(data: Id) -> Option<PathBuf> {
+    if data.is_null() {
+        return None;
+    }
+    let bytes: unsafe extern "C" fn(Id, Sel) -> *const u8 =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+    let length: unsafe extern "C" fn(Id, Sel) -> usize =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+
+    let url = CFURLCreateWithBytes(
+        ptr::null(),
+        bytes(data, sel(c"bytes")),
+        length(data, sel(c"length")) as isize,
+        UTF8,
+        ptr::null(),
+    );
+    if url.is_null() {
+        return None;
+    }
+    let path = take_string(CFURLCopyFileSystemPath(url, POSIX_PATH_STYLE));
+    CFRelease(url);
+    path.map(PathBuf::from)
+}
+
+unsafe fn msg_id(receiver: Id, selector: Sel) -> Id {
+    let send: unsafe extern "C" fn(Id, Sel) -> Id =
+        std::mem::transmute(objc_msgSend as unsafe extern "C" fn());
+    send(receiver, selector)
+}
+
+fn class(name: &CStr) -> Id {
+    unsafe { objc_getClass(name.as_ptr()) }
+}
+
+fn sel(name: &CStr) -> Sel {
+    unsafe { sel_registerName(name.as_ptr()) }
+}
@@ -0,0 +1,168 @@
+#[cfg(target_os = "macos")]
+mod apple_events;
+
+#[cfg(target_os = "macos")]
+pub use apple_events::install_open_handler;
+
+use crate::audio::headphone::parse_measurement;
+use crate::batch::is_audio_file;
+use crate::jobs::JobId;
+use crate::recent::FileRole;
+use crate::session::SESSION_EXTENSION;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// Enough of a text export to find its data rows
+const SNIFF_BYTES: u64 = 64 * 1024;
+const MEASUREMENT_EXTENSIONS: [&str; 2] = ["csv", "frd"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    Audio,
+    Session,
+    Measurement, // Frequency response export (CSV, REW, AutoEQ)
+    Unsupported,
+}
+
+/// A file the OS asked the app to open, and what became of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenRequest {
+    pub path: String,
+    pub kind: FileKind,
+    pub job_id: Option<JobId>, // Load job for audio files
+    pub error: Option<String>,
+}
+
+impl OpenRequest {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_string_lossy().into_owned(),
+            kind: classify_path(path),
+            job_id: None,
+            error: None,
+        }
+    }
+}
+
+/// Known extensions decide directly; anything else is sniffed from the
+/// start of the file.
+pub fn classify_path(path: &Path) -> FileKind {
+    if !path.is_file() {
+        return FileKind::Unsupported;
+    }
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some(SESSION_EXTENSION) => FileKind::Session,
+        Some(e) if MEASUREMENT_EXTENSIONS.contains(&e) => FileKind::Measurement,
+        _ if is_audio_file(path) => FileKind::Audio,
+        _ => sniff_contents(path),
+    }
+}
+
+fn sniff_contents(path: &Path) -> FileKind {
+    let mut head = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(SNIFF_BYTES).read_to_end(&mut head));
+    if read.is_err() {
+        return FileKind::Unsupported;
+    }
+
+    if has_audio_signature(&head) {
+        FileKind::Audio
+    } else if parse_measurement(&String::from_utf8_lossy(&head)).is_ok() {
+        FileKind::Measurement
+    } else {
+        FileKind::Unsupported
+    }
+}
+
+fn has_audio_signature(head: &[u8]) -> bool {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+    (at(0, b"RIFF") && at(8, b"WAVE"))
+        || (at(0, b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")))
+        || at(0, b"fLaC")
+        || at(0, b"OggS")
+        || at(0, b"ID3")
+        || at(4, b"ftyp")
+        // MPEG audio frame sync
+        || (head.len() >= 2 && head[0] == 0xFF && head[1] & 0xE0 == 0xE0)
+}
+
+/// Files passed on the command line, which is how Windows and Linux hand
+/// over "open with" and double-clicked files. Flags and paths that are not
+/// existing files (e.g. dev-server arguments) are ignored. Paths come back
+/// absolute, so they can be handed to an instance started elsewhere.
+pub fn launch_paths(args: impl IntoIterator<Item = OsString>) -> Vec<PathBuf> {
+    args.into_iter()
+        .skip(1)
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .filter_map(|path| std::path::absolute(path).ok())
+        .collect()
+}
+
+/// What the frontend should do with one dropped file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DropAction {
+    AskRole { suggested: FileRole }, // Audio: load as reference or input
+    ImportProfile,
+    OpenSession,
+    Ignore { reason: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropRoute {
+    pub path: String,
+    pub kind: FileKind,
+    pub action: DropAction,
+}
+
+/// Routes dropped files in drop order. The first audio file is suggested
+/// as the reference while the project has none; only the first session
+/// is opened, since it replaces the project's state.
+pub fn plan_drop(paths: &[String], has_reference: bool) -> Vec<DropRoute> {
+    let mut reference_taken = has_reference;
+    let mut session_taken = false;
+
+    paths
+        .iter()
+        .map(|path| {
+            let kind = classify_path(Path::new(path));
+            let action = match kind {
+                FileKind::Audio => {
+                    let suggested = if reference_taken {
+                        FileRole::Input
+                    } else {
+                        FileRole::Reference
+                    };
+                    reference_taken = true;
+                    DropAction::AskRole { suggested }
+                }
+                FileKind::Measurement => DropAction::ImportProfile,
+                FileKind::Session if !session_taken => {
+                    session_taken = true;
+                    DropAction::OpenSession
+                }
+                FileKind::Session => DropAction::Ignore {
+                    reason: "Only one session can be opened at a time".to_string(),
+                },
+                FileKind::Unsupported => DropAction::Ignore {
+                    reason: "Not an audio, measurement or session file".to_string(),
+                },
+            };
+            DropRoute {
+                path: path.clone(),
+                kind,
+                action,
+            }
+        })
+        .collect()
+}
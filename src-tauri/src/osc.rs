@@ -0,0 +1,151 @@
+use crate::audio::profile::{EQProfile, FrequencyBand};
+use serde::{Deserialize, Serialize};
+use std::net::UdpSocket;
+
+const BAND_PLACEHOLDER: &str = "{band}";
+
+// Parameter ranges of a console parametric band (X32 layout), used when
+// values are sent normalized
+const FREQUENCY_RANGE: (f32, f32) = (20.0, 20000.0);
+const GAIN_RANGE_DB: (f32, f32) = (-15.0, 15.0);
+const Q_RANGE: (f32, f32) = (10.0, 0.3); // X32 runs Q from narrow to wide
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OscValueScale {
+    Raw, // Hz, dB and Q as they are
+    #[default]
+    Normalized, // 0.0 - 1.0 fader positions, as consoles expect
+}
+
+/// Where and how correction bands are sent. `{band}` in an address is
+/// replaced by the band number, counted from `first_band`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OscConfig {
+    pub host: String,
+    pub port: u16,
+    pub frequency_address: String,
+    pub gain_address: String,
+    pub q_address: String,
+    pub first_band: usize,
+    pub max_bands: Option<usize>, // Sends only the largest moves, e.g. 4 for a console EQ
+    pub scale: OscValueScale,
+}
+
+impl Default for OscConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 10023, // X32
+            frequency_address: "/ch/01/eq/{band}/f".to_string(),
+            gain_address: "/ch/01/eq/{band}/g".to_string(),
+            q_address: "/ch/01/eq/{band}/q".to_string(),
+            first_band: 1,
+            max_bands: None,
+            scale: OscValueScale::Normalized, // X32 takes 0-1 positions
+        }
+    }
+}
+
+/// Sends frequency, gain and Q of each correction band as single-float OSC
+/// messages over UDP. Returns the number of messages sent.
+pub fn send_profile(profile: &EQProfile, config: &OscConfig) -> Result<usize, String> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0)).map_err(|e| e.to_string())?;
+    let target = (config.host.as_str(), config.port);
+
+    let messages = profile_messages(profile, config);
+    for (address, value) in &messages {
+        socket
+            .send_to(&encode_message(address, *value), target)
+            .map_err(|e| format!("{}:{}: {}", config.host, config.port, e))?;
+    }
+    Ok(messages.len())
+}
+
+/// Address and value of every message `send_profile` would send.
+pub fn profile_messages(profile: &EQProfile, config: &OscConfig) -> Vec<(String, f32)> {
+    let bands = strongest_bands(profile, config.max_bands);
+    let mut messages = Vec::with_capacity(bands.len() * 3);
+    for (i, band) in bands.iter().enumerate() {
+        let number = (config.first_band + i).to_string();
+        let address = |pattern: &str| pattern.replace(BAND_PLACEHOLDER, &number);
+        let (frequency, gain, q) = match config.scale {
+            OscValueScale::Raw => (band.frequency, band.gain_db, band.q()),
+            OscValueScale::Normalized => normalized_band(band),
+        };
+        messages.push((address(&config.frequency_address), frequency));
+        messages.push((address(&config.gain_address), gain));
+        messages.push((address(&config.q_address), q));
+    }
+    messages
+}
+
+// The `max` bands with the largest moves, in frequency order
+pub(crate) fn strongest_bands(profile: &EQProfile, max: Option<usize>) -> Vec<&FrequencyBand> {
+    let mut bands: Vec<&FrequencyBand> = profile.bands.iter().collect();
+    if let Some(max) = max {
+        bands.sort_by(|a, b| b.gain_db.abs().total_cmp(&a.gain_db.abs()));
+        bands.truncate(max);
+        bands.sort_by(|a, b| a.frequency.total_cmp(&b.frequency));
+    }
+    bands
+}
+
+// Frequency, gain and Q as 0.0 - 1.0 positions on a console band
+pub(crate) fn normalized_band(band: &FrequencyBand) -> (f32, f32, f32) {
+    (
+        log_position(band.frequency, FREQUENCY_RANGE),
+        linear_position(band.gain_db, GAIN_RANGE_DB),
+        log_position(band.q(), Q_RANGE),
+    )
+}
+
+fn linear_position(value: f32, (low, high): (f32, f32)) -> f32 {
+    ((value - low) / (high - low)).clamp(0.0, 1.0)
+}
+
+fn log_position(value: f32, (low, high): (f32, f32)) -> f32 {
+    if value <= 0.0 {
+        return 0.0;
+    }
+    ((value / low).ln() / (high / low).ln()).clamp(0.0, 1.0)
+}
+
+// Address, type tags ",f", then a big-endian float; strings are
+// NUL-terminated and padded to 4 bytes
+fn encode_message(address: &str, value: f32) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(address.len() + 12);
+    push_padded(&mut packet, address);
+    push_padded(&mut packet, ",f");
+    packet.extend_from_slice(&value.to_be_bytes());
+    packet
+}
+
+fn push_padded(packet: &mut Vec<u8>, text: &str) {
+    packet.extend_from_slice(text.as_bytes());
+    let padding = 4 - text.len() % 4;
+    packet.resize(packet.len() + padding, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_is_padded_to_four_bytes() {
+        let packet = encode_message("/eq/1/gain", 0.5);
+        let mut expected = b"/eq/1/gain\0\0,f\0\0".to_vec();
+        expected.extend_from_slice(&[0x3F, 0x00, 0x00, 0x00]);
+        assert_eq!(packet, expected);
+    }
+
+    #[test]
+    fn aligned_strings_still_get_a_terminator() {
+        let packet = encode_message("/abc", -1.0);
+        assert_eq!(&packet[..8], b"/abc\0\0\0\0");
+        assert_eq!(&packet[8..12], b",f\0\0");
+        assert_eq!(packet[12..], (-1.0f32).to_be_bytes());
+        assert_eq!(packet.len() % 4, 0);
+    }
+}
@@ -0,0 +1,314 @@
+use super::device::{AudioBackend, AudioDeviceInfo, OutputSink};
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Device buffer, microseconds: short enough that A/B switches feel immediate
+const BUFFER_TIME_US: u32 = 100_000;
+// Capability probes of all devices together; a busy device answers late or not at all
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const PROBE_POLL: Duration = Duration::from_millis(10);
+const COMMON_RATES: [u32; 9] = [
+    8_000, 16_000, 22_050, 44_100, 48_000, 88_200, 96_000, 176_400, 192_000,
+];
+// Anything above these is a converting PCM, not a device limit
+const MAX_REAL_RATE: u64 = 768_000;
+const MAX_REAL_CHANNELS: u64 = 64;
+
+/// ALSA through its command-line tools: `aplay -L` and `arecord -L` list the
+/// PCMs, `--dump-hw-params` reports what each accepts, and playback streams
+/// raw float samples into `aplay`. Needs alsa-utils.
+pub struct AlsaBackend;
+
+impl AlsaBackend {
+    // None when aplay isn't installed
+    pub fn detect() -> Option<Self> {
+        Command::new("aplay")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .ok()
+            .filter(|s| s.success())
+            .map(|_| Self)
+    }
+}
+
+impl AudioBackend for AlsaBackend {
+    fn name(&self) -> &str {
+        "alsa"
+    }
+
+    // A PCM in both lists is one device. Capabilities come from the
+    // playback side when it has one
+    fn devices(&self) -> Result<Vec<AudioDeviceInfo>, String> {
+        let mut devices: Vec<AudioDeviceInfo> = Vec::new();
+        let mut probes: Vec<(String, Direction)> = Vec::new();
+        for direction in [Direction::Playback, Direction::Capture] {
+            for (id, name) in list_pcms(direction)? {
+                let output = direction == Direction::Playback;
+                if let Some(device) = devices.iter_mut().find(|d| d.id == id) {
+                    device.is_input |= !output;
+                    continue;
+                }
+                probes.push((id.clone(), direction));
+                devices.push(AudioDeviceInfo {
+                    is_default: id == "default",
+                    id,
+                    name,
+                    is_input: !output,
+                    is_output: output,
+                    sample_rates: None,
+                    max_channels: None,
+                });
+            }
+        }
+
+        for (device, capabilities) in devices.iter_mut().zip(probe_pcms(&probes)) {
+            if let Some(capabilities) = capabilities {
+                device.sample_rates = capabilities.sample_rates;
+                device.max_channels = capabilities.max_channels;
+            }
+        }
+        Ok(devices)
+    }
+
+    fn open_output(
+        &self,
+        device: Option<&AudioDeviceInfo>,
+        sample_rate: u32,
+        channels: usize,
+    ) -> Result<Box<dyn OutputSink>, String> {
+        let mut command = Command::new("aplay");
+        command
+            .args(["-q", "-t", "raw", "-f", "FLOAT_LE"])
+            .arg(format!("--rate={}", sample_rate))
+            .arg(format!("--channels={}", channels))
+            .arg(format!("--buffer-time={}", BUFFER_TIME_US));
+        if let Some(device) = device {
+            command.arg(format!("--device={}", device.id));
+        }
+        let mut child = command
+            .arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("aplay: {}", e))?;
+        let stdin = child.stdin.take().ok_or("aplay: no input pipe")?;
+
+        Ok(Box::new(AplaySink {
+            child,
+            stdin,
+            bytes: Vec::new(),
+        }))
+    }
+}
+
+struct AplaySink {
+    child: Child,
+    stdin: ChildStdin,
+    bytes: Vec<u8>, // Reused conversion buffer
+}
+
+impl OutputSink for AplaySink {
+    fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        self.bytes.clear();
+        self.bytes
+            .extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+        self.stdin
+            .write_all(&self.bytes)
+            .map_err(|e| match self.child.try_wait() {
+                Ok(Some(status)) => format!("aplay exited ({})", status),
+                _ => format!("aplay: {}", e),
+            })
+    }
+}
+
+impl Drop for AplaySink {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Playback,
+    Capture,
+}
+
+impl Direction {
+    fn tool(self) -> &'static str {
+        match self {
+            Direction::Playback => "aplay",
+            Direction::Capture => "arecord",
+        }
+    }
+}
+
+fn list_pcms(direction: Direction) -> Result<Vec<(String, String)>, String> {
+    let output = Command::new(direction.tool())
+        .arg("-L")
+        .output()
+        .map_err(|e| format!("{}: {}", direction.tool(), e))?;
+    Ok(parse_pcm_list(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// `aplay -L`: a PCM name at the start of a line, its description indented
+// below. Returns (name, description) pairs.
+fn parse_pcm_list(text: &str) -> Vec<(String, String)> {
+    let mut pcms: Vec<(String, String)> = Vec::new();
+    let mut described = false;
+    for line in text.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            described = false;
+            if line != "null" {
+                pcms.push((line.to_string(), line.to_string()));
+            }
+        } else if let Some(pcm) = pcms.last_mut().filter(|_| !described) {
+            pcm.1 = line.trim().to_string();
+            described = true;
+        }
+    }
+    pcms
+}
+
+// Starts a probe of every PCM at once, then collects what each printed,
+// so a slow or busy device costs one timeout rather than one per device
+fn probe_pcms(pcms: &[(String, Direction)]) -> Vec<Option<Capabilities>> {
+    let children: Vec<Option<Child>> = pcms
+        .iter()
+        .map(|(id, direction)| {
+            let mut command = Command::new(direction.tool());
+            command.args(["-q", "-N", "--dump-hw-params", "-t", "raw"]);
+            command.arg(format!("--device={}", id));
+            if *direction == Direction::Capture {
+                command.arg("--samples=1");
+            }
+            command
+                .arg("/dev/null")
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn()
+                .ok()
+        })
+        .collect();
+
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    children
+        .into_iter()
+        .map(|child| {
+            let mut child = child?;
+            while child.try_wait().ok()?.is_none() {
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return None;
+                }
+                thread::sleep(PROBE_POLL);
+            }
+            let mut text = String::new();
+            child.stderr.take()?.read_to_string(&mut text).ok()?;
+            parse_hw_params(&text)
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct Capabilities {
+    sample_rates: Option<Vec<u32>>,
+    max_channels: Option<u16>,
+}
+
+// `--dump-hw-params` prints each parameter as a value or an interval,
+// e.g. "CHANNELS: 2" or "RATE: [44100 192000]". Intervals only give the
+// bounds, so the common rates inside them are reported. Plug PCMs convert
+// to anything and list absurd maxima; those count as unknown.
+fn parse_hw_params(text: &str) -> Option<Capabilities> {
+    let field = |name: &str| -> Option<(u64, u64)> {
+        let value = text
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(name)?.strip_prefix(':'))?;
+        let bounds: Vec<u64> = value
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|n| n.parse().ok())
+            .collect();
+        match bounds[..] {
+            [single] => Some((single, single)),
+            [low, high] => Some((low, high)),
+            _ => None,
+        }
+    };
+
+    let rates = field("RATE");
+    let channels = field("CHANNELS");
+    if rates.is_none() && channels.is_none() {
+        return None;
+    }
+    Some(Capabilities {
+        sample_rates: rates
+            .filter(|&(_, high)| high <= MAX_REAL_RATE)
+            .map(|(low, high)| {
+                COMMON_RATES
+                    .iter()
+                    .copied()
+                    .filter(|&r| (low..=high).contains(&(r as u64)))
+                    .collect()
+            }),
+        max_channels: channels
+            .map(|(_, high)| high)
+            .filter(|&high| high <= MAX_REAL_CHANNELS)
+            .map(|high| high as u16),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcm_list_takes_the_first_description_line() {
+        let text = "null\n    Discard all samples\ndefault\n    Default ALSA Output\n    (currently PipeWire)\nhw:CARD=PCH,DEV=0\n    HDA Intel PCH, ALC892 Analog\n    Direct hardware device\n";
+        assert_eq!(
+            parse_pcm_list(text),
+            vec![
+                ("default".to_string(), "Default ALSA Output".to_string()),
+                (
+                    "hw:CARD=PCH,DEV=0".to_string(),
+                    "HDA Intel PCH, ALC892 Analog".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn hardware_params_give_rates_and_channels() {
+        let text = "HW Params of device \"hw:0,0\":\n--------------------\nACCESS:  MMAP_INTERLEAVED RW_INTERLEAVED\nFORMAT:  S16_LE S32_LE\nSUBFORMAT:  STD\nSAMPLE_BITS: [16 32]\nFRAME_BITS: [32 64]\nCHANNELS: 2\nRATE: [44100 96000]\nPERIOD_TIME: (83 371520]\n--------------------\n";
+        assert_eq!(
+            parse_hw_params(text),
+            Some(Capabilities {
+                sample_rates: Some(vec![44_100, 48_000, 88_200, 96_000]),
+                max_channels: Some(2),
+            })
+        );
+    }
+
+    #[test]
+    fn converting_pcms_report_unknown_limits() {
+        let text = "CHANNELS: [1 10000]\nRATE: [4000 4294967295)\n";
+        assert_eq!(
+            parse_hw_params(text),
+            Some(Capabilities {
+                sample_rates: None,
+                max_channels: None,
+            })
+        );
+        assert_eq!(parse_hw_params("aplay: main:831: audio open error"), None);
+    }
+}
@@ -0,0 +1,503 @@
+use super::device::{AudioBackend, AudioDeviceInfo, OutputSink};
+use std::collections::VecDeque;
+use std::ffi::{c_char, c_void};
+use std::ptr;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// Queue buffers cycling through the device, and the frames waiting ahead
+// of them; together about 150 ms at 48 kHz
+const BUFFERS: usize = 3;
+const BUFFER_FRAMES: usize = 1024;
+const QUEUE_FRAMES: usize = 4096;
+// A device that takes nothing for this long has gone away
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+const WAIT: Duration = Duration::from_millis(50);
+
+const COMMON_RATES: [f64; 9] = [
+    8_000.0, 16_000.0, 22_050.0, 44_100.0, 48_000.0, 88_200.0, 96_000.0, 176_400.0, 192_000.0,
+];
+
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    u32::from_be_bytes(*code)
+}
+
+const SYSTEM_OBJECT: AudioObjectId = 1;
+const DEVICES: u32 = fourcc(b"dev#");
+const DEFAULT_OUTPUT: u32 = fourcc(b"dOut");
+const DEFAULT_INPUT: u32 = fourcc(b"dIn ");
+const NAME: u32 = fourcc(b"lnam");
+const DEVICE_UID: u32 = fourcc(b"uid ");
+const STREAM_CONFIGURATION: u32 = fourcc(b"slay");
+const NOMINAL_SAMPLE_RATES: u32 = fourcc(b"nsr#");
+const SCOPE_GLOBAL: u32 = fourcc(b"glob");
+const SCOPE_INPUT: u32 = fourcc(b"inpt");
+const SCOPE_OUTPUT: u32 = fourcc(b"outp");
+const QUEUE_CURRENT_DEVICE: u32 = fourcc(b"aqcd");
+const FORMAT_LINEAR_PCM: u32 = fourcc(b"lpcm");
+const FORMAT_FLAGS_FLOAT_PACKED: u32 = 0x1 | 0x8;
+const UTF8: u32 = 0x0800_0100;
+
+type OsStatus = i32;
+type AudioObjectId = u32;
+pub(crate) type CfStringRef = *const c_void;
+type AudioQueueRef = *mut c_void;
+type QueueCallback = extern "C" fn(*mut c_void, AudioQueueRef, *mut AudioQueueBuffer);
+
+#[repr(C)]
+struct PropertyAddress {
+    selector: u32,
+    scope: u32,
+    element: u32,
+}
+
+#[repr(C)]
+struct StreamDescription {
+    sample_rate: f64,
+    format_id: u32,
+    format_flags: u32,
+    bytes_per_packet: u32,
+    frames_per_packet: u32,
+    bytes_per_frame: u32,
+    channels_per_frame: u32,
+    bits_per_channel: u32,
+    reserved: u32,
+}
+
+#[repr(C)]
+struct AudioQueueBuffer {
+    capacity: u32,
+    data: *mut c_void,
+    byte_size: u32,
+    user_data: *mut c_void,
+    packet_description_capacity: u32,
+    packet_descriptions: *mut c_void,
+    packet_description_count: u32,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct ValueRange {
+    minimum: f64,
+    maximum: f64,
+}
+
+#[link(name = "CoreAudio", kind = "framework")]
+extern "C" {
+    fn AudioObjectGetPropertyDataSize(
+        object: AudioObjectId,
+        address: *const PropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        size: *mut u32,
+    ) -> OsStatus;
+    fn AudioObjectGetPropertyData(
+        object: AudioObjectId,
+        address: *const PropertyAddress,
+        qualifier_size: u32,
+        qualifier: *const c_void,
+        size: *mut u32,
+        data: *mut c_void,
+    ) -> OsStatus;
+}
+
+#[link(name = "AudioToolbox", kind = "framework")]
+extern "C" {
+    fn AudioQueueNewOutput(
+        format: *const StreamDescription,
+        callback: QueueCallback,
+        user_data: *mut c_void,
+        run_loop: *const c_void,
+        run_loop_mode: CfStringRef,
+        flags: u32,
+        queue: *mut AudioQueueRef,
+    ) -> OsStatus;
+    fn AudioQueueAllocateBuffer(
+        queue: AudioQueueRef,
+        byte_size: u32,
+        buffer: *mut *mut AudioQueueBuffer,
+    ) -> OsStatus;
+    fn AudioQueueEnqueueBuffer(
+        queue: AudioQueueRef,
+        buffer: *mut AudioQueueBuffer,
+        packet_descriptions: u32,
+        descriptions: *const c_void,
+    ) -> OsStatus;
+    fn AudioQueueSetProperty(
+        queue: AudioQueueRef,
+        id: u32,
+        data: *const c_void,
+        size: u32,
+    ) -> OsStatus;
+    fn AudioQueueStart(queue: AudioQueueRef, start_time: *const c_void) -> OsStatus;
+    fn AudioQueueStop(queue: AudioQueueRef, immediate: u8) -> OsStatus;
+    fn AudioQueueDispose(queue: AudioQueueRef, immediate: u8) -> OsStatus;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFStringGetLength(string: CfStringRef) -> isize;
+    fn CFStringGetCString(
+        string: CfStringRef,
+        buffer: *mut c_char,
+        size: isize,
+        encoding: u32,
+    ) -> u8;
+    fn CFStringCreateWithBytes(
+        allocator: *const c_void,
+        bytes: *const u8,
+        length: isize,
+        encoding: u32,
+        external: u8,
+    ) -> CfStringRef;
+    fn CFRelease(object: *const c_void);
+}
+
+/// Core Audio on macOS: devices come from the HAL, playback runs through an
+/// Audio Queue, which converts float samples to the device format. Device
+/// ids are the HAL's persistent UIDs.
+pub struct CoreAudioBackend;
+
+impl AudioBackend for CoreAudioBackend {
+    fn name(&self) -> &str {
+        "coreaudio"
+    }
+
+    fn devices(&self) -> Result<Vec<AudioDeviceInfo>, String> {
+        let ids: Vec<AudioObjectId> = property_vec(SYSTEM_OBJECT, DEVICES, SCOPE_GLOBAL)
+            .ok_or("Core Audio: cannot list devices")?;
+        let default_output = property::<AudioObjectId>(SYSTEM_OBJECT, DEFAULT_OUTPUT, SCOPE_GLOBAL);
+        let default_input = property::<AudioObjectId>(SYSTEM_OBJECT, DEFAULT_INPUT, SCOPE_GLOBAL);
+
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| {
+                let uid = string_property(id, DEVICE_UID)?;
+                let outputs = channel_count(id, SCOPE_OUTPUT);
+                let inputs = channel_count(id, SCOPE_INPUT);
+                if outputs == 0 && inputs == 0 {
+                    return None;
+                }
+                let channels = if outputs > 0 { outputs } else { inputs };
+                Some(AudioDeviceInfo {
+                    name: string_property(id, NAME).unwrap_or_else(|| uid.clone()),
+                    id: uid,
+                    is_input: inputs > 0,
+                    is_output: outputs > 0,
+                    is_default: Some(id) == default_output
+                        || (outputs == 0 && Some(id) == default_input),
+                    sample_rates: sample_rates(id),
+                    max_channels: u16::try_from(channels).ok(),
+                })
+            })
+            .collect())
+    }
+
+    fn open_output(
+        &self,
+        device: Option<&AudioDeviceInfo>,
+        sample_rate: u32,
+        channels: usize,
+    ) -> Result<Box<dyn OutputSink>, String> {
+        let bytes_per_frame = (channels * 4) as u32;
+        let format = StreamDescription {
+            sample_rate: sample_rate as f64,
+            format_id: FORMAT_LINEAR_PCM,
+            format_flags: FORMAT_FLAGS_FLOAT_PACKED,
+            bytes_per_packet: bytes_per_frame,
+            frames_per_packet: 1,
+            bytes_per_frame,
+            channels_per_frame: channels as u32,
+            bits_per_channel: 32,
+            reserved: 0,
+        };
+
+        let shared = Arc::new(Shared {
+            fifo: Mutex::new(VecDeque::new()),
+            space: Condvar::new(),
+        });
+        let user_data = Arc::into_raw(shared.clone()) as *mut c_void;
+        let mut queue: AudioQueueRef = ptr::null_mut();
+        // No run loop: callbacks come on the queue's own thread
+        let status = unsafe {
+            AudioQueueNewOutput(
+                &format,
+                fill_buffer,
+                user_data,
+                ptr::null(),
+                ptr::null(),
+                0,
+                &mut queue,
+            )
+        };
+        if status != 0 {
+            unsafe { drop(Arc::from_raw(user_data as *const Shared)) };
+            return Err(format!(
+                "Core Audio: cannot play {} Hz, {} channels (status {})",
+                sample_rate, channels, status
+            ));
+        }
+        let sink = QueueSink {
+            queue,
+            user_data,
+            shared,
+            capacity: QUEUE_FRAMES * channels,
+        };
+
+        if let Some(device) = device {
+            let uid = CfString::new(&device.id);
+            let status = unsafe {
+                AudioQueueSetProperty(
+                    queue,
+                    QUEUE_CURRENT_DEVICE,
+                    &uid.0 as *const CfStringRef as *const c_void,
+                    std::mem::size_of::<CfStringRef>() as u32,
+                )
+            };
+            if status != 0 {
+                return Err(format!(
+                    "Core Audio: cannot use {} (status {})",
+                    device.name, status
+                ));
+            }
+        }
+
+        for _ in 0..BUFFERS {
+            let mut buffer: *mut AudioQueueBuffer = ptr::null_mut();
+            let bytes = BUFFER_FRAMES as u32 * bytes_per_frame;
+            if unsafe { AudioQueueAllocateBuffer(queue, bytes, &mut buffer) } != 0 {
+                return Err("Core Audio: cannot allocate queue buffers".to_string());
+            }
+            // Primed with silence; the callback refills them from then on
+            fill_buffer(user_data, queue, buffer);
+        }
+        let status = unsafe { AudioQueueStart(queue, ptr::null()) };
+        if status != 0 {
+            return Err(format!(
+                "Core Audio: cannot start playback (status {})",
+                status
+            ));
+        }
+        Ok(Box::new(sink))
+    }
+}
+
+// Samples handed from `write` to the queue's callback thread
+struct Shared {
+    fifo: Mutex<VecDeque<f32>>,
+    space: Condvar,
+}
+
+impl Shared {
+    // The callback must not panic across the FFI boundary, poisoned or not
+    fn lock(&self) -> MutexGuard<'_, VecDeque<f32>> {
+        self.fifo.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// Runs on the queue's thread whenever a buffer has played; short input
+// plays as silence
+extern "C" fn fill_buffer(
+    user_data: *mut c_void,
+    queue: AudioQueueRef,
+    buffer: *mut AudioQueueBuffer,
+) {
+    let shared = unsafe { &*(user_data as *const Shared) };
+    let buffer = unsafe { &mut *buffer };
+    let out = unsafe {
+        std::slice::from_raw_parts_mut(buffer.data as *mut f32, buffer.capacity as usize / 4)
+    };
+    {
+        let mut fifo = shared.lock();
+        for sample in out.iter_mut() {
+            *sample = fifo.pop_front().unwrap_or(0.0);
+        }
+    }
+    shared.space.notify_one();
+    buffer.byte_size = buffer.capacity;
+    unsafe { AudioQueueEnqueueBuffer(queue, buffer, 0, ptr::null()) };
+}
+
+struct QueueSink {
+    queue: AudioQueueRef,
+    user_data: *mut c_void, // The callback's reference to `shared`
+    shared: Arc<Shared>,
+    capacity: usize,
+}
+
+// The queue is thread-safe; the raw pointers are only freed in Drop
+unsafe impl Send for QueueSink {}
+
+impl OutputSink for QueueSink {
+    fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        let mut fifo = self.shared.lock();
+        let mut last_progress = Instant::now();
+        let mut queued = fifo.len();
+        while fifo.len() + samples.len() > self.capacity.max(samples.len()) {
+            fifo = self
+                .shared
+                .space
+                .wait_timeout(fifo, WAIT)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+            if fifo.len() < queued {
+                last_progress = Instant::now();
+            } else if last_progress.elapsed() > STALL_TIMEOUT {
+                return Err("Core Audio: the device stopped playing".to_string());
+            }
+            queued = fifo.len();
+        }
+        fifo.extend(samples);
+        Ok(())
+    }
+}
+
+impl Drop for QueueSink {
+    fn drop(&mut self) {
+        unsafe {
+            AudioQueueStop(self.queue, 1);
+            AudioQueueDispose(self.queue, 1);
+            // No callback runs after a synchronous dispose
+            drop(Arc::from_raw(self.user_data as *const Shared));
+        }
+    }
+}
+
+fn address(selector: u32, scope: u32) -> PropertyAddress {
+    PropertyAddress {
+        selector,
+        scope,
+        element: 0,
+    }
+}
+
+fn property<T: Copy>(object: AudioObjectId, selector: u32, scope: u32) -> Option<T> {
+    let mut value = std::mem::MaybeUninit::<T>::uninit();
+    let mut size = std::mem::size_of::<T>() as u32;
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            object,
+            &address(selector, scope),
+            0,
+            ptr::null(),
+            &mut size,
+            value.as_mut_ptr() as *mut c_void,
+        )
+    };
+    (status == 0 && size as usize == std::mem::size_of::<T>())
+        .then(|| unsafe { value.assume_init() })
+}
+
+fn property_bytes(object: AudioObjectId, selector: u32, scope: u32) -> Option<Vec<u8>> {
+    let address = address(selector, scope);
+    let mut size = 0u32;
+    let status =
+        unsafe { AudioObjectGetPropertyDataSize(object, &address, 0, ptr::null(), &mut size) };
+    if status != 0 {
+        return None;
+    }
+    let mut bytes = vec![0u8; size as usize];
+    let status = unsafe {
+        AudioObjectGetPropertyData(
+            object,
+            &address,
+            0,
+            ptr::null(),
+            &mut size,
+            bytes.as_mut_ptr() as *mut c_void,
+        )
+    };
+    bytes.truncate(size as usize);
+    (status == 0).then_some(bytes)
+}
+
+fn property_vec<T: Copy>(object: AudioObjectId, selector: u32, scope: u32) -> Option<Vec<T>> {
+    let bytes = property_bytes(object, selector, scope)?;
+    Some(
+        bytes
+            .chunks_exact(std::mem::size_of::<T>())
+            .map(|chunk| unsafe { ptr::read_unaligned(chunk.as_ptr() as *const T) })
+            .collect(),
+    )
+}
+
+fn string_property(object: AudioObjectId, selector: u32) -> Option<String> {
+    take_string(property::<CfStringRef>(object, selector, SCOPE_GLOBAL)?)
+}
+
+// A string created here, released on drop
+pub(crate) struct CfString(pub(crate) CfStringRef);
+
+impl CfString {
+    pub(crate) fn new(text: &str) -> Self {
+        Self(unsafe {
+            CFStringCreateWithBytes(ptr::null(), text.as_ptr(), text.len() as isize, UTF8, 0)
+        })
+    }
+}
+
+impl Drop for CfString {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe { CFRelease(self.0) };
+        }
+    }
+}
+
+// Converts and releases a string the caller owns
+pub(crate) fn take_string(string: CfStringRef) -> Option<String> {
+    if string.is_null() {
+        return None;
+    }
+    let text = unsafe {
+        // UTF-8 takes at most four bytes per UTF-16 unit, plus the NUL
+        let size = CFStringGetLength(string) * 4 + 1;
+        let mut buffer = vec![0u8; size as usize];
+        let ok = CFStringGetCString(string, buffer.as_mut_ptr() as *mut c_char, size, UTF8);
+        CFRelease(string);
+        if ok == 0 {
+            return None;
+        }
+        let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+        buffer.truncate(len);
+        String::from_utf8(buffer).ok()?
+    };
+    Some(text)
+}
+
+// Sums the streams of an AudioBufferList: a u32 count, then 16-byte
+// AudioBuffers (channels, byte size, data pointer) from offset 8
+fn channel_count(device: AudioObjectId, scope: u32) -> u32 {
+    let Some(bytes) = property_bytes(device, STREAM_CONFIGURATION, scope) else {
+        return 0;
+    };
+    let read = |at: usize| -> Option<u32> {
+        Some(u32::from_ne_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+    };
+    let buffers = read(0).unwrap_or(0) as usize;
+    (0..buffers).filter_map(|i| read(8 + 16 * i)).sum()
+}
+
+// Devices list ranges; fixed rates come as ranges of one value
+fn sample_rates(device: AudioObjectId) -> Option<Vec<u32>> {
+    let ranges: Vec<ValueRange> = property_vec(device, NOMINAL_SAMPLE_RATES, SCOPE_GLOBAL)?;
+    let mut rates: Vec<u32> = COMMON_RATES
+        .iter()
+        .copied()
+        .filter(|&rate| {
+            ranges
+                .iter()
+                .any(|r| r.minimum <= rate && rate <= r.maximum)
+        })
+        .chain(
+            ranges
+                .iter()
+                .filter(|r| r.minimum == r.maximum)
+                .map(|r| r.minimum),
+        )
+        .map(|rate| rate as u32)
+        .collect();
+    rates.sort_unstable();
+    rates.dedup();
+    Some(rates).filter(|r| !r.is_empty())
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioDeviceInfo {
+    pub id: String,
+    pub name: String,
+    pub is_input: bool,
+    pub is_output: bool,
+    pub is_default: bool,
+    pub sample_rates: Option<Vec<u32>>, // None when the backend can't tell
+    pub max_channels: Option<u16>,      // None when unknown, or any count is converted
+}
+
+/// Host audio API that can enumerate devices and play audio. The preview
+/// and measurement features only talk to this trait, so the host backend
+/// can be swapped without touching them.
+pub trait AudioBackend: Send + Sync {
+    fn name(&self) -> &str;
+    fn devices(&self) -> Result<Vec<AudioDeviceInfo>, String>;
+    // `device` None is the system default
+    fn open_output(
+        &self,
+        device: Option<&AudioDeviceInfo>,
+        sample_rate: u32,
+        channels: usize,
+    ) -> Result<Box<dyn OutputSink>, String>;
+}
+
+/// An open output stream.
+pub trait OutputSink: Send {
+    // Interleaved samples; blocks until the device has room for them
+    fn write(&mut self, samples: &[f32]) -> Result<(), String>;
+}
+
+// Used when no host backend is available; every call says so
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn name(&self) -> &str {
+        "none"
+    }
+
+    fn devices(&self) -> Result<Vec<AudioDeviceInfo>, String> {
+        Err(NO_BACKEND.to_string())
+    }
+
+    fn open_output(
+        &self,
+        _device: Option<&AudioDeviceInfo>,
+        _sample_rate: u32,
+        _channels: usize,
+    ) -> Result<Box<dyn OutputSink>, String> {
+        Err(NO_BACKEND.to_string())
+    }
+}
+
+const NO_BACKEND: &str = "No audio backend available (on Linux, install alsa-utils)";
+
+// waveOut on Windows, Core Audio on macOS, ALSA on Linux when its tools are installed
+pub fn default_backend() -> Box<dyn AudioBackend> {
+    #[cfg(windows)]
+    {
+        Box::new(super::winmm::WinMmBackend)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        Box::new(super::coreaudio::CoreAudioBackend)
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        #[cfg(target_os = "linux")]
+        if let Some(alsa) = super::alsa::AlsaBackend::detect() {
+            return Box::new(alsa);
+        }
+        Box::new(NullBackend)
+    }
+}
+
+// Output devices only; input devices are for measurement capture
+pub fn find_output_device(backend: &dyn AudioBackend, id: &str) -> Result<AudioDeviceInfo, String> {
+    backend
+        .devices()?
+        .into_iter()
+        .find(|d| d.id == id && d.is_output)
+        .ok_or_else(|| {
+            format!(
+                "Unknown output device: {} (backend: {})",
+                id,
+                backend.name()
+            )
+        })
+}
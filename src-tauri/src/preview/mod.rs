@@ -0,0 +1,564 @@
+#[cfg(target_os = "linux")]
+pub mod alsa;
+#[cfg(target_os = "macos")]
+pub mod coreaudio;
+pub mod device;
+pub mod output;
+#[cfg(windows)]
+pub mod winmm;
+
+use crate::audio::loader::MultiChannelAudio;
+use crate::audio::profile::FrequencyBand;
+use crate::audio::warnings::MatchWarning;
+use crate::dsp::convolution::PartitionedConvolver;
+use crate::dsp::dynamic::{DynamicBand, DynamicEQ};
+use crate::dsp::filters::{band_params, sanitize_params, BandParams, FilterType};
+use crate::dsp::fir::{linear_phase_fir, DEFAULT_FIR_TAPS};
+use crate::dsp::limiter::SoftLimiter;
+use crate::dsp::oversampling::OversampledEQ;
+use biquad::{Biquad, Coefficients, DirectForm2Transposed, ToHertz, Type};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// Parameter changes are ramped over this long to avoid zipper noise
+const RAMP_SECS: f64 = 0.02;
+// Bypass/delta toggles crossfade over this long
+const BYPASS_FADE_SECS: f64 = 0.01;
+// Keeps the solo band-pass from getting so wide it stops isolating anything
+const SOLO_MIN_Q: f32 = 0.7;
+// Coefficients are recomputed once per this many frames during a ramp
+const RAMP_BLOCK: usize = 32;
+// Partition size of the linear-phase convolver, added to the FIR's own delay
+const FIR_BLOCK: usize = 512;
+
+// Sample delay for the dry path
+struct DelayLine {
+    buffer: VecDeque<f32>,
+}
+
+impl DelayLine {
+    fn new(samples: usize) -> Self {
+        Self {
+            buffer: VecDeque::from(vec![0.0; samples]),
+        }
+    }
+
+    fn process(&mut self, sample: f32) -> f32 {
+        self.buffer.push_back(sample);
+        self.buffer.pop_front().unwrap_or(sample)
+    }
+}
+
+struct ParamRamp {
+    from: Vec<BandParams>,
+    to: Vec<BandParams>,
+    elapsed: usize, // Frames
+    length: usize,  // Frames
+}
+
+/// Transport and EQ state for auditioning a file. The output thread
+/// (`output::PreviewOutput`) pulls interleaved buffers from `render`;
+/// everything else is driven by the transport commands.
+pub struct PreviewEngine {
+    source: Vec<Vec<f32>>, // Planar
+    sample_rate: u32,
+    position: usize, // Frames
+    playing: bool,
+    loop_region: Option<(usize, usize)>,
+    bands: Vec<FrequencyBand>,
+    eqs: Vec<OversampledEQ>,                         // One per source channel
+    linear_phase: Option<Vec<PartitionedConvolver>>, // Replaces `eqs` when set, one per channel
+    dynamic: Vec<DynamicEQ>,                         // One per source channel, after the static EQ
+    dry_delays: Vec<DelayLine>,
+    params: Vec<BandParams>, // What the filters currently run with
+    ramp: Option<ParamRamp>,
+    bypassed: bool,
+    delta: bool,                                   // Plays processed minus original
+    mix: (f32, f32), // Current (dry, wet) gains, ramped toward the mode's targets
+    compensation_db: f32, // Applied to the processed signal only
+    solo: Option<usize>, // Band index
+    muted: Vec<usize>, // Band indices
+    solo_filters: Vec<DirectForm2Transposed<f32>>, // Band-pass per source channel while soloing
+    limiter: SoftLimiter,
+    limiter_enabled: bool,
+    blind: bool, // ABX running: status hides what would tell the sides apart
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewStatus {
+    pub playing: bool,
+    pub position_secs: f64,
+    pub duration_secs: f64,
+    pub loop_region: Option<(f64, f64)>,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub bypassed: bool,
+    pub delta: bool,
+    pub compensation_db: Option<f32>, // None during a blind test
+    pub solo: Option<usize>,
+    pub muted: Vec<usize>,
+    pub oversampling: usize,
+    #[serde(default)]
+    pub linear_phase: bool,
+    pub limiter_enabled: bool,
+    pub latency_samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewMeter {
+    pub gain_reduction_db: f32,     // Right now
+    pub max_gain_reduction_db: f32, // Since the previous meter read
+}
+
+impl PreviewEngine {
+    pub fn new(audio: MultiChannelAudio) -> Self {
+        let eqs = audio
+            .channels
+            .iter()
+            .map(|_| OversampledEQ::new(audio.sample_rate as f32, 1, &[]))
+            .collect::<Result<_, _>>()
+            .expect("factor 1 is always supported");
+        let dry_delays = audio.channels.iter().map(|_| DelayLine::new(0)).collect();
+
+        Self {
+            source: audio.channels,
+            sample_rate: audio.sample_rate,
+            position: 0,
+            playing: false,
+            loop_region: None,
+            bands: Vec::new(),
+            eqs,
+            linear_phase: None,
+            dynamic: Vec::new(),
+            dry_delays,
+            params: Vec::new(),
+            ramp: None,
+            bypassed: false,
+            delta: false,
+            mix: (0.0, 1.0),
+            compensation_db: 0.0,
+            solo: None,
+            muted: Vec::new(),
+            solo_filters: Vec::new(),
+            limiter: SoftLimiter::new(audio.sample_rate),
+            limiter_enabled: true,
+            blind: false,
+        }
+    }
+
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn seek(&mut self, position_secs: f64) {
+        self.position = self.secs_to_frames(position_secs).min(self.frames());
+        self.reset();
+    }
+
+    // Clears filter and delay state so nothing from the old position rings on
+    pub fn reset(&mut self) {
+        for eq in &mut self.eqs {
+            eq.reset();
+        }
+        for convolver in self.linear_phase.iter_mut().flatten() {
+            convolver.reset();
+        }
+        for dynamic in &mut self.dynamic {
+            dynamic.reset();
+        }
+        for filter in &mut self.solo_filters {
+            filter.reset_state();
+        }
+        self.reset_dry_delays();
+        self.limiter.reset();
+    }
+
+    // Processing delay of the wet path; the dry path is delayed to match
+    pub fn latency_samples(&self) -> usize {
+        match &self.linear_phase {
+            Some(convolvers) => {
+                (DEFAULT_FIR_TAPS - 1) / 2 + convolvers.first().map_or(0, |c| c.latency_samples())
+            }
+            None => self.eqs.first().map(|eq| eq.latency_samples()).unwrap_or(0),
+        }
+    }
+
+    pub fn set_loop(&mut self, region: Option<(f64, f64)>) {
+        self.loop_region = region
+            .map(|(start, end)| (self.secs_to_frames(start), self.secs_to_frames(end)))
+            .map(|(start, end)| (start, end.min(self.frames())))
+            .filter(|(start, end)| end > start);
+
+        if let Some((start, end)) = self.loop_region {
+            if self.position < start || self.position >= end {
+                self.position = start;
+            }
+        }
+    }
+
+    // Live band updates while playing. Returns warnings for bands that had to
+    // be clamped or skipped at this file's sample rate.
+    pub fn set_bands(&mut self, bands: &[FrequencyBand]) -> Vec<MatchWarning> {
+        self.bands = bands.to_vec();
+        self.muted.retain(|&i| i < bands.len());
+        if self.solo.is_some_and(|i| i >= bands.len()) {
+            self.solo = None;
+        }
+
+        self.update_solo_filters();
+        self.update_params();
+        sanitize_params(self.sample_rate as f32, &band_params(bands)).1
+    }
+
+    pub fn set_solo(&mut self, band: Option<usize>) -> Result<(), String> {
+        if let Some(index) = band {
+            self.check_band_index(index)?;
+        }
+
+        self.solo = band;
+        self.update_solo_filters();
+        self.update_params();
+        Ok(())
+    }
+
+    pub fn set_muted(&mut self, band: usize, muted: bool) -> Result<(), String> {
+        self.check_band_index(band)?;
+
+        self.muted.retain(|&i| i != band);
+        if muted {
+            self.muted.push(band);
+            self.muted.sort_unstable();
+        }
+        self.update_params();
+        Ok(())
+    }
+
+    pub fn set_dynamic_bands(&mut self, bands: &[DynamicBand]) {
+        self.dynamic = if bands.is_empty() {
+            Vec::new()
+        } else {
+            self.source
+                .iter()
+                .map(|_| DynamicEQ::new(self.sample_rate as f32, bands))
+                .collect()
+        };
+    }
+
+    // A/B between the processed and original signal
+    pub fn set_bypass(&mut self, bypassed: bool) {
+        self.bypassed = bypassed;
+    }
+
+    // Delta monitoring: only what the correction adds or removes. Takes
+    // precedence over bypass.
+    pub fn set_delta(&mut self, delta: bool) {
+        self.delta = delta;
+    }
+
+    // Cancels the level change the correction introduces, so the processed
+    // side isn't preferred just for being louder
+    pub fn set_loudness_compensation(&mut self, correction_delta_db: Option<f32>) {
+        self.compensation_db = correction_delta_db.map(|d| -d).unwrap_or(0.0);
+    }
+
+    // While blind, the compensation differs between the ABX sides and would
+    // reveal which one is playing, so status leaves it out
+    pub fn set_blind(&mut self, blind: bool) {
+        self.blind = blind;
+    }
+
+    // Runs the EQ at 2x/4x so high bells aren't cramped near Nyquist
+    pub fn set_oversampling(&mut self, factor: usize) -> Result<(), String> {
+        self.eqs = self
+            .source
+            .iter()
+            .map(|_| OversampledEQ::new(self.sample_rate as f32, factor, &self.params))
+            .collect::<Result<_, _>>()?;
+        self.reset_dry_delays();
+        Ok(())
+    }
+
+    // Plays the bands through a linear-phase FIR instead of the biquads: no
+    // phase shift, at the cost of the FIR's delay. Band changes then apply
+    // at once instead of ramping, as redesigning the FIR is too slow for that.
+    pub fn set_linear_phase(&mut self, enabled: bool) -> Result<(), String> {
+        self.linear_phase = if enabled {
+            let impulse = linear_phase_fir(&self.params, self.sample_rate, DEFAULT_FIR_TAPS)?;
+            Some(
+                self.source
+                    .iter()
+                    .map(|_| PartitionedConvolver::new(&impulse, FIR_BLOCK))
+                    .collect::<Result<_, _>>()?,
+            )
+        } else {
+            None
+        };
+        self.reset_dry_delays();
+        Ok(())
+    }
+
+    // Clip protection on the output so big boosts don't hit the interface
+    pub fn set_limiter(&mut self, enabled: bool) {
+        self.limiter_enabled = enabled;
+        self.limiter.reset();
+    }
+
+    pub fn meter(&mut self) -> PreviewMeter {
+        PreviewMeter {
+            gain_reduction_db: self.limiter.gain_reduction_db(),
+            max_gain_reduction_db: self.limiter.take_max_reduction_db(),
+        }
+    }
+
+    pub fn status(&self) -> PreviewStatus {
+        PreviewStatus {
+            playing: self.playing,
+            position_secs: self.frames_to_secs(self.position),
+            duration_secs: self.frames_to_secs(self.frames()),
+            loop_region: self
+                .loop_region
+                .map(|(start, end)| (self.frames_to_secs(start), self.frames_to_secs(end))),
+            sample_rate: self.sample_rate,
+            channels: self.source.len(),
+            bypassed: self.bypassed,
+            delta: self.delta,
+            compensation_db: (!self.blind).then_some(self.compensation_db),
+            solo: self.solo,
+            muted: self.muted.clone(),
+            oversampling: self.eqs.first().map(|eq| eq.factor()).unwrap_or(1),
+            linear_phase: self.linear_phase.is_some(),
+            limiter_enabled: self.limiter_enabled,
+            latency_samples: self.latency_samples(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> usize {
+        self.source.len()
+    }
+
+    // Mono average of the source, for analyses of what is loaded
+    pub fn mixdown(&self) -> Vec<f32> {
+        let scale = 1.0 / self.source.len().max(1) as f32;
+        (0..self.frames())
+            .map(|i| self.source.iter().map(|c| c[i]).sum::<f32>() * scale)
+            .collect()
+    }
+
+    /// Fills an interleaved output buffer. Mono sources are copied to every
+    /// output channel; extra source channels beyond the output are dropped.
+    pub fn render(&mut self, out: &mut [f32], out_channels: usize) {
+        out.fill(0.0);
+        if !self.playing || out_channels == 0 || self.source.is_empty() {
+            return;
+        }
+
+        let fade_step = 1.0 / ((BYPASS_FADE_SECS * self.sample_rate as f64) as f32).max(1.0);
+        let target = self.mix_target();
+
+        for frame in out.chunks_mut(out_channels) {
+            let (start, end) = self.loop_region.unwrap_or((0, self.frames()));
+            if self.position >= end {
+                if self.loop_region.is_some() {
+                    self.position = start;
+                } else {
+                    self.playing = false;
+                    return;
+                }
+            }
+
+            if self.position.is_multiple_of(RAMP_BLOCK) {
+                self.advance_ramp();
+            }
+
+            self.mix.0 += (target.0 - self.mix.0).clamp(-fade_step, fade_step);
+            self.mix.1 += (target.1 - self.mix.1).clamp(-fade_step, fade_step);
+            let (dry_gain, wet_gain) = self.mix;
+
+            // The EQ keeps running while bypassed so switching back is seamless.
+            // The dry side is delayed by the EQ's latency so A/B stays aligned.
+            let pos = self.position;
+            let mono = self.source.len() == 1;
+            for ch in 0..self.source.len() {
+                let input = self.source[ch][pos];
+                let mut wet = match self.linear_phase.as_mut() {
+                    Some(convolvers) => convolvers[ch].process(input),
+                    None => self.eqs[ch].process(input),
+                };
+                if let Some(dynamic) = self.dynamic.get_mut(ch) {
+                    wet = dynamic.process(wet);
+                }
+                let dry = self.dry_delays[ch].process(input);
+
+                let mut sample = dry * dry_gain + wet * wet_gain;
+                if let Some(filter) = self.solo_filters.get_mut(ch) {
+                    sample = filter.run(sample);
+                }
+
+                if mono {
+                    frame.fill(sample);
+                } else if let Some(out) = frame.get_mut(ch) {
+                    *out = sample;
+                }
+            }
+
+            if self.limiter_enabled {
+                self.limiter.process_frame(frame);
+            }
+
+            self.position += 1;
+        }
+    }
+
+    // Matching band layouts are ramped to the new settings; anything else
+    // (band count or filter type changed) swaps at once.
+    fn update_params(&mut self) {
+        let target = self.active_params();
+
+        let same_layout = target.len() == self.params.len()
+            && target.iter().zip(&self.params).all(|(a, b)| a.3 == b.3);
+
+        if self.playing && same_layout && self.linear_phase.is_none() {
+            self.ramp = Some(ParamRamp {
+                from: self.params.clone(),
+                to: target,
+                elapsed: 0,
+                length: ((RAMP_SECS * self.sample_rate as f64) as usize).max(1),
+            });
+        } else {
+            self.ramp = None;
+            self.apply_params(target);
+        }
+    }
+
+    // Muted bands (and every band but the soloed one) drop to 0 dB. Pass
+    // filters have no neutral setting, so those are removed instead.
+    fn active_params(&self) -> Vec<BandParams> {
+        band_params(&self.bands)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, (frequency, gain_db, q, filter_type))| {
+                let silenced = self.muted.contains(&i) || self.solo.is_some_and(|s| s != i);
+                match (silenced, filter_type) {
+                    (false, _) => Some((frequency, gain_db, q, filter_type)),
+                    (true, FilterType::HighPass | FilterType::LowPass) => None,
+                    (true, _) => Some((frequency, 0.0, q, filter_type)),
+                }
+            })
+            .collect()
+    }
+
+    // Band-passes the soloed band's region so it can be heard in isolation
+    fn update_solo_filters(&mut self) {
+        let coefficients = self.solo.and_then(|i| {
+            let band = &self.bands[i];
+            Coefficients::<f32>::from_params(
+                Type::BandPass,
+                (self.sample_rate as f32).hz(),
+                band.frequency.hz(),
+                band.q().max(SOLO_MIN_Q),
+            )
+            .ok()
+        });
+
+        self.solo_filters = match coefficients {
+            Some(coeffs) => self
+                .source
+                .iter()
+                .map(|_| DirectForm2Transposed::<f32>::new(coeffs))
+                .collect(),
+            None => Vec::new(),
+        };
+    }
+
+    fn check_band_index(&self, index: usize) -> Result<(), String> {
+        if index < self.bands.len() {
+            Ok(())
+        } else {
+            Err(format!("Band {} does not exist", index))
+        }
+    }
+
+    // (dry, wet) gains for the current monitoring mode. The delta is taken
+    // before loudness compensation so it reflects the correction alone.
+    fn mix_target(&self) -> (f32, f32) {
+        if self.delta {
+            (-1.0, 1.0)
+        } else if self.bypassed {
+            (1.0, 0.0)
+        } else {
+            (0.0, 10f32.powf(self.compensation_db / 20.0))
+        }
+    }
+
+    fn advance_ramp(&mut self) {
+        let Some(ramp) = self.ramp.as_mut() else {
+            return;
+        };
+
+        ramp.elapsed = (ramp.elapsed + RAMP_BLOCK).min(ramp.length);
+        let t = ramp.elapsed as f32 / ramp.length as f32;
+        let params = ramp
+            .from
+            .iter()
+            .zip(&ramp.to)
+            .map(|(a, b)| interpolate_params(a, b, t))
+            .collect();
+
+        if ramp.elapsed >= ramp.length {
+            self.ramp = None;
+        }
+        self.apply_params(params);
+    }
+
+    fn apply_params(&mut self, params: Vec<BandParams>) {
+        for eq in &mut self.eqs {
+            eq.retune(&params);
+        }
+        if let Some(convolvers) = self.linear_phase.as_mut() {
+            // Same length as the one the convolvers were built with, so it fits
+            if let Ok(impulse) = linear_phase_fir(&params, self.sample_rate, DEFAULT_FIR_TAPS) {
+                for convolver in convolvers {
+                    let _ = convolver.set_impulse(&impulse);
+                }
+            }
+        }
+        self.params = params;
+    }
+
+    fn reset_dry_delays(&mut self) {
+        let latency = self.latency_samples();
+        self.dry_delays = self
+            .source
+            .iter()
+            .map(|_| DelayLine::new(latency))
+            .collect();
+    }
+
+    fn frames(&self) -> usize {
+        self.source.first().map(|c| c.len()).unwrap_or(0)
+    }
+
+    fn secs_to_frames(&self, secs: f64) -> usize {
+        (secs.max(0.0) * self.sample_rate as f64) as usize
+    }
+
+    fn frames_to_secs(&self, frames: usize) -> f64 {
+        frames as f64 / self.sample_rate.max(1) as f64
+    }
+}
+
+// Frequency moves on a log scale so sweeps sound even
+fn interpolate_params(from: &BandParams, to: &BandParams, t: f32) -> BandParams {
+    let frequency = (from.0.ln() + (to.0.ln() - from.0.ln()) * t).exp();
+    let gain_db = from.1 + (to.1 - from.1) * t;
+    let q = from.2 + (to.2 - from.2) * t;
+    (frequency, gain_db, q, to.3)
+}
@@ -0,0 +1,71 @@
+use super::device::OutputSink;
+use super::PreviewEngine;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+// Frames rendered per write; the sink's own buffer sets the latency
+const BLOCK_FRAMES: usize = 1024;
+
+/// The audio thread of the preview: renders blocks from the engine and
+/// writes them to an output sink, whose blocking writes pace the loop.
+/// Paused or unloaded engines produce silence. Stops when dropped.
+pub struct PreviewOutput {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    sample_rate: u32,
+    channels: usize,
+}
+
+impl PreviewOutput {
+    pub fn start(
+        engine: Arc<Mutex<Option<PreviewEngine>>>,
+        mut sink: Box<dyn OutputSink>,
+        sample_rate: u32,
+        channels: usize,
+    ) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = stop.clone();
+        let thread = thread::spawn(move || {
+            let mut buffer = vec![0.0f32; BLOCK_FRAMES * channels];
+            while !flag.load(Ordering::Relaxed) {
+                // The lock is held for one block only, so commands stay responsive
+                match engine.lock().unwrap().as_mut() {
+                    Some(engine) => engine.render(&mut buffer, channels),
+                    None => buffer.fill(0.0),
+                }
+                if let Err(e) = sink.write(&buffer) {
+                    log::warn!("preview output stopped: {}", e);
+                    break;
+                }
+            }
+        });
+
+        Self {
+            stop,
+            thread: Some(thread),
+            sample_rate,
+            channels,
+        }
+    }
+
+    // False once the sink failed, e.g. the device went away
+    pub fn is_running(&self) -> bool {
+        self.thread.as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    // Whether this stream can play an engine with this layout as is
+    pub fn fits(&self, sample_rate: u32, channels: usize) -> bool {
+        self.is_running() && self.sample_rate == sample_rate && self.channels == channels
+    }
+}
+
+// Callers must not hold the engine lock: the thread may be waiting for it
+impl Drop for PreviewOutput {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
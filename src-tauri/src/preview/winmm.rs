@@ -0,0 +1,343 @@
+use super::device::{AudioBackend, AudioDeviceInfo, OutputSink};
+use std::ffi::c_void;
+use std::ptr;
+
+// Buffers in flight; each holds one write of the preview thread (~21 ms)
+const BUFFERS: usize = 4;
+const WAIT_MS: u32 = 100;
+
+const WAVE_MAPPER: u32 = u32::MAX;
+const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+const CALLBACK_EVENT: u32 = 0x0005_0000;
+const WHDR_DONE: u32 = 0x1;
+const MMSYSERR_NOERROR: u32 = 0;
+const MAPPER_ID: &str = "wave-mapper";
+
+// dwFormats bits come in groups of four (mono/stereo, 8/16-bit) per rate
+const FORMAT_RATES: [(u32, u32); 5] = [
+    (0x0000_000F, 11_025),
+    (0x0000_00F0, 22_050),
+    (0x0000_0F00, 44_100),
+    (0x0000_F000, 48_000),
+    (0x000F_0000, 96_000),
+];
+
+#[repr(C, packed)]
+struct WaveFormatEx {
+    format_tag: u16,
+    channels: u16,
+    samples_per_sec: u32,
+    avg_bytes_per_sec: u32,
+    block_align: u16,
+    bits_per_sample: u16,
+    size: u16,
+}
+
+#[repr(C)]
+struct WaveHdr {
+    data: *mut u8,
+    buffer_length: u32,
+    bytes_recorded: u32,
+    user: usize,
+    flags: u32,
+    loops: u32,
+    next: *mut WaveHdr,
+    reserved: usize,
+}
+
+#[repr(C)]
+struct WaveOutCaps {
+    mid: u16,
+    pid: u16,
+    driver_version: u32,
+    name: [u16; 32],
+    formats: u32,
+    channels: u16,
+    reserved: u16,
+    support: u32,
+}
+
+#[repr(C)]
+struct WaveInCaps {
+    mid: u16,
+    pid: u16,
+    driver_version: u32,
+    name: [u16; 32],
+    formats: u32,
+    channels: u16,
+    reserved: u16,
+}
+
+type HWaveOut = *mut c_void;
+type Handle = *mut c_void;
+
+#[link(name = "winmm")]
+extern "system" {
+    fn waveOutGetNumDevs() -> u32;
+    fn waveOutGetDevCapsW(device: usize, caps: *mut WaveOutCaps, size: u32) -> u32;
+    fn waveInGetNumDevs() -> u32;
+    fn waveInGetDevCapsW(device: usize, caps: *mut WaveInCaps, size: u32) -> u32;
+    fn waveOutOpen(
+        handle: *mut HWaveOut,
+        device: u32,
+        format: *const WaveFormatEx,
+        callback: usize,
+        instance: usize,
+        flags: u32,
+    ) -> u32;
+    fn waveOutPrepareHeader(handle: HWaveOut, header: *mut WaveHdr, size: u32) -> u32;
+    fn waveOutUnprepareHeader(handle: HWaveOut, header: *mut WaveHdr, size: u32) -> u32;
+    fn waveOutWrite(handle: HWaveOut, header: *mut WaveHdr, size: u32) -> u32;
+    fn waveOutReset(handle: HWaveOut) -> u32;
+    fn waveOutClose(handle: HWaveOut) -> u32;
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn CreateEventW(
+        attributes: *mut c_void,
+        manual_reset: i32,
+        initial: i32,
+        name: *const u16,
+    ) -> Handle;
+    fn WaitForSingleObject(handle: Handle, milliseconds: u32) -> u32;
+    fn CloseHandle(handle: Handle) -> i32;
+}
+
+/// The Windows multimedia (waveOut) API, which every Windows version has
+/// and which converts float samples to the device format itself. Device
+/// ids are "out:<n>" and "in:<n>", plus the system's wave mapper.
+pub struct WinMmBackend;
+
+impl AudioBackend for WinMmBackend {
+    fn name(&self) -> &str {
+        "winmm"
+    }
+
+    fn devices(&self) -> Result<Vec<AudioDeviceInfo>, String> {
+        let mut devices = vec![AudioDeviceInfo {
+            id: MAPPER_ID.to_string(),
+            name: "Default output".to_string(),
+            is_input: false,
+            is_output: true,
+            is_default: true,
+            sample_rates: None,
+            max_channels: None,
+        }];
+
+        for index in 0..unsafe { waveOutGetNumDevs() } {
+            let mut caps: WaveOutCaps = unsafe { std::mem::zeroed() };
+            let size = std::mem::size_of::<WaveOutCaps>() as u32;
+            if unsafe { waveOutGetDevCapsW(index as usize, &mut caps, size) } != MMSYSERR_NOERROR {
+                continue;
+            }
+            devices.push(device_info(
+                format!("out:{}", index),
+                &caps.name,
+                caps.formats,
+                caps.channels,
+                false,
+            ));
+        }
+        for index in 0..unsafe { waveInGetNumDevs() } {
+            let mut caps: WaveInCaps = unsafe { std::mem::zeroed() };
+            let size = std::mem::size_of::<WaveInCaps>() as u32;
+            if unsafe { waveInGetDevCapsW(index as usize, &mut caps, size) } != MMSYSERR_NOERROR {
+                continue;
+            }
+            devices.push(device_info(
+                format!("in:{}", index),
+                &caps.name,
+                caps.formats,
+                caps.channels,
+                true,
+            ));
+        }
+        Ok(devices)
+    }
+
+    fn open_output(
+        &self,
+        device: Option<&AudioDeviceInfo>,
+        sample_rate: u32,
+        channels: usize,
+    ) -> Result<Box<dyn OutputSink>, String> {
+        let device_id = match device.map(|d| d.id.as_str()) {
+            None | Some(MAPPER_ID) => WAVE_MAPPER,
+            Some(id) => id
+                .strip_prefix("out:")
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| format!("Not an output device: {}", id))?,
+        };
+        let channels = u16::try_from(channels).map_err(|_| "Too many channels".to_string())?;
+        let block_align = channels * 4;
+        let format = WaveFormatEx {
+            format_tag: WAVE_FORMAT_IEEE_FLOAT,
+            channels,
+            samples_per_sec: sample_rate,
+            avg_bytes_per_sec: sample_rate * block_align as u32,
+            block_align,
+            bits_per_sample: 32,
+            size: 0,
+        };
+
+        let event = unsafe { CreateEventW(ptr::null_mut(), 0, 0, ptr::null()) };
+        if event.is_null() {
+            return Err("waveOut: cannot create an event".to_string());
+        }
+        let mut handle: HWaveOut = ptr::null_mut();
+        let result = unsafe {
+            waveOutOpen(
+                &mut handle,
+                device_id,
+                &format,
+                event as usize,
+                0,
+                CALLBACK_EVENT,
+            )
+        };
+        if result != MMSYSERR_NOERROR {
+            unsafe { CloseHandle(event) };
+            return Err(format!(
+                "waveOut: cannot open the device at {} Hz, {} channels (error {})",
+                sample_rate, channels, result
+            ));
+        }
+
+        Ok(Box::new(WaveOutSink {
+            handle,
+            event,
+            buffers: (0..BUFFERS).map(|_| WaveBuffer::new()).collect(),
+        }))
+    }
+}
+
+fn device_info(
+    id: String,
+    name: &[u16; 32],
+    formats: u32,
+    channels: u16,
+    is_input: bool,
+) -> AudioDeviceInfo {
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    AudioDeviceInfo {
+        id,
+        name: String::from_utf16_lossy(&name[..len]),
+        is_input,
+        is_output: !is_input,
+        is_default: false,
+        // Only the legacy rates have flags; a device with none set says nothing
+        sample_rates: Some(
+            FORMAT_RATES
+                .iter()
+                .filter(|(bits, _)| formats & bits != 0)
+                .map(|&(_, rate)| rate)
+                .collect::<Vec<u32>>(),
+        )
+        .filter(|rates| !rates.is_empty()),
+        max_channels: Some(channels).filter(|&c| c > 0),
+    }
+}
+
+// Header and samples live on the heap so their addresses stay put while
+// the driver owns them
+struct WaveBuffer {
+    header: Box<WaveHdr>,
+    bytes: Vec<u8>,
+    prepared: bool,
+}
+
+impl WaveBuffer {
+    fn new() -> Self {
+        Self {
+            header: Box::new(WaveHdr {
+                data: ptr::null_mut(),
+                buffer_length: 0,
+                bytes_recorded: 0,
+                user: 0,
+                flags: 0,
+                loops: 0,
+                next: ptr::null_mut(),
+                reserved: 0,
+            }),
+            bytes: Vec::new(),
+            prepared: false,
+        }
+    }
+
+    // The driver sets WHDR_DONE from its own thread
+    fn is_free(&self) -> bool {
+        !self.prepared || unsafe { ptr::read_volatile(&self.header.flags) } & WHDR_DONE != 0
+    }
+}
+
+struct WaveOutSink {
+    handle: HWaveOut,
+    event: Handle,
+    buffers: Vec<WaveBuffer>,
+}
+
+// The handles are only used from the thread that owns the sink
+unsafe impl Send for WaveOutSink {}
+
+impl WaveOutSink {
+    fn unprepare(&mut self, index: usize) {
+        let buffer = &mut self.buffers[index];
+        if buffer.prepared {
+            let size = std::mem::size_of::<WaveHdr>() as u32;
+            unsafe { waveOutUnprepareHeader(self.handle, &mut *buffer.header, size) };
+            buffer.prepared = false;
+        }
+    }
+}
+
+impl OutputSink for WaveOutSink {
+    fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        let index = loop {
+            if let Some(index) = self.buffers.iter().position(WaveBuffer::is_free) {
+                break index;
+            }
+            // Signalled whenever the driver finishes a buffer
+            unsafe { WaitForSingleObject(self.event, WAIT_MS) };
+        };
+        self.unprepare(index);
+
+        let size = std::mem::size_of::<WaveHdr>() as u32;
+        let handle = self.handle;
+        let buffer = &mut self.buffers[index];
+        buffer.bytes.clear();
+        buffer
+            .bytes
+            .extend(samples.iter().flat_map(|s| s.to_le_bytes()));
+        buffer.header.data = buffer.bytes.as_mut_ptr();
+        buffer.header.buffer_length = buffer.bytes.len() as u32;
+        buffer.header.flags = 0;
+
+        let header: *mut WaveHdr = &mut *buffer.header;
+        let result = unsafe { waveOutPrepareHeader(handle, header, size) };
+        if result != MMSYSERR_NOERROR {
+            return Err(format!(
+                "waveOut: cannot prepare a buffer (error {})",
+                result
+            ));
+        }
+        buffer.prepared = true;
+        match unsafe { waveOutWrite(handle, header, size) } {
+            MMSYSERR_NOERROR => Ok(()),
+            error => Err(format!("waveOut: write failed (error {})", error)),
+        }
+    }
+}
+
+impl Drop for WaveOutSink {
+    fn drop(&mut self) {
+        unsafe { waveOutReset(self.handle) };
+        for index in 0..self.buffers.len() {
+            self.unprepare(index);
+        }
+        unsafe {
+            waveOutClose(self.handle);
+            CloseHandle(self.event);
+        }
+    }
+}
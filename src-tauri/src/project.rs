@@ -0,0 +1,272 @@
+use crate::audio::analyzer::AnalysisConfig;
+use crate::audio::matcher::{MatchConfig, MatchResult};
+use crate::audio::pipeline::AnalyzedSpectra;
+use crate::audio::profile::EQProfile;
+use crate::history::{apply_band_overrides, EditHistory, MatchEdit};
+use crate::session::Session;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+pub type ProjectId = u64;
+
+const DEFAULT_NAME: &str = "Untitled";
+
+/// One song or episode being matched: its files, profiles, result and
+/// edit history. Projects are independent; jobs write back to the project
+/// that started them, even if the user switched away meanwhile.
+#[derive(Debug, Default)]
+pub struct Project {
+    pub name: String,
+    pub reference_profile: Option<EQProfile>,
+    pub input_profile: Option<EQProfile>,
+    pub match_result: Option<MatchResult>,
+    pub references: HashMap<String, EQProfile>, // Keyed by source path
+    pub reference_path: Option<String>,
+    pub input_path: Option<String>,
+    pub match_config: Option<MatchConfig>, // Config behind `match_result`
+    pub history: EditHistory,
+    pub spectra: HashMap<String, Arc<AnalyzedSpectra>>, // Behind loaded profiles, by path; not saved
+    // Not saved: bumped on every change, so pollers can skip unchanged state
+    pub revision: u64,
+    pub inputs_revision: u64, // Bumped when the reference or input profile changes
+    pub result_basis: u64,    // `inputs_revision` that `match_result` was computed from
+}
+
+impl Project {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+
+    // The reference or input profile was replaced
+    pub fn inputs_changed(&mut self) {
+        self.inputs_revision += 1;
+    }
+
+    /// Makes `result` current if it was computed from the profiles loaded
+    /// now; `basis` is the `inputs_revision` read when its job started. A
+    /// result from profiles replaced meanwhile is refused.
+    pub fn set_result(
+        &mut self,
+        result: MatchResult,
+        config: MatchConfig,
+        basis: u64,
+    ) -> Result<(), String> {
+        if basis != self.inputs_revision {
+            return Err("The reference or input changed while matching".to_string());
+        }
+        self.match_result = Some(result);
+        self.match_config = Some(config);
+        self.result_basis = basis;
+        Ok(())
+    }
+
+    // A result exists but the profiles behind it have been replaced
+    pub fn result_stale(&self) -> bool {
+        self.match_result.is_some() && self.result_basis != self.inputs_revision
+    }
+
+    pub fn to_session(&self) -> Session {
+        Session {
+            reference_path: self.reference_path.clone(),
+            input_path: self.input_path.clone(),
+            reference_profile: self.reference_profile.clone(),
+            input_profile: self.input_profile.clone(),
+            references: self.references.clone(),
+            match_config: self.match_config.clone(),
+            match_result: self.match_result.clone(),
+            band_overrides: self
+                .history
+                .current()
+                .map(|edit| edit.band_overrides.clone())
+                .unwrap_or_default(),
+            ..Session::default()
+        }
+    }
+
+    // Keeps the name; edits made before would apply to other profiles, so
+    // the history starts over, from the session's hand-set bands if any
+    pub fn load_session(&mut self, session: Session) {
+        let mut history = EditHistory::default();
+        let mut match_result = session.match_result;
+        if !session.band_overrides.is_empty() {
+            if let (Some(result), Some(reference), Some(input)) = (
+                match_result.as_mut(),
+                &session.reference_profile,
+                &session.input_profile,
+            ) {
+                apply_band_overrides(result, &session.band_overrides, reference, input);
+            }
+            history.record(MatchEdit {
+                config: session.match_config.clone().unwrap_or_default(),
+                band_overrides: session.band_overrides,
+            });
+        }
+
+        let inputs_revision = self.inputs_revision + 1;
+        *self = Self {
+            name: std::mem::take(&mut self.name),
+            reference_profile: session.reference_profile,
+            input_profile: session.input_profile,
+            match_result,
+            references: session.references,
+            reference_path: session.reference_path,
+            input_path: session.input_path,
+            match_config: session.match_config,
+            history,
+            spectra: HashMap::new(),
+            revision: self.revision,
+            inputs_revision,
+            result_basis: inputs_revision,
+        };
+    }
+
+    /// Re-extracts every loaded profile on the band grid of `config` from
+    /// the kept spectra. Returns the paths without spectra, which have to be
+    /// analyzed again first.
+    pub fn rebuild_profiles(&mut self, config: &AnalysisConfig) -> Vec<String> {
+        let mut missing = Vec::new();
+        let mut rebuild = |path: &String, profile: &mut EQProfile| match self.spectra.get(path) {
+            Some(spectra) => *profile = spectra.profile(config),
+            None if !missing.contains(path) => missing.push(path.clone()),
+            None => {}
+        };
+
+        self.inputs_revision += 1;
+        for (path, profile) in self.references.iter_mut() {
+            rebuild(path, profile);
+        }
+        if let (Some(path), Some(profile)) = (&self.reference_path, &mut self.reference_profile) {
+            rebuild(path, profile);
+        }
+        if let (Some(path), Some(profile)) = (&self.input_path, &mut self.input_profile) {
+            rebuild(path, profile);
+        }
+        missing
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    pub id: ProjectId,
+    pub name: String,
+    pub active: bool,
+    pub reference_path: Option<String>,
+    pub input_path: Option<String>,
+    pub has_result: bool,
+    pub result_stale: bool,
+    pub revision: u64,
+}
+
+/// Open projects keyed by id, one of them active. There is always at least
+/// one project.
+#[derive(Debug)]
+pub struct ProjectStore {
+    projects: BTreeMap<ProjectId, Project>,
+    active: ProjectId,
+    next_id: ProjectId,
+}
+
+impl Default for ProjectStore {
+    fn default() -> Self {
+        Self {
+            projects: BTreeMap::from([(1, Project::new(DEFAULT_NAME))]),
+            active: 1,
+            next_id: 2,
+        }
+    }
+}
+
+impl ProjectStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // New projects start empty and do not become active by themselves
+    pub fn create(&mut self, name: Option<&str>) -> ProjectId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.projects
+            .insert(id, Project::new(name.unwrap_or(DEFAULT_NAME)));
+        id
+    }
+
+    pub fn set_active(&mut self, id: ProjectId) -> Result<(), String> {
+        self.get(id)?;
+        self.active = id;
+        Ok(())
+    }
+
+    // Closing the active project activates the first remaining one
+    pub fn close(&mut self, id: ProjectId) -> Result<(), String> {
+        if self.projects.len() == 1 {
+            return Err("Cannot close the last project".to_string());
+        }
+        self.projects
+            .remove(&id)
+            .ok_or_else(|| unknown_project(id))?;
+        if self.active == id {
+            self.active = *self.projects.keys().next().unwrap_or(&id);
+        }
+        Ok(())
+    }
+
+    pub fn rename(&mut self, id: ProjectId, name: &str) -> Result<(), String> {
+        self.get_mut(id)?.name = name.to_string();
+        Ok(())
+    }
+
+    pub fn active_id(&self) -> ProjectId {
+        self.active
+    }
+
+    pub fn active(&self) -> &Project {
+        &self.projects[&self.active]
+    }
+
+    // Mutable access counts as a change
+    pub fn active_mut(&mut self) -> &mut Project {
+        let project = self
+            .projects
+            .get_mut(&self.active)
+            .expect("active project is always open");
+        project.revision += 1;
+        project
+    }
+
+    pub fn get(&self, id: ProjectId) -> Result<&Project, String> {
+        self.projects.get(&id).ok_or_else(|| unknown_project(id))
+    }
+
+    pub fn get_mut(&mut self, id: ProjectId) -> Result<&mut Project, String> {
+        let project = self
+            .projects
+            .get_mut(&id)
+            .ok_or_else(|| unknown_project(id))?;
+        project.revision += 1;
+        Ok(project)
+    }
+
+    pub fn list(&self) -> Vec<ProjectInfo> {
+        self.projects
+            .iter()
+            .map(|(&id, project)| ProjectInfo {
+                id,
+                name: project.name.clone(),
+                active: id == self.active,
+                reference_path: project.reference_path.clone(),
+                input_path: project.input_path.clone(),
+                has_result: project.match_result.is_some(),
+                result_stale: project.result_stale(),
+                revision: project.revision,
+            })
+            .collect()
+    }
+}
+
+fn unknown_project(id: ProjectId) -> String {
+    format!("Unknown project: {}", id)
+}
@@ -0,0 +1,359 @@
+use crate::audio::matcher::{match_profiles, MatchConfig};
+use crate::audio::pipeline::{analyze_audio_file_cancellable, policy_from_preset};
+use crate::audio::profile::EQProfile;
+use crate::batch::{write_export, OutputNames};
+use crate::export::file_extension;
+use crate::jobs::CancelToken;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub type QueueItemId = u64;
+
+pub const DEFAULT_WORKERS: usize = 2;
+const MAX_WORKERS: usize = 16;
+
+#[derive(Debug, Clone)]
+pub enum QueueAction {
+    Analyze,
+    Match {
+        reference: Arc<EQProfile>,
+        config: MatchConfig,
+    },
+    Export {
+        reference: Arc<EQProfile>,
+        config: MatchConfig,
+        format: String,
+        out_dir: String,
+    },
+}
+
+impl QueueAction {
+    fn label(&self) -> &'static str {
+        match self {
+            QueueAction::Analyze => "analyze",
+            QueueAction::Match { .. } => "match",
+            QueueAction::Export { .. } => "export",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueItemState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueOutput {
+    pub profile: Option<EQProfile>, // Analyze only
+    pub quality_score: Option<f32>,
+    pub residual_rms_db: Option<f32>,
+    pub output_path: Option<String>, // Export only
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueItemStatus {
+    pub id: QueueItemId,
+    pub path: String,
+    pub action: String,
+    pub state: QueueItemState,
+    pub output: Option<QueueOutput>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueueSummary {
+    pub pending: usize,
+    pub running: usize,
+    pub done: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueSnapshot {
+    pub paused: bool,
+    pub workers: usize,
+    pub items: Vec<QueueItemStatus>, // Run order
+    pub summary: QueueSummary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum QueueEvent {
+    ItemStarted { id: QueueItemId },
+    ItemFinished { item: QueueItemStatus },
+    Drained { summary: QueueSummary }, // Nothing pending or running any more
+}
+
+pub type QueueListener = Arc<dyn Fn(QueueEvent) + Send + Sync>;
+
+struct QueueItem {
+    status: QueueItemStatus,
+    action: QueueAction,
+    cancel: CancelToken,
+}
+
+struct QueueInner {
+    items: Vec<QueueItem>,
+    next_id: QueueItemId,
+    workers: usize,
+    active: usize,
+    paused: bool,
+}
+
+/// Work queue for analyze/match/export items. Pending items start in list
+/// order whenever fewer than `workers` are running and the queue isn't
+/// paused; each runs on its own thread and reports through the listener.
+#[derive(Clone)]
+pub struct BatchQueue {
+    inner: Arc<Mutex<QueueInner>>,
+    listener: QueueListener,
+    names: Arc<OutputNames>, // Shared by every export the queue runs
+}
+
+impl BatchQueue {
+    pub fn new(workers: usize, listener: QueueListener) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(QueueInner {
+                items: Vec::new(),
+                next_id: 1,
+                workers: workers.clamp(1, MAX_WORKERS),
+                active: 0,
+                paused: false,
+            })),
+            listener,
+            names: Arc::default(),
+        }
+    }
+
+    pub fn enqueue(&self, paths: &[String], action: &QueueAction) -> Vec<QueueItemId> {
+        let ids = {
+            let mut inner = self.inner.lock().unwrap();
+            paths
+                .iter()
+                .map(|path| {
+                    let id = inner.next_id;
+                    inner.next_id += 1;
+                    inner.items.push(QueueItem {
+                        status: QueueItemStatus {
+                            id,
+                            path: path.clone(),
+                            action: action.label().to_string(),
+                            state: QueueItemState::Pending,
+                            output: None,
+                            error: None,
+                        },
+                        action: action.clone(),
+                        cancel: CancelToken::new(),
+                    });
+                    id
+                })
+                .collect()
+        };
+        self.pump();
+        ids
+    }
+
+    pub fn set_workers(&self, workers: usize) {
+        self.inner.lock().unwrap().workers = workers.clamp(1, MAX_WORKERS);
+        self.pump();
+    }
+
+    // Running items finish; nothing new starts until `resume`
+    pub fn pause(&self) {
+        self.inner.lock().unwrap().paused = true;
+    }
+
+    pub fn resume(&self) {
+        self.inner.lock().unwrap().paused = false;
+        self.pump();
+    }
+
+    // Moves an item to `position` in the run order
+    pub fn move_item(&self, id: QueueItemId, position: usize) -> Result<(), String> {
+        let mut inner = self.inner.lock().unwrap();
+        let from = position_of(&inner, id)?;
+        let item = inner.items.remove(from);
+        let to = position.min(inner.items.len());
+        inner.items.insert(to, item);
+        Ok(())
+    }
+
+    // Pending items are cancelled at once, running ones at their next checkpoint
+    pub fn cancel(&self, id: QueueItemId) -> Result<(), String> {
+        let finished = {
+            let mut inner = self.inner.lock().unwrap();
+            let index = position_of(&inner, id)?;
+            let item = &mut inner.items[index];
+            item.cancel.cancel();
+            if item.status.state != QueueItemState::Pending {
+                return Ok(());
+            }
+            item.status.state = QueueItemState::Cancelled;
+            item.status.clone()
+        };
+        (self.listener)(QueueEvent::ItemFinished { item: finished });
+        Ok(())
+    }
+
+    pub fn clear_finished(&self) {
+        self.inner.lock().unwrap().items.retain(|item| {
+            matches!(
+                item.status.state,
+                QueueItemState::Pending | QueueItemState::Running
+            )
+        });
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let inner = self.inner.lock().unwrap();
+        QueueSnapshot {
+            paused: inner.paused,
+            workers: inner.workers,
+            items: inner.items.iter().map(|i| i.status.clone()).collect(),
+            summary: summarize(&inner),
+        }
+    }
+
+    // Starts as many pending items as the worker budget allows
+    fn pump(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        while !inner.paused && inner.active < inner.workers {
+            let Some(item) = inner
+                .items
+                .iter_mut()
+                .find(|i| i.status.state == QueueItemState::Pending)
+            else {
+                break;
+            };
+            item.status.state = QueueItemState::Running;
+            let (id, path, action, cancel) = (
+                item.status.id,
+                item.status.path.clone(),
+                item.action.clone(),
+                item.cancel.clone(),
+            );
+            inner.active += 1;
+
+            let queue = self.clone();
+            thread::spawn(move || {
+                (queue.listener)(QueueEvent::ItemStarted { id });
+                let result = run_item(&path, &action, &cancel, &queue.names);
+                queue.complete(id, result, cancel.is_cancelled());
+            });
+        }
+    }
+
+    fn complete(&self, id: QueueItemId, result: Result<QueueOutput, String>, cancelled: bool) {
+        let (finished, drained) = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.active -= 1;
+            let finished = inner
+                .items
+                .iter_mut()
+                .find(|i| i.status.id == id)
+                .map(|item| {
+                    let status = &mut item.status;
+                    match result {
+                        _ if cancelled => status.state = QueueItemState::Cancelled,
+                        Ok(output) => {
+                            status.state = QueueItemState::Done;
+                            status.output = Some(output);
+                        }
+                        Err(error) => {
+                            status.state = QueueItemState::Failed;
+                            status.error = Some(error);
+                        }
+                    }
+                    status.clone()
+                });
+            let summary = summarize(&inner);
+            let drained = (summary.pending == 0 && summary.running == 0).then_some(summary);
+            (finished, drained)
+        };
+
+        if let Some(item) = finished {
+            (self.listener)(QueueEvent::ItemFinished { item });
+        }
+        if let Some(summary) = drained {
+            (self.listener)(QueueEvent::Drained { summary });
+        }
+        self.pump();
+    }
+}
+
+fn position_of(inner: &QueueInner, id: QueueItemId) -> Result<usize, String> {
+    inner
+        .items
+        .iter()
+        .position(|i| i.status.id == id)
+        .ok_or_else(|| format!("Unknown queue item: {}", id))
+}
+
+fn summarize(inner: &QueueInner) -> QueueSummary {
+    let mut summary = QueueSummary::default();
+    for item in &inner.items {
+        match item.status.state {
+            QueueItemState::Pending => summary.pending += 1,
+            QueueItemState::Running => summary.running += 1,
+            QueueItemState::Done => summary.done += 1,
+            QueueItemState::Failed => summary.failed += 1,
+            QueueItemState::Cancelled => summary.cancelled += 1,
+        }
+    }
+    summary
+}
+
+fn run_item(
+    path: &str,
+    action: &QueueAction,
+    cancel: &CancelToken,
+    names: &OutputNames,
+) -> Result<QueueOutput, String> {
+    let profile = analyze_audio_file_cancellable(path, policy_from_preset(None), cancel)
+        .map_err(|e| e.to_string())?;
+
+    let (reference, config) = match action {
+        QueueAction::Analyze => {
+            return Ok(QueueOutput {
+                profile: Some(profile),
+                ..QueueOutput::default()
+            })
+        }
+        QueueAction::Match { reference, config }
+        | QueueAction::Export {
+            reference, config, ..
+        } => (reference, config),
+    };
+
+    let result = match_profiles(reference, &profile, config);
+    let mut output = QueueOutput {
+        quality_score: Some(result.quality_score),
+        residual_rms_db: Some(result.residual_rms_db),
+        ..QueueOutput::default()
+    };
+
+    if let QueueAction::Export {
+        format, out_dir, ..
+    } = action
+    {
+        cancel.check()?;
+        let extension =
+            file_extension(format).ok_or_else(|| format!("Unknown format: {}", format))?;
+        let out_dir = Path::new(out_dir);
+        fs::create_dir_all(out_dir).map_err(|e| e.to_string())?;
+        let output_path = names.reserve(Path::new(path), &extension, out_dir);
+        write_export(&result, format, &output_path)?;
+        output.output_path = Some(output_path.to_string_lossy().into_owned());
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,240 @@
+use crate::audio::compare::tonal_similarity;
+use crate::audio::profile::{ChannelMode, EQProfile};
+use crate::persist::{read_json, write_json};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const RECENT_FILE: &str = "recent.json";
+const MAX_RECENT: usize = 20; // Per role
+
+// Suggestion score = similarity * SIMILARITY_WEIGHT + usage * (1 - SIMILARITY_WEIGHT)
+const SIMILARITY_WEIGHT: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileRole {
+    Reference,
+    Input,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub path: String,
+    pub role: FileRole,
+    pub profile: EQProfile, // Cached so reopening skips analysis
+    pub opened_at: u64,     // Unix seconds
+    pub file_size: u64,     // Size and mtime when analyzed, to spot edits
+    pub modified_secs: u64,
+    #[serde(default)]
+    pub up_to_date: bool, // Filled in on read; false when the file changed or is gone
+    #[serde(default)]
+    pub match_count: u32, // References: matches run against it
+    #[serde(default)]
+    pub last_matched_at: Option<u64>, // Unix seconds
+}
+
+/// A stored reference ranked for an input by `RecentFiles::suggest_references`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferenceSuggestion {
+    pub path: String,
+    pub score: f32,      // 0.0 - 1.0, what the list is sorted by
+    pub similarity: f32, // 0.0 - 1.0, tonal shape against the input
+    pub match_count: u32,
+    pub last_matched_at: Option<u64>,
+    pub up_to_date: bool, // False: open it with a regular load, not from the cache
+}
+
+/// Most-recently-used files per role, newest first, persisted as JSON.
+#[derive(Debug, Default)]
+pub struct RecentFiles {
+    path: Option<PathBuf>,
+    entries: Vec<RecentFile>,
+}
+
+impl RecentFiles {
+    pub fn load(path: PathBuf) -> Self {
+        Self {
+            entries: read_json(&path).unwrap_or_default(),
+            path: Some(path),
+        }
+    }
+
+    pub fn record(
+        &mut self,
+        path: &str,
+        role: FileRole,
+        profile: &EQProfile,
+    ) -> Result<(), String> {
+        let (file_size, modified_secs) = fingerprint(Path::new(path)).unwrap_or_default();
+        // Reopening a file keeps its usage; each preset and channel mode is
+        // kept as its own entry
+        let (preset, channel_mode) = variant(profile);
+        let same = |e: &RecentFile| {
+            e.path == path && e.role == role && variant(&e.profile) == (preset, channel_mode)
+        };
+        let (match_count, last_matched_at) = self
+            .entries
+            .iter()
+            .find(|e| e.path == path && e.role == role)
+            .map_or((0, None), |e| (e.match_count, e.last_matched_at));
+        self.entries.retain(|e| !same(e));
+        self.entries.insert(
+            0,
+            RecentFile {
+                path: path.to_string(),
+                role,
+                profile: profile.clone(),
+                opened_at: unix_secs(SystemTime::now()),
+                file_size,
+                modified_secs,
+                up_to_date: true,
+                match_count,
+                last_matched_at,
+            },
+        );
+
+        let mut kept = 0;
+        self.entries.retain(|e| {
+            if e.role != role {
+                return true;
+            }
+            kept += 1;
+            kept <= MAX_RECENT
+        });
+
+        self.save()
+    }
+
+    // Counts a match against the reference at `path`, on every entry for it
+    pub fn record_match(&mut self, path: &str) -> Result<(), String> {
+        let now = unix_secs(SystemTime::now());
+        let mut found = false;
+        for entry in self
+            .entries
+            .iter_mut()
+            .filter(|e| e.path == path && e.role == FileRole::Reference)
+        {
+            entry.match_count += 1;
+            entry.last_matched_at = Some(now);
+            found = true;
+        }
+        if !found {
+            return Ok(());
+        }
+        self.save()
+    }
+
+    /// Up to `limit` stored references for `input`, best first: tonally
+    /// close ones that get matched against often. References on another
+    /// band grid and the input's own file are left out.
+    pub fn suggest_references(&self, input: &EQProfile, limit: usize) -> Vec<ReferenceSuggestion> {
+        let input_path = input.source.as_ref().map(|s| s.path.as_str());
+        let references: Vec<(&RecentFile, f32)> = self
+            .entries
+            .iter()
+            .filter(|e| e.role == FileRole::Reference && Some(e.path.as_str()) != input_path)
+            .filter_map(|e| Some((e, tonal_similarity(&e.profile, input)?)))
+            .collect();
+        let most_used = references
+            .iter()
+            .map(|(e, _)| e.match_count)
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let mut suggestions: Vec<ReferenceSuggestion> = references
+            .into_iter()
+            .map(|(entry, similarity)| {
+                // Log scale, so one favourite doesn't drown out the rest
+                let usage = (entry.match_count as f32).ln_1p() / (most_used as f32).ln_1p();
+                ReferenceSuggestion {
+                    path: entry.path.clone(),
+                    score: similarity * SIMILARITY_WEIGHT + usage * (1.0 - SIMILARITY_WEIGHT),
+                    similarity,
+                    match_count: entry.match_count,
+                    last_matched_at: entry.last_matched_at,
+                    up_to_date: is_current(entry),
+                }
+            })
+            .collect();
+        suggestions.sort_by(|a, b| b.score.total_cmp(&a.score));
+        // One suggestion per file, whichever entry scored best
+        let mut seen = HashSet::new();
+        suggestions.retain(|s| seen.insert(s.path.clone()));
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    fn save(&self) -> Result<(), String> {
+        match &self.path {
+            Some(path) => write_json(path, &self.entries),
+            None => Ok(()),
+        }
+    }
+
+    pub fn entries(&self, role: Option<FileRole>) -> Vec<RecentFile> {
+        self.entries
+            .iter()
+            .filter(|e| role.is_none_or(|r| r == e.role))
+            .map(|e| RecentFile {
+                up_to_date: is_current(e),
+                ..e.clone()
+            })
+            .collect()
+    }
+
+    // Cached entry analyzed with `preset` and `channel_mode`, only while the
+    // file on disk is unchanged
+    pub fn find(
+        &self,
+        path: &str,
+        role: FileRole,
+        preset: Option<&str>,
+        channel_mode: Option<ChannelMode>,
+    ) -> Option<&RecentFile> {
+        self.entries
+            .iter()
+            .find(|e| {
+                e.path == path && e.role == role && variant(&e.profile) == (preset, channel_mode)
+            })
+            .filter(|e| is_current(e))
+    }
+
+    // Newest cached entry for `path`, whatever it was analyzed with
+    pub fn find_latest(&self, path: &str, role: FileRole) -> Option<&RecentFile> {
+        self.entries
+            .iter()
+            .filter(|e| e.path == path && e.role == role)
+            .find(|e| is_current(e))
+    }
+}
+
+// Preset and channel mode a cached profile was analyzed with
+fn variant(profile: &EQProfile) -> (Option<&str>, Option<ChannelMode>) {
+    (
+        profile
+            .source
+            .as_ref()
+            .and_then(|s| s.analysis.preset.as_deref()),
+        profile.channels.as_ref().map(|c| c.mode),
+    )
+}
+
+fn is_current(entry: &RecentFile) -> bool {
+    fingerprint(Path::new(&entry.path)) == Some((entry.file_size, entry.modified_secs))
+}
+
+// File size and mtime (Unix seconds), a cheap stand-in for its content
+pub fn fingerprint(path: &Path) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.len(), unix_secs(meta.modified().ok()?)))
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
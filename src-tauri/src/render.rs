@@ -0,0 +1,439 @@
+use crate::audio::loader::{load_audio_channels_cancellable, AudioError};
+use crate::audio::metering::{inter_sample_peak, true_peak};
+use crate::audio::profile::FrequencyBand;
+use crate::audio::warnings::MatchWarning;
+use crate::audio::writer::{write_wav, BitDepth};
+use crate::dsp::dynamic::{DynamicBand, DynamicEQ};
+use crate::dsp::filters::{band_params, biquad_type, sanitize_params, FilterType};
+use crate::dsp::oversampling::{BUTTERWORTH_8_Q, CUTOFF_RATIO, SUPPORTED_FACTORS};
+use crate::jobs::{CancelToken, Cancelled};
+use biquad::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use thiserror::Error;
+
+// Frames processed between progress events
+const PROGRESS_CHUNK: usize = 65_536;
+const LIMITER_LOOKAHEAD_SECS: f64 = 0.0015;
+const LIMITER_RELEASE_SECS: f64 = 0.05;
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("Invalid render settings: {0}")]
+    InvalidConfig(String), // Oversampling factor, filter design
+
+    #[error(transparent)]
+    Audio(#[from] AudioError),
+
+    #[error("Cannot write output: {0}")]
+    Write(String),
+
+    #[error("Cancelled")]
+    Cancelled,
+}
+
+impl From<Cancelled> for RenderError {
+    fn from(_: Cancelled) -> Self {
+        Self::Cancelled
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderConfig {
+    pub oversampling: usize, // 1, 2 or 4
+    pub bit_depth: BitDepth,
+    pub dither: bool,                      // TPDF, integer formats only
+    pub true_peak_ceiling_db: Option<f32>, // dBTP, None = no limiting
+    pub dynamic_bands: Vec<DynamicBand>,   // Run after the static EQ
+}
+
+impl Default for RenderConfig {
+    fn default() -> Self {
+        Self {
+            oversampling: 1,
+            bit_depth: BitDepth::Pcm24,
+            dither: true,
+            true_peak_ceiling_db: Some(-1.0),
+            dynamic_bands: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderSummary {
+    pub output_path: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub duration_secs: f64,
+    pub true_peak_db: f32,          // Before limiting
+    pub max_gain_reduction_db: f32, // 0 when the limiter never engaged
+    #[serde(default)]
+    pub warnings: Vec<MatchWarning>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RenderEvent {
+    Progress { stage: RenderStage, fraction: f32 },
+    Finished { summary: RenderSummary },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderStage {
+    Decoding,
+    Processing,
+    Limiting,
+    Writing,
+}
+
+impl RenderStage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RenderStage::Decoding => "decoding",
+            RenderStage::Processing => "processing",
+            RenderStage::Limiting => "limiting",
+            RenderStage::Writing => "writing",
+        }
+    }
+}
+
+/// Offline counterpart of the preview chain: double precision throughout,
+/// optional oversampling, true-peak limiting and dithered quantization.
+pub fn render_file<F>(
+    input_path: &Path,
+    output_path: &Path,
+    bands: &[FrequencyBand],
+    config: &RenderConfig,
+    cancel: &CancelToken,
+    mut on_event: F,
+) -> Result<RenderSummary, RenderError>
+where
+    F: FnMut(RenderEvent),
+{
+    if !SUPPORTED_FACTORS.contains(&config.oversampling) {
+        return Err(RenderError::InvalidConfig(format!(
+            "unsupported oversampling factor {}",
+            config.oversampling
+        )));
+    }
+
+    on_event(RenderEvent::Progress {
+        stage: RenderStage::Decoding,
+        fraction: 0.0,
+    });
+    let audio = load_audio_channels_cancellable(input_path, cancel)?;
+    let sample_rate = audio.sample_rate;
+
+    let mut channels: Vec<Vec<f64>> = audio
+        .channels
+        .iter()
+        .map(|c| c.iter().map(|&s| s as f64).collect())
+        .collect();
+    let frames = channels.first().map(|c| c.len()).unwrap_or(0);
+    let total_work = (frames * channels.len()).max(1);
+    let (params, warnings) = sanitize_params(sample_rate as f32, &band_params(bands));
+
+    for (ch, channel) in channels.iter_mut().enumerate() {
+        let mut chain = RenderChain::new(sample_rate as f64, config.oversampling, &params)?;
+        let mut dynamic = DynamicEQ::new(sample_rate as f32, &config.dynamic_bands);
+        for (i, chunk) in channel.chunks_mut(PROGRESS_CHUNK).enumerate() {
+            cancel.check()?;
+            for sample in chunk.iter_mut() {
+                *sample = chain.process(*sample);
+                if !dynamic.is_empty() {
+                    *sample = dynamic.process(*sample as f32) as f64;
+                }
+            }
+            on_event(RenderEvent::Progress {
+                stage: RenderStage::Processing,
+                fraction: (ch * frames + i * PROGRESS_CHUNK + chunk.len()) as f32
+                    / total_work as f32,
+            });
+        }
+    }
+
+    cancel.check()?;
+    let true_peak_db = linear_to_db(true_peak(&channels));
+    let max_gain_reduction_db = match config.true_peak_ceiling_db {
+        Some(ceiling_db) => {
+            on_event(RenderEvent::Progress {
+                stage: RenderStage::Limiting,
+                fraction: 0.0,
+            });
+            limit_true_peak(&mut channels, sample_rate, ceiling_db)
+        }
+        None => 0.0,
+    };
+
+    cancel.check()?;
+    on_event(RenderEvent::Progress {
+        stage: RenderStage::Writing,
+        fraction: 0.0,
+    });
+    write_wav(
+        output_path,
+        &channels,
+        sample_rate,
+        config.bit_depth,
+        config.dither,
+    )
+    .map_err(RenderError::Write)?;
+
+    let summary = RenderSummary {
+        output_path: output_path.to_string_lossy().into_owned(),
+        sample_rate,
+        channels: channels.len(),
+        duration_secs: frames as f64 / sample_rate.max(1) as f64,
+        true_peak_db,
+        max_gain_reduction_db,
+        warnings,
+    };
+    on_event(RenderEvent::Finished {
+        summary: summary.clone(),
+    });
+
+    Ok(summary)
+}
+
+// f64 EQ with the same IIR oversampling scheme as the preview
+struct RenderChain {
+    factor: usize,
+    eq: Vec<DirectForm2Transposed<f64>>,
+    upsample: Vec<DirectForm2Transposed<f64>>,
+    downsample: Vec<DirectForm2Transposed<f64>>,
+}
+
+impl RenderChain {
+    // `params` are expected to be sanitized for the base rate already
+    fn new(
+        sample_rate: f64,
+        factor: usize,
+        params: &[(f32, f32, f32, FilterType)],
+    ) -> Result<Self, RenderError> {
+        let rate = sample_rate * factor as f64;
+
+        let eq = params
+            .iter()
+            .filter_map(|&(frequency, gain_db, q, filter_type)| {
+                Coefficients::<f64>::from_params(
+                    biquad_type(filter_type, gain_db as f64),
+                    rate.hz(),
+                    (frequency as f64).hz(),
+                    q as f64,
+                )
+                .ok()
+            })
+            .map(DirectForm2Transposed::<f64>::new)
+            .collect();
+
+        let anti_alias = || -> Result<Vec<DirectForm2Transposed<f64>>, RenderError> {
+            if factor == 1 {
+                return Ok(Vec::new());
+            }
+            BUTTERWORTH_8_Q
+                .iter()
+                .map(|&q| {
+                    Coefficients::<f64>::from_params(
+                        Type::LowPass,
+                        rate.hz(),
+                        (sample_rate * CUTOFF_RATIO as f64).hz(),
+                        q as f64,
+                    )
+                    .map(DirectForm2Transposed::<f64>::new)
+                    .map_err(|e| RenderError::InvalidConfig(format!("{:?}", e)))
+                })
+                .collect()
+        };
+
+        Ok(Self {
+            factor,
+            eq,
+            upsample: anti_alias()?,
+            downsample: anti_alias()?,
+        })
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        let mut output = 0.0;
+        for k in 0..self.factor {
+            let stuffed = if k == 0 {
+                sample * self.factor as f64
+            } else {
+                0.0
+            };
+            let up = run_cascade(&mut self.upsample, stuffed);
+            let processed = run_cascade(&mut self.eq, up);
+            output = run_cascade(&mut self.downsample, processed);
+        }
+        output
+    }
+}
+
+fn run_cascade(filters: &mut [DirectForm2Transposed<f64>], sample: f64) -> f64 {
+    filters.iter_mut().fold(sample, |acc, f| f.run(acc))
+}
+
+/// Linked lookahead limiter: the required gain is min-held over the
+/// lookahead in both directions and then averaged over the same span, which
+/// keeps the smoothed gain at or below the requirement at every sample.
+/// Returns the deepest gain reduction in dB.
+fn limit_true_peak(channels: &mut [Vec<f64>], sample_rate: u32, ceiling_db: f32) -> f32 {
+    let frames = channels.first().map(|c| c.len()).unwrap_or(0);
+    if frames == 0 {
+        return 0.0;
+    }
+
+    let ceiling = 10f64.powf(ceiling_db as f64 / 20.0);
+    let required: Vec<f64> = (0..frames)
+        .map(|n| {
+            let peak = channels
+                .iter()
+                .map(|c| inter_sample_peak(c, n))
+                .fold(0.0, f64::max);
+            if peak > ceiling {
+                ceiling / peak
+            } else {
+                1.0
+            }
+        })
+        .collect();
+
+    if required.iter().all(|&g| g >= 1.0) {
+        return 0.0;
+    }
+
+    let lookahead = ((LIMITER_LOOKAHEAD_SECS * sample_rate as f64) as usize).max(1);
+    let held = sliding_min(&required, lookahead);
+    let averaged = sliding_mean(&held, lookahead);
+
+    let release_step = 1.0 / (LIMITER_RELEASE_SECS * sample_rate as f64).max(1.0);
+    let mut gain = 1.0f64;
+    let mut min_gain = 1.0f64;
+    for (n, &target) in averaged.iter().enumerate() {
+        gain = target.min(gain + release_step);
+        min_gain = min_gain.min(gain);
+        for channel in channels.iter_mut() {
+            channel[n] *= gain;
+        }
+    }
+
+    -linear_to_db(min_gain)
+}
+
+// Minimum over [n - radius, n + radius] via a monotonic deque
+fn sliding_min(values: &[f64], radius: usize) -> Vec<f64> {
+    let mut window: VecDeque<usize> = VecDeque::new();
+    let mut out = Vec::with_capacity(values.len());
+    let mut next = 0;
+
+    for n in 0..values.len() {
+        while next < values.len() && next <= n + radius {
+            while window.back().is_some_and(|&i| values[i] >= values[next]) {
+                window.pop_back();
+            }
+            window.push_back(next);
+            next += 1;
+        }
+        while window.front().is_some_and(|&i| i + radius < n) {
+            window.pop_front();
+        }
+        out.push(window.front().map(|&i| values[i]).unwrap_or(1.0));
+    }
+
+    out
+}
+
+// Mean over [n - radius, n + radius], clipped at the edges
+fn sliding_mean(values: &[f64], radius: usize) -> Vec<f64> {
+    let mut prefix = Vec::with_capacity(values.len() + 1);
+    prefix.push(0.0);
+    for &v in values {
+        prefix.push(prefix.last().copied().unwrap_or(0.0) + v);
+    }
+
+    (0..values.len())
+        .map(|n| {
+            let start = n.saturating_sub(radius);
+            let end = (n + radius + 1).min(values.len());
+            (prefix[end] - prefix[start]) / (end - start) as f64
+        })
+        .collect()
+}
+
+fn linear_to_db(value: f64) -> f32 {
+    (20.0 * value.max(1e-12).log10()) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn sine(frequency: f64, amplitude: f64, frames: usize) -> Vec<f64> {
+        (0..frames)
+            .map(|n| amplitude * (2.0 * PI * frequency * n as f64 / 48_000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn true_peak_stays_under_the_ceiling() {
+        // A near-Nyquist tone whose inter-sample peaks exceed its samples,
+        // plus bursts that need fast gain changes
+        let mut left = sine(11_025.0, 1.6, 48_000);
+        let right = sine(997.0, 0.7, 48_000);
+        for burst in left.chunks_mut(4800).step_by(2) {
+            for sample in burst.iter_mut() {
+                *sample *= 0.25;
+            }
+        }
+        let mut channels = vec![left, right];
+
+        let ceiling_db = -1.0;
+        let reduction_db = limit_true_peak(&mut channels, 48_000, ceiling_db);
+        let ceiling = 10f64.powf(ceiling_db as f64 / 20.0);
+        assert!(reduction_db > 4.0);
+        assert!(
+            true_peak(&channels) <= ceiling * 1.001,
+            "{} dBTP",
+            linear_to_db(true_peak(&channels))
+        );
+    }
+
+    #[test]
+    fn quiet_audio_is_untouched() {
+        let original = vec![sine(1000.0, 0.5, 4800)];
+        let mut channels = original.clone();
+        assert_eq!(limit_true_peak(&mut channels, 48_000, -1.0), 0.0);
+        assert_eq!(channels, original);
+    }
+
+    #[test]
+    fn bad_settings_and_missing_input_keep_their_kind() {
+        let cancel = CancelToken::new();
+        let render = |path: &str, oversampling| {
+            let config = RenderConfig {
+                oversampling,
+                ..RenderConfig::default()
+            };
+            render_file(
+                Path::new(path),
+                Path::new("unused.wav"),
+                &[],
+                &config,
+                &cancel,
+                |_| {},
+            )
+        };
+        assert!(matches!(
+            render("missing.wav", 3),
+            Err(RenderError::InvalidConfig(_))
+        ));
+        assert!(matches!(
+            render("missing.wav", 1),
+            Err(RenderError::Audio(AudioError::FileOpen(_)))
+        ));
+    }
+}
@@ -0,0 +1,291 @@
+use crate::atomic_file::write_atomic;
+use crate::audio::matcher::{blend_profiles, match_profiles, MatchConfig, MatchResult};
+use crate::audio::pipeline::{analyze_audio_file, policy_from_preset};
+use crate::audio::presets::list_match_presets;
+use crate::audio::profile::EQProfile;
+use crate::export::export_result;
+use crate::jobs::CancelToken;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// What a script run produced, for the caller to show or reuse.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptOutput {
+    pub log: Vec<String>,
+    pub written: Vec<String>, // Files written by `export`
+    pub result: Option<MatchResult>,
+}
+
+#[derive(Default)]
+struct ScriptState {
+    reference: Option<EQProfile>,
+    input: Option<EQProfile>,
+    config: MatchConfig,
+    result: Option<MatchResult>,
+}
+
+/// Runs a pipeline script: one command per line, `#` starts a comment and
+/// paths with spaces go in double quotes. This is a fixed list of commands
+/// run top to bottom, not a scripting language: there are no variables,
+/// loops or conditions. Input paths resolve against `base_dir`; `export`
+/// only writes below it, so a script received from someone else can't
+/// overwrite files elsewhere.
+///
+/// ```text
+/// reference a.wav b.wav "c d.wav"   # several files are averaged
+/// input mix.wav
+/// preset subtle
+/// set intensity 0.8                 # any MatchConfig field
+/// match
+/// clamp above 10000 2               # limit bands >= 10 kHz to +/-2 dB
+/// export apo out/eq.txt
+/// ```
+pub fn run_script(
+    source: &str,
+    base_dir: &Path,
+    cancel: &CancelToken,
+) -> Result<ScriptOutput, String> {
+    let mut state = ScriptState::default();
+    let mut output = ScriptOutput {
+        log: Vec::new(),
+        written: Vec::new(),
+        result: None,
+    };
+
+    for (index, line) in source.lines().enumerate() {
+        let words = tokenize(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+        let Some((command, args)) = words.split_first() else {
+            continue;
+        };
+        cancel.check()?;
+
+        let message = run_command(&mut state, command, args, base_dir, &mut output.written)
+            .map_err(|e| format!("line {}: {}", index + 1, e))?;
+        output.log.push(message);
+    }
+
+    output.result = state.result;
+    Ok(output)
+}
+
+fn run_command(
+    state: &mut ScriptState,
+    command: &str,
+    args: &[String],
+    base_dir: &Path,
+    written: &mut Vec<String>,
+) -> Result<String, String> {
+    match command {
+        "reference" => {
+            if args.is_empty() {
+                return Err("reference needs at least one file".to_string());
+            }
+            let profiles = args
+                .iter()
+                .map(|path| analyze(&resolve(base_dir, path)))
+                .collect::<Result<Vec<_>, _>>()?;
+            state.reference = Some(average_profiles(&profiles)?);
+            Ok(format!("reference: {} file(s)", profiles.len()))
+        }
+        "input" => {
+            let [path] = args else {
+                return Err("input takes one file".to_string());
+            };
+            state.input = Some(analyze(&resolve(base_dir, path))?);
+            Ok(format!("input: {}", path))
+        }
+        "preset" => {
+            let [id] = args else {
+                return Err("preset takes one id".to_string());
+            };
+            state.config = list_match_presets()
+                .into_iter()
+                .find(|p| &p.id == id)
+                .map(|p| p.config)
+                .ok_or_else(|| format!("Unknown match preset: {}", id))?;
+            Ok(format!("preset: {}", id))
+        }
+        "set" => {
+            let [field, value] = args else {
+                return Err("set takes a field and a value".to_string());
+            };
+            state.config = set_config_field(&state.config, field, value)?;
+            Ok(format!("set {} = {}", field, value))
+        }
+        "match" => {
+            let reference = state.reference.as_ref().ok_or("No reference loaded")?;
+            let input = state.input.as_ref().ok_or("No input loaded")?;
+            let result = match_profiles(reference, input, &state.config);
+            let message = format!(
+                "match: quality {:.2}, residual {:.2} dB RMS",
+                result.quality_score, result.residual_rms_db
+            );
+            state.result = Some(result);
+            Ok(message)
+        }
+        "clamp" => {
+            let [side, frequency, limit] = args else {
+                return Err("clamp takes above|below, a frequency and a dB limit".to_string());
+            };
+            let above = match side.as_str() {
+                "above" => true,
+                "below" => false,
+                other => return Err(format!("Expected above or below, got {}", other)),
+            };
+            let frequency = parse_number(frequency)?;
+            let limit = parse_number(limit)?.abs();
+            let result = state.result.as_mut().ok_or("Run match before clamp")?;
+            clamp_result(result, |f| (f >= frequency) == above, limit);
+            Ok(format!("clamp {} {} Hz to {} dB", side, frequency, limit))
+        }
+        "export" => {
+            let [format, path] = args else {
+                return Err("export takes a format and a file".to_string());
+            };
+            let path = resolve_output(base_dir, path)?;
+            let result = state.result.as_ref().ok_or("Run match before export")?;
+            let text = export_result(result, format)?;
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            write_atomic(&path, text)?;
+            let path = path.to_string_lossy().into_owned();
+            written.push(path.clone());
+            Ok(format!("wrote {}", path))
+        }
+        other => Err(format!("Unknown command: {}", other)),
+    }
+}
+
+fn analyze(path: &Path) -> Result<EQProfile, String> {
+    analyze_audio_file(path, policy_from_preset(None))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Absolute paths are kept as they are
+fn resolve(base_dir: &Path, path: &str) -> PathBuf {
+    base_dir.join(path)
+}
+
+// Relative, without `..`; symlinks inside `base_dir` are trusted
+fn resolve_output(base_dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let inside = Path::new(path)
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if !inside {
+        return Err(format!(
+            "export paths must stay inside the script's folder: {}",
+            path
+        ));
+    }
+    Ok(base_dir.join(path))
+}
+
+fn parse_number(text: &str) -> Result<f32, String> {
+    text.parse()
+        .map_err(|_| format!("Expected a number, got {}", text))
+}
+
+// Equal-weight average, built up as a running blend
+pub fn average_profiles(profiles: &[EQProfile]) -> Result<EQProfile, String> {
+    let (first, rest) = profiles.split_first().ok_or("No profiles to average")?;
+    rest.iter()
+        .enumerate()
+        .try_fold(first.clone(), |average, (i, profile)| {
+            let count = (i + 2) as f32;
+            blend_profiles(&average, profile, (count - 1.0) / count)
+        })
+}
+
+// Goes through JSON so every serializable field is settable by name
+fn set_config_field(config: &MatchConfig, field: &str, value: &str) -> Result<MatchConfig, String> {
+    let mut json = serde_json::to_value(config).map_err(|e| e.to_string())?;
+    let slot = json
+        .get_mut(field)
+        .ok_or_else(|| format!("Unknown match setting: {}", field))?;
+    *slot = serde_json::from_str(value).unwrap_or_else(|_| value.into());
+    serde_json::from_value(json).map_err(|e| format!("{}: {}", field, e))
+}
+
+fn clamp_result(result: &mut MatchResult, selected: impl Fn(f32) -> bool, limit: f32) {
+    let mut profiles = vec![&mut result.correction_profile];
+    if let Some(channels) = result.channel_corrections.as_mut() {
+        profiles.push(&mut channels.first);
+        profiles.push(&mut channels.second);
+    }
+    for profile in profiles {
+        for band in profile.bands.iter_mut().filter(|b| selected(b.frequency)) {
+            band.gain_db = band.gain_db.clamp(-limit, limit);
+        }
+    }
+}
+
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '#' => break,
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '"' => {
+                chars.next();
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("Unterminated quote".to_string()),
+                    }
+                }
+                words.push(word);
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '#' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exports_stay_inside_the_base_dir() {
+        let base = Path::new("/scripts");
+        assert_eq!(
+            resolve_output(base, "out/eq.txt").unwrap(),
+            base.join("out/eq.txt")
+        );
+        assert_eq!(
+            resolve_output(base, "./eq.txt").unwrap(),
+            base.join("./eq.txt")
+        );
+        for path in ["/etc/eq.txt", "../eq.txt", "out/../../eq.txt"] {
+            assert!(resolve_output(base, path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn escaping_export_fails_its_line() {
+        let mut state = ScriptState::default();
+        let mut written = Vec::new();
+        let args = ["apo".to_string(), "../eq.txt".to_string()];
+        let error = run_command(&mut state, "export", &args, Path::new("."), &mut written);
+        assert!(error.unwrap_err().contains("inside the script's folder"));
+        assert!(written.is_empty());
+    }
+}
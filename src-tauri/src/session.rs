@@ -0,0 +1,63 @@
+use crate::audio::matcher::{MatchConfig, MatchResult};
+use crate::audio::profile::EQProfile;
+use crate::error::FileError;
+use crate::history::BandOverride;
+use crate::persist::write_json;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+pub const SESSION_VERSION: u32 = 1;
+pub const AUTOSAVE_FILE: &str = "autosave.session.json";
+pub const SESSION_EXTENSION: &str = "sfeq"; // Saved sessions, associated with the app
+
+/// Everything needed to pick up where the user left off. Profiles are
+/// stored alongside their paths so a session opens without re-analyzing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub version: u32,
+    pub reference_path: Option<String>,
+    pub input_path: Option<String>,
+    pub reference_profile: Option<EQProfile>,
+    pub input_profile: Option<EQProfile>,
+    pub references: HashMap<String, EQProfile>, // Keyed by source path
+    pub match_config: Option<MatchConfig>,
+    pub match_result: Option<MatchResult>,
+    pub band_overrides: Vec<BandOverride>, // Hand-set gains, re-applied on load
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            version: SESSION_VERSION,
+            reference_path: None,
+            input_path: None,
+            reference_profile: None,
+            input_profile: None,
+            references: HashMap::new(),
+            match_config: None,
+            match_result: None,
+            band_overrides: Vec::new(),
+        }
+    }
+}
+
+pub fn save_session(path: &Path, session: &Session) -> Result<(), String> {
+    write_json(path, session)
+}
+
+pub fn load_session(path: &Path) -> Result<Session, FileError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| FileError::Read(format!("{}: {}", path.display(), e)))?;
+    let session: Session = serde_json::from_str(&text)
+        .map_err(|e| FileError::Invalid(format!("{}: {}", path.display(), e)))?;
+    if session.version > SESSION_VERSION {
+        return Err(FileError::Invalid(format!(
+            "Session was saved by a newer version (format {})",
+            session.version
+        )));
+    }
+    Ok(session)
+}
@@ -0,0 +1,61 @@
+use crate::audio::analyzer::AnalysisConfig;
+use crate::audio::matcher::MatchConfig;
+use crate::i18n::Locale;
+use crate::midi::MidiConfig;
+use crate::osc::OscConfig;
+use crate::persist::{read_json, write_json};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub match_config: MatchConfig,
+    pub analysis_config: AnalysisConfig,
+    pub export_dir: Option<String>,
+    pub export_format: Option<String>,
+    pub preview_device: Option<String>, // Device id, None = system default
+    pub input_device: Option<String>,
+    pub automation_port: Option<u16>, // Local automation API, started at launch when set
+    pub automation_token: Option<String>, // Bearer token of that API, created on first start
+    pub cache_limit_mb: Option<u64>,  // Analysis cache size, None = default
+    pub batch_memory_mb: Option<u64>, // Budget for concurrent batch analyses, None = default
+    pub locale: Locale,               // Language of error messages
+    pub midi: MidiConfig,             // Target of `send_correction_midi`
+    pub osc: OscConfig,               // Target of `send_correction_osc`
+    pub ui: serde_json::Map<String, serde_json::Value>, // Owned by the frontend
+}
+
+/// Settings plus the file they live in. Without a path (no config dir, or
+/// before setup) changes are kept in memory only.
+#[derive(Debug, Default)]
+pub struct SettingsStore {
+    path: Option<PathBuf>,
+    current: Settings,
+}
+
+impl SettingsStore {
+    // A missing or unreadable file falls back to defaults rather than failing startup
+    pub fn load(path: PathBuf) -> Self {
+        let current = read_json(&path).unwrap_or_default();
+
+        Self {
+            path: Some(path),
+            current,
+        }
+    }
+
+    pub fn get(&self) -> &Settings {
+        &self.current
+    }
+
+    pub fn update(&mut self, settings: Settings) -> Result<(), String> {
+        if let Some(path) = &self.path {
+            write_json(path, &settings)?;
+        }
+        self.current = settings;
+        Ok(())
+    }
+}
@@ -0,0 +1,377 @@
+use crate::atomic_file::write_atomic;
+use crate::audio::curve::ResponseCurve;
+use crate::audio::profile::EQProfile;
+use crate::error::FileError;
+use crate::export::image::Canvas;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const SHARE_FORMAT: &str = "spectraforge-profile";
+pub const SHARE_VERSION: u32 = 1;
+pub const SHARE_EXTENSION: &str = "sfprofile";
+/// JSON Schema of the file format, for tools outside the app.
+pub const SHARE_SCHEMA: &str = include_str!("../schemas/shared-profile.schema.json");
+
+const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+const MAX_BANDS: usize = 1024;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+// Preview chart
+const PREVIEW_WIDTH: usize = 480;
+const PREVIEW_HEIGHT: usize = 160;
+const PREVIEW_RANGE_DB: f32 = 15.0; // ± around the profile's mean level
+const BACKGROUND: [u8; 3] = [22, 24, 29];
+const GRID: [u8; 3] = [58, 62, 70];
+const TRACE: [u8; 3] = [94, 176, 255];
+
+/// Who made a shared profile and what it is for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareInfo {
+    pub name: String,
+    pub description: String,
+    pub author: Option<String>,
+}
+
+/// A reference "sound profile" that can be passed around without the audio
+/// it was analyzed from. `checksum` covers `profile` only, so a renamed or
+/// re-described file still verifies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedProfile {
+    pub format: String,
+    pub version: u32,
+    pub app_version: String,
+    pub created_at: u64, // Unix seconds
+    #[serde(flatten)]
+    pub info: ShareInfo,
+    pub profile: EQProfile,
+    pub checksum: String, // "sha256:<hex>"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_png: Option<String>, // Base64, a chart of the profile's shape
+}
+
+pub fn create_shared_profile(
+    profile: &EQProfile,
+    info: ShareInfo,
+    include_preview: bool,
+) -> Result<SharedProfile, String> {
+    let info = ShareInfo {
+        name: info.name.trim().to_string(),
+        ..info
+    };
+    if info.name.is_empty() {
+        return Err("A shared profile needs a name".to_string());
+    }
+    validate_profile(profile)?;
+    // Keep the file name and hash, not where it sits on this machine
+    let mut profile = profile.clone();
+    if let Some(source) = &mut profile.source {
+        if let Some(name) = Path::new(&source.path).file_name() {
+            source.path = name.to_string_lossy().into_owned();
+        }
+    }
+
+    Ok(SharedProfile {
+        format: SHARE_FORMAT.to_string(),
+        version: SHARE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        info,
+        checksum: profile_checksum(&profile_json(&profile)?),
+        preview_png: if include_preview {
+            Some(BASE64.encode(render_preview_png(&profile)?))
+        } else {
+            None
+        },
+        profile,
+    })
+}
+
+// Compact JSON, the files are meant to be posted and downloaded
+pub fn write_shared_profile(path: &Path, shared: &SharedProfile) -> Result<(), String> {
+    let text = serde_json::to_string(shared).map_err(|e| e.to_string())?;
+    write_atomic(path, text)
+}
+
+pub fn read_shared_profile(path: &Path) -> Result<SharedProfile, FileError> {
+    let read_error = |e: std::io::Error| FileError::Read(format!("{}: {}", path.display(), e));
+    let size = fs::metadata(path).map_err(read_error)?.len();
+    if size > MAX_FILE_BYTES {
+        return Err(FileError::Invalid(format!(
+            "{} is too large for a shared profile ({} bytes)",
+            path.display(),
+            size
+        )));
+    }
+    let text = fs::read_to_string(path).map_err(read_error)?;
+    parse_shared_profile(&text)
+        .map_err(|e| FileError::Invalid(format!("{}: {}", path.display(), e)))
+}
+
+/// Parses and verifies a shared profile: format, version, checksum, band
+/// sanity and, when present, that the preview is a PNG. Files come from
+/// strangers, so nothing unverified gets through.
+pub fn parse_shared_profile(text: &str) -> Result<SharedProfile, String> {
+    let header: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    if header.get("format").and_then(|f| f.as_str()) != Some(SHARE_FORMAT) {
+        return Err("Not a shared profile".to_string());
+    }
+    match header.get("version").and_then(|v| v.as_u64()) {
+        Some(version) if version > SHARE_VERSION as u64 => {
+            return Err(format!(
+                "Shared profile was made by a newer version (format {})",
+                version
+            ))
+        }
+        Some(_) => {}
+        None => return Err("Shared profile has no format version".to_string()),
+    }
+
+    let checksum = header.get("profile").map(profile_checksum);
+    let shared: SharedProfile = serde_json::from_value(header).map_err(|e| e.to_string())?;
+    if checksum.as_ref() != Some(&shared.checksum) {
+        return Err("Checksum mismatch, the profile was modified or damaged".to_string());
+    }
+    validate_profile(&shared.profile)?;
+    if let Some(preview) = &shared.preview_png {
+        let bytes = BASE64
+            .decode(preview)
+            .map_err(|e| format!("Preview is not valid base64: {}", e))?;
+        if !bytes.starts_with(PNG_SIGNATURE) {
+            return Err("Preview is not a PNG image".to_string());
+        }
+    }
+    Ok(shared)
+}
+
+impl SharedProfile {
+    // Resampled along the shared shape when the grids differ, so a profile
+    // made with another band layout still matches against the current one
+    pub fn profile_on_grid(&self, band_centers: &[f32]) -> EQProfile {
+        let same_grid = self.profile.bands.len() == band_centers.len()
+            && self
+                .profile
+                .bands
+                .iter()
+                .zip(band_centers)
+                .all(|(b, f)| (b.frequency - f).abs() <= 0.01);
+        if same_grid {
+            return self.profile.clone();
+        }
+        EQProfile {
+            channels: None,
+            ..ResponseCurve::new(in_range_points(&self.profile)).to_profile(band_centers)
+        }
+    }
+}
+
+// SHA-256 of the profile's JSON with sorted keys, taken from the parsed
+// text rather than the struct so fields added later don't break old files
+fn profile_checksum(profile: &serde_json::Value) -> String {
+    let mut text = String::new();
+    canonical_json(profile, &mut text);
+    let digest = Sha256::digest(text.as_bytes());
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+fn canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::from(key.as_str()).to_string());
+                out.push(':');
+                canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+// Through text, so numbers come out exactly as they are written to the file
+fn profile_json(profile: &EQProfile) -> Result<serde_json::Value, String> {
+    let text = serde_json::to_string(profile).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+fn validate_profile(profile: &EQProfile) -> Result<(), String> {
+    if profile.bands.is_empty() || profile.bands.len() > MAX_BANDS {
+        return Err(format!(
+            "A shared profile needs 1 to {} bands, got {}",
+            MAX_BANDS,
+            profile.bands.len()
+        ));
+    }
+    if let Some(band) = profile.bands.iter().find(|b| {
+        !(b.frequency.is_finite()
+            && b.frequency > 0.0
+            && b.gain_db.is_finite()
+            && b.bandwidth.is_finite())
+    }) {
+        return Err(format!(
+            "Band at {} Hz has invalid values ({} dB)",
+            band.frequency, band.gain_db
+        ));
+    }
+    if profile
+        .bands
+        .windows(2)
+        .any(|w| w[0].frequency >= w[1].frequency)
+    {
+        return Err("Band frequencies must be ascending".to_string());
+    }
+    Ok(())
+}
+
+// Band gains relative to their mean; absolute levels depend on the source
+// and mean nothing on a chart
+fn in_range_points(profile: &EQProfile) -> Vec<(f32, f32)> {
+    let points: Vec<(f32, f32)> = profile
+        .bands
+        .iter()
+        .filter(|b| !b.out_of_range)
+        .map(|b| (b.frequency, b.gain_db))
+        .collect();
+    if points.is_empty() {
+        return profile
+            .bands
+            .iter()
+            .map(|b| (b.frequency, b.gain_db))
+            .collect();
+    }
+    points
+}
+
+/// Line chart of the profile's shape: 20 Hz-20 kHz on a log axis, ±15 dB
+/// around its mean, grid lines at 100 Hz, 1 kHz, 10 kHz and the mean.
+pub fn render_preview_png(profile: &EQProfile) -> Result<Vec<u8>, String> {
+    let (width, height) = (PREVIEW_WIDTH, PREVIEW_HEIGHT);
+    let mut canvas = Canvas::new(width, height, BACKGROUND);
+
+    let (low, high) = (20.0f32.ln(), 20_000.0f32.ln());
+    let x_of = |freq: f32| (freq.ln() - low) / (high - low) * (width - 1) as f32;
+    let y_of = |db: f32| {
+        let t = (db.clamp(-PREVIEW_RANGE_DB, PREVIEW_RANGE_DB) + PREVIEW_RANGE_DB)
+            / (2.0 * PREVIEW_RANGE_DB);
+        (1.0 - t) * (height - 1) as f32
+    };
+
+    for freq in [100.0, 1_000.0, 10_000.0] {
+        canvas.fill_rect(x_of(freq).round() as i64, 0, 1, height as i64, GRID);
+    }
+    canvas.fill_rect(0, y_of(0.0).round() as i64, width as i64, 1, GRID);
+
+    let points = in_range_points(profile);
+    let mean = points.iter().map(|p| p.1).sum::<f32>() / points.len().max(1) as f32;
+    let curve = ResponseCurve::new(points.iter().map(|&(f, g)| (f, g - mean)).collect());
+    let line: Vec<(f32, f32)> = (0..width)
+        .map(|x| {
+            let freq = (low + (high - low) * x as f32 / (width - 1) as f32).exp();
+            (x as f32, y_of(curve.gain_at(freq)))
+        })
+        .collect();
+    canvas.polyline(&line, 2, TRACE);
+
+    canvas.encode_png()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> EQProfile {
+        ResponseCurve::new(vec![(100.0, 2.0), (1000.0, 0.0), (10_000.0, -3.0)])
+            .to_profile(&[100.0, 250.0, 1000.0, 4000.0, 10_000.0])
+    }
+
+    fn info() -> ShareInfo {
+        ShareInfo {
+            name: "Test".to_string(),
+            ..Default::default()
+        }
+    }
+
+    // As the file reads back; to_value would widen the f32 fields
+    fn file_json(shared: &SharedProfile) -> serde_json::Value {
+        serde_json::from_str(&serde_json::to_string(shared).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn checksum_ignores_key_order_and_whitespace() {
+        let a: serde_json::Value =
+            serde_json::from_str(r#"{"b": [1, 2.5], "a": {"y": null, "x": "s"}}"#).unwrap();
+        let b: serde_json::Value =
+            serde_json::from_str(r#"{"a":{"x":"s","y":null},"b":[1,2.5]}"#).unwrap();
+        assert_eq!(profile_checksum(&a), profile_checksum(&b));
+
+        let mut text = String::new();
+        canonical_json(&a, &mut text);
+        assert_eq!(text, r#"{"a":{"x":"s","y":null},"b":[1,2.5]}"#);
+    }
+
+    #[test]
+    fn checksum_is_sha256_of_the_canonical_text() {
+        // SHA-256 of "{}"
+        assert_eq!(
+            profile_checksum(&serde_json::json!({})),
+            "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+    }
+
+    #[test]
+    fn shared_profiles_round_trip() {
+        let shared = create_shared_profile(&profile(), info(), true).unwrap();
+        let text = serde_json::to_string(&shared).unwrap();
+        let parsed = parse_shared_profile(&text).unwrap();
+        assert_eq!(parsed.checksum, shared.checksum);
+        assert_eq!(parsed.profile.bands.len(), 5);
+    }
+
+    #[test]
+    fn edited_profiles_fail_the_checksum() {
+        let shared = create_shared_profile(&profile(), info(), false).unwrap();
+        let mut value = file_json(&shared);
+        value["profile"]["bands"][0]["gain_db"] = serde_json::json!(9.0);
+        let error = parse_shared_profile(&value.to_string()).unwrap_err();
+        assert!(error.contains("Checksum mismatch"), "{}", error);
+
+        // Name and description sit outside the checksum
+        let mut value = file_json(&shared);
+        value["name"] = serde_json::json!("Renamed");
+        assert!(parse_shared_profile(&value.to_string()).is_ok());
+    }
+
+    #[test]
+    fn newer_formats_are_refused() {
+        let shared = create_shared_profile(&profile(), info(), false).unwrap();
+        let mut value = file_json(&shared);
+        value["version"] = serde_json::json!(SHARE_VERSION + 1);
+        assert!(parse_shared_profile(&value.to_string())
+            .unwrap_err()
+            .contains("newer version"));
+    }
+}
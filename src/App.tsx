@@ -1,6 +1,9 @@
 import { useState } from 'react';
 import { invoke } from '@tauri-apps/api/tauri';
 import { open } from '@tauri-apps/api/dialog';
+import { runJob } from './jobs';
+import { errorMessage } from './errors';
+import type { MatchWarning } from './types';
 import { FileUploader } from './components/FileUploader';
 import { FrequencyAnalyzer } from './components/FrequencyAnalyzer';
 import { EQVisualization } from './components/EQVisualization';
@@ -26,7 +29,7 @@ interface MatchResult {
   reference_normalized: number[];
   input_normalized: number[];
   quality_score: number;
-  warnings: string[];
+  warnings: MatchWarning[];
 }
 
 interface MatchConfig {
@@ -35,6 +38,7 @@ interface MatchConfig {
   smoothing_factor: number;
   use_psychoacoustic: boolean;
   preserve_dynamics: boolean;
+  listening_level_db?: number;
 }
 
 type ProcessStep = 'upload' | 'analyze' | 'match' | 'export';
@@ -68,13 +72,13 @@ function App() {
       });
 
       if (selected && typeof selected === 'string') {
-        const profile = await invoke<EQProfile>('load_reference_audio', { 
+        const profile = await runJob<EQProfile>('load_reference_audio', { 
           path: selected 
         });
         setReferenceProfile(profile);
       }
     } catch (err) {
-      setError(`Reference load error: ${err}`);
+      setError(errorMessage(err));
     } finally {
       setLoading(false);
     }
@@ -94,14 +98,14 @@ function App() {
       });
 
       if (selected && typeof selected === 'string') {
-        const profile = await invoke<EQProfile>('load_input_audio', { 
+        const profile = await runJob<EQProfile>('load_input_audio', { 
           path: selected 
         });
         setInputProfile(profile);
         setStep('analyze');
       }
     } catch (err) {
-      setError(`Input load error: ${err}`);
+      setError(errorMessage(err));
     } finally {
       setLoading(false);
     }
@@ -114,7 +118,7 @@ function App() {
       setLoading(true);
       setError(null);
       
-      const result = await invoke<MatchResult>('calculate_eq_match', {
+      const result = await runJob<MatchResult>('calculate_eq_match', {
         reference: referenceProfile,
         input: inputProfile,
         config: matchConfig,
@@ -123,7 +127,7 @@ function App() {
       setMatchResult(result);
       setStep('match');
     } catch (err) {
-      setError(`Match calculation error: ${err}`);
+      setError(errorMessage(err));
     } finally {
       setLoading(false);
     }
@@ -147,7 +151,7 @@ function App() {
       a.click();
       URL.revokeObjectURL(url);
     } catch (err) {
-      setError(`Export error: ${err}`);
+      setError(errorMessage(err));
     }
   };
 
@@ -263,7 +267,7 @@ function App() {
                 <h4>⚠️ Warnings</h4>
                 <ul>
                   {matchResult.warnings.map((warning, i) => (
-                    <li key={i}>{warning}</li>
+                    <li key={i}>{warning.message}</li>
                   ))}
                 </ul>
               </div>
@@ -4,9 +4,11 @@ import './ControlPanel.css';
 interface MatchConfig {
   intensity: number;
   max_correction: number;
+  max_correction_curve?: [number, number][]; // [Hz, max ±dB] anchors; overrides max_correction
   smoothing_factor: number;
   use_psychoacoustic: boolean;
   preserve_dynamics: boolean;
+  listening_level_db?: number;
 }
 
 interface ControlPanelProps {
@@ -124,6 +126,27 @@ export function ControlPanel({ config, onChange }: ControlPanelProps) {
             </label>
           </div>
 
+          {config.use_psychoacoustic && (
+            <div className="control-group">
+              <label>
+                <span className="label-text">Listening Level</span>
+                <span className="label-value">{(config.listening_level_db ?? 83).toFixed(0)} dB SPL</span>
+              </label>
+              <input
+                type="range"
+                min="60"
+                max="95"
+                step="1"
+                value={config.listening_level_db ?? 83}
+                onChange={(e) => updateConfig({ listening_level_db: parseFloat(e.target.value) })}
+                className="slider"
+              />
+              <p className="help-text">
+                Quieter monitoring makes lows and highs harder to hear, so they get less weight
+              </p>
+            </div>
+          )}
+
           <div className="toggle-group">
             <label className="toggle-label">
               <input
@@ -1,5 +1,6 @@
 import { Line } from 'react-chartjs-2';
 import { Chart as ChartJS, LogarithmicScale } from 'chart.js';
+import type { MatchWarning } from '../types';
 import './EQVisualization.css';
 
 ChartJS.register(LogarithmicScale);
@@ -22,7 +23,7 @@ interface MatchResult {
   reference_normalized: number[];
   input_normalized: number[];
   quality_score: number;
-  warnings: string[];
+  warnings: MatchWarning[];
 }
 
 interface EQVisualizationProps {
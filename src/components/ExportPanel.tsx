@@ -1,4 +1,5 @@
 import { useState } from 'react';
+import type { MatchWarning } from '../types';
 import './ExportPanel.css';
 
 interface MatchResult {
@@ -11,7 +12,7 @@ interface MatchResult {
     }>;
   };
   quality_score: number;
-  warnings: string[];
+  warnings: MatchWarning[];
 }
 
 interface ExportPanelProps {
@@ -100,6 +101,18 @@ export function ExportPanel({ matchResult, onExport, onBack }: ExportPanelProps)
         <h3>Choose Export Format</h3>
         
         <div className="format-grid">
+          <ExportCard
+            icon="🎚️"
+            title="TDR Nova Preset"
+            description="Free dynamic EQ for every DAW and platform; dynamic suggestions included"
+            format="nova"
+            extension=".xml"
+            onExport={handleExport}
+            exporting={exporting}
+            exported={exportedFormat === 'nova'}
+            recommended
+          />
+
           <ExportCard
             icon="🎛️"
             title="REAPER FX Chain"
@@ -109,7 +122,6 @@ export function ExportPanel({ matchResult, onExport, onBack }: ExportPanelProps)
             onExport={handleExport}
             exporting={exporting}
             exported={exportedFormat === 'reaper'}
-            recommended
           />
 
           <ExportCard
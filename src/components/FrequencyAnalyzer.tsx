@@ -29,6 +29,7 @@ interface EQProfile {
     gain_db: number;
     bandwidth: number;
     confidence: number;
+    out_of_range?: boolean;
   }>;
   overall_loudness: number;
   dynamic_range: number;
@@ -139,12 +140,14 @@ export function FrequencyAnalyzer({ title, profile, color }: FrequencyAnalyzerPr
           {profile.bands.map((band, i) => (
             <div 
               key={i}
-              className="confidence-bar"
+              className={`confidence-bar${band.out_of_range ? ' out-of-range' : ''}`}
               style={{ 
                 height: `${band.confidence * 100}%`,
                 backgroundColor: color,
               }}
-              title={`${band.frequency} Hz: ${(band.confidence * 100).toFixed(0)}%`}
+              title={band.out_of_range
+                ? `${band.frequency} Hz: above Nyquist for this sample rate`
+                : `${band.frequency} Hz: ${(band.confidence * 100).toFixed(0)}%`}
             />
           ))}
         </div>
@@ -0,0 +1,24 @@
+// Mirrors `AppError` in src-tauri/src/error.rs; `message` is already
+// localized by the backend.
+export interface AppError {
+  code: string;
+  message: string;
+  details?: string | null;
+  fields?: FieldError[]; // Rejected config fields, for invalid_argument
+}
+
+export interface FieldError {
+  field: string; // e.g. "intensity", "room_correction.null_depth_db"
+  message: string;
+}
+
+export function isAppError(err: unknown): err is AppError {
+  return typeof err === 'object' && err !== null && 'code' in err && 'message' in err;
+}
+
+export function errorMessage(err: unknown): string {
+  if (isAppError(err)) {
+    return err.details ? `${err.message} (${err.details})` : err.message;
+  }
+  return String(err);
+}
@@ -0,0 +1,67 @@
+import { invoke } from '@tauri-apps/api/tauri';
+import { listen } from '@tauri-apps/api/event';
+import type { AppError } from './errors';
+
+export type JobEvent =
+  | { kind: 'started'; id: number; job: string }
+  | { kind: 'progress'; id: number; stage: string; fraction: number }
+  | { kind: 'partial'; id: number; fraction: number; data: unknown }
+  | { kind: 'finished'; id: number; result: unknown }
+  | { kind: 'failed'; id: number; error: AppError }
+  | { kind: 'cancelled'; id: number };
+
+// Starts a background command and resolves with its result. Events that
+// arrive before the job id is known are buffered, so fast jobs aren't missed.
+export async function runJob<T>(
+  command: string,
+  args: Record<string, unknown>,
+  onProgress?: (stage: string, fraction: number, id: number) => void,
+  onPartial?: (data: unknown, fraction: number, id: number) => void,
+): Promise<T> {
+  let jobId: number | null = null;
+  const early: JobEvent[] = [];
+  let handle: (event: JobEvent) => void = () => {};
+
+  const unlisten = await listen<JobEvent>('job-event', ({ payload }) => {
+    if (jobId === null) {
+      early.push(payload);
+    } else if (payload.id === jobId) {
+      handle(payload);
+    }
+  });
+
+  try {
+    return await new Promise<T>((resolve, reject) => {
+      handle = (event) => {
+        switch (event.kind) {
+          case 'progress':
+            onProgress?.(event.stage, event.fraction, event.id);
+            break;
+          case 'partial':
+            onPartial?.(event.data, event.fraction, event.id);
+            break;
+          case 'finished':
+            resolve(event.result as T);
+            break;
+          case 'failed':
+            reject(event.error);
+            break;
+          case 'cancelled':
+            reject('Cancelled');
+            break;
+        }
+      };
+
+      invoke<number>(command, args).then((id) => {
+        jobId = id;
+        early.filter((e) => e.id === id).forEach(handle);
+      }, reject);
+    });
+  } finally {
+    unlisten();
+  }
+}
+
+export function cancelJob(id: number): Promise<boolean> {
+  return invoke<boolean>('cancel_job', { id });
+}
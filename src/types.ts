@@ -0,0 +1,8 @@
+// Mirrors `MatchWarning` in src-tauri/src/audio/warnings.rs
+export interface MatchWarning {
+  code: string;
+  severity: 'info' | 'warning' | 'critical';
+  frequency_range: [number, number] | null;
+  message: string;
+  suggested_action: string | null;
+}